@@ -23,6 +23,7 @@
 #![no_std]
 
 use crate::hal::{
+    can,
     gpio::{GpioExt as _, Speed},
     nb::block,
     pac,
@@ -31,15 +32,13 @@ use crate::hal::{
     rcc::rec,
 };
 use fdcan::{
-    config::{DataBitTiming, FrameTransmissionConfig, NominalBitTiming},
+    config::FrameTransmissionConfig,
     filter::{StandardFilter, StandardFilterSlot},
     frame::{FrameFormat, TxFrameHeader},
     id::StandardId,
 };
 use stm32h7xx_hal as hal;
 
-use core::num::{NonZeroU16, NonZeroU8};
-
 use cortex_m_rt::entry;
 
 use log::info;
@@ -51,27 +50,6 @@ mod utilities;
 fn main() -> ! {
     utilities::logger::init();
 
-    // Kernel Clock 32MHz, Bit rate: 500kBit/s, Sample Point 87.5%
-    // Value was calculated with http://www.bittiming.can-wiki.info/
-    // TODO: use the can_bit_timings crate
-    let nominal_bit_timing = NominalBitTiming {
-        prescaler: NonZeroU16::new(4).unwrap(),
-        seg1: NonZeroU8::new(13).unwrap(),
-        seg2: NonZeroU8::new(2).unwrap(),
-        sync_jump_width: NonZeroU8::new(1).unwrap(),
-    };
-
-    // Kernel Clock 32MHz, Bit rate: 1MBit/s, Sample Point 87.5%
-    // Value was calculated with http://www.bittiming.can-wiki.info/
-    // TODO: use the can_bit_timings crate
-    let data_bit_timing = DataBitTiming {
-        prescaler: NonZeroU8::new(2).unwrap(),
-        seg1: NonZeroU8::new(13).unwrap(),
-        seg2: NonZeroU8::new(2).unwrap(),
-        sync_jump_width: NonZeroU8::new(1).unwrap(),
-        transceiver_delay_compensation: true,
-    };
-
     let dp = pac::Peripherals::take().unwrap();
     let cp =
         cortex_m::Peripherals::take().expect("cannot take core peripherals");
@@ -97,6 +75,12 @@ fn main() -> ! {
         .FDCAN
         .kernel_clk_mux(rec::FdcanClkSel::Pll1Q);
 
+    // Bit rate: 500kBit/s, 1MBit/s in the data phase
+    let fdcan_ker_ck = ccdr.clocks.pll1_q_ck().unwrap();
+    let nominal_bit_timing =
+        can::nominal_bit_timing(fdcan_ker_ck, 500.kHz()).unwrap();
+    let data_bit_timing = can::data_bit_timing(fdcan_ker_ck, 1.MHz()).unwrap();
+
     let mut delay = cp.SYST.delay(ccdr.clocks);
 
     let can1 = {
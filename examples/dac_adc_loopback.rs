@@ -0,0 +1,233 @@
+//! Example of a DAC to ADC loopback, with both paced by the same timer
+//!
+//! A sine wave is output from DAC1 channel 1 on PA4, and sampled by ADC1 on
+//! PC0. Connect a jumper between pins PA4 and PC0
+//!
+//! The trigger routing is:
+//!
+//! ```text
+//!            TRGO      +-> DAC1 CH1 --(request)--> DMA1 stream 0 (memory to DAC)
+//!   TIM6 ------------- |
+//!   (update event)     +-> ADC1 -------(request)--> DMA1 stream 1 (ADC to memory)
+//! ```
+//!
+//! On each update event of TIM6, the DAC outputs the next value from the
+//! waveform and requests another one from the DMA. At the same time the ADC
+//! starts a single conversion, which the DMA moves to the capture buffer.
+
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+use core::{mem, mem::MaybeUninit};
+use log::info;
+
+use cortex_m_rt::entry;
+
+use stm32h7xx_hal::dma::{
+    dma::{DmaConfig, Stream0, Stream1, StreamsTuple},
+    DBTransfer, MemoryToPeripheral, PeripheralToMemory, Transfer,
+};
+use stm32h7xx_hal::{
+    adc, dac, delay::Delay, gpio, pac, prelude::*, rcc::rec::AdcClkSel,
+    timer::Timer,
+};
+
+#[macro_use]
+mod utilities;
+
+/// Length of the waveform and capture buffers
+const SAMPLES: usize = 64;
+
+/// One period of a sine wave, using the 12 bit range of the DAC
+const fn sine() -> [u16; SAMPLES] {
+    // Quarter period, scaled to 2047
+    const QUARTER: [u16; SAMPLES / 4] = [
+        0, 201, 399, 594, 783, 965, 1137, 1299, 1447, 1582, 1702, 1805, 1891,
+        1959, 2008, 2037,
+    ];
+    let mut table = [0; SAMPLES];
+    let mut i = 0;
+    while i < SAMPLES / 4 {
+        table[i] = 2048 + QUARTER[i];
+        table[SAMPLES / 2 - 1 - i] = 2048 + QUARTER[i];
+        table[SAMPLES / 2 + i] = 2048 - QUARTER[i];
+        table[SAMPLES - 1 - i] = 2048 - QUARTER[i];
+        i += 1;
+    }
+    table
+}
+
+type DacC1 = dac::C1<pac::DAC, dac::Enabled>;
+type Adc1 = adc::Adc<pac::ADC1, adc::Enabled>;
+
+/// Handles for a running loopback
+struct Loopback {
+    /// Pacing timer, which can be paused with `pause` and restarted with
+    /// `resume`
+    timer: Timer<pac::TIM6>,
+    /// Waveform output
+    output: Transfer<
+        Stream0<pac::DMA1>,
+        DacC1,
+        MemoryToPeripheral,
+        &'static mut [u16; SAMPLES],
+        DBTransfer,
+    >,
+    /// Captured samples
+    capture: Transfer<
+        Stream1<pac::DMA1>,
+        Adc1,
+        PeripheralToMemory,
+        &'static mut [u16; SAMPLES],
+        DBTransfer,
+    >,
+}
+
+/// Starts outputting `waveform` from the DAC and capturing it with the ADC,
+/// one sample for each TIM6 update event at `frequency`
+#[allow(clippy::too_many_arguments)]
+fn loopback(
+    mut timer: Timer<pac::TIM6>,
+    frequency: stm32h7xx_hal::time::Hertz,
+    dac: dac::C1<pac::DAC, dac::Disabled>,
+    adc: Adc1,
+    mut pin: gpio::PC0<gpio::Analog>,
+    streams: (Stream0<pac::DMA1>, Stream1<pac::DMA1>),
+    waveform: &'static mut [u16; SAMPLES],
+    capture: &'static mut [u16; SAMPLES],
+) -> Loopback {
+    // Configure the timer, and route its update event to TRGO. The timer is
+    // started last so the DAC and ADC see the first trigger together
+    timer.pause();
+    timer.set_freq(frequency);
+    let trgo = timer.trigger_on_update();
+
+    let config = DmaConfig::default()
+        .memory_increment(true)
+        .circular_buffer(true);
+
+    // DAC output
+    let dac = dac.enable_triggered(trgo, true);
+    let mut output: Transfer<_, _, MemoryToPeripheral, _, _> =
+        Transfer::init(streams.0, dac, waveform, None, config);
+    output.start(|_| {});
+
+    // ADC capture
+    let mut capture: Transfer<_, _, PeripheralToMemory, _, _> =
+        Transfer::init(streams.1, adc, capture, None, config);
+    capture.start(|adc| {
+        adc.start_triggered_conversion_dma(
+            &mut pin,
+            adc::AdcDmaMode::Circular,
+            trgo,
+        );
+    });
+    timer.resume();
+
+    Loopback {
+        timer,
+        output,
+        capture,
+    }
+}
+
+/// Moves `init` into a buffer in AXI SRAM, which can be accessed by DMA1
+fn axisram_buffer(
+    buffer: &'static mut MaybeUninit<[u16; SAMPLES]>,
+    init: [u16; SAMPLES],
+) -> &'static mut [u16; SAMPLES] {
+    let buf: &mut [MaybeUninit<u16>; SAMPLES] =
+        unsafe { mem::transmute(buffer) };
+    for (slot, value) in buf.iter_mut().zip(init) {
+        slot.write(value);
+    }
+    unsafe { mem::transmute(buf) }
+}
+
+#[entry]
+fn main() -> ! {
+    utilities::logger::init();
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    #[link_section = ".axisram"]
+    static mut WAVEFORM: MaybeUninit<[u16; SAMPLES]> = MaybeUninit::uninit();
+    #[link_section = ".axisram"]
+    static mut CAPTURE: MaybeUninit<[u16; SAMPLES]> = MaybeUninit::uninit();
+
+    let waveform = axisram_buffer(
+        unsafe { &mut *core::ptr::addr_of_mut!(WAVEFORM) },
+        sine(),
+    );
+    let capture = axisram_buffer(
+        unsafe { &mut *core::ptr::addr_of_mut!(CAPTURE) },
+        [0; SAMPLES],
+    );
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let mut ccdr = rcc.sys_ck(200.MHz()).freeze(pwrcfg, &dp.SYSCFG);
+
+    // Use per_ck (64MHz HSI) for the ADC kernel clock
+    ccdr.peripheral.kernel_adc_clk_mux(AdcClkSel::Per);
+
+    info!("");
+    info!("stm32h7xx-hal example - DAC to ADC loopback");
+    info!("");
+
+    let mut delay = Delay::new(cp.SYST, ccdr.clocks);
+
+    let mut adc1 = adc::Adc::adc1(
+        dp.ADC1,
+        16.MHz(),
+        &mut delay,
+        ccdr.peripheral.ADC12,
+        &ccdr.clocks,
+    )
+    .enable();
+    adc1.set_resolution(adc::Resolution::TwelveBit);
+
+    let gpioa = dp.GPIOA.split(ccdr.peripheral.GPIOA);
+    let gpioc = dp.GPIOC.split(ccdr.peripheral.GPIOC);
+    let dac_pin = gpioa.pa4.into_analog();
+    let adc_pin = gpioc.pc0.into_analog();
+
+    let dac = dp.DAC.dac(dac_pin, ccdr.peripheral.DAC12);
+    let dac = dac.calibrate_buffer(&mut delay);
+
+    let timer = Timer::tim6(dp.TIM6, ccdr.peripheral.TIM6, &ccdr.clocks);
+    let streams = StreamsTuple::new(dp.DMA1, ccdr.peripheral.DMA1);
+
+    // 1kHz sine wave
+    let mut lb = loopback(
+        timer,
+        (1_000 * SAMPLES as u32).Hz(),
+        dac,
+        adc1,
+        adc_pin,
+        (streams.0, streams.1),
+        waveform,
+        capture,
+    );
+
+    // Wait for one full period to be captured
+    while !lb.capture.get_transfer_complete_flag() {}
+    lb.timer.pause();
+
+    let (_, _, capture, _) = lb.capture.free();
+    let (_, dac, _, _) = lb.output.free();
+    let _ = dac.disable();
+    info!("Output: {:?}", sine());
+    info!("Capture: {:?}", capture);
+
+    loop {
+        cortex_m::asm::nop()
+    }
+}
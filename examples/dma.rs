@@ -11,11 +11,7 @@ use cortex_m_rt::entry;
 mod utilities;
 use stm32h7xx_hal::{pac, prelude::*};
 
-use stm32h7xx_hal::dma::{
-    dma::{DmaConfig, StreamsTuple},
-    traits::Direction,
-    MemoryToMemory, Transfer,
-};
+use stm32h7xx_hal::dma::{dma::StreamsTuple, MemoryToMemory, Transfer};
 
 use log::info;
 
@@ -80,20 +76,13 @@ fn main() -> ! {
 
     let streams = StreamsTuple::new(dp.DMA1, ccdr.peripheral.DMA1);
 
-    let config = DmaConfig::default()
-        .memory_increment(true) // destination mem
-        .peripheral_increment(true) // source mem
-        .fifo_enable(true);
-
     let mut transfer: Transfer<_, _, MemoryToMemory<u32>, _, _> =
-        Transfer::init(
+        Transfer::init_memory_to_memory(
             streams.4,
-            MemoryToMemory::new(),
+            source_buffer,
             unsafe {
                 (*core::ptr::addr_of_mut!(TARGET_BUFFER)).assume_init_mut()
             }, // Uninitialised memory
-            Some(source_buffer),
-            config,
         );
 
     transfer.start(|_| {});
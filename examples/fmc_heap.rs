@@ -0,0 +1,178 @@
+//! FMC Example using the SDRAM as the heap
+//!
+//! Tested on a STM32H747I-DISCO
+#![deny(warnings)]
+#![no_main]
+#![no_std]
+
+extern crate alloc;
+
+use alloc::vec::Vec;
+
+#[macro_use]
+#[allow(dead_code)]
+mod utilities;
+
+extern crate cortex_m;
+
+use cortex_m_rt::entry;
+use embedded_alloc::Heap;
+use stm32h7xx_hal::gpio::Speed;
+use stm32h7xx_hal::{pac, prelude::*};
+
+use stm32_fmc::devices::is42s32800g_6;
+
+#[global_allocator]
+static HEAP: Heap = Heap::empty();
+
+/// Configre a pin for the FMC controller
+macro_rules! fmc_pins {
+    ($($pin:expr),*) => {
+        (
+            $(
+                $pin.into_push_pull_output()
+                    .speed(Speed::VeryHigh)
+                    .into_alternate::<12>()
+                    .internal_pull_up(true)
+            ),*
+        )
+    };
+}
+
+/// Configure the MPU so that the SDRAM is normal memory. In the default
+/// memory map it is device memory, where unaligned accesses fault
+fn mpu_sdram_init(mpu: cortex_m::peripheral::MPU, size: u32) {
+    // Refer to ARM®v7-M Architecture Reference Manual ARM DDI 0403
+    // Version E.b Section B3.5
+    const REGION_NUMBER0: u32 = 0x00;
+    const REGION_BASE_ADDRESS: u32 = 0xD000_0000;
+
+    const REGION_FULL_ACCESS: u32 = 0x03;
+    const REGION_CACHEABLE: u32 = 0x01;
+    const REGION_WRITE_BACK: u32 = 0x01;
+    const REGION_ENABLE: u32 = 0x01;
+
+    const MPU_ENABLE: u32 = 0x01;
+    const MPU_DEFAULT_MMAP_FOR_PRIVILEGED: u32 = 0x04;
+
+    assert!(size.is_power_of_two() && size >= 32);
+    let log2minus1 = size.trailing_zeros() - 1;
+
+    unsafe {
+        // Make sure outstanding transfers are done
+        cortex_m::asm::dmb();
+        mpu.ctrl.write(0);
+
+        // Cacheable, outer and inner write-back, no write allocate
+        mpu.rnr.write(REGION_NUMBER0);
+        mpu.rbar.write(REGION_BASE_ADDRESS);
+        mpu.rasr.write(
+            (REGION_FULL_ACCESS << 24)
+                | (REGION_CACHEABLE << 17)
+                | (REGION_WRITE_BACK << 16)
+                | (log2minus1 << 1)
+                | REGION_ENABLE,
+        );
+
+        mpu.ctrl.write(MPU_DEFAULT_MMAP_FOR_PRIVILEGED | MPU_ENABLE);
+
+        // Ensure MPU settings take effect
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+}
+
+#[entry]
+fn main() -> ! {
+    let mut cp = cortex_m::Peripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Initialise power...
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = example_power!(pwr).freeze();
+
+    // Initialise clocks...
+    let rcc = dp.RCC.constrain();
+    let ccdr = rcc
+        .sys_ck(200.MHz())
+        .hclk(200.MHz()) // FMC clock from HCLK by default
+        .freeze(pwrcfg, &dp.SYSCFG);
+
+    // Get the delay provider.
+    let mut delay = cp.SYST.delay(ccdr.clocks);
+
+    // Initialise system...
+    cp.SCB.enable_icache();
+
+    // Initialise IO...
+    let gpiod = dp.GPIOD.split(ccdr.peripheral.GPIOD);
+    let gpioe = dp.GPIOE.split(ccdr.peripheral.GPIOE);
+    let gpiof = dp.GPIOF.split(ccdr.peripheral.GPIOF);
+    let gpiog = dp.GPIOG.split(ccdr.peripheral.GPIOG);
+    let gpioh = dp.GPIOH.split(ccdr.peripheral.GPIOH);
+    let gpioi = dp.GPIOI.split(ccdr.peripheral.GPIOI);
+
+    // 32MB SDRAM
+    let sdram_size = 32 * 1024 * 1024;
+    mpu_sdram_init(cp.MPU, sdram_size as u32);
+
+    // ----------------------------------------------------------
+    // SDRAM
+    // Initialise SDRAM...
+    let sdram_pins = fmc_pins! {
+        // A0-A11
+        gpiof.pf0, gpiof.pf1, gpiof.pf2, gpiof.pf3,
+        gpiof.pf4, gpiof.pf5, gpiof.pf12, gpiof.pf13,
+        gpiof.pf14, gpiof.pf15, gpiog.pg0, gpiog.pg1,
+        // BA0-BA1
+        gpiog.pg4, gpiog.pg5,
+        // D0-D31
+        gpiod.pd14, gpiod.pd15, gpiod.pd0, gpiod.pd1,
+        gpioe.pe7, gpioe.pe8, gpioe.pe9, gpioe.pe10,
+        gpioe.pe11, gpioe.pe12, gpioe.pe13, gpioe.pe14,
+        gpioe.pe15, gpiod.pd8, gpiod.pd9, gpiod.pd10,
+        gpioh.ph8, gpioh.ph9, gpioh.ph10, gpioh.ph11,
+        gpioh.ph12, gpioh.ph13, gpioh.ph14, gpioh.ph15,
+        gpioi.pi0, gpioi.pi1, gpioi.pi2, gpioi.pi3,
+        gpioi.pi6, gpioi.pi7, gpioi.pi9, gpioi.pi10,
+        // NBL0 - NBL3
+        gpioe.pe0, gpioe.pe1, gpioi.pi4, gpioi.pi5,
+        gpioh.ph7,              // SDCKE1
+        gpiog.pg8,              // SDCLK
+        gpiog.pg15,             // SDNCAS
+        gpioh.ph6,              // SDNE1 (!CS)
+        gpiof.pf11,             // SDRAS
+        gpioh.ph5               // SDNWE
+    };
+
+    let mut sdram = dp.FMC.sdram(
+        sdram_pins,
+        is42s32800g_6::Is42s32800g {},
+        ccdr.peripheral.FMC,
+        &ccdr.clocks,
+    );
+
+    // Initialise controller and SDRAM
+    let ram_ptr: *mut u32 = sdram.init(&mut delay);
+
+    // unsafe: the SDRAM is initialised, it is normal memory in the MPU and
+    // nothing else is placed in it by the linker script
+    let heap = unsafe { sdram.as_heap(ram_ptr) };
+    assert_eq!(heap.size(), sdram_size);
+
+    // unsafe: called only once, before any allocation
+    heap.init_allocator(|start, size| unsafe { HEAP.init(start, size) });
+
+    // ----------------------------------------------------------
+    // Use the heap in SDRAM
+
+    let mut v: Vec<u32> = Vec::with_capacity(1024 * 1024);
+    for i in 0..1024 * 1024 {
+        v.push(i);
+    }
+    assert_eq!(v.iter().map(|&x| x as u64).sum::<u64>(), 549_755_289_600);
+
+    loop {
+        cortex_m::asm::nop()
+    }
+}
@@ -5,7 +5,11 @@
 use cortex_m_rt::entry;
 #[macro_use]
 mod utilities;
-use stm32h7xx_hal::{gpio::Speed, pac, prelude::*, rcc::PllConfigStrategy};
+use stm32h7xx_hal::{
+    pac,
+    prelude::*,
+    rcc::{Mco1Pin, Mco2Pin, PllConfigStrategy},
+};
 
 use log::info;
 
@@ -32,8 +36,8 @@ fn main() -> ! {
     let gpioa = dp.GPIOA.split(ccdr.peripheral.GPIOA);
     let gpioc = dp.GPIOC.split(ccdr.peripheral.GPIOC);
 
-    let _mco1_pin = gpioa.pa8.into_alternate::<0>();
-    let _mco2_pin = gpioc.pc9.into_alternate::<0>().speed(Speed::High);
+    let mco1 = Mco1Pin::new(gpioa.pa8, &ccdr.clocks);
+    let mco2 = Mco2Pin::new(gpioc.pc9, &ccdr.clocks);
 
     info!("");
     info!("stm32h7xx-hal example - MCO output");
@@ -44,18 +48,12 @@ fn main() -> ! {
     assert_eq!(ccdr.clocks.sys_ck().raw(), 100_000_000);
 
     // MCO
-    info!(
-        "mco1 = {} MHz",
-        ccdr.clocks.mco1_ck().unwrap().raw() as f32 / 1e6
-    );
-    assert_eq!(ccdr.clocks.mco1_ck().unwrap().raw(), 24_000_000);
+    info!("mco1 = {} MHz", mco1.frequency().raw() as f32 / 1e6);
+    assert_eq!(mco1.frequency().raw(), 24_000_000);
 
     // MCO
-    info!(
-        "mco2 = {} MHz",
-        ccdr.clocks.mco2_ck().unwrap().raw() as f32 / 1e6
-    );
-    assert_eq!(ccdr.clocks.mco2_ck().unwrap().raw(), 25_600_000);
+    info!("mco2 = {} MHz", mco2.frequency().raw() as f32 / 1e6);
+    assert_eq!(mco2.frequency().raw(), 25_600_000);
 
     info!("MCO outputs running!");
 
@@ -0,0 +1,67 @@
+//! Example of two timers cascaded to form a 48-bit counter
+//!
+//! TIM1 counts at 1MHz, and outputs its update event on TRGO. TIM2 is a
+//! slave of TIM1 in external clock mode, so it counts the overflows of TIM1.
+
+#![no_main]
+#![no_std]
+
+use cortex_m::asm;
+use cortex_m_rt::entry;
+use log::info;
+
+use stm32h7xx_hal::timer::{MasterMode, SlaveMode, Timer};
+use stm32h7xx_hal::{pac, prelude::*};
+
+#[path = "utilities/logger.rs"]
+mod logger;
+
+#[entry]
+fn main() -> ! {
+    logger::init();
+    let dp = pac::Peripherals::take().unwrap();
+
+    // Constrain and Freeze power
+    info!("Setup PWR...                  ");
+    let pwr = dp.PWR.constrain();
+    let pwrcfg = pwr.freeze();
+
+    // Constrain and Freeze clock
+    info!("Setup RCC...                  ");
+    let rcc = dp.RCC.constrain();
+    let ccdr = rcc.sys_ck(100.MHz()).freeze(pwrcfg, &dp.SYSCFG);
+
+    info!("");
+    info!("stm32h7xx-hal example - Cascaded timers");
+    info!("");
+
+    // TIM1 counts from 0 to 65535 at 1MHz
+    let mut tim1 =
+        dp.TIM1
+            .tick_timer(1.MHz(), ccdr.peripheral.TIM1, &ccdr.clocks);
+    tim1.pause();
+    let master = tim1.set_master_mode(MasterMode::Update);
+
+    // TIM2 counts the overflows of TIM1
+    let mut tim2 = Timer::tim2(dp.TIM2, ccdr.peripheral.TIM2, &ccdr.clocks);
+    tim2.pause();
+    tim2.sync_to_master(master, SlaveMode::ExternalClock);
+    tim2.reset_counter();
+    tim2.resume();
+
+    tim1.reset_counter();
+    tim1.resume();
+
+    loop {
+        // Read the high word until it is stable around the low word
+        let timestamp = loop {
+            let high = tim2.counter();
+            let low = tim1.counter();
+            if tim2.counter() == high {
+                break ((high as u64) << 16) | low as u64;
+            }
+        };
+        info!("timestamp: {} µs", timestamp);
+        asm::delay(10_000_000);
+    }
+}
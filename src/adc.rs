@@ -3,6 +3,110 @@
 //! ADC1 and ADC2 share a reset line. To initialise both of them, use the
 //! [`adc12`] method.
 //!
+//! # Internal channels
+//!
+//! The internal temperature sensor, VREFINT and VBAT channels are connected to
+//! ADC3 (ADC2 on RM0455 parts). The ADC can measure them directly, applying
+//! the factory calibration values stored in the [signature](crate::signature)
+//! for the part.
+//!
+//! ```
+//! let mut adc = adc3.enable();
+//!
+//! let vref = adc.read_vref_mv(&mut delay); // mV
+//! let temperature = adc.read_temperature(&mut delay); // °C
+//! let vbat = adc.read_vbat_mv(&mut delay); // mV
+//! ```
+//!
+//! # Sampling time
+//!
+//! The sampling time set by `set_sample_time` applies to all channels. High
+//! impedance sources, such as an NTC divider, need a longer sampling time,
+//! which can be set for just that channel:
+//!
+//! ```
+//! adc1.set_sample_time(AdcSampleTime::T_16);
+//! // NTC divider on PB1, ADC12_INP5
+//! adc1.set_channel_sample_time(5, AdcSampleTime::T_387);
+//! ```
+//!
+//! # External triggers
+//!
+//! Conversions can be started by a timer event rather than by software, to
+//! sample at a fixed point of a PWM period. The trigger of the regular group
+//! and of the injected group are set before the ADC is enabled.
+//!
+//! ```
+//! adc1.set_external_trigger(AdcExtTrig::Tim1Trgo, TriggerEdge::Rising);
+//! adc1.set_injected_trigger(AdcInjectedTrig::Tim1Cc4, TriggerEdge::Rising);
+//! let mut adc1 = adc1.enable();
+//!
+//! adc1.start_injected_conversion(&mut current_sense_pin);
+//! // ... on each TIM1 CC4 event
+//! let current = adc1.read_injected_sample().unwrap();
+//! ```
+//!
+//! # Sequences
+//!
+//! Several channels can be converted in turn as a regular sequence of up to
+//! 16 channels, given by their channel numbers. The results are returned in
+//! the order of the sequence, or transferred by the DMA with
+//! `start_sequence_dma`.
+//!
+//! ```
+//! let mut results = [0; 3];
+//! adc1.convert_sequence(&[0, 5, 10], &mut results);
+//! ```
+//!
+//! In discontinuous mode, each trigger converts only the next few channels of
+//! the regular sequence rather than the whole sequence.
+//!
+//! ```
+//! adc1.set_external_trigger(AdcExtTrig::Tim1Trgo, TriggerEdge::Rising);
+//! adc1.set_discontinuous_mode(2); // two channels on each trigger
+//! ```
+//!
+//! # Calibration
+//!
+//! The constructors run an offset and linearity calibration, which takes
+//! some time. The calibration factors can be saved and restored on later
+//! boots instead.
+//!
+//! ```
+//! let mut adc1 = adc1.enable();
+//! let factors = adc1.read_calibration_factors();
+//!
+//! // ... on a later boot
+//! let adc1 = Adc::<ADC1, Disabled>::new_uncalibrated(dp.ADC1, 4.MHz(), &mut delay, ccdr.peripheral.ADC12, &ccdr.clocks);
+//! let mut adc1 = adc1.enable();
+//! adc1.write_calibration_factors(factors);
+//! ```
+//!
+//! The offset calibration for single ended and differential channels is
+//! separate, so a channel that is changed to differential mode requires a
+//! calibration with [`AdcCalMode::Differential`].
+//!
+//! # Async
+//!
+//! With the `async` feature, [`AdcAsync`] makes continuous conversions into a
+//! circular DMA buffer, and returns each half of the buffer when it is
+//! complete. This suits block based processing, such as audio.
+//!
+//! ```
+//! let mut adc = AdcAsync::new(adc1.enable(), streams.0, buffer);
+//! adc.start(&mut pin);
+//!
+//! loop {
+//!     let block = adc.next().await?;
+//!     // process the block whilst the DMA writes the other half
+//! }
+//!
+//! #[interrupt]
+//! fn DMA1_STR0() {
+//!     Stream0::<DMA1>::on_interrupt();
+//! }
+//! ```
+//!
 //! # Examples
 //!
 //! - [Reading a voltage using ADC1](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/adc.rs)
@@ -10,6 +114,7 @@
 //! - [Using ADC1 and ADC2 together](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/adc12.rs)
 //! - [Using ADC1 and ADC2 in parallel](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/adc12_parallel.rs)
 //! - [Using ADC1 through DMA](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/adc_dma.rs)
+//! - [DAC to ADC loopback, paced by a timer](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/dac_adc_loopback.rs)
 
 use crate::hal::adc::{Channel, OneShot};
 use crate::hal::blocking::delay::DelayUs;
@@ -33,13 +138,92 @@ use crate::gpio::{self, Analog};
 use crate::pwr::{current_vos, VoltageScale};
 use crate::rcc::rec::AdcClkSelGetter;
 use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::signature::{
+    TS_CAL1_TEMP, TS_CAL2_TEMP, TS_CAL_110, TS_CAL_30, VDDA_CALIB, VREFIN_CAL,
+};
 use crate::time::Hertz;
+use crate::timer::Trgo;
 
 #[cfg(any(feature = "rm0433", feature = "rm0399"))]
 pub type Resolution = crate::stm32::adc3::cfgr::RES_A;
 #[cfg(any(feature = "rm0455", feature = "rm0468"))]
 pub type Resolution = crate::stm32::adc1::cfgr::RES_A;
 
+/// External trigger for the regular group
+///
+/// The value of each variant is its ADC_CFGR.EXTSEL value
+//
+// Refer to RM0433 Rev 7 - Table 205
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AdcExtTrig {
+    Tim1Cc1 = 0,
+    Tim1Cc2 = 1,
+    Tim1Cc3 = 2,
+    Tim2Cc2 = 3,
+    Tim3Trgo = 4,
+    Tim4Cc4 = 5,
+    Exti11 = 6,
+    Tim8Trgo = 7,
+    Tim8Trgo2 = 8,
+    Tim1Trgo = 9,
+    Tim1Trgo2 = 10,
+    Tim2Trgo = 11,
+    Tim4Trgo = 12,
+    Tim6Trgo = 13,
+    Tim15Trgo = 14,
+    Tim3Cc4 = 15,
+}
+
+impl From<Trgo> for AdcExtTrig {
+    fn from(trigger: Trgo) -> Self {
+        match trigger {
+            Trgo::Tim1 => AdcExtTrig::Tim1Trgo,
+            Trgo::Tim2 => AdcExtTrig::Tim2Trgo,
+            Trgo::Tim4 => AdcExtTrig::Tim4Trgo,
+            Trgo::Tim6 => AdcExtTrig::Tim6Trgo,
+            Trgo::Tim8 => AdcExtTrig::Tim8Trgo,
+        }
+    }
+}
+
+/// External trigger for the injected group
+///
+/// The value of each variant is its ADC_JSQR.JEXTSEL value
+//
+// Refer to RM0433 Rev 7 - Table 206
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AdcInjectedTrig {
+    Tim1Trgo = 0,
+    Tim1Cc4 = 1,
+    Tim2Trgo = 2,
+    Tim2Cc1 = 3,
+    Tim3Cc4 = 4,
+    Tim4Trgo = 5,
+    Exti15 = 6,
+    Tim8Cc4 = 7,
+    Tim1Trgo2 = 8,
+    Tim8Trgo = 9,
+    Tim8Trgo2 = 10,
+    Tim3Cc3 = 11,
+    Tim3Trgo = 12,
+    Tim3Cc1 = 13,
+    Tim6Trgo = 14,
+    Tim15Trgo = 15,
+}
+
+/// Edge of an external trigger that starts a conversion
+///
+/// The value of each variant is its ADC_CFGR.EXTEN / ADC_JSQR.JEXTEN value
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TriggerEdge {
+    Rising = 0b01,
+    Falling = 0b10,
+    Both = 0b11,
+}
+
 trait NumberOfBits {
     fn number_of_bits(&self) -> u32;
 }
@@ -68,10 +252,14 @@ impl ED for Disabled {}
 pub struct Adc<ADC, ED> {
     rb: ADC,
     sample_time: AdcSampleTime,
+    channel_sample_time: [Option<AdcSampleTime>; 20],
     resolution: Resolution,
     lshift: AdcLshift,
     clock: Hertz,
     current_channel: Option<u8>,
+    external_trigger: Option<(AdcExtTrig, TriggerEdge)>,
+    injected_trigger: Option<(AdcInjectedTrig, TriggerEdge)>,
+    discontinuous: Option<u8>,
     _enabled: PhantomData<ED>,
 }
 
@@ -171,6 +359,18 @@ impl AdcLshift {
 pub struct AdcCalOffset(u16);
 
 impl AdcCalOffset {
+    /// Creates an offset calibration factor from a value previously returned
+    /// by [`value`](Self::value)
+    ///
+    /// Only values in range of 0..=0x7FF are allowed.
+    pub fn new(offset: u16) -> Self {
+        if offset > 0x7FF {
+            panic!("CALFACT must be in range of 0..=0x7FF");
+        }
+
+        AdcCalOffset(offset)
+    }
+
     pub fn value(self) -> u16 {
         self.0
     }
@@ -181,11 +381,128 @@ impl AdcCalOffset {
 pub struct AdcCalLinear([u32; 6]);
 
 impl AdcCalLinear {
+    /// Creates linearity calibration factors from values previously returned
+    /// by [`value`](Self::value)
+    ///
+    /// Only values in range of 0..=0x3FFF_FFFF are allowed.
+    pub fn new(linear: [u32; 6]) -> Self {
+        if linear.iter().any(|&word| word > 0x3FFF_FFFF) {
+            panic!("LINCALFACT must be in range of 0..=0x3FFF_FFFF");
+        }
+
+        AdcCalLinear(linear)
+    }
+
     pub fn value(self) -> [u32; 6] {
         self.0
     }
 }
 
+/// ADC calibration factors
+///
+/// The factors from a calibration can be saved, for example to flash, and
+/// restored on a later boot with the same supply and temperature conditions
+/// rather than running the calibration again.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AdcCalFactors {
+    /// Offset calibration factor for single ended channels
+    pub single_ended: AdcCalOffset,
+    /// Offset calibration factor for differential channels
+    pub differential: AdcCalOffset,
+    /// Linearity calibration factors
+    pub linear: AdcCalLinear,
+}
+
+/// ADC calibration mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AdcCalMode {
+    /// Offset calibration for single ended channels
+    SingleEnded,
+    /// Offset calibration for differential channels
+    Differential,
+    /// Offset calibration for single ended channels, and linearity
+    /// calibration. The linearity calibration is much slower than the offset
+    /// calibration
+    SingleEndedLinearity,
+}
+
+// Refer to DS12110 Rev 8 - Chapter 6.3.22 to 6.3.24
+/// Startup time of the temperature sensor, in µs
+const TEMPERATURE_START_US: u8 = 26;
+/// Startup time of the VREFINT buffer, in µs
+const VREFINT_START_US: u8 = 5;
+/// Minimum sampling time of the temperature sensor, in ns
+const TEMPERATURE_SAMPLE_NS: u32 = 9_000;
+/// Minimum sampling time of VREFINT, in ns
+const VREFINT_SAMPLE_NS: u32 = 4_300;
+/// Minimum sampling time of VBAT, in ns
+const VBAT_SAMPLE_NS: u32 = 9_000;
+/// The VBAT channel measures VBAT through a divide-by-4 bridge
+const VBAT_DIVIDER: u32 = 4;
+
+/// Returns the shortest sampling time that is at least as long as `current`
+/// and at least `t_min_ns` at an ADC clock of `clock`
+fn internal_sample_time(
+    current: AdcSampleTime,
+    clock: Hertz,
+    t_min_ns: u32,
+) -> AdcSampleTime {
+    use AdcSampleTime::*;
+
+    // cycles_x2 / (2 * clock) >= t_min_ns / 1e9
+    let min_cycles_x2 = 2 * t_min_ns as u64 * clock.raw() as u64;
+    [T_1, T_2, T_8, T_16, T_32, T_64, T_387, T_810]
+        .into_iter()
+        .find(|t| {
+            t.clock_cycles_x2() >= current.clock_cycles_x2()
+                && t.clock_cycles_x2() as u64 * 1_000_000_000 >= min_cycles_x2
+        })
+        .unwrap_or(T_810)
+}
+
+/// Maximum length of the regular sequence
+const MAX_SEQUENCE_LEN: usize = 16;
+
+/// Returns the ADC_SQR1 to ADC_SQR4 values for the regular sequence
+/// `channels`
+fn sequence_registers(channels: &[u8]) -> [u32; 4] {
+    // L is the sequence length - 1, followed by SQ1 - SQ4 in ADC_SQR1. The
+    // following registers hold five channels each
+    let mut sqr = [channels.len() as u32 - 1, 0, 0, 0];
+    for (rank, &chan) in channels.iter().enumerate() {
+        let (reg, slot) = match rank {
+            0..=3 => (0, rank + 1),
+            _ => (1 + (rank - 4) / 5, (rank - 4) % 5),
+        };
+        sqr[reg] |= (chan as u32) << (6 * slot);
+    }
+    sqr
+}
+
+/// Returns VREF+ in millivolts, from a 16-bit conversion of VREFINT
+fn vref_mv(vrefint_cal: u16, vrefint: u32) -> u32 {
+    VDDA_CALIB * vrefint_cal as u32 / vrefint.max(1)
+}
+
+/// Returns the temperature in °C, from a 16-bit conversion of the temperature
+/// sensor referenced to `vref_mv`
+fn temperature(ts_cal1: u16, ts_cal2: u16, ts_data: u32, vref_mv: u32) -> f32 {
+    // The calibration values are measured with VREF+ = VDDA_CALIB
+    let ts_data = ts_data as f32 * vref_mv as f32 / VDDA_CALIB as f32;
+    let slope = (TS_CAL2_TEMP - TS_CAL1_TEMP) as f32
+        / (ts_cal2 as f32 - ts_cal1 as f32);
+
+    slope * (ts_data - ts_cal1 as f32) + TS_CAL1_TEMP as f32
+}
+
+/// Returns VBAT in millivolts, from a 16-bit conversion of the VBAT channel
+/// referenced to `vref_mv`
+fn vbat_mv(vbat: u32, vref_mv: u32) -> u32 {
+    ((VBAT_DIVIDER as u64 * vbat as u64 * vref_mv as u64) >> 16) as u32
+}
+
 macro_rules! adc_pins {
     ($ADC:ident, $($input:ty => $chan:expr),+ $(,)*) => {
         $(
@@ -228,6 +545,85 @@ macro_rules! adc_internal {
 
             adc_pins!($INT_ADC, $input => $chan);
         )+
+
+        impl Adc<$INT_ADC, Enabled> {
+            /// Converts an internal channel with a sampling time of at
+            /// least `t_min_ns`, and returns the sample scaled to 16 bits
+            /// like the factory calibration values
+            fn read_internal<PIN>(&mut self, pin: &mut PIN, t_min_ns: u32) -> u32
+                where PIN: Channel<$INT_ADC, ID = u8>,
+            {
+                let sample_time = self.get_sample_time();
+                self.set_sample_time(internal_sample_time(
+                    sample_time, self.clock_frequency(), t_min_ns));
+
+                self.start_conversion(pin);
+                let word = block!(self.read_sample()).unwrap();
+
+                self.set_sample_time(sample_time);
+                (((word as u64) << 16) / self.slope() as u64) as u32
+            }
+
+            /// Measures the internal reference voltage VREFINT, and returns
+            /// the voltage of VREF+ in millivolts using the factory
+            /// calibration [`VREFIN_CAL`].
+            ///
+            /// VREFINT is enabled in the ADC common register if it is not
+            /// already, in which case `delay` is used to wait for it to start.
+            /// It remains enabled afterwards. There must not be an ongoing
+            /// conversion on any ADC sharing the common register.
+            pub fn read_vref_mv(&mut self, delay: &mut impl DelayUs<u8>) -> u32 {
+                let common = unsafe { &*$INT_ADC_COMMON::ptr() };
+                if common.ccr.read().vrefen().is_disabled() {
+                    common.ccr.modify(|_, w| w.vrefen().enabled());
+                    delay.delay_us(VREFINT_START_US);
+                }
+
+                let vrefint = self.read_internal(&mut Vrefint, VREFINT_SAMPLE_NS);
+                vref_mv(VREFIN_CAL::read(), vrefint)
+            }
+
+            /// Measures the internal temperature sensor, and returns the
+            /// junction temperature in °C using the factory calibrations
+            /// [`TS_CAL_30`] and [`TS_CAL_110`]. VREF+ is measured with
+            /// [`read_vref_mv`](#method.read_vref_mv) first.
+            ///
+            /// The temperature sensor is enabled in the ADC common register if
+            /// it is not already, in which case `delay` is used to wait for it
+            /// to start. It remains enabled afterwards. There must not be an
+            /// ongoing conversion on any ADC sharing the common register.
+            pub fn read_temperature(&mut self, delay: &mut impl DelayUs<u8>) -> f32 {
+                let vref_mv = self.read_vref_mv(delay);
+
+                let common = unsafe { &*$INT_ADC_COMMON::ptr() };
+                if common.ccr.read().vsenseen().is_disabled() {
+                    common.ccr.modify(|_, w| w.vsenseen().enabled());
+                    delay.delay_us(TEMPERATURE_START_US);
+                }
+
+                let ts_data = self.read_internal(&mut Temperature, TEMPERATURE_SAMPLE_NS);
+                temperature(TS_CAL_30::read(), TS_CAL_110::read(), ts_data, vref_mv)
+            }
+
+            /// Measures the VBAT channel, and returns the voltage of VBAT in
+            /// millivolts. VREF+ is measured with
+            /// [`read_vref_mv`](#method.read_vref_mv) first.
+            ///
+            /// The VBAT channel is enabled in the ADC common register if it is
+            /// not already. It remains enabled afterwards, which draws a small
+            /// current from the battery through the bridge divider. There must
+            /// not be an ongoing conversion on any ADC sharing the common
+            /// register.
+            pub fn read_vbat_mv(&mut self, delay: &mut impl DelayUs<u8>) -> u32 {
+                let vref_mv = self.read_vref_mv(delay);
+
+                let common = unsafe { &*$INT_ADC_COMMON::ptr() };
+                common.ccr.modify(|_, w| w.vbaten().enabled());
+
+                let vbat = self.read_internal(&mut Vbat, VBAT_SAMPLE_NS);
+                vbat_mv(vbat, vref_mv)
+            }
+        }
     };
 }
 
@@ -424,6 +820,27 @@ pub fn adc12(
     delay: &mut impl DelayUs<u8>,
     prec: rec::Adc12,
     clocks: &CoreClocks,
+) -> (Adc<ADC1, Disabled>, Adc<ADC2, Disabled>) {
+    let (mut adc1, mut adc2) =
+        adc12_uncalibrated(adc1, adc2, f_adc, delay, prec, clocks);
+    adc1.calibrate();
+    adc2.calibrate();
+
+    (adc1, adc2)
+}
+
+/// Initialise ADC12 together without calibrating them
+///
+/// Sets all configurable parameters to one-shot defaults. Calibration
+/// factors saved from an earlier calibration should be restored with
+/// `write_calibration_factors` after the ADCs are enabled.
+pub fn adc12_uncalibrated(
+    adc1: ADC1,
+    adc2: ADC2,
+    f_adc: impl Into<Hertz>,
+    delay: &mut impl DelayUs<u8>,
+    prec: rec::Adc12,
+    clocks: &CoreClocks,
 ) -> (Adc<ADC1, Disabled>, Adc<ADC2, Disabled>) {
     // Consume ADC register block, produce ADC1/2 with default settings
     let mut adc1 = Adc::<ADC1, Disabled>::default_from_rb(adc1);
@@ -442,15 +859,13 @@ pub fn adc12(
     // Reset peripheral
     let prec = prec.reset();
 
-    // Power Up, Preconfigure and Calibrate
+    // Power Up and Preconfigure
     adc1.power_up(delay);
     adc2.power_up(delay);
     let f_adc = adc1.configure_clock(f_adc.into(), prec, clocks); // ADC12_COMMON
     adc2.clock = f_adc;
     adc1.preconfigure();
     adc2.preconfigure();
-    adc1.calibrate();
-    adc2.calibrate();
 
     (adc1, adc2)
 }
@@ -515,6 +930,22 @@ macro_rules! adc_hal {
                 /// performs a boot-time calibration.
                 pub fn $adcX(adc: $ADC, f_adc: impl Into<Hertz>, delay: &mut impl DelayUs<u8>,
                              prec: rec::$Rec, clocks: &CoreClocks
+                ) -> Self {
+                    let mut adc = Self::new_uncalibrated(adc, f_adc, delay, prec, clocks);
+                    adc.calibrate();
+
+                    adc
+                }
+
+                /// Initialise ADC without calibrating it
+                ///
+                /// Sets all configurable parameters to one-shot defaults.
+                /// Calibration factors saved from an earlier calibration
+                /// should be restored with
+                /// [`write_calibration_factors`](#method.write_calibration_factors)
+                /// after the ADC is enabled.
+                pub fn new_uncalibrated(adc: $ADC, f_adc: impl Into<Hertz>, delay: &mut impl DelayUs<u8>,
+                             prec: rec::$Rec, clocks: &CoreClocks
                 ) -> Self {
                     // Consume ADC register block, produce Self with default
                     // settings
@@ -529,11 +960,10 @@ macro_rules! adc_hal {
                     // Reset peripheral
                     let prec = prec.reset();
 
-                    // Power Up, Preconfigure and Calibrate
+                    // Power Up and Preconfigure
                     adc.power_up(delay);
                     adc.configure_clock(f_adc.into(), prec, clocks);
                     adc.preconfigure();
-                    adc.calibrate();
 
                     adc
                 }
@@ -542,10 +972,14 @@ macro_rules! adc_hal {
                     Self {
                         rb,
                         sample_time: AdcSampleTime::default(),
+                        channel_sample_time: [None; 20],
                         resolution: Resolution::SixteenBit,
                         lshift: AdcLshift::default(),
                         clock: Hertz::from_raw(0),
                         current_channel: None,
+                        external_trigger: None,
+                        injected_trigger: None,
+                        discontinuous: None,
                         _enabled: PhantomData,
                     }
                 }
@@ -664,13 +1098,30 @@ macro_rules! adc_hal {
                 ///
                 /// Note: The ADC must be disabled
                 pub fn calibrate(&mut self) {
+                    self.calibrate_mode(AdcCalMode::SingleEndedLinearity);
+                }
+
+                /// Runs the calibration given by `mode`
+                ///
+                /// The offset calibration factors for single ended and
+                /// differential channels are separate, so changing a
+                /// channel between single ended and differential mode
+                /// requires a calibration in the new mode. The linearity
+                /// calibration applies to both.
+                ///
+                /// Note: The ADC must be disabled
+                pub fn calibrate_mode(&mut self, mode: AdcCalMode) {
                     // Refer to RM0433 Rev 7 - Chapter 25.4.8
                     self.check_calibration_conditions();
 
-                    // single channel (INNx equals to V_ref-)
+                    let (differential, linearity) = match mode {
+                        AdcCalMode::SingleEnded => (false, false),
+                        AdcCalMode::Differential => (true, false),
+                        AdcCalMode::SingleEndedLinearity => (false, true),
+                    };
                     self.rb.cr.modify(|_, w|
-                        w.adcaldif().clear_bit()
-                            .adcallin().set_bit()
+                        w.adcaldif().bit(differential)
+                            .adcallin().bit(linearity)
                     );
                     // calibrate
                     self.rb.cr.modify(|_, w| w.adcal().set_bit());
@@ -731,6 +1182,69 @@ macro_rules! adc_hal {
                     });
                 }
 
+                /// Start regular conversions on an edge of an external trigger
+                ///
+                /// Once the ADC is enabled, each conversion started by
+                /// [`start_conversion`](#method.start_conversion) or
+                /// [`start_conversion_dma`](#method.start_conversion_dma)
+                /// waits for `edge` of `trigger`. In DMA mode, one conversion
+                /// is performed for each trigger event rather than converting
+                /// continuously.
+                pub fn set_external_trigger(&mut self, trigger: AdcExtTrig, edge: TriggerEdge) {
+                    self.external_trigger = Some((trigger, edge));
+                }
+
+                /// Start injected conversions on an edge of an external trigger
+                ///
+                /// Once the ADC is enabled, the injected conversion started by
+                /// [`start_injected_conversion`](#method.start_injected_conversion)
+                /// is repeated on each `edge` of `trigger`. This is typically
+                /// used to sample at a fixed point of a PWM period: for a
+                /// timer in center aligned mode, a compare channel that
+                /// matches at the top of the count samples at the center of
+                /// the active period.
+                pub fn set_injected_trigger(&mut self, trigger: AdcInjectedTrig, edge: TriggerEdge) {
+                    self.injected_trigger = Some((trigger, edge));
+                }
+
+                /// Convert `channels_per_trigger` channels of the regular
+                /// sequence on each trigger, rather than the whole sequence
+                ///
+                /// Each following trigger converts the next
+                /// `channels_per_trigger` channels. This applies to
+                /// conversions started by
+                /// [`start_conversion_dma`](#method.start_conversion_dma),
+                /// which are then not continuous: discontinuous mode (DISCEN)
+                /// and continuous mode (CONT) cannot both be set. An
+                /// [external trigger](#method.set_external_trigger) is
+                /// normally used to pace the conversions.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `channels_per_trigger` is not in the range 1 to 8
+                pub fn set_discontinuous_mode(&mut self, channels_per_trigger: u8) {
+                    assert!(
+                        (1..=8).contains(&channels_per_trigger),
+                        "Between 1 and 8 channels can be converted on each trigger"
+                    );
+                    self.discontinuous = Some(channels_per_trigger);
+                }
+
+                /// Convert the whole regular sequence on each trigger
+                ///
+                /// This is the default
+                pub fn clear_discontinuous_mode(&mut self) {
+                    self.discontinuous = None;
+                }
+
+                /// Start regular and injected conversions by software
+                ///
+                /// This is the default
+                pub fn clear_triggers(&mut self) {
+                    self.external_trigger = None;
+                    self.injected_trigger = None;
+                }
+
                 /// Enable ADC
                 pub fn enable(mut self) -> Adc<$ADC, Enabled> {
                     // Refer to RM0433 Rev 7 - Chapter 25.4.9
@@ -744,28 +1258,99 @@ macro_rules! adc_hal {
                     Adc {
                         rb: self.rb,
                         sample_time: self.sample_time,
+                        channel_sample_time: self.channel_sample_time,
                         resolution: self.resolution,
                         lshift: self.lshift,
                         clock: self.clock,
                         current_channel: None,
+                        external_trigger: self.external_trigger,
+                        injected_trigger: self.injected_trigger,
+                        discontinuous: self.discontinuous,
                         _enabled: PhantomData,
                     }
                 }
             }
 
             impl Adc<$ADC, Enabled> {
+                /// Returns the current calibration factors, so that they
+                /// can be restored with
+                /// [`write_calibration_factors`](#method.write_calibration_factors)
+                pub fn read_calibration_factors(&mut self) -> AdcCalFactors {
+                    let calfact = self.rb.calfact.read();
+                    let single_ended = AdcCalOffset(calfact.calfact_s().bits());
+                    let differential = AdcCalOffset(calfact.calfact_d().bits());
+
+                    AdcCalFactors {
+                        single_ended,
+                        differential,
+                        linear: self.read_linear_calibration_values(),
+                    }
+                }
+
+                /// Restores calibration factors returned by
+                /// [`read_calibration_factors`](#method.read_calibration_factors),
+                /// instead of running the calibration
+                ///
+                /// Note: No conversion may be ongoing
+                pub fn write_calibration_factors(&mut self, factors: AdcCalFactors) {
+                    // Refer to RM0433 Rev 7 - Chapter 25.4.8
+                    let cr = self.rb.cr.read();
+                    if cr.adstart().bit_is_set() || cr.jadstart().bit_is_set() {
+                        panic!("Cannot write calibration factors when a conversion is ongoing");
+                    }
+
+                    self.rb.calfact.write(|w| {
+                        w.calfact_s().bits(factors.single_ended.value())
+                            .calfact_d().bits(factors.differential.value())
+                    });
+
+                    let [res_1, res_2, res_3, res_4, res_5, res_6] = factors.linear.value();
+
+                    // Write each block of linear correction
+                    self.rb.calfact2.write(|w| w.lincalfact().bits(res_1));
+                    self.rb.cr.modify(|_, w| w.lincalrdyw1().set_bit());
+                    while self.rb.cr.read().lincalrdyw1().bit_is_clear() {}
+
+                    self.rb.calfact2.write(|w| w.lincalfact().bits(res_2));
+                    self.rb.cr.modify(|_, w| w.lincalrdyw2().set_bit());
+                    while self.rb.cr.read().lincalrdyw2().bit_is_clear() {}
+
+                    self.rb.calfact2.write(|w| w.lincalfact().bits(res_3));
+                    self.rb.cr.modify(|_, w| w.lincalrdyw3().set_bit());
+                    while self.rb.cr.read().lincalrdyw3().bit_is_clear() {}
+
+                    self.rb.calfact2.write(|w| w.lincalfact().bits(res_4));
+                    self.rb.cr.modify(|_, w| w.lincalrdyw4().set_bit());
+                    while self.rb.cr.read().lincalrdyw4().bit_is_clear() {}
+
+                    self.rb.calfact2.write(|w| w.lincalfact().bits(res_5));
+                    self.rb.cr.modify(|_, w| w.lincalrdyw5().set_bit());
+                    while self.rb.cr.read().lincalrdyw5().bit_is_clear() {}
+
+                    self.rb.calfact2.write(|w| w.lincalfact().bits(res_6));
+                    self.rb.cr.modify(|_, w| w.lincalrdyw6().set_bit());
+                    while self.rb.cr.read().lincalrdyw6().bit_is_clear() {}
+                }
+
                 fn stop_regular_conversion(&mut self) {
                     self.rb.cr.modify(|_, w| w.adstp().set_bit());
                     while self.rb.cr.read().adstp().bit_is_set() {}
                 }
 
-                fn stop_injected_conversion(&mut self) {
+                /// Stop injected conversions, including those started by an
+                /// external trigger
+                pub fn stop_injected_conversion(&mut self) {
+                    if self.rb.cr.read().jadstart().bit_is_clear() {
+                        return;
+                    }
                     self.rb.cr.modify(|_, w| w.jadstp().set_bit());
                     while self.rb.cr.read().jadstp().bit_is_set() {}
                 }
 
                 fn set_chan_smp(&mut self, chan: u8) {
-                    let t = self.get_sample_time().into();
+                    let t = self.channel_sample_time[chan as usize]
+                        .unwrap_or(self.sample_time)
+                        .into();
                     if chan <= 9 {
                         self.rb.smpr1.modify(|_, w| match chan {
                             0 => w.smp0().bits(t),
@@ -817,6 +1402,202 @@ macro_rules! adc_hal {
                     self.rb.cr.modify(|_, w| w.adstart().set_bit());
                 }
 
+                /// Writes the regular sequence `channels` and preselects
+                /// its channels
+                fn write_sequence(&mut self, channels: &[u8]) {
+                    assert!(
+                        !channels.is_empty() && channels.len() <= MAX_SEQUENCE_LEN,
+                        "The regular sequence must have between 1 and 16 channels"
+                    );
+                    assert!(channels.iter().all(|&chan| chan <= 19));
+
+                    // Set LSHIFT[3:0]
+                    self.rb.cfgr2.modify(|_, w| w.lshift().bits(self.get_lshift().value()));
+
+                    // Select channels (with preselection, refer to RM0433 Rev 7 - Chapter 25.4.12)
+                    for &chan in channels {
+                        self.rb.pcsel.modify(|r, w| unsafe { w.pcsel().bits(r.pcsel().bits() | (1 << chan)) });
+                        self.set_chan_smp(chan);
+                    }
+
+                    let [sqr1, sqr2, sqr3, sqr4] = sequence_registers(channels);
+                    self.rb.sqr1.write(|w| unsafe { w.bits(sqr1) });
+                    self.rb.sqr2.write(|w| unsafe { w.bits(sqr2) });
+                    self.rb.sqr3.write(|w| unsafe { w.bits(sqr3) });
+                    self.rb.sqr4.write(|w| unsafe { w.bits(sqr4) });
+                    self.current_channel = None;
+                }
+
+                /// Convert the regular sequence `channels` once, and store
+                /// the results in order in `results`
+                ///
+                /// Blocks until all the channels have been converted. If an
+                /// [external trigger](#method.set_external_trigger) is set,
+                /// the conversion of the sequence starts on the trigger.
+                /// Discontinuous mode is not used.
+                ///
+                /// ```
+                /// let mut results = [0; 3];
+                /// adc1.convert_sequence(&[0, 5, 10], &mut results);
+                /// ```
+                ///
+                /// # Panics
+                ///
+                /// Panics if the sequence is empty or longer than 16
+                /// channels, if a channel number is greater than 19, or if
+                /// `results` is shorter than the sequence.
+                // Refer to RM0433 Rev 7 - Chapter 25.4.15
+                pub fn convert_sequence(&mut self, channels: &[u8], results: &mut [u32]) {
+                    assert!(results.len() >= channels.len(), "Too few results for the sequence");
+                    self.check_conversion_conditions();
+
+                    // Set resolution
+                    self.rb.cfgr.modify(|_, w| unsafe { w.res().bits(self.get_resolution().into()) });
+                    // Single conversion of the whole sequence, results in DR
+                    self.rb.cfgr.modify(|_, w| {
+                        w.cont().clear_bit().discen().clear_bit().dmngt().bits(0b00)
+                    });
+                    self.write_external_trigger();
+                    self.write_sequence(channels);
+
+                    // Perform conversions
+                    self.rb.isr.write(|w| w.eoc().set_bit().eos().set_bit().ovr().set_bit());
+                    self.rb.cr.modify(|_, w| w.adstart().set_bit());
+
+                    for result in results[..channels.len()].iter_mut() {
+                        while self.rb.isr.read().eoc().bit_is_clear() {}
+                        // Reading DR clears EOC
+                        *result = self.rb.dr.read().bits();
+                    }
+                    self.rb.isr.write(|w| w.eos().set_bit());
+
+                    // Disable preselection of the channels
+                    for &chan in channels {
+                        self.rb.pcsel.modify(|r, w| unsafe { w.pcsel().bits(r.pcsel().bits() & !(1 << chan)) });
+                    }
+                }
+
+                /// Start conversions of the regular sequence `channels` in
+                /// DMA mode
+                ///
+                /// Like [`start_conversion_dma`](#method.start_conversion_dma),
+                /// but the DMA transfers the result of each channel of the
+                /// sequence in turn.
+                ///
+                /// # Panics
+                ///
+                /// Panics if the sequence is empty or longer than 16
+                /// channels, or if a channel number is greater than 19.
+                pub fn start_sequence_dma(&mut self, channels: &[u8], mode: AdcDmaMode) {
+                    self.check_conversion_conditions();
+
+                    // Set resolution
+                    self.rb.cfgr.modify(|_, w| unsafe { w.res().bits(self.get_resolution().into()) });
+
+                    self.rb.cfgr.modify(|_, w| w.dmngt().bits(match mode {
+                        AdcDmaMode::OneShot => 0b01,
+                        AdcDmaMode::Circular => 0b11,
+                    }));
+
+                    self.write_dma_conversion_mode();
+                    self.write_external_trigger();
+                    self.write_sequence(channels);
+
+                    // Perform conversions
+                    self.rb.cr.modify(|_, w| w.adstart().set_bit());
+                }
+
+                /// Writes the continuous or discontinuous mode for DMA
+                /// conversions
+                fn write_dma_conversion_mode(&mut self) {
+                    // Set continuous mode, unless each conversion is triggered.
+                    // Continuous and discontinuous modes cannot both be set
+                    match self.discontinuous {
+                        Some(n) => self.rb.cfgr.modify(|_, w| {
+                            w.cont().clear_bit().discen().set_bit().discnum().bits(n - 1)
+                        }),
+                        None => {
+                            let cont = self.external_trigger.is_none();
+                            self.rb.cfgr.modify(|_, w| w.cont().bit(cont).discen().clear_bit());
+                        }
+                    }
+                }
+
+                /// Writes the external trigger of the regular group
+                fn write_external_trigger(&mut self) {
+                    let trigger = self.external_trigger;
+                    self.rb.cfgr.modify(|_, w| match trigger {
+                        Some((trigger, edge)) => unsafe {
+                            w.extsel().bits(trigger as u8).exten().bits(edge as u8)
+                        },
+                        None => w.exten().disabled(),
+                    });
+                }
+
+                /// Start injected conversion
+                ///
+                /// This method starts an injected conversion on the given pin.
+                /// If an [injected trigger](#method.set_injected_trigger) is
+                /// set, the conversion is performed on every trigger event
+                /// until [`stop_injected_conversion`](#method.stop_injected_conversion)
+                /// is called. Otherwise a single conversion is started. The
+                /// value can be then read through the
+                /// [`read_injected_sample`](#method.read_injected_sample) method.
+                // Refer to RM0433 Rev 7 - Chapter 25.4.21
+                pub fn start_injected_conversion<PIN>(&mut self, _pin: &mut PIN)
+                    where PIN: Channel<$ADC, ID = u8>,
+                {
+                    let chan = PIN::channel();
+                    assert!(chan <= 19);
+
+                    let cr = self.rb.cr.read();
+                    if cr.jadstart().bit_is_set() {
+                        panic!("Cannot start conversion because an injected conversion is ongoing");
+                    }
+                    if cr.aden().bit_is_clear() {
+                        panic!("Cannot start conversion because ADC is currently disabled");
+                    }
+
+                    // Set resolution and LSHIFT[3:0]
+                    self.rb.cfgr.modify(|_, w| unsafe { w.res().bits(self.get_resolution().into()) });
+                    self.rb.cfgr2.modify(|_, w| w.lshift().bits(self.get_lshift().value()));
+
+                    // Select channel (with preselection, refer to RM0433 Rev 7 - Chapter 25.4.12)
+                    self.rb.pcsel.modify(|r, w| unsafe { w.pcsel().bits(r.pcsel().bits() | (1 << chan)) });
+                    self.set_chan_smp(chan);
+
+                    let trigger = self.injected_trigger;
+                    self.rb.jsqr.write(|w| unsafe {
+                        let w = w.jsq1().bits(chan).jl().bits(0);
+                        match trigger {
+                            Some((trigger, edge)) => {
+                                w.jextsel().bits(trigger as u8).jexten().bits(edge as u8)
+                            }
+                            None => w.jexten().disabled(),
+                        }
+                    });
+
+                    // Perform conversion
+                    self.rb.isr.write(|w| w.jeoc().set_bit());
+                    self.rb.cr.modify(|_, w| w.jadstart().set_bit());
+                }
+
+                /// Read injected sample
+                ///
+                /// `nb::Error::WouldBlock` in case no injected conversion has
+                /// completed since the last sample was read.
+                pub fn read_injected_sample(&mut self) -> nb::Result<u32, Infallible> {
+                    // Check if the conversion is finished
+                    if self.rb.isr.read().jeoc().bit_is_clear() {
+                        return Err(nb::Error::WouldBlock);
+                    }
+                    self.rb.isr.write(|w| w.jeoc().set_bit());
+
+                    // Retrieve result
+                    let result = self.rb.jdr1.read().bits();
+                    nb::Result::Ok(result)
+                }
+
                 /// Start conversion
                 ///
                 /// This method starts a conversion sequence on the given pin.
@@ -832,6 +1613,7 @@ macro_rules! adc_hal {
                     self.rb.cfgr.modify(|_, w| unsafe { w.res().bits(self.get_resolution().into()) });
                     // Set discontinuous mode
                     self.rb.cfgr.modify(|_, w| w.cont().clear_bit().discen().set_bit());
+                    self.write_external_trigger();
 
                     self.start_conversion_common(chan);
                 }
@@ -855,8 +1637,39 @@ macro_rules! adc_hal {
                         AdcDmaMode::Circular => 0b11,
                     }));
 
-                    // Set continuous mode
-                    self.rb.cfgr.modify(|_, w| w.cont().set_bit().discen().clear_bit() );
+                    self.write_dma_conversion_mode();
+                    self.write_external_trigger();
+
+                    self.start_conversion_common(chan);
+                }
+
+                /// Start triggered conversions in DMA mode
+                ///
+                /// Like [`start_conversion_dma`](#method.start_conversion_dma),
+                /// but a single conversion is performed on each rising edge
+                /// of `trigger` instead of converting continuously
+                pub fn start_triggered_conversion_dma<PIN>(&mut self, _pin: &mut PIN, mode: AdcDmaMode, trigger: Trgo)
+                    where PIN: Channel<$ADC, ID = u8>,
+                {
+                    let chan = PIN::channel();
+                    assert!(chan <= 19);
+
+                    // Set resolution
+                    self.rb.cfgr.modify(|_, w| unsafe { w.res().bits(self.get_resolution().into()) });
+
+                    self.rb.cfgr.modify(|_, w| w.dmngt().bits(match mode {
+                        AdcDmaMode::OneShot => 0b01,
+                        AdcDmaMode::Circular => 0b11,
+                    }));
+
+                    // Single conversion mode, hardware trigger
+                    // Refer to RM0433 Rev 7 - Chapter 25.4.19
+                    self.rb.cfgr.modify(|_, w| unsafe {
+                        w.cont().clear_bit()
+                            .discen().clear_bit()
+                            .extsel().bits(AdcExtTrig::from(trigger) as u8)
+                            .exten().rising_edge()
+                    });
 
                     self.start_conversion_common(chan);
                 }
@@ -919,10 +1732,14 @@ macro_rules! adc_hal {
                     Adc {
                         rb: self.rb,
                         sample_time: self.sample_time,
+                        channel_sample_time: self.channel_sample_time,
                         resolution: self.resolution,
                         lshift: self.lshift,
                         clock: self.clock,
                         current_channel: None,
+                        external_trigger: self.external_trigger,
+                        injected_trigger: self.injected_trigger,
+                        discontinuous: self.discontinuous,
                         _enabled: PhantomData,
                     }
                 }
@@ -1004,6 +1821,46 @@ macro_rules! adc_hal {
                     self.sample_time = t_samp;
                 }
 
+                /// Set the sampling time of channel `chan`, overriding the
+                /// sampling time set by
+                /// [`set_sample_time`](#method.set_sample_time) for this channel
+                ///
+                /// High impedance sources need a longer sampling time to
+                /// charge the sampling capacitor. Refer to the
+                /// datasheet for the sampling time required for a given
+                /// source impedance.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `chan` is not a channel index, 0 to 19
+                pub fn set_channel_sample_time(&mut self, chan: u8, t_samp: AdcSampleTime) {
+                    assert!(chan <= 19, "ADC channel index must be 0 to 19");
+                    self.channel_sample_time[chan as usize] = Some(t_samp);
+                }
+
+                /// Returns the sampling time of channel `chan`
+                ///
+                /// # Panics
+                ///
+                /// Panics if `chan` is not a channel index, 0 to 19
+                pub fn get_channel_sample_time(&self, chan: u8) -> AdcSampleTime {
+                    assert!(chan <= 19, "ADC channel index must be 0 to 19");
+                    self.channel_sample_time[chan as usize].unwrap_or(self.sample_time)
+                }
+
+                /// Removes the sampling time set for channel `chan` by
+                /// [`set_channel_sample_time`](#method.set_channel_sample_time),
+                /// so that it uses the sampling time set by
+                /// [`set_sample_time`](#method.set_sample_time)
+                ///
+                /// # Panics
+                ///
+                /// Panics if `chan` is not a channel index, 0 to 19
+                pub fn clear_channel_sample_time(&mut self, chan: u8) {
+                    assert!(chan <= 19, "ADC channel index must be 0 to 19");
+                    self.channel_sample_time[chan as usize] = None;
+                }
+
                 /// Set ADC sampling resolution
                 pub fn set_resolution(&mut self, res: Resolution) {
                     self.resolution = res;
@@ -1140,3 +1997,80 @@ adc_hal!(
 adc_hal!(ADC3, ADC3_COMMON: (adc3, Adc3, ldordy));
 #[cfg(feature = "rm0468")]
 adc_hal!(ADC3, ADC3_COMMON: (adc3, Adc3));
+
+// Declared after the macros above, which define the methods it uses
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub use asynch::{AdcAsync, AdcAsyncError};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::Hertz;
+
+    #[test]
+    fn internal_sample_time_minimum() {
+        let clock = Hertz::MHz(4);
+        // 9µs at 4MHz is 36 cycles
+        assert_eq!(
+            internal_sample_time(AdcSampleTime::T_1, clock, 9_000),
+            AdcSampleTime::T_64
+        );
+        // A longer configured sampling time is kept
+        assert_eq!(
+            internal_sample_time(AdcSampleTime::T_387, clock, 9_000),
+            AdcSampleTime::T_387
+        );
+        // The longest sampling time is used if none are long enough
+        assert_eq!(
+            internal_sample_time(AdcSampleTime::T_1, Hertz::MHz(100), 9_000),
+            AdcSampleTime::T_810
+        );
+    }
+
+    #[test]
+    fn timer_trigger_extsel() {
+        assert_eq!(AdcExtTrig::from(Trgo::Tim1) as u8, 9);
+        assert_eq!(AdcExtTrig::from(Trgo::Tim2) as u8, 11);
+        assert_eq!(AdcExtTrig::from(Trgo::Tim4) as u8, 12);
+        assert_eq!(AdcExtTrig::from(Trgo::Tim6) as u8, 13);
+        assert_eq!(AdcExtTrig::from(Trgo::Tim8) as u8, 7);
+    }
+
+    #[test]
+    fn calibrated_values() {
+        // VREFINT reads as its calibration value when VREF+ = VDDA_CALIB
+        assert_eq!(vref_mv(0x5C00, 0x5C00), VDDA_CALIB);
+        assert_eq!(vref_mv(0x5C00, 0x5C00 * 33 / 25), 2500);
+
+        // The calibration readings are at the calibration temperatures
+        let (cal1, cal2) = (12_000, 15_000);
+        let t1 = temperature(cal1, cal2, cal1 as u32, VDDA_CALIB);
+        let t2 = temperature(cal1, cal2, cal2 as u32, VDDA_CALIB);
+        assert!((t1 - TS_CAL1_TEMP as f32).abs() < 1e-3);
+        assert!((t2 - TS_CAL2_TEMP as f32).abs() < 1e-3);
+
+        // Full scale VBAT is four times VREF+
+        assert_eq!(vbat_mv(1 << 16, 3300), 13_200);
+        assert_eq!(vbat_mv(1 << 15, 2500), 5000);
+    }
+
+    #[test]
+    fn sequence_register_values() {
+        assert_eq!(sequence_registers(&[7]), [7 << 6, 0, 0, 0]);
+        assert_eq!(
+            sequence_registers(&[0, 5, 10]),
+            [2 | (5 << 12) | (10 << 18), 0, 0, 0]
+        );
+
+        let channels: [u8; 16] =
+            [1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16];
+        let [sqr1, sqr2, sqr3, sqr4] = sequence_registers(&channels);
+        assert_eq!(sqr1, 15 | (1 << 6) | (2 << 12) | (3 << 18) | (4 << 24));
+        assert_eq!(sqr2, 5 | (6 << 6) | (7 << 12) | (8 << 18) | (9 << 24));
+        assert_eq!(sqr3, 10 | (11 << 6) | (12 << 12) | (13 << 18) | (14 << 24));
+        assert_eq!(sqr4, 15 | (16 << 6));
+    }
+}
@@ -0,0 +1,193 @@
+//! Async continuous conversions using DMA1 or DMA2
+
+use core::future::poll_fn;
+use core::sync::atomic::{self, Ordering};
+
+use super::{Adc, AdcDmaMode, Channel, Enabled};
+use crate::dma::dma::{AsyncStream, DmaConfig};
+use crate::dma::traits::TargetAddress;
+use crate::dma::{CurrentBuffer, DmaDirection, PeripheralToMemory};
+use crate::stm32::{ADC1, ADC2};
+
+#[cfg(not(feature = "rm0455"))]
+use crate::stm32::ADC3;
+
+type P2M = PeripheralToMemory;
+
+/// Errors from [`AdcAsync::next`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AdcAsyncError {
+    /// The DMA wrote to both halves of the buffer since the last call, so
+    /// at least one block of samples was lost
+    Overrun,
+    /// The DMA stream was disabled by a transfer error
+    Dma,
+}
+
+/// Half of the buffer of an [`AdcAsync`]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Half {
+    First,
+    Second,
+}
+
+/// Continuous ADC conversions into a circular DMA buffer, returning each
+/// half of the buffer when it is complete
+///
+/// The DMA writes to the two halves of the buffer in turn. Each call to
+/// [`next`](AdcAsync::next) waits until the half after the one that was
+/// last returned is complete, and returns it whilst the DMA writes to the
+/// other half. The interrupt handler for the stream must call
+/// [`on_interrupt`](AsyncStream::on_interrupt).
+///
+/// A block must be processed before the DMA completes the other half,
+/// otherwise the next call returns [`AdcAsyncError::Overrun`].
+///
+/// The buffer must be in memory that is accessible by DMA1 and DMA2, and
+/// must not be cached by the data cache.
+pub struct AdcAsync<ADC, STREAM> {
+    adc: Adc<ADC, Enabled>,
+    stream: STREAM,
+    buffer: &'static mut [u16],
+    next: Half,
+}
+
+macro_rules! adc_async {
+    ($($ADC:ident),+) => {
+        $(
+            impl<STREAM: AsyncStream> AdcAsync<$ADC, STREAM> {
+                /// Creates an async ADC, converting into `buffer`
+                ///
+                /// # Panics
+                ///
+                /// Panics if the length of `buffer` is zero, odd, or more
+                /// than 65535 samples
+                pub fn new(
+                    adc: Adc<$ADC, Enabled>,
+                    stream: STREAM,
+                    buffer: &'static mut [u16],
+                ) -> Self {
+                    assert!(
+                        !buffer.is_empty()
+                            && buffer.len() % 2 == 0
+                            && buffer.len() <= u16::MAX as usize,
+                        "The buffer length must be even, and between 2 and 65534"
+                    );
+                    AdcAsync { adc, stream, buffer, next: Half::First }
+                }
+
+                /// Starts continuous conversions of `pin`
+                ///
+                /// If an external trigger is set, a conversion is made on
+                /// each trigger event instead.
+                pub fn start<PIN>(&mut self, pin: &mut PIN)
+                where
+                    PIN: Channel<$ADC, ID = u8>,
+                {
+                    self.stop();
+
+                    let stream = &mut self.stream;
+                    stream.clear_interrupts();
+                    stream.apply_config(
+                        DmaConfig::default()
+                            .memory_increment(true)
+                            .circular_buffer(true),
+                    );
+                    if let Some(request_line) =
+                        <Adc<$ADC, Enabled> as TargetAddress<P2M>>::REQUEST_LINE
+                    {
+                        stream.set_request_line(request_line);
+                    }
+                    stream.set_direction(DmaDirection::PeripheralToMemory);
+                    stream.set_number_of_transfers(self.buffer.len() as u16);
+
+                    // unsafe: the buffer is owned until the stream is
+                    // disabled by `stop`, and the data register is 16 bits
+                    unsafe {
+                        stream.set_peripheral_address(
+                            <Adc<$ADC, Enabled> as TargetAddress<P2M>>::address(&self.adc),
+                        );
+                        stream.set_memory_address(
+                            CurrentBuffer::Buffer0,
+                            self.buffer.as_mut_ptr() as usize,
+                        );
+                        stream.set_memory_size(1);
+                        stream.set_peripheral_size(1);
+                        stream.enable();
+                    }
+
+                    self.next = Half::First;
+                    self.adc.start_conversion_dma(pin, AdcDmaMode::Circular);
+                }
+
+                /// Stops the conversions and the DMA transfer
+                pub fn stop(&mut self) {
+                    self.adc.stop_regular_conversion();
+                    self.stream.disable();
+                }
+
+                /// Stops the conversions, and releases the ADC, the DMA
+                /// stream and the buffer
+                pub fn free(mut self) -> (Adc<$ADC, Enabled>, STREAM, &'static mut [u16]) {
+                    self.stop();
+                    (self.adc, self.stream, self.buffer)
+                }
+
+                /// Waits for the next half of the buffer to be complete, and
+                /// returns it
+                ///
+                /// On an overrun, this resynchronises with the DMA, so the
+                /// following call returns the next half that is completed.
+                pub async fn next(&mut self) -> Result<&[u16], AdcAsyncError> {
+                    let stream = &mut self.stream;
+                    if !poll_fn(|cx| stream.poll_circular(cx)).await {
+                        return Err(AdcAsyncError::Dma);
+                    }
+
+                    let half_transfer = STREAM::get_half_transfer_flag();
+                    let complete = STREAM::get_transfer_complete_flag();
+                    let (done, other) = match self.next {
+                        Half::First => (half_transfer, complete),
+                        Half::Second => (complete, half_transfer),
+                    };
+
+                    let half = self.buffer.len() / 2;
+                    if !done || other {
+                        // Both flags are set, so the DMA has written to the
+                        // half that would be returned again
+                        self.stream.clear_half_transfer_interrupt();
+                        self.stream.clear_transfer_complete_flag();
+                        self.next = if usize::from(STREAM::get_number_of_transfers()) > half {
+                            Half::First
+                        } else {
+                            Half::Second
+                        };
+                        return Err(AdcAsyncError::Overrun);
+                    }
+
+                    let range = match self.next {
+                        Half::First => {
+                            self.stream.clear_half_transfer_interrupt();
+                            self.next = Half::Second;
+                            0..half
+                        }
+                        Half::Second => {
+                            self.stream.clear_transfer_complete_flag();
+                            self.next = Half::First;
+                            half..2 * half
+                        }
+                    };
+
+                    // Ensure the samples are read after the DMA flags
+                    atomic::fence(Ordering::Acquire);
+                    Ok(&self.buffer[range])
+                }
+            }
+        )+
+    };
+}
+
+adc_async!(ADC1, ADC2);
+#[cfg(not(feature = "rm0455"))]
+adc_async!(ADC3);
@@ -0,0 +1,42 @@
+//! Bus sharing
+//!
+//! Several device drivers can share a single SPI or I2C bus. Each device
+//! borrows the bus for the duration of a transaction, and implements the
+//! embedded-hal 1.0 [`SpiDevice`](embedded_hal_1::spi::SpiDevice) or
+//! [`I2c`](embedded_hal_1::i2c::I2c) trait. SPI devices also own their chip
+//! select pin.
+//!
+//! Two kinds of sharing are provided:
+//!
+//! * `RefCellDevice` shares a bus held in a [`RefCell`](core::cell::RefCell).
+//!   All the devices must be used from the same execution context.
+//! * `CriticalSectionDevice` shares a bus held in a
+//!   [`critical_section::Mutex`]. Each transaction is performed within a
+//!   critical section, so the devices can be used from different
+//!   interrupt priorities.
+//!
+//! ```
+//! use core::cell::RefCell;
+//! use stm32h7xx_hal::bus;
+//!
+//! let spi: spi::Spi<_, _, u8> = dp.SPI1.spi(...);
+//! let spi = RefCell::new(spi);
+//!
+//! let mut flash = bus::spi::RefCellDevice::new(&spi, cs0, &ccdr.clocks);
+//! let mut display = bus::spi::RefCellDevice::new(&spi, cs1, &ccdr.clocks);
+//!
+//! let i2c = RefCell::new(dp.I2C1.i2c(...));
+//! let mut sensor = bus::i2c::RefCellDevice::new(&i2c);
+//! let mut eeprom = bus::i2c::RefCellDevice::new(&i2c);
+//! ```
+//!
+//! ```
+//! use core::cell::RefCell;
+//! use critical_section::Mutex;
+//!
+//! let i2c = Mutex::new(RefCell::new(dp.I2C1.i2c(...)));
+//! let mut sensor = bus::i2c::CriticalSectionDevice::new(&i2c);
+//! ```
+
+pub mod i2c;
+pub mod spi;
@@ -0,0 +1,67 @@
+//! Shared I2C bus
+
+use core::cell::RefCell;
+
+use critical_section::Mutex;
+use embedded_hal_1::i2c::{ErrorType, I2c, Operation};
+
+/// A device on an I2C bus held in a [`RefCell`], implementing the
+/// embedded-hal 1.0 [`I2c`] trait
+///
+/// The bus is borrowed for the duration of each transaction. Panics if the
+/// bus is already borrowed.
+pub struct RefCellDevice<'a, BUS> {
+    bus: &'a RefCell<BUS>,
+}
+
+impl<'a, BUS> RefCellDevice<'a, BUS> {
+    /// Creates a device on a shared I2C bus
+    pub fn new(bus: &'a RefCell<BUS>) -> Self {
+        RefCellDevice { bus }
+    }
+}
+
+impl<'a, BUS: ErrorType> ErrorType for RefCellDevice<'a, BUS> {
+    type Error = BUS::Error;
+}
+
+impl<'a, BUS: I2c> I2c for RefCellDevice<'a, BUS> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.bus.borrow_mut().transaction(address, operations)
+    }
+}
+
+/// A device on an I2C bus held in a [`critical_section::Mutex`],
+/// implementing the embedded-hal 1.0 [`I2c`] trait
+///
+/// Each transaction is performed within a critical section.
+pub struct CriticalSectionDevice<'a, BUS> {
+    bus: &'a Mutex<RefCell<BUS>>,
+}
+
+impl<'a, BUS> CriticalSectionDevice<'a, BUS> {
+    /// Creates a device on a shared I2C bus
+    pub fn new(bus: &'a Mutex<RefCell<BUS>>) -> Self {
+        CriticalSectionDevice { bus }
+    }
+}
+
+impl<'a, BUS: ErrorType> ErrorType for CriticalSectionDevice<'a, BUS> {
+    type Error = BUS::Error;
+}
+
+impl<'a, BUS: I2c> I2c for CriticalSectionDevice<'a, BUS> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        critical_section::with(|cs| {
+            self.bus.borrow_ref_mut(cs).transaction(address, operations)
+        })
+    }
+}
@@ -0,0 +1,139 @@
+//! Shared SPI bus
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+
+use critical_section::Mutex;
+
+use crate::hal;
+use crate::rcc::CoreClocks;
+use crate::spi::transaction;
+use crate::time::Hertz;
+
+/// A device on an SPI bus held in a [`RefCell`], implementing the
+/// embedded-hal 1.0 [`SpiDevice`](embedded_hal_1::spi::SpiDevice) trait
+///
+/// The bus is borrowed for the duration of each transaction, and the chip
+/// select pin is driven low. Panics if the bus is already borrowed.
+pub struct RefCellDevice<'a, BUS, CS> {
+    bus: &'a RefCell<BUS>,
+    cs: CS,
+    c_ck: Hertz,
+}
+
+impl<'a, BUS, CS> RefCellDevice<'a, BUS, CS>
+where
+    CS: hal::digital::v2::OutputPin<Error = Infallible>,
+{
+    /// Creates a device on a shared SPI bus, with its own chip select pin.
+    /// The chip select pin is set high
+    ///
+    /// The core clock is used to implement delay operations within a
+    /// transaction
+    pub fn new(bus: &'a RefCell<BUS>, mut cs: CS, clocks: &CoreClocks) -> Self {
+        cs.set_high().ok();
+
+        RefCellDevice {
+            bus,
+            cs,
+            c_ck: clocks.c_ck(),
+        }
+    }
+
+    /// Releases the chip select pin
+    pub fn free(self) -> CS {
+        self.cs
+    }
+}
+
+impl<'a, BUS, CS> embedded_hal_1::spi::ErrorType for RefCellDevice<'a, BUS, CS>
+where
+    BUS: embedded_hal_1::spi::ErrorType,
+{
+    type Error = BUS::Error;
+}
+
+impl<'a, W, BUS, CS> embedded_hal_1::spi::SpiDevice<W>
+    for RefCellDevice<'a, BUS, CS>
+where
+    W: Copy + 'static,
+    BUS: embedded_hal_1::spi::SpiBus<W>,
+    CS: hal::digital::v2::OutputPin<Error = Infallible>,
+{
+    fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal_1::spi::Operation<'_, W>],
+    ) -> Result<(), Self::Error> {
+        let bus = &mut *self.bus.borrow_mut();
+
+        transaction(bus, &mut self.cs, self.c_ck, operations)
+    }
+}
+
+/// A device on an SPI bus held in a [`critical_section::Mutex`],
+/// implementing the embedded-hal 1.0
+/// [`SpiDevice`](embedded_hal_1::spi::SpiDevice) trait
+///
+/// Each transaction is performed within a critical section, with the chip
+/// select pin driven low.
+pub struct CriticalSectionDevice<'a, BUS, CS> {
+    bus: &'a Mutex<RefCell<BUS>>,
+    cs: CS,
+    c_ck: Hertz,
+}
+
+impl<'a, BUS, CS> CriticalSectionDevice<'a, BUS, CS>
+where
+    CS: hal::digital::v2::OutputPin<Error = Infallible>,
+{
+    /// Creates a device on a shared SPI bus, with its own chip select pin.
+    /// The chip select pin is set high
+    ///
+    /// The core clock is used to implement delay operations within a
+    /// transaction
+    pub fn new(
+        bus: &'a Mutex<RefCell<BUS>>,
+        mut cs: CS,
+        clocks: &CoreClocks,
+    ) -> Self {
+        cs.set_high().ok();
+
+        CriticalSectionDevice {
+            bus,
+            cs,
+            c_ck: clocks.c_ck(),
+        }
+    }
+
+    /// Releases the chip select pin
+    pub fn free(self) -> CS {
+        self.cs
+    }
+}
+
+impl<'a, BUS, CS> embedded_hal_1::spi::ErrorType
+    for CriticalSectionDevice<'a, BUS, CS>
+where
+    BUS: embedded_hal_1::spi::ErrorType,
+{
+    type Error = BUS::Error;
+}
+
+impl<'a, W, BUS, CS> embedded_hal_1::spi::SpiDevice<W>
+    for CriticalSectionDevice<'a, BUS, CS>
+where
+    W: Copy + 'static,
+    BUS: embedded_hal_1::spi::SpiBus<W>,
+    CS: hal::digital::v2::OutputPin<Error = Infallible>,
+{
+    fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal_1::spi::Operation<'_, W>],
+    ) -> Result<(), Self::Error> {
+        critical_section::with(|cs| {
+            let bus = &mut *self.bus.borrow_ref_mut(cs);
+
+            transaction(bus, &mut self.cs, self.c_ck, operations)
+        })
+    }
+}
@@ -12,6 +12,7 @@
 //! | Extended 29-bit filters | 8
 //! | Rx FIFO 0 | 3 elements
 //! | Rx FIFO 1 | 3 elements
+//! | Tx event FIFO | 3 elements
 //! | Tx Buffers | 3 elements
 //!
 //! # Usage
@@ -34,6 +35,111 @@
 //! let mut can: FdCan<_, fdcan::ConfigMode> = dp.FDCAN1.fdcan(tx, rx, fdcan_prec);
 //! ```
 //!
+//! # Acceptance Filters
+//!
+//! The message RAM filter elements are written by the [fdcan] crate. Standard
+//! and extended ID filters support range, dual ID and mask matching via
+//! `FilterType`. The slot is given as a `StandardFilterSlot` or
+//! `ExtendedFilterSlot`, which are bounded by the filter list sizes in the
+//! table above, so an out-of-range slot cannot be written.
+//!
+//! ```
+//! use fdcan::config::{GlobalFilter, NonMatchingFilter};
+//! use fdcan::filter::{Action, ExtendedFilter, ExtendedFilterSlot, FilterType};
+//!
+//! can.set_standard_filter(
+//!     StandardFilterSlot::_0,
+//!     StandardFilter::accept_all_into_fifo0(),
+//! );
+//! can.set_extended_filter(
+//!     ExtendedFilterSlot::_0,
+//!     ExtendedFilter {
+//!         filter: FilterType::BitMask {
+//!             filter: 0x1800_0000,
+//!             mask: 0x1F00_0000,
+//!         },
+//!         action: Action::StoreInFifo1,
+//!     },
+//! );
+//!
+//! // Reject frames that do not match any filter
+//! can.set_global_filter(GlobalFilter {
+//!     handle_standard_frames: NonMatchingFilter::Reject,
+//!     handle_extended_frames: NonMatchingFilter::Reject,
+//!     reject_remote_standard_frames: true,
+//!     reject_remote_extended_frames: true,
+//! });
+//! ```
+//!
+//! # Bit Timing
+//!
+//! The nominal and data phase bit timings can be calculated from the FDCAN
+//! kernel clock with [nominal_bit_timing] and [data_bit_timing]. The data
+//! phase timing is only used for CAN-FD frames with Bit Rate Switching (BRS),
+//! which is enabled with `FrameTransmissionConfig::AllowFdCanAndBRS` and the
+//! `bit_rate_switching` field of each `TxFrameHeader`.
+//!
+//! ```
+//! let fdcan_ker_ck = ccdr.clocks.pll1_q_ck().unwrap();
+//!
+//! can.set_nominal_bit_timing(
+//!     can::nominal_bit_timing(fdcan_ker_ck, 500.kHz()).unwrap(),
+//! );
+//! can.set_data_bit_timing(can::data_bit_timing(fdcan_ker_ck, 2.MHz()).unwrap());
+//! ```
+//!
+//! # Timestamps
+//!
+//! The timestamp counter is configured with [Can::enable_timestamp]. With the
+//! internal source, it is incremented once every `prescaler` nominal bit
+//! times. Received frames carry the counter value captured at the start of
+//! frame in the `time_stamp` field of their `RxFrameInfo`.
+//!
+//! Frames transmitted with a `marker` in their `TxFrameHeader` are stored in
+//! the Tx event FIFO once they have been sent. The event can then be read
+//! with [Can::tx_event] to match the marker with its transmit timestamp.
+//!
+//! ```
+//! use stm32h7xx_hal::can::TimestampSource;
+//!
+//! // One count every 8 bit times
+//! can.instance().enable_timestamp(TimestampSource::Internal, 8);
+//!
+//! // ... after transmitting a frame with `marker: Some(7)`
+//! if let Some(event) = can.instance().tx_event() {
+//!     assert_eq!(event.marker, 7);
+//! }
+//! ```
+//!
+//! The counter is 16 bits wide and wraps around, so timestamps only order
+//! events that are less than 65536 counts apart.
+//!
+//! # Error Handling
+//!
+//! The transmit and receive error counters are returned by
+//! [Can::error_counters]. When the transmit error counter exceeds 255 the
+//! controller enters the bus-off state and stops participating in bus
+//! activity until [Can::request_bus_off_recovery] is called.
+//!
+//! A handler can be called when the controller enters the bus-off state. The
+//! FDCAN interrupt handler must call [Can::on_interrupt].
+//!
+//! ```
+//! use fdcan::interrupt::InterruptLine;
+//!
+//! fn bus_off() {
+//!     // ...
+//! }
+//!
+//! can.instance().on_bus_off(bus_off);
+//! can.enable_interrupt_line(InterruptLine::_0, true);
+//!
+//! #[interrupt]
+//! fn FDCAN1_IT0() {
+//!     Can::<pac::FDCAN1>::on_interrupt();
+//! }
+//! ```
+//!
 //! [fdcan]: https://docs.rs/fdcan
 //!
 //! # Examples
@@ -47,6 +153,14 @@ use crate::gpio::gpiod::{PD0, PD1};
 use crate::gpio::gpioh::{PH13, PH14};
 use crate::gpio::Alternate;
 use crate::rcc::{rec, rec::ResetEnable};
+use crate::time::Hertz;
+
+use core::mem;
+use core::num::{NonZeroU16, NonZeroU8};
+use core::ops::Deref;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use fdcan::config::{DataBitTiming, NominalBitTiming};
 
 /// Storage type for the CAN controller
 #[derive(Debug)]
@@ -60,6 +174,215 @@ impl<FDCAN> Can<FDCAN> {
     }
 }
 
+/// Base address of the Message RAM shared by all FDCAN instances
+const MESSAGE_RAM: usize = 0x4000_ac00;
+
+/// Transmit event, read from the Tx event FIFO
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TxEvent {
+    /// Message marker given in the `TxFrameHeader` of the transmitted frame
+    pub marker: u8,
+    /// Value of the timestamp counter at the start of frame transmission
+    pub timestamp: u16,
+}
+
+impl<FDCAN> Can<FDCAN>
+where
+    FDCAN: Deref<Target = crate::stm32::fdcan1::RegisterBlock>,
+{
+    /// Returns the oldest event in the Tx event FIFO, or `None` if the FIFO
+    /// is empty
+    ///
+    /// An event is stored for each frame that was transmitted with a
+    /// `marker` in its `TxFrameHeader`. The Tx event FIFO holds 3 events;
+    /// further events are lost until it is read.
+    pub fn tx_event(&mut self) -> Option<TxEvent> {
+        let can = self.inner();
+
+        let txefs = can.txefs.read();
+        if txefs.effl().bits() == 0 {
+            return None;
+        }
+
+        // Each element is two words. The start address is in words from the
+        // beginning of the Message RAM
+        let index = txefs.efgi().bits();
+        let word = can.txefc.read().efsa().bits() as usize + 2 * index as usize;
+        let element = (MESSAGE_RAM + 4 * word) as *const u32;
+        let t1 = unsafe { ptr::read_volatile(element.add(1)) };
+
+        // Acknowledge, freeing the element
+        can.txefa.write(|w| unsafe { w.efai().bits(index) });
+
+        Some(TxEvent {
+            marker: (t1 >> 24) as u8,
+            timestamp: t1 as u16,
+        })
+    }
+}
+
+/// Transmit and receive error counters, read from the error counter register
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ErrorCounters {
+    /// Transmit error counter
+    pub tx_errors: u8,
+    /// Receive error counter
+    pub rx_errors: u8,
+    /// Receive error passive. Set when the receive error counter has reached
+    /// the error passive level of 128
+    pub rp: bool,
+}
+
+/// Bus-off handlers called by [Can::on_interrupt]. Null if not set
+static BUS_OFF_HANDLERS: [AtomicPtr<()>; 2] = [
+    AtomicPtr::new(ptr::null_mut()),
+    AtomicPtr::new(ptr::null_mut()),
+];
+
+impl<FDCAN> Can<FDCAN>
+where
+    FDCAN:
+        Deref<Target = crate::stm32::fdcan1::RegisterBlock> + sealed::Instance,
+{
+    /// Returns the transmit and receive error counters
+    ///
+    /// The controller enters the bus-off state when the transmit error
+    /// counter exceeds 255.
+    pub fn error_counters(&self) -> ErrorCounters {
+        let ecr = self.inner().ecr.read();
+
+        ErrorCounters {
+            tx_errors: ecr.tec().bits(),
+            rx_errors: ecr.rec().bits(),
+            rp: ecr.rp().bit_is_set(),
+        }
+    }
+
+    /// Returns true if the controller is in the bus-off state
+    pub fn is_bus_off(&self) -> bool {
+        self.inner().psr.read().bo().bit_is_set()
+    }
+
+    /// Starts the bus-off recovery sequence
+    ///
+    /// On entering the bus-off state the controller sets `CCCR.INIT` and
+    /// stops all bus activity. Clearing `INIT` lets the controller rejoin
+    /// the bus once it has monitored 128 occurrences of 11 consecutive
+    /// recessive bits. Poll [is_bus_off](Can::is_bus_off) to find when the
+    /// recovery is complete.
+    pub fn request_bus_off_recovery(&mut self) {
+        self.inner().cccr.modify(|_, w| w.init().clear_bit());
+    }
+
+    /// Sets a handler that is called by [on_interrupt](Can::on_interrupt)
+    /// when the controller enters the bus-off state, and enables the
+    /// Bus_Off status interrupt
+    ///
+    /// The interrupt is signalled on the interrupt line selected for
+    /// `Interrupt::BusOff`, which must be enabled with
+    /// `enable_interrupt_line`.
+    pub fn on_bus_off(&mut self, handler: fn()) {
+        BUS_OFF_HANDLERS[FDCAN::INDEX]
+            .store(handler as *mut (), Ordering::Release);
+
+        self.inner().ie.modify(|_, w| w.boe().set_bit());
+    }
+
+    /// Handles the Bus_Off status interrupt. To be called from the FDCAN
+    /// interrupt handler
+    ///
+    /// If the Bus_Off status has changed, clears the interrupt flag. Then if
+    /// the controller is in the bus-off state, calls the handler passed to
+    /// [on_bus_off](Can::on_bus_off). Other interrupt flags are not
+    /// modified.
+    pub fn on_interrupt() {
+        // unsafe: the interrupt register is write-1-to-clear, and the
+        // protocol status register is only read
+        let can = unsafe { &*FDCAN::PTR };
+
+        if can.ir.read().bo().bit_is_clear() {
+            return;
+        }
+        can.ir.write(|w| w.bo().set_bit());
+
+        if can.psr.read().bo().bit_is_clear() {
+            return;
+        }
+
+        let handler = BUS_OFF_HANDLERS[FDCAN::INDEX].load(Ordering::Acquire);
+        if !handler.is_null() {
+            // unsafe: only ever set from a `fn()` in `on_bus_off`
+            let handler: fn() = unsafe { mem::transmute(handler) };
+            handler();
+        }
+    }
+}
+
+/// Source of the timestamp counter
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TimestampSource {
+    /// The counter is incremented once every `prescaler` nominal CAN bit
+    /// times
+    Internal,
+    /// The counter value is taken from the external timestamp counter
+    External,
+}
+
+impl<FDCAN> Can<FDCAN>
+where
+    FDCAN: Deref<Target = crate::stm32::fdcan1::RegisterBlock>,
+{
+    /// Configures and resets the timestamp counter
+    ///
+    /// With [TimestampSource::Internal], the 16-bit counter `TSCV.TSC` is
+    /// incremented once every `prescaler` nominal bit times, so one count is
+    /// `prescaler` bits at the nominal bit rate. The prescaler is written to
+    /// `TSCC.TCP` as `prescaler - 1`. With [TimestampSource::External] the
+    /// prescaler is not used.
+    ///
+    /// The counter value is captured at the start of each received frame,
+    /// in the `time_stamp` field of its `RxFrameInfo`, and at the start of
+    /// each transmitted frame in its [TxEvent].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `prescaler` is not in the range 1 to 16, or if the
+    /// controller is not in configuration mode.
+    pub fn enable_timestamp(&mut self, source: TimestampSource, prescaler: u8) {
+        assert!(
+            (1..=16).contains(&prescaler),
+            "FDCAN: Timestamp prescaler must be 1 to 16"
+        );
+
+        let can = self.inner();
+
+        // TSCC is only writable in configuration mode
+        let cccr = can.cccr.read();
+        assert!(
+            cccr.init().bit_is_set() && cccr.cce().bit_is_set(),
+            "FDCAN: Timestamp counter can only be configured in configuration mode"
+        );
+
+        let tss = match source {
+            TimestampSource::Internal => 0b01,
+            TimestampSource::External => 0b10,
+        };
+        can.tscc
+            .write(|w| unsafe { w.tcp().bits(prescaler - 1).tss().bits(tss) });
+
+        // Any write to TSCV resets the internal counter
+        can.tscv.write(|w| unsafe { w.bits(0) });
+    }
+
+    /// Returns the current value of the timestamp counter
+    pub fn timestamp(&self) -> u16 {
+        self.inner().tscv.read().tsc().bits()
+    }
+}
+
 /// Extension trait for CAN controller
 pub trait CanExt: Sized
 where
@@ -84,6 +407,101 @@ where
     ) -> fdcan::FdCan<Can<Self>, fdcan::ConfigMode>;
 }
 
+/// Finds the bit timing with the most time quanta per bit that exactly
+/// achieves `bit_rate` within the given register limits
+///
+/// Returns `(prescaler, seg1, seg2)`. The sample point is placed as close as
+/// possible to `sample_point` (in per mille), measured from the start of the
+/// bit including the synchronisation segment.
+fn bit_timing(
+    ker_ck: u32,
+    bit_rate: u32,
+    max_prescaler: u32,
+    max_seg1: u32,
+    max_seg2: u32,
+    sample_point: u32,
+) -> Option<(u32, u32, u32)> {
+    if bit_rate == 0 {
+        return None;
+    }
+
+    (1..=max_prescaler)
+        .take_while(|prescaler| {
+            prescaler
+                .checked_mul(bit_rate)
+                .map_or(false, |d| d <= ker_ck)
+        })
+        .filter(|prescaler| ker_ck % (prescaler * bit_rate) == 0)
+        .find_map(|prescaler| {
+            // Time quanta per bit
+            let tq = ker_ck / (prescaler * bit_rate);
+
+            // One time quantum is used by the synchronisation segment
+            let seg1 = ((tq * sample_point + 500) / 1000).checked_sub(1)?;
+            let seg2 = tq.checked_sub(1 + seg1)?;
+
+            if (1..=max_seg1).contains(&seg1) && (1..=max_seg2).contains(&seg2)
+            {
+                Some((prescaler, seg1, seg2))
+            } else {
+                None
+            }
+        })
+}
+
+/// Calculates the nominal (arbitration phase) bit timing for a given FDCAN
+/// kernel clock and bit rate
+///
+/// The sample point is placed at 87.5%, as recommended by CiA for bit rates
+/// up to 800 kbit/s. The synchronisation jump width is set to the maximum
+/// allowed value.
+///
+/// Returns `None` if the kernel clock is not an integer multiple of the bit
+/// rate that can be achieved with the available prescaler and segment
+/// lengths.
+pub fn nominal_bit_timing(
+    fdcan_ker_ck: Hertz,
+    bit_rate: Hertz,
+) -> Option<NominalBitTiming> {
+    let (prescaler, seg1, seg2) =
+        bit_timing(fdcan_ker_ck.raw(), bit_rate.raw(), 511, 255, 127, 875)?;
+
+    Some(NominalBitTiming {
+        prescaler: NonZeroU16::new(prescaler as u16)?,
+        seg1: NonZeroU8::new(seg1 as u8)?,
+        seg2: NonZeroU8::new(seg2 as u8)?,
+        sync_jump_width: NonZeroU8::new(seg2 as u8)?,
+    })
+}
+
+/// Calculates the data phase bit timing for CAN-FD frames with Bit Rate
+/// Switching, for a given FDCAN kernel clock and bit rate
+///
+/// The sample point is placed at 75%, which leaves margin for the
+/// transceiver loop delay at data phase bit rates of several Mbit/s. The
+/// synchronisation jump width is set to the maximum allowed value.
+/// Transceiver delay compensation is requested for bit rates of 1 Mbit/s and
+/// above.
+///
+/// Returns `None` if the kernel clock is not an integer multiple of the bit
+/// rate that can be achieved with the available prescaler and segment
+/// lengths.
+pub fn data_bit_timing(
+    fdcan_ker_ck: Hertz,
+    bit_rate: Hertz,
+) -> Option<DataBitTiming> {
+    let (prescaler, seg1, seg2) =
+        bit_timing(fdcan_ker_ck.raw(), bit_rate.raw(), 31, 31, 15, 750)?;
+
+    Some(DataBitTiming {
+        transceiver_delay_compensation: bit_rate.raw() >= 1_000_000,
+        prescaler: NonZeroU8::new(prescaler as u8)?,
+        seg1: NonZeroU8::new(seg1 as u8)?,
+        seg2: NonZeroU8::new(seg2 as u8)?,
+        sync_jump_width: NonZeroU8::new(seg2 as u8)?,
+    })
+}
+
 /// Configure Message RAM layout on H7 to match the fixed sized used on G4
 ///
 /// These are protected bits, write access is only possible when bit CCE and bit
@@ -157,6 +575,13 @@ mod sealed {
     pub trait Tx<FDCAN> {}
     /// An RX pin configured for CAN communication
     pub trait Rx<FDCAN> {}
+    /// An FDCAN instance
+    pub trait Instance {
+        /// Index of the instance, from zero
+        const INDEX: usize;
+        /// Pointer to the register block
+        const PTR: *const crate::stm32::fdcan1::RegisterBlock;
+    }
 }
 
 /// Implements sealed::{Tx,Rx} for pins associated with a CAN peripheral
@@ -231,6 +656,11 @@ mod fdcan1 {
             Can::fdcan1(self, prec)
         }
     }
+    impl super::sealed::Instance for FDCAN1 {
+        const INDEX: usize = 0;
+        const PTR: *const crate::stm32::fdcan1::RegisterBlock =
+            FDCAN1::ptr() as *const _;
+    }
     unsafe impl fdcan::Instance for Can<FDCAN1> {
         const REGISTERS: *mut fdcan::RegisterBlock = FDCAN1::ptr() as *mut _;
     }
@@ -267,6 +697,11 @@ mod fdcan2 {
             Can::fdcan2(self, prec)
         }
     }
+    impl super::sealed::Instance for FDCAN2 {
+        const INDEX: usize = 1;
+        const PTR: *const crate::stm32::fdcan1::RegisterBlock =
+            FDCAN2::ptr() as *const _;
+    }
     unsafe impl fdcan::Instance for Can<FDCAN2> {
         const REGISTERS: *mut fdcan::RegisterBlock = FDCAN2::ptr() as *mut _;
     }
@@ -275,3 +710,91 @@ mod fdcan2 {
             ((0x4000_ac00 + 0x1000) as *mut _); // FDCAN1 + 4kB
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::Hertz;
+
+    /// Returns the bit rate and sample point (per mille) of a timing
+    fn evaluate(
+        ker_ck: u32,
+        prescaler: u32,
+        seg1: u32,
+        seg2: u32,
+    ) -> (u32, u32) {
+        let tq = 1 + seg1 + seg2;
+        (ker_ck / (prescaler * tq), 1000 * (1 + seg1) / tq)
+    }
+
+    #[test]
+    fn nominal_bit_timing_exact() {
+        for ker_ck in [8_000_000, 32_000_000, 80_000_000, 100_000_000] {
+            for bit_rate in [125_000, 250_000, 500_000, 1_000_000] {
+                let t = nominal_bit_timing(
+                    Hertz::from_raw(ker_ck),
+                    Hertz::from_raw(bit_rate),
+                )
+                .unwrap();
+                let (rate, sample_point) = evaluate(
+                    ker_ck,
+                    t.prescaler.get() as u32,
+                    t.seg1.get() as u32,
+                    t.seg2.get() as u32,
+                );
+
+                assert_eq!(rate, bit_rate);
+                assert!((850..=900).contains(&sample_point));
+                assert!(t.sync_jump_width <= t.seg2);
+            }
+        }
+    }
+
+    #[test]
+    fn data_bit_timing_exact() {
+        for (ker_ck, bit_rate) in [
+            (32_000_000, 1_000_000),
+            (40_000_000, 2_000_000),
+            (80_000_000, 5_000_000),
+            (80_000_000, 8_000_000),
+        ] {
+            let t = data_bit_timing(
+                Hertz::from_raw(ker_ck),
+                Hertz::from_raw(bit_rate),
+            )
+            .unwrap();
+            let (rate, sample_point) = evaluate(
+                ker_ck,
+                t.prescaler.get() as u32,
+                t.seg1.get() as u32,
+                t.seg2.get() as u32,
+            );
+
+            assert_eq!(rate, bit_rate);
+            assert!((700..=800).contains(&sample_point));
+            assert!(t.seg1.get() <= 31 && t.seg2.get() <= 15);
+            assert!(t.transceiver_delay_compensation);
+        }
+    }
+
+    #[test]
+    fn bit_timing_unachievable() {
+        // Not an integer number of time quanta
+        assert!(nominal_bit_timing(
+            Hertz::from_raw(100_000_000),
+            Hertz::from_raw(300_000)
+        )
+        .is_none());
+        // Too few time quanta for the data phase
+        assert!(data_bit_timing(
+            Hertz::from_raw(8_000_000),
+            Hertz::from_raw(4_000_000)
+        )
+        .is_none());
+        assert!(nominal_bit_timing(
+            Hertz::from_raw(8_000_000),
+            Hertz::from_raw(0)
+        )
+        .is_none());
+    }
+}
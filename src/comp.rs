@@ -0,0 +1,305 @@
+//! Comparators (COMP)
+//!
+//! There are two ultra-low-power comparators, COMP1 and COMP2. Both share a
+//! single register block and reset line, so they are split from the `COMP1`
+//! peripheral together with [`CompExt::comp`].
+//!
+//! The non-inverting (plus) input is a GPIO pin. The inverting (minus) input
+//! is either a GPIO pin, a fraction of the internal reference voltage
+//! VREFINT, or one of the outputs of DAC1. Using a DAC output as the
+//! reference allows an adjustable threshold, for example for over-current
+//! detection.
+//!
+//! ```
+//! use stm32h7xx_hal::comp::{self, CompExt};
+//!
+//! let (comp1, _comp2) = dp.COMP1.comp(ccdr.peripheral.COMP12);
+//!
+//! let plus = gpiob.pb0.into_analog();
+//! let mut comp1 = comp1.enable(plus, comp::Vrefint::Half, comp::Config::default());
+//!
+//! if comp1.is_high() {
+//!     // PB0 is above VREFINT / 2
+//! }
+//! ```
+//!
+//! # Interrupts
+//!
+//! The output of each comparator is connected to the EXTI, lines 20 and 21
+//! for COMP1 and COMP2. An interrupt on an edge of the comparator output is
+//! configured with [`trigger_on_edge`](Comparator::trigger_on_edge) and
+//! [`enable_interrupt`](Comparator::enable_interrupt), in the same way as for
+//! a GPIO pin. The interrupt is raised on the `COMP` interrupt vector.
+
+use core::marker::PhantomData;
+
+use crate::exti::{Event, ExtiExt};
+use crate::gpio::{self, Analog, Edge};
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::{COMP1, EXTI};
+
+/// Enabled comparator (type state)
+pub struct Enabled;
+/// Disabled comparator (type state)
+pub struct Disabled;
+
+/// COMP1 (type state)
+pub struct C1;
+/// COMP2 (type state)
+pub struct C2;
+
+/// Comparator hysteresis
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Hysteresis {
+    #[default]
+    None = 0b00,
+    Low = 0b01,
+    Medium = 0b10,
+    High = 0b11,
+}
+
+/// Comparator power mode. Lower power modes have longer propagation delays
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerMode {
+    #[default]
+    HighSpeed = 0b00,
+    MediumSpeed = 0b01,
+    UltraLowPower = 0b11,
+}
+
+/// Comparator configuration
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    pub hysteresis: Hysteresis,
+    /// Invert the comparator output
+    pub inverted: bool,
+    pub power_mode: PowerMode,
+}
+
+/// Fractions of the internal reference voltage VREFINT, which can be used
+/// as the inverting input
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Vrefint {
+    Quarter = 0b000,
+    Half = 0b001,
+    ThreeQuarters = 0b010,
+    Full = 0b011,
+}
+
+/// DAC1 channel 1 output, as an analog input. The DAC channel must be
+/// enabled separately
+pub struct Dac1Out1;
+/// DAC1 channel 2 output, as an analog input. The DAC channel must be
+/// enabled separately
+pub struct Dac1Out2;
+
+/// Non-inverting inputs of a comparator
+pub trait PositiveInput<COMP> {
+    /// Value of the INPSEL bit
+    const INPSEL: bool;
+}
+
+/// Inverting inputs of a comparator
+pub trait NegativeInput<COMP> {
+    /// Value of the INMSEL field
+    fn inmsel(&self) -> u8;
+}
+
+macro_rules! positive_input {
+    ($($COMP:ident: $PIN:ident => $inpsel:expr,)+) => {
+        $(
+            impl PositiveInput<$COMP> for gpio::$PIN<Analog> {
+                const INPSEL: bool = $inpsel;
+            }
+        )+
+    };
+}
+
+macro_rules! negative_input {
+    ($($COMP:ident: $INPUT:ty => $inmsel:expr,)+) => {
+        $(
+            impl NegativeInput<$COMP> for $INPUT {
+                fn inmsel(&self) -> u8 {
+                    $inmsel
+                }
+            }
+        )+
+    };
+}
+
+positive_input! {
+    C1: PB0 => false,
+    C1: PB2 => true,
+    C2: PE9 => false,
+    C2: PE11 => true,
+}
+
+negative_input! {
+    C1: Dac1Out1 => 0b100,
+    C1: Dac1Out2 => 0b101,
+    C1: gpio::PB1<Analog> => 0b110,
+    C1: gpio::PC4<Analog> => 0b111,
+    C2: Dac1Out1 => 0b100,
+    C2: Dac1Out2 => 0b101,
+    C2: gpio::PE10<Analog> => 0b110,
+    C2: gpio::PE7<Analog> => 0b111,
+}
+
+impl<COMP> NegativeInput<COMP> for Vrefint {
+    fn inmsel(&self) -> u8 {
+        *self as u8
+    }
+}
+
+/// A single comparator
+pub struct Comparator<COMP, ED> {
+    _comp: PhantomData<COMP>,
+    _enabled: PhantomData<ED>,
+}
+
+impl<COMP, ED> Comparator<COMP, ED> {
+    fn new() -> Self {
+        Comparator {
+            _comp: PhantomData,
+            _enabled: PhantomData,
+        }
+    }
+}
+
+pub trait CompExt {
+    /// Enables and resets the comparators, and splits them into COMP1 and
+    /// COMP2
+    fn comp(
+        self,
+        prec: rec::Comp12,
+    ) -> (Comparator<C1, Disabled>, Comparator<C2, Disabled>);
+}
+
+impl CompExt for COMP1 {
+    fn comp(
+        self,
+        prec: rec::Comp12,
+    ) -> (Comparator<C1, Disabled>, Comparator<C2, Disabled>) {
+        prec.enable().reset();
+
+        (Comparator::new(), Comparator::new())
+    }
+}
+
+macro_rules! comp {
+    ($($COMP:ident: ($cfgr:ident, $cxval:ident, $event:ident),)+) => {
+        $(
+            impl Comparator<$COMP, Disabled> {
+                /// Enables the comparator, comparing `plus` against `minus`
+                pub fn enable<P, M>(
+                    self,
+                    _plus: P,
+                    minus: M,
+                    config: Config,
+                ) -> Comparator<$COMP, Enabled>
+                where
+                    P: PositiveInput<$COMP>,
+                    M: NegativeInput<$COMP>,
+                {
+                    // unsafe: only this comparator's configuration register
+                    let comp = unsafe { &*COMP1::ptr() };
+                    let inmsel = minus.inmsel();
+
+                    comp.$cfgr.write(|w| unsafe {
+                        w.inpsel()
+                            .bit(P::INPSEL)
+                            .inmsel()
+                            .bits(inmsel)
+                            // The VREFINT scaler is needed for VREFINT, and
+                            // its bridge for fractions of VREFINT
+                            .scalen()
+                            .bit(inmsel <= Vrefint::Full as u8)
+                            .brgen()
+                            .bit(inmsel < Vrefint::Full as u8)
+                            .hyst()
+                            .bits(config.hysteresis as u8)
+                            .polarity()
+                            .bit(config.inverted)
+                            .pwrmode()
+                            .bits(config.power_mode as u8)
+                    });
+                    comp.$cfgr.modify(|_, w| w.en().set_bit());
+
+                    Comparator::new()
+                }
+            }
+
+            impl Comparator<$COMP, Enabled> {
+                /// Returns `true` if the comparator output is high. The
+                /// output is high if the plus input is above the minus
+                /// input, unless the output is inverted
+                pub fn is_high(&self) -> bool {
+                    // unsafe: read only
+                    unsafe { (*COMP1::ptr()).sr.read().$cxval().bit_is_set() }
+                }
+
+                /// Returns `true` if the comparator output is low
+                pub fn is_low(&self) -> bool {
+                    !self.is_high()
+                }
+
+                /// Generate an EXTI event on the rising edge, falling edge
+                /// or both edges of the comparator output
+                pub fn trigger_on_edge(&mut self, exti: &mut EXTI, edge: Edge) {
+                    let line = Event::$event as u8;
+                    let (rising, falling) = match edge {
+                        Edge::Rising => (true, false),
+                        Edge::Falling => (false, true),
+                        Edge::RisingFalling => (true, true),
+                    };
+
+                    exti.rtsr1.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(1 << line)) | ((rising as u32) << line))
+                    });
+                    exti.ftsr1.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(1 << line)) | ((falling as u32) << line))
+                    });
+                }
+
+                /// Enable the EXTI interrupt from the comparator output
+                pub fn enable_interrupt(&mut self, exti: &mut EXTI) {
+                    exti.listen(Event::$event);
+                }
+
+                /// Disable the EXTI interrupt from the comparator output
+                pub fn disable_interrupt(&mut self, exti: &mut EXTI) {
+                    exti.unlisten(Event::$event);
+                }
+
+                /// Clear the EXTI interrupt pending bit for the comparator
+                pub fn clear_interrupt_pending_bit(&mut self, exti: &mut EXTI) {
+                    exti.unpend(Event::$event);
+                }
+
+                /// Returns `true` if the EXTI interrupt for the comparator
+                /// is pending
+                pub fn check_interrupt(&self, exti: &EXTI) -> bool {
+                    exti.is_pending(Event::$event)
+                }
+
+                /// Disables the comparator
+                pub fn disable(self) -> Comparator<$COMP, Disabled> {
+                    // unsafe: only this comparator's configuration register
+                    let comp = unsafe { &*COMP1::ptr() };
+                    comp.$cfgr.modify(|_, w| w.en().clear_bit());
+
+                    Comparator::new()
+                }
+            }
+        )+
+    };
+}
+
+comp! {
+    C1: (cfgr1, c1val, COMP1),
+    C2: (cfgr2, c2val, COMP2),
+}
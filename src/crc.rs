@@ -1,5 +1,18 @@
 //! Cyclic Redundancy Check (CRC)
 //!
+//! # Streams
+//!
+//! A [`CrcStream`] computes the CRC of data that arrives in pieces, without
+//! resetting the unit between them.
+//!
+//! ```
+//! let crc = dp.CRC.crc(ccdr.peripheral.CRC);
+//! let mut stream = CrcStream::new(crc, &crc::Config::new());
+//!
+//! stream.feed(b"123").feed(b"456789");
+//! let result = stream.finalize();
+//! ```
+//!
 //! # Examples
 //!
 //! - [CRC example](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/crc.rs)
@@ -144,6 +157,43 @@ impl Crc {
     }
 }
 
+/// A CRC computed over data that is fed piece by piece
+///
+/// The CRC unit is only reset when the result is read with
+/// [`finalize`](Self::finalize), so the pieces can be fed as they become
+/// available, for example as packets are received.
+pub struct CrcStream {
+    crc: Crc,
+}
+
+impl CrcStream {
+    /// Configures the CRC unit with `config` and starts a new stream
+    pub fn new(mut crc: Crc, config: &Config) -> Self {
+        crc.set_config(config);
+        CrcStream { crc }
+    }
+
+    /// Feeds the next piece of data to the stream
+    ///
+    /// The data is written a word at a time where possible, so feeding
+    /// larger pieces is faster.
+    pub fn feed(&mut self, data: &[u8]) -> &mut Self {
+        self.crc.update(data);
+        self
+    }
+
+    /// Returns the CRC of all the data fed since the stream started, and
+    /// starts a new stream with the same configuration
+    pub fn finalize(&mut self) -> u32 {
+        self.crc.finish()
+    }
+
+    /// Releases the CRC unit
+    pub fn free(self) -> Crc {
+        self.crc
+    }
+}
+
 #[macro_use]
 mod macros {
     /// Generate an error if number passed is even
@@ -0,0 +1,178 @@
+//! Clock Recovery System (CRS)
+//!
+//! The CRS trims the HSI48 oscillator against an external synchronisation
+//! signal. This is typically used to derive an accurate 48MHz USB clock from
+//! the HSI48 without an external crystal, by synchronising to the 1kHz Start
+//! Of Frame (SOF) packets sent by the USB host.
+//!
+//! HSI48 is always enabled by [`freeze`](crate::rcc::Rcc::freeze).
+//!
+//! ```
+//! use stm32h7xx_hal::crs::CrsSync;
+//!
+//! let dp = pac::Peripherals::take().unwrap();
+//! let ccdr = rcc.freeze(pwrcfg, &dp.SYSCFG);
+//!
+//! let crs = dp.CRS.crs(ccdr.peripheral.CRS, CrsSync::UsbSof, &ccdr.clocks);
+//!
+//! // ... later, once USB is running
+//! let status = crs.sync_status();
+//! assert!(status.sync_ok);
+//! ```
+
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::CRS;
+use crate::time::Hertz;
+
+/// Nominal frequency of the HSI48 oscillator
+const HSI48: u32 = 48_000_000;
+
+/// Source of the CRS synchronisation signal
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CrsSync {
+    /// CRS_SYNC pin, with the given signal frequency. The pin must be put in
+    /// the correct alternate function mode separately
+    Gpio(Hertz),
+    /// LSE oscillator (32.768kHz)
+    Lse,
+    /// USB Start Of Frame packets (1kHz)
+    UsbSof,
+}
+
+impl CrsSync {
+    /// Frequency of the synchronisation signal
+    fn frequency(&self) -> u32 {
+        match self {
+            CrsSync::Gpio(f) => f.raw(),
+            CrsSync::Lse => 32_768,
+            CrsSync::UsbSof => 1_000,
+        }
+    }
+
+    /// Value of the CFGR.SYNCSRC field
+    fn bits(&self) -> u8 {
+        match self {
+            CrsSync::Gpio(_) => 0b00,
+            CrsSync::Lse => 0b01,
+            CrsSync::UsbSof => 0b10,
+        }
+    }
+}
+
+/// Synchronisation status flags of the CRS
+///
+/// Returned by [`Hsi48Crs::sync_status`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CrsStatus {
+    /// At the last SYNC event the frequency error was within FELIM
+    pub sync_ok: bool,
+    /// At the last SYNC event the frequency error exceeded FELIM, but was
+    /// within 3 x FELIM
+    pub sync_warning: bool,
+    /// The frequency error exceeded 3 x FELIM, or a SYNC event was missed
+    pub sync_error: bool,
+    /// A SYNC event was missed
+    pub sync_missed: bool,
+    /// The automatic trimming reached the limit of the TRIM range
+    pub trim_overflow: bool,
+}
+
+pub trait CrsExt {
+    /// Enable the CRS and start trimming the HSI48 against `sync`
+    ///
+    /// # Panics
+    ///
+    /// Panics if HSI48 is not running, or if the frequency of `sync` is too
+    /// low or too high to be measured
+    fn crs(
+        self,
+        prec: rec::Crs,
+        sync: CrsSync,
+        clocks: &CoreClocks,
+    ) -> Hsi48Crs;
+}
+
+impl CrsExt for CRS {
+    fn crs(
+        self,
+        prec: rec::Crs,
+        sync: CrsSync,
+        clocks: &CoreClocks,
+    ) -> Hsi48Crs {
+        clocks.hsi48_ck().expect("CRS: HSI48 must be enabled");
+        prec.enable().reset();
+
+        // RELOAD = (fTARGET / fSYNC) - 1
+        // See RM0433 Rev 7 Section 7.3.3
+        let f_sync = sync.frequency();
+        assert!(f_sync > 0 && f_sync <= HSI48 / 2, "CRS: Invalid SYNC");
+        let ratio = (HSI48 + f_sync / 2) / f_sync;
+        assert!(ratio <= 0x1_0000, "CRS: SYNC frequency too low");
+        let reload = (ratio - 1) as u16;
+
+        // FELIM = (fTARGET / fSYNC) * STEP[%] / 100% / 2, with a trimming
+        // step of 0.14%
+        let felim = ((ratio * 14 + 10_000) / 20_000).clamp(1, 255) as u8;
+
+        self.cfgr.write(|w| unsafe {
+            w.reload()
+                .bits(reload)
+                .felim()
+                .bits(felim)
+                .syncdiv()
+                .bits(0)
+                .syncsrc()
+                .bits(sync.bits())
+        });
+        self.cr
+            .modify(|_, w| w.autotrimen().set_bit().cen().set_bit());
+
+        Hsi48Crs { rb: self, sync }
+    }
+}
+
+/// HSI48 oscillator being trimmed by the CRS
+pub struct Hsi48Crs {
+    rb: CRS,
+    sync: CrsSync,
+}
+
+impl Hsi48Crs {
+    /// Returns the synchronisation source
+    pub fn sync_source(&self) -> CrsSync {
+        self.sync
+    }
+
+    /// Returns the current HSI48 trimming value
+    pub fn trim(&self) -> u8 {
+        self.rb.cr.read().trim().bits()
+    }
+
+    /// Returns the synchronisation status since the last call, and clears
+    /// the status flags
+    pub fn sync_status(&self) -> CrsStatus {
+        let isr = self.rb.isr.read();
+        let status = CrsStatus {
+            sync_ok: isr.syncokf().bit_is_set(),
+            sync_warning: isr.syncwarnf().bit_is_set(),
+            sync_error: isr.syncerr().bit_is_set(),
+            sync_missed: isr.syncmiss().bit_is_set(),
+            trim_overflow: isr.trimovf().bit_is_set(),
+        };
+
+        // ERRC also clears SYNCERR, SYNCMISS and TRIMOVF
+        self.rb.icr.write(|w| {
+            w.syncokc().set_bit().syncwarnc().set_bit().errc().set_bit()
+        });
+
+        status
+    }
+
+    /// Disables the CRS and releases the peripheral. The last trimming value
+    /// is kept
+    pub fn free(self) -> CRS {
+        self.rb.cr.modify(|_, w| w.cen().clear_bit());
+        self.rb
+    }
+}
@@ -4,10 +4,94 @@
 //!
 //! - [Simple DAC example](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/dac.rs)
 //! - [Using DAC and ADC together](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/dac_adc.rs)
+//! - [DAC to ADC loopback, paced by a timer](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/dac_adc_loopback.rs)
+//!
+//! # Switching the Output Buffer
+//!
+//! An enabled channel can be switched between a buffered output, which can
+//! drive a load, and an unbuffered output with a high output impedance, with
+//! `into_unbuffered` and `into_buffered`. The output value is kept.
+//!
+//! ```
+//! let dac = dac.enable();
+//! // ...
+//! let dac = dac.into_unbuffered(&mut delay);
+//! ```
+//!
+//! The mode can only be changed whilst the channel is disabled, so the
+//! output is briefly not driven during the switch, and then settles as the
+//! buffer is powered up or down. This glitch lasts up to the wakeup time of
+//! the output buffer, tWAKEUP in the datasheet, and both methods wait 10µs
+//! for the output to settle.
+//!
+//! # 8-bit Values
+//!
+//! When 8 bits of resolution are enough, a channel can be written with 8-bit
+//! values using [`set_value_8bit`](C1#method.set_value_8bit), or through the
+//! `DacOut<u8>` implementation of [`Dac8Bit`].
+//!
+//! ```
+//! let mut dac = dac.enable().into_8bit();
+//! dac.set_value(128); // 50% of vdda
+//! ```
+//!
+//! An 8-bit value is written to the upper 8 bits of the same 12-bit data
+//! holding register as `DacOut<u16>`, so the output is updated with the same
+//! timing: one APB clock cycle after the write, or on the next trigger event
+//! for a triggered channel. Since there is only one data holding register
+//! per channel, only the last value written before the output is updated is
+//! converted, whether it was written as an 8-bit or a 12-bit value.
+//!
+//! # Triggered Output
+//!
+//! A channel enabled with `enable_triggered` only transfers the value
+//! written with [`DacOut::set_value`] to its output on the next trigger
+//! event. The trigger is the TRGO output of a timer, see
+//! [`Trgo`](crate::timer::Trgo), or another [`DacTrigger`] source such as
+//! EXTI line 9 or a software trigger.
+//!
+//! ```
+//! let mut dac = dac.enable_triggered(DacTrigger::Tim7Trgo, false);
+//! dac.set_value(2048); // Output on the next TIM7 TRGO event
+//! ```
+//!
+//! With DMA enabled, each trigger also requests the next value from the DMA.
+//! This allows a waveform from memory to be output at the timer frequency. A
+//! triggered channel can be used as the peripheral of a DMA memory to
+//! peripheral [`Transfer`](crate::dma::Transfer) on DMA1 or DMA2.
+//!
+//! # Waveform Playback
+//!
+//! [`play_waveform`](C1#method.play_waveform) combines a timer, a triggered
+//! channel and a circular DMA transfer to output a buffer repeatedly, one
+//! sample on each update event of the timer.
+//!
+//! ```
+//! static WAVEFORM: [u16; 64] = [...];
+//!
+//! let timer = Timer::tim6(dp.TIM6, ccdr.peripheral.TIM6, &ccdr.clocks);
+//! let mut waveform = dac.play_waveform(&WAVEFORM, 64.kHz(), timer, streams.0);
+//!
+//! if waveform.is_underrun() {
+//!     waveform.recover_underrun();
+//! }
+//! ```
+//!
+//! The buffer is read by DMA1 or DMA2 until playback is stopped with
+//! [`free`](Waveform#method.free), so it must have a `'static` lifetime. It
+//! must be located in memory that these DMAs can access, that is not in the
+//! DTCM, and either not be cached or be cleaned from the data cache before
+//! playback starts. Each sample is a right aligned 12-bit value, so the
+//! buffer only needs the natural 2 byte alignment of `u16`.
 
 use core::marker::PhantomData;
 use core::mem::MaybeUninit;
 
+use crate::dma::{
+    dma::{DMAReq, DmaConfig},
+    traits::{DoubleBufferedStream, Stream, TargetAddress},
+    ConstDBTransfer, DMAError, MemoryToPeripheral, Transfer,
+};
 use crate::gpio::{self, Analog};
 use crate::hal::blocking::delay::DelayUs;
 use crate::rcc::{rec, ResetEnable};
@@ -15,6 +99,8 @@ use crate::rcc::{rec, ResetEnable};
 use crate::stm32::DAC as DAC1;
 #[cfg(feature = "rm0455")]
 use crate::stm32::{DAC1, DAC2};
+use crate::time::Hertz;
+use crate::timer::{Trgo, TriggerTimer};
 use crate::traits::DacOut;
 
 /// Enabled DAC (type state)
@@ -24,6 +110,56 @@ pub struct EnabledUnbuffered;
 /// Disabled DAC (type state)
 pub struct Disabled;
 
+/// Trigger that transfers the value written with [`DacOut::set_value`] to the
+/// output of a triggered channel
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DacTrigger {
+    /// Triggered by software, see `trigger_software`
+    Software,
+    Tim1Trgo,
+    Tim2Trgo,
+    Tim4Trgo,
+    Tim5Trgo,
+    Tim6Trgo,
+    Tim7Trgo,
+    Tim8Trgo,
+    Tim15Trgo,
+    /// EXTI line 9, which must be configured with the
+    /// [exti](crate::exti) module
+    Exti9,
+}
+
+impl DacTrigger {
+    /// Value of the DAC_CR.TSELx field
+    fn tsel(self) -> u8 {
+        match self {
+            DacTrigger::Software => 0,
+            DacTrigger::Tim1Trgo => 1,
+            DacTrigger::Tim2Trgo => 2,
+            DacTrigger::Tim4Trgo => 3,
+            DacTrigger::Tim5Trgo => 4,
+            DacTrigger::Tim6Trgo => 5,
+            DacTrigger::Tim7Trgo => 6,
+            DacTrigger::Tim8Trgo => 7,
+            DacTrigger::Tim15Trgo => 8,
+            DacTrigger::Exti9 => 13,
+        }
+    }
+}
+
+impl From<Trgo> for DacTrigger {
+    fn from(trigger: Trgo) -> Self {
+        match trigger {
+            Trgo::Tim1 => DacTrigger::Tim1Trgo,
+            Trgo::Tim2 => DacTrigger::Tim2Trgo,
+            Trgo::Tim4 => DacTrigger::Tim4Trgo,
+            Trgo::Tim6 => DacTrigger::Tim6Trgo,
+            Trgo::Tim8 => DacTrigger::Tim8Trgo,
+        }
+    }
+}
+
 pub trait ED {}
 impl ED for Enabled {}
 impl ED for EnabledUnbuffered {}
@@ -38,6 +174,21 @@ pub struct C2<DAC, ED> {
     _enabled: PhantomData<ED>,
 }
 
+/// DAC channel written with 8-bit values
+///
+/// Returned by `into_8bit`. Implements `DacOut<u8>`, where each value is
+/// the upper 8 bits of the 12-bit output.
+pub struct Dac8Bit<CX> {
+    channel: CX,
+}
+
+impl<CX> Dac8Bit<CX> {
+    /// Returns the channel, to be written with 12-bit values again
+    pub fn free(self) -> CX {
+        self.channel
+    }
+}
+
 /// Trait for GPIO pins that can be converted to DAC output pins
 pub trait Pins<DAC> {
     type Output;
@@ -81,9 +232,30 @@ where
     }
 }
 
+/// Settling time of the output after the buffer mode is changed, in
+/// microseconds. This is more than the maximum wakeup time tWAKEUP of the
+/// output buffer in the datasheet
+const MODE_SETTLING_US: u32 = 10;
+
+// Changes the MODEx field of an enabled channel. MODEx can only be written
+// whilst the channel is disabled. The output value (DOR) is not changed by
+// disabling the channel
+macro_rules! set_mode {
+    ($DAC:ident, $en:ident, $mode:ident, $bits:expr, $delay:expr) => {
+        let dac = unsafe { &(*$DAC::ptr()) };
+
+        dac.cr.modify(|_, w| w.$en().clear_bit());
+        dac.mcr.modify(|_, w| unsafe { w.$mode().bits($bits) });
+        dac.cr.modify(|_, w| w.$en().set_bit());
+
+        $delay.delay_us(MODE_SETTLING_US);
+    };
+}
+
 macro_rules! dac {
     ($DAC:ident, $CX:ident, $en:ident, $cen:ident, $cal_flag:ident, $trim:ident,
-     $mode:ident, $dhrx:ident, $dor:ident, $daccxdhr:ident) => {
+     $mode:ident, $dhrx:ident, $dhr8x:ident, $dor:ident, $daccxdhr:ident,
+     $ten:ident, $tsel:ident, $dmaen:ident, $swtrig:ident) => {
         impl $CX<$DAC, Disabled> {
             pub fn enable(self) -> $CX<$DAC, Enabled> {
                 let dac = unsafe { &(*$DAC::ptr()) };
@@ -108,6 +280,69 @@ macro_rules! dac {
                     _enabled: PhantomData,
                 }
             }
+
+            /// Enable the DAC channel with the output buffer, updating the
+            /// output on each `trigger` event. If `dma` is true, each
+            /// trigger event also generates a DMA request
+            ///
+            /// The trigger is either a [`Trgo`] returned by a timer, or a
+            /// [`DacTrigger`].
+            pub fn enable_triggered(
+                self,
+                trigger: impl Into<DacTrigger>,
+                dma: bool,
+            ) -> $CX<$DAC, Enabled> {
+                let dac = unsafe { &(*$DAC::ptr()) };
+
+                // The trigger must be selected while the channel is disabled
+                dac.cr.modify(|_, w| unsafe {
+                    w.$tsel()
+                        .bits(trigger.into().tsel())
+                        .$ten()
+                        .set_bit()
+                        .$dmaen()
+                        .bit(dma)
+                });
+
+                self.enable()
+            }
+        }
+
+        impl $CX<$DAC, Enabled> {
+            /// Disable the output buffer of an enabled channel, keeping the
+            /// current output value. See the [module level
+            /// documentation](crate::dac#switching-the-output-buffer)
+            pub fn into_unbuffered<T>(
+                self,
+                delay: &mut T,
+            ) -> $CX<$DAC, EnabledUnbuffered>
+            where
+                T: DelayUs<u32>,
+            {
+                set_mode!($DAC, $en, $mode, 2, delay);
+
+                $CX {
+                    _dac: PhantomData,
+                    _enabled: PhantomData,
+                }
+            }
+        }
+
+        impl $CX<$DAC, EnabledUnbuffered> {
+            /// Enable the output buffer of an enabled channel, keeping the
+            /// current output value. See the [module level
+            /// documentation](crate::dac#switching-the-output-buffer)
+            pub fn into_buffered<T>(self, delay: &mut T) -> $CX<$DAC, Enabled>
+            where
+                T: DelayUs<u32>,
+            {
+                set_mode!($DAC, $en, $mode, 0, delay);
+
+                $CX {
+                    _dac: PhantomData,
+                    _enabled: PhantomData,
+                }
+            }
         }
 
         impl<ED> $CX<$DAC, ED> {
@@ -150,10 +385,40 @@ macro_rules! dac {
                 }
             }
 
-            /// Disable the DAC channel
+            /// Generate a software trigger. If the channel was enabled with
+            /// [`DacTrigger::Software`], this transfers the value written
+            /// with [`DacOut::set_value`] to the output
+            pub fn trigger_software(&mut self) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.swtrgr.write(|w| w.$swtrig().set_bit());
+            }
+
+            /// Writes an 8-bit value, which is output as the upper 8 bits
+            /// of the 12-bit output
+            pub fn set_value_8bit(&mut self, val: u8) {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                dac.$dhr8x.write(|w| unsafe { w.bits(val as u32) });
+            }
+
+            /// Returns the upper 8 bits of the current output
+            pub fn get_value_8bit(&mut self) -> u8 {
+                let dac = unsafe { &(*$DAC::ptr()) };
+                (dac.$dor.read().bits() >> 4) as u8
+            }
+
+            /// Returns the channel as a [`Dac8Bit`], which implements
+            /// `DacOut<u8>`
+            pub fn into_8bit(self) -> Dac8Bit<Self> {
+                Dac8Bit { channel: self }
+            }
+
+            /// Disable the DAC channel. This also disables the trigger and
+            /// DMA requests
             pub fn disable(self) -> $CX<$DAC, Disabled> {
                 let dac = unsafe { &(*$DAC::ptr()) };
-                dac.cr.modify(|_, w| w.$en().clear_bit());
+                dac.cr.modify(|_, w| {
+                    w.$en().clear_bit().$ten().clear_bit().$dmaen().clear_bit()
+                });
 
                 $CX {
                     _dac: PhantomData,
@@ -174,6 +439,16 @@ macro_rules! dac {
                 dac.$dor.read().bits() as u16
             }
         }
+
+        impl<ED> DacOut<u8> for Dac8Bit<$CX<$DAC, ED>> {
+            fn set_value(&mut self, val: u8) {
+                self.channel.set_value_8bit(val);
+            }
+
+            fn get_value(&mut self) -> u8 {
+                self.channel.get_value_8bit()
+            }
+        }
     };
 }
 
@@ -211,10 +486,192 @@ impl DacExt for DAC2 {
     }
 }
 
-dac!(DAC1, C1, en1, cen1, cal_flag1, otrim1, mode1, dhr12r1, dor1, dacc1dhr);
-dac!(DAC1, C2, en2, cen2, cal_flag2, otrim2, mode2, dhr12r2, dor2, dacc2dhr);
+dac!(
+    DAC1, C1, en1, cen1, cal_flag1, otrim1, mode1, dhr12r1, dhr8r1, dor1,
+    dacc1dhr, ten1, tsel1, dmaen1, swtrig1
+);
+dac!(
+    DAC1, C2, en2, cen2, cal_flag2, otrim2, mode2, dhr12r2, dhr8r2, dor2,
+    dacc2dhr, ten2, tsel2, dmaen2, swtrig2
+);
 
 #[cfg(feature = "rm0455")]
-dac!(DAC2, C1, en1, cen1, cal_flag1, otrim1, mode1, dhr12r1, dor1, dacc1dhr);
+dac!(
+    DAC2, C1, en1, cen1, cal_flag1, otrim1, mode1, dhr12r1, dhr8r1, dor1,
+    dacc1dhr, ten1, tsel1, dmaen1, swtrig1
+);
 #[cfg(feature = "rm0455")]
-dac!(DAC2, C2, en2, cen2, cal_flag2, otrim2, mode2, dhr12r2, dor2, dacc2dhr);
+dac!(
+    DAC2, C2, en2, cen2, cal_flag2, otrim2, mode2, dhr12r2, dhr8r2, dor2,
+    dacc2dhr, ten2, tsel2, dmaen2, swtrig2
+);
+
+/// Circular DMA transfer of a waveform to a DAC channel, paced by a timer
+///
+/// Returned by `play_waveform`
+pub struct Waveform<CX, TIM, STREAM>
+where
+    CX: TargetAddress<MemoryToPeripheral>,
+    STREAM: Stream,
+{
+    timer: TIM,
+    transfer: Transfer<
+        STREAM,
+        CX,
+        MemoryToPeripheral,
+        &'static [u16],
+        ConstDBTransfer,
+    >,
+}
+
+// DMA requests from DAC1, routed through DMAMUX1
+macro_rules! dac_dma {
+    ($($CX:ident: ($dhrx:ident, $mux:expr, $dmaen:ident, $dmaudr:ident,
+                   $dmaudrie:ident),)+) => {
+        $(
+            impl $CX<DAC1, Disabled> {
+                /// Output `buffer` repeatedly, one sample on each update
+                /// event of `timer` at `sample_rate`
+                ///
+                /// The samples are transferred by `stream`, which must be a
+                /// DMA1 or DMA2 stream. See the [module level
+                /// documentation](crate::dac#waveform-playback) for the
+                /// requirements on `buffer`.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `buffer` is longer than 65535 samples
+                pub fn play_waveform<TIM, STREAM>(
+                    self,
+                    buffer: &'static [u16],
+                    sample_rate: Hertz,
+                    mut timer: TIM,
+                    stream: STREAM,
+                ) -> Waveform<$CX<DAC1, Enabled>, TIM, STREAM>
+                where
+                    TIM: TriggerTimer,
+                    STREAM: DoubleBufferedStream + Stream<Config = DmaConfig>,
+                {
+                    timer.pause();
+                    timer.set_freq(sample_rate);
+                    timer.apply_freq();
+                    let trigger = timer.trigger_on_update();
+
+                    let dac = self.enable_triggered(trigger, true);
+
+                    let config = DmaConfig::default()
+                        .memory_increment(true)
+                        .circular_buffer(true);
+                    let mut transfer: Transfer<_, _, MemoryToPeripheral, _, _> =
+                        Transfer::init_const(stream, dac, buffer, None, config);
+                    transfer.start(|_| {});
+                    timer.resume();
+
+                    Waveform { timer, transfer }
+                }
+            }
+
+            impl<TIM, STREAM> Waveform<$CX<DAC1, Enabled>, TIM, STREAM>
+            where
+                TIM: TriggerTimer,
+                STREAM: DoubleBufferedStream + Stream<Config = DmaConfig>,
+            {
+                /// Stop playback. The output holds its last value
+                pub fn stop(&mut self) {
+                    self.timer.pause();
+                }
+
+                /// Restart playback after [`stop`](#method.stop)
+                pub fn restart(&mut self) {
+                    self.timer.resume();
+                }
+
+                /// Replace the buffer being played. Playback restarts from
+                /// the first sample of `buffer`, and the previous buffer is
+                /// returned
+                ///
+                /// # Panics
+                ///
+                /// Panics if `buffer` is longer than 65535 samples
+                pub fn swap_buffer(
+                    &mut self,
+                    buffer: &'static [u16],
+                ) -> Result<&'static [u16], DMAError> {
+                    assert!(
+                        buffer.len() <= 65535,
+                        "Hardware does not support more than 65535 transfers"
+                    );
+                    self.transfer.next_transfer(buffer).map(|(old, _, _)| old)
+                }
+
+                /// Returns `true` if the DMA did not provide a sample in time
+                /// for a trigger event. The DAC stops requesting samples
+                /// until [`recover_underrun`](#method.recover_underrun) is
+                /// called
+                pub fn is_underrun(&self) -> bool {
+                    let dac = unsafe { &(*DAC1::ptr()) };
+                    dac.sr.read().$dmaudr().bit_is_set()
+                }
+
+                /// Enable an interrupt on DMA underrun
+                pub fn listen_underrun(&mut self) {
+                    let dac = unsafe { &(*DAC1::ptr()) };
+                    dac.cr.modify(|_, w| w.$dmaudrie().set_bit());
+                }
+
+                /// Disable the interrupt on DMA underrun
+                pub fn unlisten_underrun(&mut self) {
+                    let dac = unsafe { &(*DAC1::ptr()) };
+                    dac.cr.modify(|_, w| w.$dmaudrie().clear_bit());
+                }
+
+                /// Clear a DMA underrun and restart playback from the first
+                /// sample of the buffer
+                //
+                // Refer to RM0433 Rev 7 - Chapter 26.4.8
+                pub fn recover_underrun(&mut self) {
+                    let dac = unsafe { &(*DAC1::ptr()) };
+
+                    self.timer.pause();
+                    dac.cr.modify(|_, w| w.$dmaen().clear_bit());
+                    dac.sr.write(|w| w.$dmaudr().set_bit());
+
+                    // Reinitialise the stream with the same buffer. This
+                    // only fails in double buffer mode
+                    let _ = self.transfer.next_transfer_with(|buf, _, _| (buf, ()));
+
+                    dac.cr.modify(|_, w| w.$dmaen().set_bit());
+                    self.timer.resume();
+                }
+
+                /// Stop playback and release the DAC channel, timer, DMA
+                /// stream and buffer. The DAC channel is disabled
+                pub fn free(
+                    mut self,
+                ) -> ($CX<DAC1, Disabled>, TIM, STREAM, &'static [u16]) {
+                    self.timer.pause();
+                    let (stream, dac, buffer, _) = self.transfer.free();
+
+                    (dac.disable(), self.timer, stream, buffer)
+                }
+            }
+
+            unsafe impl TargetAddress<MemoryToPeripheral> for $CX<DAC1, Enabled> {
+                #[inline(always)]
+                fn address(&self) -> usize {
+                    let dac = unsafe { &(*DAC1::ptr()) };
+                    &dac.$dhrx as *const _ as usize
+                }
+
+                type MemSize = u16;
+
+                const REQUEST_LINE: Option<u8> = Some($mux as u8);
+            }
+        )+
+    };
+}
+
+dac_dma! {
+    C1: (dhr12r1, DMAReq::DacCh1Dma, dmaen1, dmaudr1, dmaudrie1),
+    C2: (dhr12r2, DMAReq::DacCh2Dma, dmaen2, dmaudr2, dmaudrie2),
+}
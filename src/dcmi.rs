@@ -0,0 +1,393 @@
+//! Digital Camera Interface (DCMI)
+//!
+//! The DCMI receives data from a parallel camera sensor with an 8, 10, 12 or
+//! 14 bit data bus. Frames are synchronised either by the HSYNC and VSYNC
+//! pins (hardware synchronisation) or by codes embedded in the data
+//! stream. Received data is packed into 32-bit words in the data register,
+//! and moved to memory by DMA1 or DMA2.
+//!
+//! ```
+//! use stm32h7xx_hal::dcmi::{self, DcmiExt};
+//!
+//! let pins = (pixclk, hsync, vsync, d0, d1, d2, d3, d4, d5, d6, d7);
+//! let mut dcmi = dp.DCMI.dcmi(pins, dcmi::Config::default(), ccdr.peripheral.DCMI);
+//!
+//! let mut transfer: Transfer<_, _, PeripheralToMemory, _, _> =
+//!     Transfer::init(streams.0, dcmi, frame, None, config);
+//! transfer.start(|dcmi| dcmi.start_capture());
+//! ```
+//!
+//! # Continuous capture
+//!
+//! In [`CaptureMode::Continuous`] frames are captured until
+//! [`stop_capture`](Dcmi::stop_capture) is called. Combined with a double
+//! buffered DMA [`Transfer`](crate::dma::Transfer), one frame buffer can be
+//! processed while the next frame is received into the other. Each DMA
+//! transfer is limited to 65535 words, so a frame is at most 256kB unless it
+//! is split across both buffers.
+//!
+//! # Events
+//!
+//! The end of a frame is signalled by [`Event::FrameComplete`]. If the DMA
+//! falls behind and data is lost, [`Event::Overrun`] is raised.
+
+use core::marker::PhantomData;
+
+use crate::gpio::{self, Alternate};
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::DCMI;
+
+/// Width of the data bus
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DataWidth {
+    #[default]
+    Bits8 = 0b00,
+    Bits10 = 0b01,
+    Bits12 = 0b10,
+    Bits14 = 0b11,
+}
+
+/// Level of a synchronisation signal that indicates blanking, when data is
+/// not valid
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Polarity {
+    #[default]
+    ActiveLow,
+    ActiveHigh,
+}
+
+/// Edge of the pixel clock that data is captured on
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PixelClockEdge {
+    #[default]
+    Falling,
+    Rising,
+}
+
+/// Synchronisation codes embedded in the data stream. Each code is
+/// preceded by 0xFF 0x00 0x00
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EmbeddedCodes {
+    pub frame_start: u8,
+    pub line_start: u8,
+    pub line_end: u8,
+    pub frame_end: u8,
+}
+
+/// Frame synchronisation
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Synchronisation {
+    /// Synchronisation from the HSYNC and VSYNC pins
+    Hardware { hsync: Polarity, vsync: Polarity },
+    /// Synchronisation from codes in the data stream. Only supported with
+    /// an 8 bit data bus
+    Embedded(EmbeddedCodes),
+}
+
+impl Default for Synchronisation {
+    fn default() -> Self {
+        Synchronisation::Hardware {
+            hsync: Polarity::ActiveLow,
+            vsync: Polarity::ActiveLow,
+        }
+    }
+}
+
+/// Capture mode
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CaptureMode {
+    /// Frames are captured continuously
+    #[default]
+    Continuous,
+    /// A single frame is captured
+    Snapshot,
+}
+
+/// DCMI configuration
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    pub data_width: DataWidth,
+    pub synchronisation: Synchronisation,
+    pub pixel_clock: PixelClockEdge,
+    pub capture_mode: CaptureMode,
+    /// Capture compressed data in JPEG format. The frame size is not known
+    /// in advance, and is given by the HSYNC signal
+    pub jpeg: bool,
+}
+
+/// DCMI Events
+///
+/// Each event is a possible interrupt source, if enabled
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// A complete frame has been captured (FRAME)
+    FrameComplete,
+    /// Data was lost because the DMA did not read the data register in time
+    /// (OVR)
+    Overrun,
+    /// Embedded synchronisation codes were received in the wrong order
+    /// (ERR)
+    SyncError,
+    /// VSYNC changed from the active to the inactive state (VSYNC)
+    Vsync,
+    /// A complete line has been received (LINE)
+    Line,
+}
+
+pub trait PinPixclk {}
+pub trait PinHsync {}
+pub trait PinVsync {}
+pub trait PinD0 {}
+pub trait PinD1 {}
+pub trait PinD2 {}
+pub trait PinD3 {}
+pub trait PinD4 {}
+pub trait PinD5 {}
+pub trait PinD6 {}
+pub trait PinD7 {}
+
+/// A set of pins for an 8 bit DCMI bus with hardware synchronisation. For
+/// wider data busses, or embedded synchronisation, use
+/// [`dcmi_unchecked`](DcmiExt::dcmi_unchecked) and configure the pins
+/// separately.
+pub trait Pins {}
+
+impl<PIXCLK, HSYNC, VSYNC, D0, D1, D2, D3, D4, D5, D6, D7> Pins
+    for (PIXCLK, HSYNC, VSYNC, D0, D1, D2, D3, D4, D5, D6, D7)
+where
+    PIXCLK: PinPixclk,
+    HSYNC: PinHsync,
+    VSYNC: PinVsync,
+    D0: PinD0,
+    D1: PinD1,
+    D2: PinD2,
+    D3: PinD3,
+    D4: PinD4,
+    D5: PinD5,
+    D6: PinD6,
+    D7: PinD7,
+{
+}
+
+macro_rules! pins {
+    ($($Pin:ident: [$($PIN:ident),*])+) => {
+        $(
+            $(
+                impl $Pin for gpio::$PIN<Alternate<13>> {}
+            )*
+        )+
+    }
+}
+
+pins! {
+    PinPixclk: [PA6]
+    PinHsync: [PA4, PH8]
+    PinVsync: [PB7, PG9]
+    PinD0: [PA9, PC6, PH9]
+    PinD1: [PA10, PC7, PH10]
+    PinD2: [PC8, PE0, PG10, PH11]
+    PinD3: [PC9, PE1, PG11, PH12]
+    PinD4: [PC11, PE4, PH14]
+    PinD5: [PB6, PD3]
+    PinD6: [PB8, PE5]
+    PinD7: [PB9, PE6]
+}
+
+#[cfg(not(feature = "rm0468"))]
+pins! {
+    PinVsync: [PI5]
+    PinD5: [PI4]
+    PinD6: [PI6]
+    PinD7: [PI7]
+}
+
+/// Digital Camera Interface
+pub struct Dcmi {
+    rb: DCMI,
+}
+
+pub trait DcmiExt: Sized {
+    /// Enables and configures the DCMI, ready to start capturing
+    ///
+    /// # Panics
+    ///
+    /// Panics if embedded synchronisation is used with a data bus wider
+    /// than 8 bits
+    fn dcmi<PINS>(self, _pins: PINS, config: Config, prec: rec::Dcmi) -> Dcmi
+    where
+        PINS: Pins;
+
+    /// Enables and configures the DCMI, ready to start capturing. No pin
+    /// types are required
+    ///
+    /// # Panics
+    ///
+    /// Panics if embedded synchronisation is used with a data bus wider
+    /// than 8 bits
+    fn dcmi_unchecked(self, config: Config, prec: rec::Dcmi) -> Dcmi;
+}
+
+impl DcmiExt for DCMI {
+    fn dcmi<PINS>(self, _pins: PINS, config: Config, prec: rec::Dcmi) -> Dcmi
+    where
+        PINS: Pins,
+    {
+        Dcmi::new(self, config, prec)
+    }
+
+    fn dcmi_unchecked(self, config: Config, prec: rec::Dcmi) -> Dcmi {
+        Dcmi::new(self, config, prec)
+    }
+}
+
+impl Dcmi {
+    fn new(rb: DCMI, config: Config, prec: rec::Dcmi) -> Self {
+        prec.enable().reset();
+
+        let (embedded, hsync, vsync) = match config.synchronisation {
+            Synchronisation::Hardware { hsync, vsync } => (false, hsync, vsync),
+            Synchronisation::Embedded(codes) => {
+                assert!(
+                    config.data_width == DataWidth::Bits8,
+                    "DCMI: Embedded synchronisation requires an 8 bit bus"
+                );
+
+                rb.escr.write(|w| unsafe {
+                    w.fsc()
+                        .bits(codes.frame_start)
+                        .lsc()
+                        .bits(codes.line_start)
+                        .lec()
+                        .bits(codes.line_end)
+                        .fec()
+                        .bits(codes.frame_end)
+                });
+                // Compare all bits of each code
+                rb.esur.write(|w| unsafe { w.bits(0xFFFF_FFFF) });
+
+                (true, Polarity::ActiveLow, Polarity::ActiveLow)
+            }
+        };
+
+        // All frames, all bytes and all lines are captured
+        rb.cr.write(|w| unsafe {
+            w.edm()
+                .bits(config.data_width as u8)
+                .ess()
+                .bit(embedded)
+                .hspol()
+                .bit(hsync == Polarity::ActiveHigh)
+                .vspol()
+                .bit(vsync == Polarity::ActiveHigh)
+                .pckpol()
+                .bit(config.pixel_clock == PixelClockEdge::Rising)
+                .cm()
+                .bit(config.capture_mode == CaptureMode::Snapshot)
+                .jpeg()
+                .bit(config.jpeg)
+        });
+        rb.cr.modify(|_, w| w.enable().set_bit());
+
+        Dcmi { rb }
+    }
+
+    /// Starts capturing from the start of the next frame. The DMA transfer
+    /// should be started first
+    pub fn start_capture(&mut self) {
+        self.rb.cr.modify(|_, w| w.capture().set_bit());
+    }
+
+    /// Stops capturing. In continuous mode, capture stops at the end of the
+    /// current frame
+    pub fn stop_capture(&mut self) {
+        self.rb.cr.modify(|_, w| w.capture().clear_bit());
+    }
+
+    /// Returns `true` if a capture is in progress. In snapshot mode this
+    /// becomes `false` at the end of the frame
+    pub fn is_capturing(&self) -> bool {
+        self.rb.cr.read().capture().bit_is_set()
+    }
+
+    /// Start listening for `event`
+    pub fn listen(&mut self, event: Event) {
+        self.rb.ier.modify(|_, w| match event {
+            Event::FrameComplete => w.frame_ie().set_bit(),
+            Event::Overrun => w.ovr_ie().set_bit(),
+            Event::SyncError => w.err_ie().set_bit(),
+            Event::Vsync => w.vsync_ie().set_bit(),
+            Event::Line => w.line_ie().set_bit(),
+        });
+    }
+
+    /// Stop listening for `event`
+    pub fn unlisten(&mut self, event: Event) {
+        self.rb.ier.modify(|_, w| match event {
+            Event::FrameComplete => w.frame_ie().clear_bit(),
+            Event::Overrun => w.ovr_ie().clear_bit(),
+            Event::SyncError => w.err_ie().clear_bit(),
+            Event::Vsync => w.vsync_ie().clear_bit(),
+            Event::Line => w.line_ie().clear_bit(),
+        });
+        let _ = self.rb.ier.read();
+        let _ = self.rb.ier.read(); // Delay 2 peripheral clocks
+    }
+
+    /// Returns `true` if `event` is pending, regardless of whether it is
+    /// enabled as an interrupt
+    pub fn is_pending(&self, event: Event) -> bool {
+        let ris = self.rb.ris.read();
+        match event {
+            Event::FrameComplete => ris.frame_ris().bit_is_set(),
+            Event::Overrun => ris.ovr_ris().bit_is_set(),
+            Event::SyncError => ris.err_ris().bit_is_set(),
+            Event::Vsync => ris.vsync_ris().bit_is_set(),
+            Event::Line => ris.line_ris().bit_is_set(),
+        }
+    }
+
+    /// Clears interrupt flag for `event`
+    pub fn clear_irq(&mut self, event: Event) {
+        self.rb.icr.write(|w| match event {
+            Event::FrameComplete => w.frame_isc().set_bit(),
+            Event::Overrun => w.ovr_isc().set_bit(),
+            Event::SyncError => w.err_isc().set_bit(),
+            Event::Vsync => w.vsync_isc().set_bit(),
+            Event::Line => w.line_isc().set_bit(),
+        });
+        let _ = self.rb.ris.read();
+        let _ = self.rb.ris.read(); // Delay 2 peripheral clocks
+    }
+
+    /// Returns a reference to the inner peripheral
+    pub fn inner(&self) -> &DCMI {
+        &self.rb
+    }
+
+    /// Returns a mutable reference to the inner peripheral
+    pub fn inner_mut(&mut self) -> &mut DCMI {
+        &mut self.rb
+    }
+
+    /// Disables the DCMI and releases the peripheral
+    pub fn free(self) -> (DCMI, rec::Dcmi) {
+        self.rb
+            .cr
+            .modify(|_, w| w.capture().clear_bit().enable().clear_bit());
+
+        (
+            self.rb,
+            rec::Dcmi {
+                _marker: PhantomData,
+            },
+        )
+    }
+}
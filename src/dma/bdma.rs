@@ -259,6 +259,7 @@ trait InstanceStream {
     fn stream_clear_transfer_complete_interrupt(&mut self);
     fn stream_clear_transfer_error_interrupt(&mut self);
     fn stream_get_transfer_complete_flag() -> bool;
+    fn stream_get_transfer_error_flag() -> bool;
     fn stream_get_half_transfer_flag() -> bool;
     fn stream_clear_half_transfer_interrupt(&mut self);
 }
@@ -314,6 +315,10 @@ where
     fn get_transfer_complete_flag() -> bool {
         Self::stream_get_transfer_complete_flag()
     }
+    #[inline(always)]
+    fn get_transfer_error_flag() -> bool {
+        Self::stream_get_transfer_error_flag()
+    }
 
     #[inline(always)]
     unsafe fn enable(&mut self) {
@@ -643,6 +648,13 @@ macro_rules! bdma_stream {
                     dma.$isr.read().$tcisr().bit_is_set()
                 }
 
+                #[inline(always)]
+                fn stream_get_transfer_error_flag() -> bool {
+                    //NOTE(unsafe) Atomic read with no side effects
+                    let dma = unsafe { &*I::ptr() };
+                    dma.$isr.read().$teisr().bit_is_set()
+                }
+
                 #[inline(always)]
                 fn stream_get_half_transfer_flag() -> bool {
                     //NOTE(unsafe) Atomic read with no side effects
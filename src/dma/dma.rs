@@ -14,13 +14,22 @@ use core::marker::PhantomData;
 use crate::{
     adc,
     adc::Adc,
+    dcmi::Dcmi,
+    gpio::Edge,
     i2c::I2c,
     pac::{self, DMA1, DMA2, DMAMUX1},
     rcc::{rec, rec::ResetEnable},
-    sai, serial, spi,
+    sai, serial,
+    spdifrx::Spdifrx,
+    spi,
 };
 
 use core::ops::Deref;
+#[cfg(feature = "async")]
+use core::task::{Context, Poll};
+
+#[cfg(feature = "async")]
+use atomic_waker::AtomicWaker;
 
 impl Sealed for DMA1 {}
 impl Sealed for DMA2 {}
@@ -336,6 +345,7 @@ trait InstanceStream {
     fn stream_clear_transfer_complete_interrupt(&mut self);
     fn stream_clear_transfer_error_interrupt(&mut self);
     fn stream_get_transfer_complete_flag() -> bool;
+    fn stream_get_transfer_error_flag() -> bool;
     fn stream_get_half_transfer_flag() -> bool;
     fn stream_clear_half_transfer_interrupt(&mut self);
 }
@@ -463,6 +473,10 @@ where
     fn get_transfer_complete_flag() -> bool {
         Self::stream_get_transfer_complete_flag()
     }
+    #[inline(always)]
+    fn get_transfer_error_flag() -> bool {
+        Self::stream_get_transfer_error_flag()
+    }
 
     #[inline(always)]
     unsafe fn enable(&mut self) {
@@ -754,6 +768,142 @@ where
     }
 }
 
+/// Wakers for the tasks waiting on each stream. DMA1 streams are at indices 0
+/// to 7, and DMA2 streams at indices 8 to 15
+#[cfg(feature = "async")]
+static WAKERS: [AtomicWaker; 16] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const WAKER: AtomicWaker = AtomicWaker::new();
+    [WAKER; 16]
+};
+
+/// A DMA1 or DMA2 stream that can wake an async task when its transfer ends
+///
+/// The interrupt handler for the stream must call
+/// [`on_interrupt`](AsyncStream::on_interrupt):
+///
+/// ```
+/// #[interrupt]
+/// fn DMA1_STR0() {
+///     Stream0::<DMA1>::on_interrupt();
+/// }
+/// ```
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub trait AsyncStream: DoubleBufferedStream<Config = DmaConfig> {
+    /// Handles the interrupt for this stream, by disabling the half
+    /// transfer, transfer complete and transfer error interrupts and waking
+    /// the task waiting for the transfer
+    fn on_interrupt();
+
+    /// Polls for the end of the current transfer, enabling the transfer
+    /// complete and transfer error interrupts whilst it is ongoing. Returns
+    /// `Ready(false)` if the transfer ended with a transfer error
+    fn poll_transfer(&mut self, cx: &mut Context<'_>) -> Poll<bool>;
+
+    /// Polls a circular transfer until the half transfer or the transfer
+    /// complete flag is set, enabling the half transfer, transfer complete
+    /// and transfer error interrupts whilst neither is set. Returns
+    /// `Ready(false)` if the stream was disabled by a transfer error
+    fn poll_circular(&mut self, cx: &mut Context<'_>) -> Poll<bool>;
+}
+
+#[cfg(feature = "async")]
+impl<I: Instance, const S: u8> AsyncStream for StreamX<I, S>
+where
+    Self: DoubleBufferedStream<Config = DmaConfig>,
+{
+    fn on_interrupt() {
+        //NOTE(unsafe) We only access the registers that belongs to the StreamX
+        unsafe { Self::stream() }.cr.modify(|_, w| {
+            w.tcie().clear_bit().teie().clear_bit().htie().clear_bit()
+        });
+
+        WAKERS[I::DMA_MUX_STREAM_OFFSET + S as usize].wake();
+    }
+
+    fn poll_transfer(&mut self, cx: &mut Context<'_>) -> Poll<bool> {
+        WAKERS[I::DMA_MUX_STREAM_OFFSET + S as usize].register(cx.waker());
+
+        // The stream is disabled by hardware at the end of the transfer, or
+        // on a transfer error
+        if !<Self as traits::Stream>::is_enabled() {
+            return Poll::Ready(
+                <Self as traits::Stream>::get_transfer_complete_flag(),
+            );
+        }
+
+        // If the transfer ends before the interrupts are enabled, the flags
+        // are already set and the interrupt is taken immediately
+        //NOTE(unsafe) We only access the registers that belongs to the StreamX
+        unsafe { Self::stream() }
+            .cr
+            .modify(|_, w| w.tcie().set_bit().teie().set_bit());
+        Poll::Pending
+    }
+
+    fn poll_circular(&mut self, cx: &mut Context<'_>) -> Poll<bool> {
+        WAKERS[I::DMA_MUX_STREAM_OFFSET + S as usize].register(cx.waker());
+
+        // A circular transfer only stops on a transfer error
+        if !<Self as traits::Stream>::is_enabled() {
+            return Poll::Ready(false);
+        }
+        if <Self as DoubleBufferedStream>::get_half_transfer_flag()
+            || <Self as traits::Stream>::get_transfer_complete_flag()
+        {
+            return Poll::Ready(true);
+        }
+
+        // If a flag is set before the interrupts are enabled, the interrupt
+        // is taken immediately
+        //NOTE(unsafe) We only access the registers that belongs to the StreamX
+        unsafe { Self::stream() }.cr.modify(|_, w| {
+            w.htie().set_bit().tcie().set_bit().teie().set_bit()
+        });
+        Poll::Pending
+    }
+}
+
+/// Configures `stream` for a single transfer of `len` bytes between the
+/// byte-sized `target` and `memory`, and enables it. `memory` is incremented
+/// after each byte if `increment` is set
+///
+/// # Safety
+///
+/// `memory` must be valid for the transfer until the stream is disabled.
+#[cfg(feature = "async")]
+pub(crate) unsafe fn start_async_transfer<S, D, T>(
+    stream: &mut S,
+    target: &T,
+    memory: usize,
+    increment: bool,
+    len: u16,
+) where
+    S: AsyncStream,
+    D: traits::Direction,
+    T: TargetAddress<D, MemSize = u8>,
+{
+    stream.disable();
+    stream.clear_interrupts();
+    stream.apply_config(DmaConfig::default().memory_increment(increment));
+    if let Some(request_line) = T::REQUEST_LINE {
+        stream.set_request_line(request_line);
+    }
+    stream.set_direction(D::direction());
+    #[cfg(not(feature = "rm0455"))]
+    if T::TRBUFF {
+        stream.set_trbuff(true);
+    }
+    stream.set_number_of_transfers(len);
+
+    stream.set_peripheral_address(target.address());
+    stream.set_memory_address(CurrentBuffer::Buffer0, memory);
+    stream.set_memory_size(0);
+    stream.set_peripheral_size(0);
+    stream.enable();
+}
+
 // Macro that creates a struct representing a stream on either DMA controller
 //
 // The implementation does the heavy lifting of mapping to the right fields on
@@ -815,6 +965,13 @@ macro_rules! dma_stream {
                     dma.$isr.read().$tcisr().bit_is_set()
                 }
 
+                #[inline(always)]
+                fn stream_get_transfer_error_flag() -> bool {
+                    //NOTE(unsafe) Atomic read with no side effects
+                    let dma = unsafe { &*I::ptr() };
+                    dma.$isr.read().$teisr().bit_is_set()
+                }
+
                 #[inline(always)]
                 fn stream_get_half_transfer_flag() -> bool {
                     //NOTE(unsafe) Atomic read with no side effects
@@ -916,6 +1073,137 @@ peripheral_register_markers!(CCR1, CCR2, CCR3, CCR4, DMAR, ARR);
 /// Type alias for the DMA Request Multiplexer
 pub type DMAReq = pac::dmamux1::ccr::DMAREQ_ID_A;
 
+/// Type alias for the trigger signals of the DMAMUX1 request generators
+pub type DmamuxSyncId = pac::dmamux1::rgcr::SIG_ID_A;
+
+/// A DMAMUX1 request generator
+///
+/// Each of the eight request generators produces DMA requests when its
+/// trigger signal is detected. The generated requests are selected as the
+/// request line of a DMA1 or DMA2 stream with
+/// `DMAReq::Dmamux1ReqGen0` to `DMAReq::Dmamux1ReqGen7`, allowing a DMA
+/// transfer to be paced by an LPTIM output, `TIM12_TRGO`, EXTI line 0 or the
+/// event output of another DMAMUX1 channel.
+///
+/// If a new trigger is detected before all the requests for the previous
+/// trigger have been served, the request generator sets its overrun flag.
+#[derive(Debug)]
+pub struct DmamuxReqGen {
+    index: u8,
+}
+
+impl DmamuxReqGen {
+    /// Configures and enables request generator `req_gen_index` of
+    /// DMAMUX1. Each `edge` of `signal` generates `requests_per_sync` DMA
+    /// requests
+    ///
+    /// # Panics
+    ///
+    /// * When `req_gen_index` is greater than 7
+    /// * When `requests_per_sync` is not in the range 1 to 32
+    ///
+    /// # Safety
+    ///
+    /// Only one `DmamuxReqGen` may exist for each request generator. The
+    /// caller must not create another for the same `req_gen_index` until
+    /// the existing one has been dropped or disabled.
+    pub unsafe fn new(
+        dmamux: &DMAMUX1,
+        req_gen_index: u8,
+        signal: DmamuxSyncId,
+        edge: Edge,
+        requests_per_sync: u8,
+    ) -> Self {
+        assert!(req_gen_index < 8, "There are 8 request generators");
+        assert!(
+            (1..=32).contains(&requests_per_sync),
+            "Between 1 and 32 requests can be generated for each trigger"
+        );
+
+        let rgcr = &dmamux.rgcr[req_gen_index as usize];
+
+        // The configuration can only be changed when the generator is
+        // disabled
+        rgcr.modify(|_, w| w.ge().disabled());
+        rgcr.modify(|_, w| {
+            match edge {
+                Edge::Rising => w.gpol().rising_edge(),
+                Edge::Falling => w.gpol().falling_edge(),
+                Edge::RisingFalling => w.gpol().both_edges(),
+            };
+            w.sig_id()
+                .variant(signal)
+                .gnbreq()
+                .bits(requests_per_sync - 1)
+        });
+        rgcr.modify(|_, w| w.ge().enabled());
+
+        DmamuxReqGen {
+            index: req_gen_index,
+        }
+    }
+
+    /// Returns the DMA request line for this request generator
+    pub fn request_line(&self) -> DMAReq {
+        match self.index {
+            0 => DMAReq::Dmamux1ReqGen0,
+            1 => DMAReq::Dmamux1ReqGen1,
+            2 => DMAReq::Dmamux1ReqGen2,
+            3 => DMAReq::Dmamux1ReqGen3,
+            4 => DMAReq::Dmamux1ReqGen4,
+            5 => DMAReq::Dmamux1ReqGen5,
+            6 => DMAReq::Dmamux1ReqGen6,
+            _ => DMAReq::Dmamux1ReqGen7,
+        }
+    }
+
+    /// Triggers the request generator from software, by generating a
+    /// software event on EXTI line 0
+    ///
+    /// This only has an effect when the request generator was configured
+    /// with `DmamuxSyncId::Extit0` and a rising edge.
+    pub fn trigger(&mut self) {
+        debug_assert!(
+            self.rgcr().read().sig_id().variant() == Some(DmamuxSyncId::Extit0),
+            "Software triggers require EXTI line 0 as the trigger signal"
+        );
+
+        // NOTE(unsafe) Atomic write to a stateless register, only setting
+        // the bit for EXTI line 0
+        unsafe {
+            (*pac::EXTI::ptr()).swier1.write(|w| w.bits(1));
+        }
+    }
+
+    /// Returns `true` if a trigger was detected before all the requests for
+    /// the previous trigger had been served
+    pub fn is_overrun(&self) -> bool {
+        // NOTE(unsafe) Atomic read with no side effects
+        let rgsr = unsafe { &(*DMAMUX1::ptr()).rgsr };
+        rgsr.read().bits() & (1 << self.index) != 0
+    }
+
+    /// Clears the overrun flag
+    pub fn clear_overrun(&mut self) {
+        // NOTE(unsafe) Atomic write to a stateless register, only setting
+        // the bit for this request generator
+        unsafe {
+            (*DMAMUX1::ptr()).rgcfr.write(|w| w.bits(1 << self.index));
+        }
+    }
+
+    /// Disables the request generator
+    pub fn disable(self) {
+        self.rgcr().modify(|_, w| w.ge().disabled());
+    }
+
+    fn rgcr(&self) -> &'static pac::dmamux1::RGCR {
+        // NOTE(unsafe) Each request generator is owned by only one
+        // DmamuxReqGen, as required by the safety contract of `new`
+        unsafe { &(*DMAMUX1::ptr()).rgcr[self.index as usize] }
+    }
+}
+
 type P2M = PeripheralToMemory;
 type M2P = MemoryToPeripheral;
 
@@ -1169,6 +1457,43 @@ peripheral_target_address!(
         DMAReq::Adc2Dma
     )
 );
+peripheral_target_address!(
+    (pac::DCMI, dr, u32, P2M, DMAReq::DcmiDma),
+    (INNER: Dcmi, dr, u32, P2M, DMAReq::DcmiDma)
+);
+
+// The SPDIFRX data register is accessed through a method on most parts
+#[cfg(not(feature = "rm0468"))]
+macro_rules! spdifrx_dr {
+    ($rb:expr) => {
+        $rb.dr_00()
+    };
+}
+#[cfg(feature = "rm0468")]
+macro_rules! spdifrx_dr {
+    ($rb:expr) => {
+        &$rb.fmt0_dr
+    };
+}
+unsafe impl TargetAddress<P2M> for pac::SPDIFRX {
+    #[inline(always)]
+    fn address(&self) -> usize {
+        spdifrx_dr!(self) as *const _ as usize
+    }
+
+    type MemSize = u32;
+    const REQUEST_LINE: Option<u8> = Some(DMAReq::SpdifrxDatDma as u8);
+}
+unsafe impl TargetAddress<P2M> for Spdifrx {
+    #[inline(always)]
+    fn address(&self) -> usize {
+        spdifrx_dr!(self.inner()) as *const _ as usize
+    }
+
+    type MemSize = u32;
+    const REQUEST_LINE: Option<u8> = Some(DMAReq::SpdifrxDatDma as u8);
+}
+
 #[cfg(not(feature = "rm0455"))]
 peripheral_target_address!((
     HAL: Adc<pac::ADC3, adc::Enabled>,
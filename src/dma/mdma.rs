@@ -49,6 +49,21 @@
 //! line originates from software and the transfer is started immediately when
 //! [`enable`](Stream0#method.enable) is called.
 //!
+//! ## Memory to Memory Copies
+//!
+//! [`mdma_copy`](StreamX::mdma_copy) copies between two byte slices, choosing
+//! the largest data size and burst size allowed by the alignment of the
+//! buffers. The chosen parameters can be inspected with [`copy_parameters`].
+//! For other width conversions, use a
+//! [`Transfer`](super::Transfer) with the [`packing_alignment`](MdmaConfig#method.packing_alignment)
+//! and burst sizes set in the [`MdmaConfig`].
+//!
+//! ```
+//! let streams = StreamsTuple::new(dp.MDMA, ccdr.peripheral.MDMA);
+//! let mut stream = streams.0;
+//!
+//! stream.mdma_copy(&mut target[..], &source[..]).unwrap();
+//! ```
 //!
 
 use super::{
@@ -57,12 +72,14 @@ use super::{
         sealed::{Bits, Sealed},
         MasterStream, TargetAddress,
     },
-    DmaDirection, MemoryToPeripheral, PeripheralToMemory,
+    DMAError, DmaDirection, MemoryToPeripheral, PeripheralToMemory,
 };
 
+use core::cmp;
 use core::fmt;
 use core::marker::PhantomData;
 use core::mem;
+use core::sync::atomic::{self, Ordering};
 
 use crate::{
     pac::{self, MDMA},
@@ -661,6 +678,155 @@ pub fn is_ahb_port(address: usize) -> bool {
     is_itcm || is_dtcm
 }
 
+/// Transfer parameters for a memory to memory copy, chosen by
+/// [`copy_parameters`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MdmaCopyParameters {
+    /// Source and destination size
+    pub size: MdmaSize,
+    /// Source and destination burst size
+    pub burst: MdmaBurstSize,
+    /// Number of bytes in each buffer, up to 128
+    pub buffer_length: u8,
+}
+
+impl MdmaCopyParameters {
+    /// Returns the value of the MDMA_CxTCR register for these parameters.
+    /// Both addresses are incremented by the data size, and the block is
+    /// started by a software request
+    pub fn tcr(&self) -> u32 {
+        let size = self.size as u32;
+        let burst = self.burst.0 as u32;
+
+        0b10 // SINC: Increment
+            | (0b10 << 2) // DINC: Increment
+            | (size << 4) // SSIZE
+            | (size << 6) // DSIZE
+            | (size << 8) // SINCOS
+            | (size << 10) // DINCOS
+            | (burst << 12) // SBURST
+            | (burst << 15) // DBURST
+            | ((self.buffer_length as u32 - 1) << 18) // TLEN
+            | ((MdmaTrigger::Block as u32) << 28) // TRGM
+            | (1 << 30) // SWRM
+    }
+}
+
+/// Chooses the transfer parameters for copying `len` bytes from `source` to
+/// `destination`
+///
+/// The data size is the largest size that both addresses and the length
+/// are aligned to, limited to a word if either address is accessed through
+/// the 32-bit AHB port. The burst size is the largest that fits in one
+/// buffer of up to 128 bytes.
+///
+/// # Panics
+///
+/// Panics if `len` is zero
+pub fn copy_parameters(
+    source: usize,
+    destination: usize,
+    len: usize,
+) -> MdmaCopyParameters {
+    assert!(len > 0, "Cannot copy zero bytes");
+
+    let alignment = source | destination | len;
+    let ahb = is_ahb_port(source) || is_ahb_port(destination);
+
+    let size = if alignment % 8 == 0 && !ahb {
+        MdmaSize::DoubleWord
+    } else if alignment % 4 == 0 {
+        MdmaSize::Word
+    } else if alignment % 2 == 0 {
+        MdmaSize::HalfWord
+    } else {
+        MdmaSize::Byte
+    };
+
+    // A multiple of every size
+    let buffer_length = cmp::min(len, 128);
+    let buffer_length = buffer_length - buffer_length % size.n_bytes();
+
+    // Largest power of two beats that fit in one buffer
+    let beats = buffer_length / size.n_bytes();
+    let burst = MdmaBurstSize::from_size(cmp::min(beats, 128));
+
+    MdmaCopyParameters {
+        size,
+        burst,
+        buffer_length: buffer_length as u8,
+    }
+}
+
+/// Memory to memory copy on an MDMA stream, see [`StreamX::mdma_copy`]
+fn copy<STREAM>(
+    stream: &mut STREAM,
+    destination: &mut [u8],
+    source: &[u8],
+) -> Result<(), DMAError>
+where
+    STREAM: InstanceStream + MasterStream,
+{
+    assert_eq!(
+        destination.len(),
+        source.len(),
+        "Source and destination must have the same length"
+    );
+    assert!(
+        source.len() <= 65536,
+        "Hardware does not support more than 65536 bytes in a single transfer"
+    );
+    if source.is_empty() {
+        return Ok(());
+    }
+
+    stream.disable();
+
+    let params = copy_parameters(
+        source.as_ptr() as usize,
+        destination.as_mut_ptr() as usize,
+        source.len(),
+    );
+
+    // NOTE(unsafe) The parameters are valid for the alignment of both buffers,
+    // and the block length is the length of both buffers
+    unsafe {
+        STREAM::channel().tcr.write(|w| w.bits(params.tcr()));
+        stream.set_block_bytes(source.len() as u32);
+        stream.set_source_address(source.as_ptr() as usize);
+        stream.set_destination_address(destination.as_mut_ptr() as usize);
+    }
+    stream.clear_interrupts();
+
+    // Preceding reads and writes cannot be moved past the start of the
+    // transfer
+    atomic::fence(Ordering::SeqCst);
+
+    // NOTE(unsafe) Both buffers are borrowed until the transfer is complete
+    unsafe { stream.enable() };
+
+    //NOTE(unsafe) Atomic read with no side effects
+    let ch = unsafe { STREAM::channel() };
+    let result = loop {
+        let isr = ch.isr.read();
+        if isr.teif().bit_is_set() {
+            break Err(DMAError::TransferError);
+        }
+        if isr.ctcif().bit_is_set() {
+            break Ok(());
+        }
+    };
+
+    stream.disable();
+    stream.clear_interrupts();
+
+    // Subsequent reads cannot be moved before the end of the transfer
+    atomic::fence(Ordering::SeqCst);
+
+    result
+}
+
 impl<I: Instance, const S: u8> traits::Stream for StreamX<I, S>
 where
     Self: InstanceStream + Sealed,
@@ -773,6 +939,12 @@ where
         unsafe { Self::channel() }.isr.read().ctcif().bit_is_set()
     }
 
+    #[inline(always)]
+    fn get_transfer_error_flag() -> bool {
+        //NOTE(unsafe) Atomic read with no side effects
+        unsafe { Self::channel() }.isr.read().teif().bit_is_set()
+    }
+
     #[inline(always)]
     unsafe fn enable(&mut self) {
         //NOTE(unsafe) We only access the registers that belongs to the StreamX
@@ -1176,6 +1348,32 @@ where
 macro_rules! mdma_stream {
     ($( ($name:ident, $channel:ident, $number:expr) ),+$(,)*) => {
         $(
+            impl<I: Instance> StreamX<I, $number> {
+                /// Copies `source` to `destination`, blocking until the
+                /// copy is complete. The transfer parameters are chosen by
+                /// [`copy_parameters`] from the alignment of the buffers.
+                ///
+                /// The MDMA does not access memory through the data cache.
+                /// If the data cache is enabled, the source must be cleaned
+                /// and the destination invalidated.
+                ///
+                /// # Panics
+                ///
+                /// Panics if the slices have different lengths, or if they
+                /// are longer than 65536 bytes.
+                ///
+                /// # Errors
+                ///
+                /// Returns an error if the MDMA reports a transfer error.
+                pub fn mdma_copy(
+                    &mut self,
+                    destination: &mut [u8],
+                    source: &[u8],
+                ) -> Result<(), DMAError> {
+                    copy(self, destination, source)
+                }
+            }
+
             impl<I: Instance> InstanceStream for StreamX<I, $number> {
                 unsafe fn channel() -> &'static pac::mdma::CH {
                     &(*I::ptr()).$channel
@@ -1292,3 +1490,39 @@ peripheral_target_address!(
     (INNER: crate::xspi::Octospi<pac::OCTOSPI2>, dr, u32, P2M),
     (INNER: crate::xspi::Octospi<pac::OCTOSPI2>, dr, u32, M2P),
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_parameters_aligned() {
+        // AXI SRAM, double-word aligned
+        let params = copy_parameters(0x2400_0000, 0x2400_1000, 1024);
+        assert_eq!(params.size, MdmaSize::DoubleWord);
+        assert_eq!(params.burst, MdmaBurstSize(4)); // 16 beats
+        assert_eq!(params.buffer_length, 128);
+        assert_eq!(params.tcr(), 0x51FE_4FFA);
+
+        // DTCM is accessed through the 32-bit AHB port
+        let params = copy_parameters(0x2000_0000, 0x2400_0000, 1024);
+        assert_eq!(params.size, MdmaSize::Word);
+        assert_eq!(params.burst, MdmaBurstSize(5)); // 32 beats
+        assert_eq!(params.buffer_length, 128);
+    }
+
+    #[test]
+    fn copy_parameters_unaligned() {
+        let params = copy_parameters(0x2400_0000, 0x2400_1001, 1000);
+        assert_eq!(params.size, MdmaSize::Byte);
+        assert_eq!(params.burst, MdmaBurstSize(7)); // 128 beats
+        assert_eq!(params.buffer_length, 128);
+
+        // 3 half-words fit in one buffer, the burst is 2 beats
+        let params = copy_parameters(0x2400_0002, 0x2400_1000, 6);
+        assert_eq!(params.size, MdmaSize::HalfWord);
+        assert_eq!(params.burst, MdmaBurstSize(1));
+        assert_eq!(params.buffer_length, 6);
+        assert_eq!(params.tcr(), 0x5014_955A);
+    }
+}
@@ -64,6 +64,12 @@
 //! behaviour**. Instead, the transfer should be
 //! [continued](#continuing-a-transfer).
 //!
+//! ### Memory to memory transfers
+//!
+//! On DMA1 and DMA2, a memory to memory transfer between two buffers of the
+//! same length can be configured with
+//! [`init_memory_to_memory`](Transfer#method.init_memory_to_memory).
+//!
 //! ### Continuing a transfer
 //!
 //! For DMA controllers that support Double Buffering, the following methods are
@@ -127,6 +133,23 @@ pub enum DMAError {
     SmallBuffer,
     /// DMA started transfer on the inactive buffer while the user was processing it.
     Overflow,
+    /// The transfer was stopped by a bus error
+    TransferError,
+}
+
+/// A transfer aborted with [`Transfer::abort`] had been stopped by a
+/// transfer error. Contains the resources of the transfer
+pub struct DmaAbortError<STREAM, PERIPHERAL, BUF> {
+    pub stream: STREAM,
+    pub peripheral: PERIPHERAL,
+    pub buf: BUF,
+    pub double_buf: Option<BUF>,
+}
+
+impl<STREAM, PERIPHERAL, BUF> Debug for DmaAbortError<STREAM, PERIPHERAL, BUF> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("DmaAbortError")
+    }
 }
 
 /// Possible DMA's directions.
@@ -639,6 +662,7 @@ macro_rules! db_transfer_def {
                 if single_buffer {
                     // Set length before the writing the new valid address.
                     self.stream.set_number_of_transfers(buf_len as u16);
+                    self.inner.transfer_length = buf_len as u16;
                 } else if buf_len != usize::from(self.inner.transfer_length) {
                     // We can't change the transfer length while double buffering
                     return Err(DMAError::SmallBuffer);
@@ -751,6 +775,33 @@ macro_rules! db_transfer_def {
             pub fn get_half_transfer_flag(&self) -> bool {
                 STREAM::get_half_transfer_flag()
             }
+
+            /// Returns the number of transfers remaining for the current
+            /// buffer, from the NDTR register
+            ///
+            /// This can be called whilst the transfer is ongoing. In circular
+            /// mode, the count is reloaded at the end of each pass over the
+            /// buffer.
+            #[inline(always)]
+            pub fn remaining_transfers(&self) -> u16 {
+                STREAM::get_number_of_transfers()
+            }
+
+            /// Returns the number of bytes transferred to or from the
+            /// current buffer
+            ///
+            /// This can be called whilst the transfer is ongoing. For a
+            /// circular peripheral to memory transfer, this is the position
+            /// in the buffer of the next write by the DMA, so the data before
+            /// it can be consumed.
+            pub fn bytes_transferred(&self) -> usize {
+                let transferred = self
+                    .inner
+                    .transfer_length
+                    .saturating_sub(STREAM::get_number_of_transfers());
+                usize::from(transferred)
+                    * mem::size_of::<<PERIPHERAL as TargetAddress<DIR>>::MemSize>()
+            }
         }
     };
 }
@@ -759,6 +810,64 @@ db_transfer_def!(DBTransfer, init, WriteBuffer, write_buffer, mut;);
 db_transfer_def!(ConstDBTransfer, init_const, ReadBuffer, read_buffer;
                  assert!(DIR::direction() != DmaDirection::PeripheralToMemory));
 
+impl<STREAM, T, BUF>
+    Transfer<STREAM, MemoryToMemory<T>, MemoryToMemory<T>, BUF, DBTransfer>
+where
+    STREAM: DoubleBufferedStream + Stream<Config = dma::DmaConfig>,
+    MemoryToMemory<T>: TargetAddress<MemoryToMemory<T>, MemSize = T>,
+    BUF: WriteBuffer<Word = T>,
+{
+    /// Configures a memory to memory transfer on a DMA1 or DMA2 stream,
+    /// copying all of `source` into `destination`.
+    ///
+    /// Both addresses are incremented and the FIFO is enabled, as required
+    /// for memory to memory transfers. Other settings are left at their
+    /// defaults; use [`init`](Transfer#method.init) with a [`DmaConfig`] to
+    /// change them. The source is returned as the second buffer when the
+    /// transfer is freed.
+    ///
+    /// ```
+    /// let mut transfer: Transfer<_, _, MemoryToMemory<u32>, _, _> =
+    ///     Transfer::init_memory_to_memory(streams.4, source, destination);
+    /// transfer.start(|_| {});
+    /// while !transfer.get_transfer_complete_flag() {}
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// * When `source` and `destination` have different lengths.
+    /// * When the transfer length is greater than (2^16 - 1)
+    ///
+    /// [`DmaConfig`]: dma::DmaConfig
+    pub fn init_memory_to_memory(
+        stream: STREAM,
+        mut source: BUF,
+        mut destination: BUF,
+    ) -> Self {
+        // NOTE(unsafe) The lengths are only compared here. The buffers are
+        // owned by the transfer from now on
+        let (_, source_len) = unsafe { source.write_buffer() };
+        let (_, destination_len) = unsafe { destination.write_buffer() };
+        assert_eq!(
+            source_len, destination_len,
+            "Source and destination lengths differ"
+        );
+
+        let config = dma::DmaConfig::default()
+            .memory_increment(true) // destination
+            .peripheral_increment(true) // source
+            .fifo_enable(true);
+
+        Self::init(
+            stream,
+            MemoryToMemory::new(),
+            destination,
+            Some(source),
+            config,
+        )
+    }
+}
+
 impl<STREAM, CONFIG, PERIPHERAL, DIR, BUF, TXFRT>
     Transfer<STREAM, PERIPHERAL, DIR, BUF, TXFRT>
 where
@@ -812,6 +921,46 @@ where
         }
     }
 
+    /// Aborts the transfer, and returns the underlying resources. The
+    /// stream is disabled, and its flags are cleared once it has stopped.
+    ///
+    /// If the peripheral must stop making DMA requests before the stream is
+    /// disabled, do this first with [`pause`](#method.pause).
+    ///
+    /// The number of transfers that were not done can be read from the
+    /// returned stream with
+    /// [`get_number_of_transfers`](traits::DoubleBufferedStream::get_number_of_transfers),
+    /// where this is supported. If the transfer completed before it could be
+    /// aborted, the resources are returned as normal.
+    ///
+    /// # Errors
+    ///
+    /// Returns a [`DmaAbortError`], containing the resources, if the
+    /// transfer had already been stopped by a transfer error.
+    #[allow(clippy::type_complexity)]
+    pub fn abort(
+        self,
+    ) -> Result<
+        (STREAM, PERIPHERAL, BUF, Option<BUF>),
+        DmaAbortError<STREAM, PERIPHERAL, BUF>,
+    > {
+        // The stream is disabled by hardware on a transfer error, and the
+        // flags are then kept until they are cleared by `free`
+        let error = !STREAM::is_enabled() && STREAM::get_transfer_error_flag();
+
+        let (stream, peripheral, buf, double_buf) = self.free();
+        if error {
+            Err(DmaAbortError {
+                stream,
+                peripheral,
+                buf,
+                double_buf,
+            })
+        } else {
+            Ok((stream, peripheral, buf, double_buf))
+        }
+    }
+
     /// Clear all interrupts for the DMA stream.
     #[inline(always)]
     pub fn clear_interrupts(&mut self) {
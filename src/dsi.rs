@@ -33,6 +33,7 @@ pub enum DsiChannel {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     RegTimeout,
     PllTimeout,
@@ -24,6 +24,7 @@
 
 use core::ptr;
 
+use crate::dma::mdma;
 use crate::rcc::{rec, CoreClocks, ResetEnable};
 use crate::stm32;
 
@@ -145,11 +146,16 @@ impl<const TD: usize> TDesRing<TD> {
 
     /// Release the next TDes to the DMA engine for transmission
     pub fn release(&mut self) {
+        let address = ptr::addr_of!(self.tbuf[self.tdidx]) as u32;
+        self.release_buffer(address);
+    }
+
+    /// Release the next TDes to the DMA engine for transmission from
+    /// the buffer at `address`. The buffer length must already be set
+    fn release_buffer(&mut self, address: u32) {
         let x = self.tdidx;
         assert!(self.td[x].tdes3 & EMAC_DES3_OWN == 0); // Owned by us
 
-        let address = ptr::addr_of!(self.tbuf[x]) as u32;
-
         // Read format
         self.td[x].tdes0 = address; // Buffer 1
         self.td[x].tdes1 = 0; // Not used
@@ -749,16 +755,22 @@ impl EthernetMAC {
             clock_range: self.clock_range,
         }
     }
-}
 
-/// PHY Operations
-impl StationManagement for EthernetMAC {
-    /// Read a register over SMI.
-    fn smi_read(&mut self, reg: u8) -> u16 {
+    /// Returns the SMI address used for the PHY by the
+    /// [StationManagement](super::StationManagement) trait
+    pub fn phy_addr(&self) -> u8 {
+        self.eth_phy_addr
+    }
+
+    /// Read register `reg` of the PHY at SMI address `phy_addr`.
+    ///
+    /// This allows access to any PHY on the MDIO bus, independently of the
+    /// PHY address set by [set_phy_addr](EthernetMAC::set_phy_addr).
+    pub fn smi_read_phy(&mut self, phy_addr: u8, reg: u8) -> u16 {
         while self.eth_mac.macmdioar.read().mb().bit_is_set() {}
         self.eth_mac.macmdioar.modify(|_, w| unsafe {
             w.pa()
-                .bits(self.eth_phy_addr)
+                .bits(phy_addr)
                 .rda()
                 .bits(reg)
                 .goc()
@@ -772,15 +784,18 @@ impl StationManagement for EthernetMAC {
         self.eth_mac.macmdiodr.read().md().bits()
     }
 
-    /// Write a register over SMI.
-    fn smi_write(&mut self, reg: u8, val: u16) {
+    /// Write `val` to register `reg` of the PHY at SMI address `phy_addr`.
+    ///
+    /// This allows access to any PHY on the MDIO bus, independently of the
+    /// PHY address set by [set_phy_addr](EthernetMAC::set_phy_addr).
+    pub fn smi_write_phy(&mut self, phy_addr: u8, reg: u8, val: u16) {
         while self.eth_mac.macmdioar.read().mb().bit_is_set() {}
         self.eth_mac
             .macmdiodr
             .write(|w| unsafe { w.md().bits(val) });
         self.eth_mac.macmdioar.modify(|_, w| unsafe {
             w.pa()
-                .bits(self.eth_phy_addr)
+                .bits(phy_addr)
                 .rda()
                 .bits(reg)
                 .goc()
@@ -794,6 +809,33 @@ impl StationManagement for EthernetMAC {
     }
 }
 
+/// PHY Operations
+impl StationManagement for EthernetMAC {
+    /// Read a register over SMI.
+    fn smi_read(&mut self, reg: u8) -> u16 {
+        self.smi_read_phy(self.eth_phy_addr, reg)
+    }
+
+    /// Write a register over SMI.
+    fn smi_write(&mut self, reg: u8, val: u16) {
+        self.smi_write_phy(self.eth_phy_addr, reg, val)
+    }
+}
+
+/// Errors from [`transmit_zero_copy`](EthernetDMA::transmit_zero_copy)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum TxError {
+    /// All the transmit descriptors are owned by the DMA engine
+    Busy,
+    /// The payload is empty, or longer than a transmit buffer
+    InvalidLength,
+    /// The payload is in ITCM or DTCM, which cannot be accessed by the
+    /// ethernet DMA engine
+    InaccessibleMemory,
+}
+
 /// Define TxToken type and implement consume method
 pub struct TxToken<'a, const TD: usize>(&'a mut TDesRing<TD>);
 
@@ -871,6 +913,47 @@ impl<const TD: usize, const RD: usize> EthernetDMA<TD, RD> {
     pub fn number_packets_dropped(&self) -> u32 {
         self.eth_dma.dmacmfcr.read().mfc().bits() as u32
     }
+
+    /// Transmit a frame directly from `payload`, without copying it into
+    /// the transmit buffers
+    ///
+    /// The transmit descriptor is pointed at `payload`, and the ethernet
+    /// DMA engine reads the frame from there. `payload` must contain the
+    /// complete ethernet frame without the CRC, which is appended by the
+    /// MAC.
+    ///
+    /// The ethernet DMA engine cannot access ITCM or DTCM. If the data
+    /// cache is enabled, `payload` must be cleaned from the data cache
+    /// before calling this method.
+    ///
+    /// This does not use the MDMA. The ethernet DMA engine is a bus master
+    /// that reads the transmit buffer through the descriptor, so pointing
+    /// the descriptor at `payload` avoids the copy altogether. Using the
+    /// MDMA to move `payload` into a transmit buffer would still be a copy,
+    /// only made by another bus master. For the same reason no `TxToken`
+    /// is returned: a `TxToken` lends out a transmit buffer to be filled,
+    /// whereas here the frame is already complete and is queued directly.
+    pub fn transmit_zero_copy(
+        &mut self,
+        payload: &'static [u8],
+    ) -> Result<(), TxError> {
+        if payload.is_empty() || payload.len() > ETH_BUF_SIZE {
+            return Err(TxError::InvalidLength);
+        }
+        let address = payload.as_ptr() as usize;
+        if mdma::is_ahb_port(address) {
+            return Err(TxError::InaccessibleMemory);
+        }
+
+        let tx = &mut self.ring.tx;
+        if !tx.available() {
+            return Err(TxError::Busy);
+        }
+
+        tx.td[tx.tdidx].tdes2 = (payload.len() as u32) & EMAC_TDES2_B1L;
+        tx.release_buffer(address as u32);
+        Ok(())
+    }
 }
 
 /// Clears the Ethernet interrupt flag
@@ -5,6 +5,20 @@
 //! - SMSC LAN8742a
 //! - Micrel KSZ8081R
 //!
+//! Other PHYs can be accessed through the [StationManagement] trait, or
+//! with [smi_read_phy](EthernetMAC::smi_read_phy) and
+//! [smi_write_phy](EthernetMAC::smi_write_phy) for any SMI address. This
+//! gives access to link status, auto-negotiation results and vendor
+//! specific registers.
+//!
+//! # Zero-copy transmit
+//!
+//! Frames are usually transmitted through smoltcp, which writes each frame
+//! into a transmit buffer in the [DesRing]. Large frames that are already in
+//! static memory can instead be transmitted with
+//! [transmit_zero_copy](EthernetDMA::transmit_zero_copy), where the ethernet
+//! DMA engine reads the frame directly from the application's buffer.
+//!
 //! # Examples
 //!
 //! - [Simple link checker for the Nucleo-H743ZI2](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/ethernet-nucleo-h743zi2.rs)
@@ -41,7 +55,7 @@ pub mod phy {
 
 mod eth;
 pub use eth::{enable_interrupt, interrupt_handler, new, new_unchecked};
-pub use eth::{DesRing, EthernetDMA, EthernetMAC};
+pub use eth::{DesRing, EthernetDMA, EthernetMAC, TxError};
 
 /// Marks a set of pins used to communciate to a PHY with a Reduced Media
 /// Independent Interface (RMII)
@@ -0,0 +1,140 @@
+//! ECC error reporting
+//!
+//! Each flash word is protected by an ECC. Single bit errors are corrected
+//! on read, and double bit errors are detected. Either can raise the `FLASH`
+//! interrupt, which must be unmasked in the NVIC and call
+//! [`flash_ecc_interrupt`] from its handler.
+//!
+//! ```
+//! fn single_bit(address: u32) {
+//!     defmt::warn!("Corrected ECC error at {=u32:#x}", address);
+//! }
+//!
+//! fn double_bit(address: u32) {
+//!     defmt::error!("Uncorrectable ECC error at {=u32:#x}", address);
+//! }
+//!
+//! let (mut bank1, _) = dp.FLASH.split();
+//! bank1.enable_ecc_interrupt(single_bit, double_bit);
+//! unsafe { NVIC::unmask(pac::Interrupt::FLASH) };
+//!
+//! #[interrupt]
+//! fn FLASH() {
+//!     flash::flash_ecc_interrupt();
+//! }
+//! ```
+
+use core::mem;
+use core::sync::atomic::{AtomicPtr, AtomicU32, Ordering};
+
+use super::{LockedFlashBank, WRITE_SIZE};
+use crate::stm32::flash::BANK;
+use crate::stm32::FLASH;
+
+/// Handler for corrected single bit errors. Null if not enabled
+static SINGLE_HANDLER: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+/// Handler for detected double bit errors. Null if not enabled
+static DOUBLE_HANDLER: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Number of corrected single bit errors
+static ECC_ERRORS: AtomicU32 = AtomicU32::new(0);
+/// Address of the last corrected single bit error
+static ECC_ERROR_ADDRESS: AtomicU32 = AtomicU32::new(0);
+
+impl LockedFlashBank {
+    /// Enables the interrupts for ECC errors in this bank
+    ///
+    /// [`flash_ecc_interrupt`] calls `single_bit` with the address of each
+    /// corrected single bit error, and `double_bit` with the address of a
+    /// double bit error. The handlers are shared by both banks.
+    pub fn enable_ecc_interrupt(
+        &mut self,
+        single_bit: fn(u32),
+        double_bit: fn(u32),
+    ) {
+        SINGLE_HANDLER.store(single_bit as *mut (), Ordering::Release);
+        DOUBLE_HANDLER.store(double_bit as *mut (), Ordering::Release);
+
+        let regs = self.registers();
+        regs.ccr
+            .write(|w| w.clr_sneccerr().set_bit().clr_dbeccerr().set_bit());
+        regs.cr
+            .modify(|_, w| w.sneccerrie().set_bit().dbeccerrie().set_bit());
+    }
+
+    /// Disables the interrupts for ECC errors in this bank
+    pub fn disable_ecc_interrupt(&mut self) {
+        self.registers()
+            .cr
+            .modify(|_, w| w.sneccerrie().clear_bit().dbeccerrie().clear_bit());
+    }
+}
+
+/// Returns the number of corrected single bit errors reported by
+/// [`flash_ecc_interrupt`]
+pub fn ecc_error_count() -> u32 {
+    ECC_ERRORS.load(Ordering::Relaxed)
+}
+
+/// Returns the address of the last corrected single bit error, or `None` if
+/// no error has been corrected
+pub fn ecc_error_address() -> Option<u32> {
+    (ecc_error_count() > 0).then(|| ECC_ERROR_ADDRESS.load(Ordering::Relaxed))
+}
+
+/// Address of the flash word in which an ECC error occurred
+fn failing_address(regs: &BANK, base: u32) -> u32 {
+    base + regs.far.read().fail_ecc_addr().bits() as u32 * WRITE_SIZE as u32
+}
+
+/// Calls the handler stored in `handler`, if any
+fn call(handler: &AtomicPtr<()>, address: u32) {
+    let handler = handler.load(Ordering::Acquire);
+    if !handler.is_null() {
+        // unsafe: only ever set from a `fn(u32)` in `enable_ecc_interrupt`
+        let handler: fn(u32) = unsafe { mem::transmute(handler) };
+        handler(address);
+    }
+}
+
+/// Handles ECC errors. To be called from the `FLASH` interrupt handler
+///
+/// For each corrected single bit error, records the address, increments
+/// [`ecc_error_count`] and calls the single bit handler passed to
+/// [`enable_ecc_interrupt`](LockedFlashBank::enable_ecc_interrupt).
+///
+/// The contents of the flash are not valid after a double bit error, so
+/// after the double bit handler returns this triggers a system reset.
+pub fn flash_ecc_interrupt() {
+    // unsafe: the ECC flags and the fail address are only accessed here
+    let flash = unsafe { &*FLASH::ptr() };
+
+    for (regs, base) in
+        [(flash.bank1(), 0x0800_0000), (flash.bank2(), 0x0810_0000)]
+    {
+        if regs.cr.read().sneccerrie().bit_is_clear() {
+            continue;
+        }
+
+        let sr = regs.sr.read();
+        if sr.dbeccerr().bit_is_set() {
+            let address = failing_address(regs, base);
+            regs.ccr.write(|w| w.clr_dbeccerr().set_bit());
+
+            call(&DOUBLE_HANDLER, address);
+            cortex_m::peripheral::SCB::sys_reset();
+        }
+        #[cfg(not(feature = "rm0399"))]
+        let sneccerr = sr.sneccerr1().bit_is_set();
+        #[cfg(feature = "rm0399")]
+        let sneccerr = sr.sneccerr().bit_is_set();
+        if sneccerr {
+            let address = failing_address(regs, base);
+            regs.ccr.write(|w| w.clr_sneccerr().set_bit());
+
+            ECC_ERROR_ADDRESS.store(address, Ordering::Relaxed);
+            ECC_ERRORS.fetch_add(1, Ordering::Relaxed);
+            call(&SINGLE_HANDLER, address);
+        }
+    }
+}
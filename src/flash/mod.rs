@@ -17,6 +17,12 @@
 //! | RM0399 | 1MB, 2MB | Two | 128kB
 //! | RM0455 | 128kB, 1MB, 2MB | One or Two | 8kB
 //! | RM0468 | 128kB, 512kB, 1MB | One | 128kB
+//!
+//! # ECC errors
+//!
+//! Corrected and uncorrectable ECC errors can be reported by the `FLASH`
+//! interrupt, see [`LockedFlashBank::enable_ecc_interrupt`] and
+//! [`flash_ecc_interrupt`].
 
 use core::slice;
 
@@ -24,7 +30,9 @@ use crate::signature::FlashSize;
 use crate::stm32::{flash::BANK, FLASH};
 use embedded_storage::nor_flash;
 
+mod ecc;
 mod operations;
+pub use ecc::{ecc_error_address, ecc_error_count, flash_ecc_interrupt};
 pub use operations::{Error, UnlockedFlashBank};
 
 // All sectors in the user main memory sectors have the same size. Only the
@@ -12,6 +12,7 @@ use embedded_storage::nor_flash::{
 /// Flash erase/program error. From RM0433 Rev 7. Section 4.7
 #[non_exhaustive]
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// The arguments are not properly aligned
     NotAligned,
@@ -26,9 +26,56 @@
 //! `sdram` usage is described
 //! [here](https://github.com/stm32-rs/stm32-fmc#usage).
 //!
+//! ## SDRAM as the heap
+//!
+//! After it is initialised, the SDRAM can be used as the heap for a global
+//! allocator such as [embedded-alloc](https://docs.rs/embedded-alloc). The
+//! [as_heap](SdramExt::as_heap) extension method consumes the SDRAM and
+//! returns its memory region, with the size taken from the SDRAM chip's
+//! configuration.
+//!
+//! ```
+//! use embedded_alloc::Heap;
+//!
+//! #[global_allocator]
+//! static HEAP: Heap = Heap::empty();
+//!
+//! let ram_ptr: *mut u32 = sdram.init(&mut delay);
+//! let heap = unsafe { sdram.as_heap(ram_ptr) };
+//! heap.init_allocator(|start, size| unsafe { HEAP.init(start, size) });
+//! ```
+//!
+//! This is only sound if:
+//!
+//! * Nothing else is placed in the SDRAM. In particular the linker script
+//!   must not place `.data`, `.bss`, `.uninit` or the stack in the SDRAM.
+//! * The SDRAM is initialised before the first allocation, and it is not in
+//!   self-refresh or power-down mode whilst the allocator is in use.
+//! * The SDRAM is normal memory in the MPU. In the default memory map of
+//!   the Cortex-M7 the SDRAM banks are device memory, and the unaligned
+//!   accesses made by the allocator and the compiler would fault.
+//! * Any ECC on the SDRAM device has been initialised. The FMC itself only
+//!   has ECC for NAND flash.
+//!
+//! The SDRAM does not need to be in the `MEMORY` section of the linker
+//! script. If it is, for example to place the heap with a symbol, no output
+//! sections may use it:
+//!
+//! ```text
+//! MEMORY
+//! {
+//!   /* FMC SDRAM bank 2. Only used by the heap */
+//!   SDRAM : ORIGIN = 0xD0000000, LENGTH = 32M
+//! }
+//!
+//! _sdram_heap_start = ORIGIN(SDRAM);
+//! _sdram_heap_size = LENGTH(SDRAM);
+//! ```
+//!
 //! # Examples
 //!
 //! - [FMC example using the IS42S32800G SDRAM](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/fmc.rs)
+//! - [Using the IS42S32800G SDRAM as the heap](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/fmc_heap.rs)
 
 // From stm32_fmc
 use stm32_fmc::FmcPeripheral;
@@ -179,6 +226,72 @@ unsafe impl FmcPeripheral for FMC {
     }
 }
 
+/// The memory region of an initialised SDRAM, for use as the heap
+///
+/// Returned by [as_heap](SdramExt::as_heap)
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SdramHeap {
+    start: usize,
+    size: usize,
+}
+
+impl SdramHeap {
+    /// Start address of the heap
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Size of the heap in bytes
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Initialises an allocator over the whole SDRAM
+    ///
+    /// `init` is called with the start address and size of the SDRAM in
+    /// bytes, for example `|start, size| unsafe { HEAP.init(start, size) }`
+    /// for an [embedded-alloc](https://docs.rs/embedded-alloc) `Heap`
+    pub fn init_allocator<F: FnOnce(usize, usize)>(self, init: F) {
+        init(self.start, self.size)
+    }
+}
+
+/// Extension trait for using an SDRAM as the heap
+pub trait SdramExt {
+    /// Consumes an initialised SDRAM and returns its memory region for use
+    /// as the heap. `ram_ptr` is the pointer returned by `init`
+    ///
+    /// Consuming the SDRAM ensures that it cannot be reconfigured, or put
+    /// in self-refresh or power-down mode, whilst the allocator is in use.
+    ///
+    /// # Safety
+    ///
+    /// * `init` must have been called on this SDRAM, and returned `ram_ptr`
+    /// * Nothing else may be placed in the SDRAM. In particular the linker
+    ///   script must not place `.data`, `.bss`, `.uninit` or the stack in the
+    ///   SDRAM
+    /// * The SDRAM must be normal memory in the MPU
+    /// * Any ECC on the SDRAM device must have been initialised
+    #[allow(clippy::wrong_self_convention)]
+    unsafe fn as_heap(self, ram_ptr: *mut u32) -> SdramHeap;
+}
+
+impl<IC: SdramChip> SdramExt for Sdram<FMC, IC> {
+    unsafe fn as_heap(self, ram_ptr: *mut u32) -> SdramHeap {
+        let config = IC::CONFIG;
+        let words = 1usize << (config.column_bits + config.row_bits);
+        let size = words
+            * config.internal_banks as usize
+            * (config.memory_data_width as usize / 8);
+
+        SdramHeap {
+            start: ram_ptr as usize,
+            size,
+        }
+    }
+}
+
 macro_rules! pins {
     (FMC: $($pin:ident: [$( $( #[ $pmeta:meta ] )* $inst:ty),*])+) => {
         $(
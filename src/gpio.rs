@@ -34,7 +34,7 @@
 //! - Output
 //!     - **PushPull**: Output which either drives the pin high or low
 //!     - **OpenDrain**: Output which leaves the gate floating, or pulls it do ground in drain
-//!     mode. Can be used as an input in the `open` configuration
+//!     mode. Can be used as an input in the `open` configuration. An internal pull-up can be enabled at the same time with `into_open_drain_output_with_pull`
 //!
 //! ## Changing modes
 //! The simplest way to change the pin mode is to use the `into_<mode>` functions. These return a
@@ -54,6 +54,45 @@
 //! To make a pin dynamic, use the `into_dynamic` function, and then use the `make_<mode>` functions to
 //! change the mode
 //!
+//! ## Erasing pins
+//! Each pin is a distinct type, which prevents collecting pins in an array. The `erase_number`
+//! function converts a pin into a `PartiallyErasedPin` that keeps the port in the type but stores
+//! the pin number at runtime. Each port module has an alias for this type, for example `PAn`:
+//!
+//! ```rust
+//! let leds: [gpioa::PAn<Output<PushPull>>; 2] = [
+//!     gpioa.pa0.into_push_pull_output().erase_number(),
+//!     gpioa.pa1.into_push_pull_output().erase_number(),
+//! ];
+//! ```
+//!
+//! The `erase` (or `downgrade`) function additionally stores the port at runtime, returning an
+//! `ErasedPin`. Pins from different ports can then be collected together, and each access is
+//! dispatched to the port at runtime. `ErasedPin` implements the digital traits of both
+//! embedded-hal 0.2 and embedded-hal 1.0:
+//!
+//! ```rust
+//! let chip_selects: [ErasedPin<Output<PushPull>>; 3] = [
+//!     gpioa.pa4.into_push_pull_output().erase(),
+//!     gpiob.pb12.into_push_pull_output().erase(),
+//!     gpioe.pe11.into_push_pull_output().downgrade(),
+//! ];
+//! ```
+//!
+//! ## Locking the configuration
+//! The `lock` function applies the hardware lock sequence to a pin. This returns a `LockedPin` that
+//! can still be read or driven, but whose configuration can not be changed until the next reset.
+//!
+//! The lock sequence freezes the lock register of the whole port, so it can only be applied once
+//! per port. Several pins of the same port are locked together with [`LockPins::lock`]:
+//!
+//! ```rust
+//! let (pa0, pa1) = (
+//!     gpioa.pa0.into_push_pull_output(),
+//!     gpioa.pa1.into_push_pull_output(),
+//! ).lock()?;
+//! ```
+//!
 //! # Examples
 //!
 //! - [Simple Blinky](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/blinky.rs)
@@ -75,7 +114,10 @@ mod exti;
 pub use exti::ExtiPin;
 mod dynamic;
 pub use dynamic::{Dynamic, DynamicPin};
+mod lock;
+pub use lock::{LockError, LockPins, LockedPin};
 mod hal_02;
+mod hal_1;
 
 pub use embedded_hal::digital::v2::PinState;
 
@@ -145,7 +187,7 @@ pub struct Alternate<const A: u8, Otype = PushPull>(PhantomData<Otype>);
 /// Input mode (type state)
 pub struct Input;
 
-/// Pull setting for an input.
+/// Pull setting for an input or an open drain output.
 #[derive(Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Pull {
@@ -388,6 +430,12 @@ impl<const P: char, const N: u8, MODE> Pin<P, N, MODE> {
     pub fn erase(self) -> ErasedPin<MODE> {
         ErasedPin::new(P as u8 - b'A', N)
     }
+
+    /// Erases the pin number and the port from the type. Equivalent to
+    /// [`erase`](Self::erase)
+    pub fn downgrade(self) -> ErasedPin<MODE> {
+        self.erase()
+    }
 }
 
 impl<const P: char, const N: u8, MODE> From<Pin<P, N, MODE>>
@@ -219,6 +219,18 @@ impl<const P: char, const N: u8, MODE: PinMode> Pin<P, N, MODE> {
         self.into_mode()
     }
 
+    /// Configures the pin to operate as an open drain output pin, with the
+    /// internal pull-up or pull-down resistor set to `pull`.
+    ///
+    /// The level of the pin can be read back with `is_high` and `is_low`,
+    /// for example to sense another device holding down a bit-banged bus.
+    pub fn into_open_drain_output_with_pull(
+        self,
+        pull: Pull,
+    ) -> Pin<P, N, Output<OpenDrain>> {
+        self.into_mode().internal_resistor(pull)
+    }
+
     /// Configures the pin to operate as an push pull output pin
     /// Initial state will be low.
     pub fn into_push_pull_output(mut self) -> Pin<P, N, Output<PushPull>> {
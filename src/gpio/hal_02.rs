@@ -1,8 +1,8 @@
 use core::convert::Infallible;
 
 use super::{
-    dynamic::PinModeError, marker, DynamicPin, ErasedPin, Input, OpenDrain,
-    Output, PartiallyErasedPin, Pin, PinMode, PinState,
+    dynamic::PinModeError, marker, DynamicPin, ErasedPin, Input, LockedPin,
+    OpenDrain, Output, PartiallyErasedPin, Pin, PinMode, PinState,
 };
 
 use embedded_hal::digital::v2::{
@@ -234,6 +234,69 @@ where
     }
 }
 
+// Implementations for `LockedPin`
+
+impl<const P: char, const N: u8, MODE> OutputPin
+    for LockedPin<P, N, Output<MODE>>
+{
+    type Error = Infallible;
+
+    #[inline(always)]
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set_high();
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set_low();
+        Ok(())
+    }
+}
+
+impl<const P: char, const N: u8, MODE> StatefulOutputPin
+    for LockedPin<P, N, Output<MODE>>
+{
+    #[inline(always)]
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.is_set_high())
+    }
+
+    #[inline(always)]
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        Ok(self.is_set_low())
+    }
+}
+
+impl<const P: char, const N: u8, MODE> ToggleableOutputPin
+    for LockedPin<P, N, Output<MODE>>
+{
+    type Error = Infallible;
+
+    #[inline(always)]
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        self.toggle();
+        Ok(())
+    }
+}
+
+impl<const P: char, const N: u8, MODE> InputPin for LockedPin<P, N, MODE>
+where
+    MODE: marker::Readable,
+{
+    type Error = Infallible;
+
+    #[inline(always)]
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.is_high())
+    }
+
+    #[inline(always)]
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(self.is_low())
+    }
+}
+
 // Implementations for `DynamicPin`
 
 impl<const P: char, const N: u8> OutputPin for DynamicPin<P, N> {
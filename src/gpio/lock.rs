@@ -0,0 +1,236 @@
+use super::*;
+
+/// Error returned when the configuration of pins cannot be locked
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LockError {
+    /// The LCKR register of the port was already frozen by an earlier lock
+    /// sequence, which did not include all of the pins
+    PortLocked,
+}
+
+/// Pin with a locked configuration
+///
+/// Created by [`Pin::lock`] or [`LockPins::lock`]. The mode, output type,
+/// speed, pull and alternate function of the pin are frozen in hardware until
+/// the next reset. A locked pin can still be read (if readable) or driven (if
+/// an output), but there are no methods to change its configuration.
+///
+/// The lock cannot be released by software, so there is no way to get the
+/// `Pin` back. The configuration stays frozen until the next MCU or
+/// peripheral reset.
+///
+/// - `MODE` is one of the pin modes (see [Modes](crate::gpio#modes) section).
+/// - `P` is port name: `A` for GPIOA, `B` for GPIOB, etc.
+/// - `N` is pin number: from `0` to `15`.
+pub struct LockedPin<const P: char, const N: u8, MODE> {
+    pin: Pin<P, N, MODE>,
+}
+
+impl<const P: char, const N: u8, MODE> fmt::Debug for LockedPin<P, N, MODE> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_fmt(format_args!(
+            "P{}{}<{}, Locked>",
+            P,
+            N,
+            crate::stripped_type_name::<MODE>()
+        ))
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const P: char, const N: u8, MODE> defmt::Format for LockedPin<P, N, MODE> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "P{}{}<{}, Locked>",
+            P,
+            N,
+            crate::stripped_type_name::<MODE>()
+        );
+    }
+}
+
+impl<const P: char, const N: u8, MODE> PinExt for LockedPin<P, N, MODE> {
+    type Mode = MODE;
+
+    #[inline(always)]
+    fn pin_id(&self) -> u8 {
+        N
+    }
+    #[inline(always)]
+    fn port_id(&self) -> u8 {
+        P as u8 - b'A'
+    }
+}
+
+/// Applies the LCKR write sequence (`LCKK` = 1, 0, 1, then a read back of
+/// `LCKK`) to lock the pins of port `P` in `mask`
+///
+/// If LCKR is already frozen, succeeds only if all the pins in `mask` were
+/// locked by the earlier sequence.
+fn lock_port<const P: char>(mask: u16) -> Result<(), LockError> {
+    let gpio = unsafe { &*Gpio::<P>::ptr() };
+    let mask = mask as u32;
+
+    let lckr = gpio.lckr.read();
+    if lckr.lckk().is_active() {
+        return if lckr.bits() & mask == mask {
+            Ok(())
+        } else {
+            Err(LockError::PortLocked)
+        };
+    }
+
+    // The LCK[15:0] bits must not change during the sequence
+    let lck = (lckr.bits() & 0xFFFF) | mask;
+    const LCKK: u32 = 1 << 16;
+
+    gpio.lckr.write(|w| unsafe { w.bits(LCKK | lck) });
+    gpio.lckr.write(|w| unsafe { w.bits(lck) });
+    gpio.lckr.write(|w| unsafe { w.bits(LCKK | lck) });
+    // The sequence is only complete after this read
+    let _ = gpio.lckr.read();
+
+    let lckr = gpio.lckr.read();
+    if lckr.lckk().is_active() && lckr.bits() & mask == mask {
+        Ok(())
+    } else {
+        Err(LockError::PortLocked)
+    }
+}
+
+impl<const P: char, const N: u8, MODE> Pin<P, N, MODE> {
+    /// Locks the configuration of this pin until the next reset
+    ///
+    /// Applies the LCKR write sequence so that the MODER, OTYPER, OSPEEDR,
+    /// PUPDR, AFRL and AFRH bits for this pin can no longer be modified,
+    /// even by runaway code writing directly to the GPIO registers.
+    ///
+    /// The lock sequence freezes the whole LCKR register of the port, so
+    /// only one sequence can succeed for each port between resets. To lock
+    /// several pins of a port, lock them together with [`LockPins::lock`].
+    ///
+    /// # Errors
+    ///
+    /// Returns `LockError::PortLocked` if the port's LCKR register was
+    /// already frozen by a sequence that did not lock this pin.
+    pub fn lock(self) -> Result<LockedPin<P, N, MODE>, LockError> {
+        lock_port::<P>(1 << N)?;
+        Ok(LockedPin { pin: self })
+    }
+}
+
+/// Locks the configuration of several pins of the same port until the next
+/// reset, with a single lock sequence
+///
+/// Implemented for tuples of 2 to 8 pins of the same port.
+///
+/// ```rust
+/// use stm32h7xx_hal::gpio::LockPins;
+///
+/// let (pa0, pa1) = (
+///     gpioa.pa0.into_push_pull_output(),
+///     gpioa.pa1.into_push_pull_output(),
+/// ).lock()?;
+/// ```
+pub trait LockPins {
+    /// The locked pins
+    type Locked;
+
+    /// Locks the configuration of the pins
+    ///
+    /// # Errors
+    ///
+    /// Returns `LockError::PortLocked` if the port's LCKR register was
+    /// already frozen by a sequence that did not lock all of the pins.
+    fn lock(self) -> Result<Self::Locked, LockError>;
+}
+
+macro_rules! lock_pins {
+    ($(($($N:ident, $MODE:ident, $i:tt),+),)+) => {
+        $(
+            impl<const P: char, $(const $N: u8, $MODE),+> LockPins
+                for ($(Pin<P, $N, $MODE>,)+)
+            {
+                type Locked = ($(LockedPin<P, $N, $MODE>,)+);
+
+                fn lock(self) -> Result<Self::Locked, LockError> {
+                    lock_port::<P>(0 $(| 1 << $N)+)?;
+                    Ok(($(LockedPin { pin: self.$i },)+))
+                }
+            }
+        )+
+    };
+}
+
+lock_pins! {
+    (N0, M0, 0, N1, M1, 1),
+    (N0, M0, 0, N1, M1, 1, N2, M2, 2),
+    (N0, M0, 0, N1, M1, 1, N2, M2, 2, N3, M3, 3),
+    (N0, M0, 0, N1, M1, 1, N2, M2, 2, N3, M3, 3, N4, M4, 4),
+    (N0, M0, 0, N1, M1, 1, N2, M2, 2, N3, M3, 3, N4, M4, 4, N5, M5, 5),
+    (N0, M0, 0, N1, M1, 1, N2, M2, 2, N3, M3, 3, N4, M4, 4, N5, M5, 5, N6, M6, 6),
+    (N0, M0, 0, N1, M1, 1, N2, M2, 2, N3, M3, 3, N4, M4, 4, N5, M5, 5, N6, M6, 6, N7, M7, 7),
+}
+
+impl<const P: char, const N: u8, MODE> LockedPin<P, N, Output<MODE>> {
+    /// Drives the pin high
+    #[inline(always)]
+    pub fn set_high(&mut self) {
+        self.pin.set_high()
+    }
+
+    /// Drives the pin low
+    #[inline(always)]
+    pub fn set_low(&mut self) {
+        self.pin.set_low()
+    }
+
+    /// Is the pin in drive high or low mode?
+    #[inline(always)]
+    pub fn get_state(&self) -> PinState {
+        self.pin.get_state()
+    }
+
+    /// Drives the pin high or low depending on the provided value
+    #[inline(always)]
+    pub fn set_state(&mut self, state: PinState) {
+        self.pin.set_state(state)
+    }
+
+    /// Is the pin in drive high mode?
+    #[inline(always)]
+    pub fn is_set_high(&self) -> bool {
+        self.pin.is_set_high()
+    }
+
+    /// Is the pin in drive low mode?
+    #[inline(always)]
+    pub fn is_set_low(&self) -> bool {
+        self.pin.is_set_low()
+    }
+
+    /// Toggle pin output
+    #[inline(always)]
+    pub fn toggle(&mut self) {
+        self.pin.toggle()
+    }
+}
+
+impl<const P: char, const N: u8, MODE> LockedPin<P, N, MODE>
+where
+    MODE: marker::Readable,
+{
+    /// Is the input pin high?
+    #[inline(always)]
+    pub fn is_high(&self) -> bool {
+        self.pin.is_high()
+    }
+
+    /// Is the input pin low?
+    #[inline(always)]
+    pub fn is_low(&self) -> bool {
+        self.pin.is_low()
+    }
+}
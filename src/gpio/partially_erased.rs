@@ -18,6 +18,11 @@ impl<const P: char, MODE> PartiallyErasedPin<P, MODE> {
             _mode: PhantomData,
         }
     }
+
+    /// Erases the port from the type
+    pub fn downgrade(self) -> ErasedPin<MODE> {
+        self.into()
+    }
 }
 
 impl<const P: char, MODE> fmt::Debug for PartiallyErasedPin<P, MODE> {
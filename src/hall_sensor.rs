@@ -0,0 +1,368 @@
+//! # Hall Sensor Interface
+//!
+//! Measures the commutation intervals of a brushless motor from its three
+//! Hall effect sensors, which are connected to the channel 1, 2 and 3 pins
+//! of a timer.
+//!
+//! The three inputs are combined by an XOR gate onto the timer input 1, so
+//! that each commutation produces an edge on that input. Each edge captures
+//! the counter in channel 1, which is the time since the previous
+//! commutation, and resets the counter to zero.
+//!
+//! ```
+//! let pins = (
+//!     gpioa.pa8.into_alternate(),
+//!     gpioa.pa9.into_alternate(),
+//!     gpioa.pa10.into_alternate(),
+//! );
+//!
+//! let mut hall = dp.TIM1.hall_sensor(
+//!     pins,
+//!     10.Hz(),
+//!     ccdr.peripheral.TIM1,
+//!     &ccdr.clocks,
+//! );
+//!
+//! let angle = hall.commutation_angle();
+//! ```
+//!
+//! The minimum frequency is the lowest rate of commutations that can be
+//! measured, and sets the timer prescaler in the same way as for
+//! [PWM input](crate::pwm_input).
+//!
+//! ## Commutation Angle
+//!
+//! There are six commutations in each electrical revolution, so the
+//! electrical angle moves by 60° between two commutations. The
+//! [`commutation_angle`](HallSensor::commutation_angle) is estimated from
+//! the time since the last commutation, assuming that the motor speed is the
+//! same as during the last complete commutation interval. The angle is a
+//! fraction of a whole electrical revolution, where 65536 is 360°, and never
+//! exceeds one commutation step of 60°.
+use crate::gpio::{self, Alternate};
+use crate::pwm_input::calculate_prescaler;
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::{TIM1, TIM2, TIM3, TIM4, TIM5, TIM8};
+use crate::time::Hertz;
+use crate::timer::{smcr_slave_bits, GetClk, SlaveMode};
+
+/// Electrical angle between two commutations, where 65536 is a whole
+/// electrical revolution
+pub const COMMUTATION_STEP: u16 = 10923;
+
+pub trait Pins<TIM> {}
+pub trait PinCh1<TIM> {}
+pub trait PinCh2<TIM> {}
+pub trait PinCh3<TIM> {}
+
+impl<TIM, PCH1, PCH2, PCH3> Pins<TIM> for (PCH1, PCH2, PCH3)
+where
+    PCH1: PinCh1<TIM>,
+    PCH2: PinCh2<TIM>,
+    PCH3: PinCh3<TIM>,
+{
+}
+
+macro_rules! pins {
+    ($($TIMX:ty:
+       CH1: [$($( #[ $pmeta1:meta ] )* $CH1:ty),*]
+       CH2: [$($( #[ $pmeta2:meta ] )* $CH2:ty),*]
+       CH3: [$($( #[ $pmeta3:meta ] )* $CH3:ty),*])+) => {
+        $(
+            $(
+                $( #[ $pmeta1 ] )*
+                impl PinCh1<$TIMX> for $CH1 {}
+            )*
+            $(
+                $( #[ $pmeta2 ] )*
+                impl PinCh2<$TIMX> for $CH2 {}
+            )*
+            $(
+                $( #[ $pmeta3 ] )*
+                impl PinCh3<$TIMX> for $CH3 {}
+            )*
+        )+
+    }
+}
+
+pins! {
+    TIM1:
+        CH1: [
+            gpio::PA8<Alternate<1>>,
+            gpio::PE9<Alternate<1>>,
+            #[cfg(not(feature = "stm32h7b0"))]
+            gpio::PK1<Alternate<1>>
+        ]
+        CH2: [
+            gpio::PA9<Alternate<1>>,
+            gpio::PE11<Alternate<1>>,
+            #[cfg(not(feature = "stm32h7b0"))]
+            gpio::PJ11<Alternate<1>>
+        ]
+        CH3: [
+            gpio::PA10<Alternate<1>>,
+            gpio::PE13<Alternate<1>>,
+            #[cfg(not(feature = "stm32h7b0"))]
+            gpio::PJ9<Alternate<1>>
+        ]
+
+    TIM2:
+        CH1: [
+            gpio::PA0<Alternate<1>>,
+            gpio::PA5<Alternate<1>>,
+            gpio::PA15<Alternate<1>>
+        ]
+        CH2: [
+            gpio::PA1<Alternate<1>>,
+            gpio::PB3<Alternate<1>>
+        ]
+        CH3: [
+            gpio::PA2<Alternate<1>>,
+            gpio::PB10<Alternate<1>>
+        ]
+
+    TIM3:
+        CH1: [
+            gpio::PA6<Alternate<2>>,
+            gpio::PB4<Alternate<2>>,
+            gpio::PC6<Alternate<2>>
+        ]
+        CH2: [
+            gpio::PA7<Alternate<2>>,
+            gpio::PB5<Alternate<2>>,
+            gpio::PC7<Alternate<2>>
+        ]
+        CH3: [
+            gpio::PB0<Alternate<2>>,
+            gpio::PC8<Alternate<2>>
+        ]
+
+    TIM4:
+        CH1: [
+            gpio::PB6<Alternate<2>>,
+            gpio::PD12<Alternate<2>>
+        ]
+        CH2: [
+            gpio::PB7<Alternate<2>>,
+            gpio::PD13<Alternate<2>>
+        ]
+        CH3: [
+            gpio::PB8<Alternate<2>>,
+            gpio::PD14<Alternate<2>>
+        ]
+
+    TIM5:
+        CH1: [
+            gpio::PA0<Alternate<2>>,
+            gpio::PH10<Alternate<2>>
+        ]
+        CH2: [
+            gpio::PA1<Alternate<2>>,
+            gpio::PH11<Alternate<2>>
+        ]
+        CH3: [
+            gpio::PA2<Alternate<2>>,
+            gpio::PH12<Alternate<2>>
+        ]
+
+    TIM8:
+        CH1: [
+            gpio::PC6<Alternate<3>>,
+            #[cfg(not(feature = "rm0468"))]
+            gpio::PI5<Alternate<3>>,
+            #[cfg(not(feature = "stm32h7b0"))]
+            gpio::PJ8<Alternate<3>>
+        ]
+        CH2: [
+            gpio::PC7<Alternate<3>>,
+            #[cfg(not(feature = "rm0468"))]
+            gpio::PI6<Alternate<3>>,
+            #[cfg(not(any(feature = "stm32h7b0", feature = "rm0468")))]
+            gpio::PJ6<Alternate<3>>,
+            #[cfg(not(feature = "stm32h7b0"))]
+            gpio::PJ10<Alternate<3>>
+        ]
+        CH3: [
+            gpio::PC8<Alternate<3>>,
+            #[cfg(not(feature = "rm0468"))]
+            gpio::PI7<Alternate<3>>,
+            #[cfg(not(feature = "stm32h7b0"))]
+            gpio::PK0<Alternate<3>>
+        ]
+}
+
+/// Returns the electrical angle after `elapsed` counter ticks of a
+/// commutation step that lasts `interval` ticks
+fn interpolate_angle(elapsed: u32, interval: u32) -> u16 {
+    if interval == 0 {
+        return 0;
+    }
+
+    let elapsed = core::cmp::min(elapsed, interval);
+    (u64::from(COMMUTATION_STEP) * u64::from(elapsed) / u64::from(interval))
+        as u16
+}
+
+/// Timer interfacing with the three Hall effect sensors of a brushless motor
+pub struct HallSensor<TIM> {
+    tim: TIM,
+    tick_freq: Hertz,
+    stalled: bool,
+}
+
+pub trait HallSensorExt: Sized {
+    type Rec: ResetEnable;
+
+    /// Configures the timer to measure the intervals between the
+    /// commutations signalled by the Hall sensors on `pins`. Commutations
+    /// that occur at a rate lower than `min_frequency` are not measured
+    ///
+    /// Panics if `min_frequency` is zero.
+    fn hall_sensor<PINS>(
+        self,
+        pins: PINS,
+        min_frequency: Hertz,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> HallSensor<Self>
+    where
+        PINS: Pins<Self>;
+}
+
+macro_rules! tim_hal {
+    ($($TIM:ident: ($Rec:ident, $max:expr),)+) => {
+        $(
+            impl HallSensorExt for $TIM {
+                type Rec = rec::$Rec;
+
+                fn hall_sensor<PINS>(
+                    self,
+                    _pins: PINS,
+                    min_frequency: Hertz,
+                    prec: rec::$Rec,
+                    clocks: &CoreClocks,
+                ) -> HallSensor<$TIM>
+                where
+                    PINS: Pins<$TIM>,
+                {
+                    // enable and reset peripheral to a clean slate
+                    let _ = prec.enable().reset(); // drop
+
+                    let clk = $TIM::get_clk(clocks)
+                        .expect(concat!(stringify!($TIM), ": Input clock not running!"))
+                        .raw();
+                    let psc = calculate_prescaler(clk, min_frequency.raw(), $max);
+
+                    // TI1 is the XOR of the CH1, CH2 and CH3 inputs
+                    self.cr2.write(|w| w.ti1s().set_bit());
+
+                    // unsafe: CC1S = TRC, so that IC1 captures on each edge
+                    // of the XOR input
+                    self.ccmr1_input().write(|w| unsafe { w.bits(0b11) });
+                    // unsafe: CC1E
+                    self.ccer.write(|w| unsafe { w.bits(1) });
+                    // unsafe: only the SMS and TS fields are modified. TS =
+                    // TI1F_ED, the edge detector of TI1
+                    self.smcr.modify(|r, w| unsafe {
+                        w.bits(smcr_slave_bits(r.bits(), 0b00100, SlaveMode::Reset))
+                    });
+
+                    self.psc.write(|w| w.psc().bits(psc));
+                    #[allow(unused_unsafe)] // method is safe for some timers
+                    self.arr.write(|w| unsafe { w.bits($max) });
+
+                    // Only counter overflow sets the update flag, not the
+                    // reset from the trigger input
+                    self.cr1.write(|w| w.urs().set_bit());
+                    self.egr.write(|w| w.ug().set_bit());
+                    self.cr1.modify(|_, w| w.cen().set_bit());
+
+                    HallSensor {
+                        tim: self,
+                        tick_freq: Hertz::from_raw(clk / (u32::from(psc) + 1)),
+                        stalled: true,
+                    }
+                }
+            }
+
+            impl HallSensor<$TIM> {
+                /// Returns the electrical angle since the last commutation,
+                /// where 65536 is a whole electrical revolution
+                ///
+                /// The angle is estimated from the last complete
+                /// commutation interval, and is at most
+                /// [`COMMUTATION_STEP`]. Returns zero until the first
+                /// interval has been measured, and [`COMMUTATION_STEP`]
+                /// once the counter overflows because the motor has stopped
+                pub fn commutation_angle(&mut self) -> u16 {
+                    let interval = self.last_interval();
+                    if self.stalled {
+                        return if interval == 0 { 0 } else { COMMUTATION_STEP };
+                    }
+
+                    interpolate_angle(self.tim.cnt.read().bits(), interval)
+                }
+
+                /// Returns the length of the last complete commutation
+                /// interval, in ticks of the [tick
+                /// frequency](Self::tick_frequency)
+                pub fn commutation_interval(&mut self) -> u32 {
+                    self.last_interval()
+                }
+
+                /// Reads the last captured interval and whether the counter
+                /// has overflowed since the last commutation
+                fn last_interval(&mut self) -> u32 {
+                    let sr = self.tim.sr.read();
+
+                    if sr.cc1if().bit_is_set() {
+                        // There was a commutation since the last call, so
+                        // any earlier overflow is stale
+                        self.tim.sr.modify(|_, w| w.uif().clear_bit());
+                        self.stalled = false;
+                    } else if sr.uif().bit_is_set() {
+                        self.stalled = true;
+                    }
+
+                    // Reading the capture register clears the capture flag
+                    self.tim.ccr[0].read().bits()
+                }
+
+                /// Returns the frequency of the counter
+                pub fn tick_frequency(&self) -> Hertz {
+                    self.tick_freq
+                }
+
+                /// Stops the timer and releases the TIM peripheral
+                pub fn release(self) -> ($TIM, rec::$Rec) {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    (self.tim, rec::$Rec { _marker: core::marker::PhantomData })
+                }
+            }
+        )+
+    }
+}
+
+tim_hal! {
+    TIM1: (Tim1, 0xFFFF),
+    TIM8: (Tim8, 0xFFFF),
+    TIM2: (Tim2, u32::MAX),
+    TIM3: (Tim3, 0xFFFF),
+    TIM4: (Tim4, 0xFFFF),
+    TIM5: (Tim5, u32::MAX),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn angle() {
+        assert_eq!(interpolate_angle(0, 1000), 0);
+        assert_eq!(interpolate_angle(500, 1000), COMMUTATION_STEP / 2);
+        assert_eq!(interpolate_angle(1000, 1000), COMMUTATION_STEP);
+        assert_eq!(interpolate_angle(5000, 1000), COMMUTATION_STEP);
+        assert_eq!(interpolate_angle(u32::MAX, u32::MAX), COMMUTATION_STEP);
+        assert_eq!(interpolate_angle(100, 0), 0);
+    }
+}
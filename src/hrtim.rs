@@ -0,0 +1,279 @@
+//! High Resolution Timer (HRTIM)
+//!
+//! Basic PWM output from the timing units of the HRTIM. Each timing unit
+//! `HRTIM_TIM[A-E]` generates a PWM signal on its first output `CHx1`. The
+//! output is set at the start of each period and reset on compare 1.
+//!
+//! The HRTIM is clocked by `f_HRTIM`, which is either the timer kernel clock
+//! `timy_ker_ck` or the CPU clock `c_ck` depending on RCC_CFGR.HRTIMSEL. Each
+//! timing unit divides this by a power of two prescaler between 1 and 128.
+//! The prescaler is chosen as small as possible for the requested frequency,
+//! so the resolution is one period of `f_HRTIM` (about 2ns at 480MHz) for
+//! frequencies above `f_HRTIM / 0xFFFD`. The STM32H7 HRTIM does not have a
+//! DLL, so there is no sub-cycle resolution.
+//!
+//! # Usage
+//!
+//! ```
+//! use stm32h7xx_hal::hrtim::HrtimExt;
+//!
+//! let mut hrtim = dp.HRTIM_MASTER.hrtim(
+//!     dp.HRTIM_COMMON,
+//!     ccdr.peripheral.HRTIM,
+//!     &ccdr.clocks,
+//! );
+//! let pin = gpioc.pc6.into_alternate();
+//!
+//! let mut pwm = hrtim.timer_a(dp.HRTIM_TIMA, pin, 100.kHz());
+//! pwm.set_duty(pwm.get_max_duty() / 4);
+//! pwm.enable();
+//! ```
+
+use crate::hal;
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::rcc::cfgr::HRTIMSEL_A;
+use crate::stm32::{
+    HRTIM_COMMON, HRTIM_MASTER, HRTIM_TIMA, HRTIM_TIMB, HRTIM_TIMC, HRTIM_TIMD,
+    HRTIM_TIME, RCC,
+};
+use crate::time::Hertz;
+
+use crate::gpio::{self, Alternate};
+
+/// Minimum value of the period and compare registers
+const MIN_PERIOD: u32 = 0x0003;
+/// Maximum value of the period and compare registers
+const MAX_PERIOD: u32 = 0xFFFD;
+
+/// Pins is a trait that marks which GPIO pins may be used as the output of
+/// an HRTIM timing unit; it should not be directly used.
+pub trait Pins<TIM> {}
+
+/// Returns the prescaler setting `CKPSC` and period for a given `f_HRTIM`
+/// and PWM frequency
+///
+/// # Panics
+///
+/// Panics if the frequency cannot be achieved with any prescaler
+fn prescaler_period(hrtim_ck: u32, frequency: u32) -> (u8, u16) {
+    assert!(frequency > 0, "HRTIM: Invalid frequency");
+
+    (0..=7)
+        .find_map(|ckpsc| {
+            let f_hrck = hrtim_ck >> ckpsc;
+            let period = (f_hrck + frequency / 2) / frequency;
+
+            if period <= MAX_PERIOD {
+                assert!(period >= MIN_PERIOD, "HRTIM: Frequency too high");
+                Some((ckpsc as u8, period as u16))
+            } else {
+                None
+            }
+        })
+        .expect("HRTIM: Frequency too low")
+}
+
+/// Extension trait for the HRTIM
+pub trait HrtimExt {
+    /// Enables and resets the HRTIM
+    fn hrtim(
+        self,
+        common: HRTIM_COMMON,
+        prec: rec::Hrtim,
+        clocks: &CoreClocks,
+    ) -> Hrtim;
+}
+
+impl HrtimExt for HRTIM_MASTER {
+    fn hrtim(
+        self,
+        common: HRTIM_COMMON,
+        prec: rec::Hrtim,
+        clocks: &CoreClocks,
+    ) -> Hrtim {
+        prec.enable().reset();
+
+        // unsafe: read only
+        let hrtimsel =
+            unsafe { (*RCC::ptr()).cfgr.read().hrtimsel().variant() };
+        let clk = match hrtimsel {
+            HRTIMSEL_A::TimyKer => clocks.timy_ker_ck(),
+            HRTIMSEL_A::CCk => clocks.c_ck(),
+        };
+
+        Hrtim {
+            master: self,
+            common,
+            clk,
+        }
+    }
+}
+
+/// The HRTIM, with its master timer and common registers
+pub struct Hrtim {
+    master: HRTIM_MASTER,
+    common: HRTIM_COMMON,
+    clk: Hertz,
+}
+
+impl Hrtim {
+    /// Returns the HRTIM clock `f_HRTIM`
+    pub fn clk(&self) -> Hertz {
+        self.clk
+    }
+
+    /// Releases the HRTIM peripherals
+    pub fn free(self) -> (HRTIM_MASTER, HRTIM_COMMON) {
+        (self.master, self.common)
+    }
+}
+
+/// PWM output from an HRTIM timing unit
+pub struct HrPwm<TIM> {
+    tim: TIM,
+}
+
+macro_rules! hrtim_timer {
+    ($($TIMX:ident: ($timer_x:ident, $timxcr:ident, $perxr:ident,
+                     $cmp1xr:ident, $setx1r:ident, $rstx1r:ident,
+                     $txcen:ident, $tx1oen:ident, $tx1odis:ident),)+) => {
+        $(
+            impl Hrtim {
+                /// Configures PWM output on the first output of the timing
+                /// unit at `frequency`. The output is initially disabled,
+                /// with the minimum duty cycle.
+                ///
+                /// # Panics
+                ///
+                /// Panics if the `frequency` is too high or too low to be
+                /// generated from `f_HRTIM`
+                pub fn $timer_x<PIN>(
+                    &mut self,
+                    tim: $TIMX,
+                    _pin: PIN,
+                    frequency: Hertz,
+                ) -> HrPwm<$TIMX>
+                where
+                    PIN: Pins<$TIMX>,
+                {
+                    let (ckpsc, period) =
+                        prescaler_period(self.clk.raw(), frequency.raw());
+
+                    // Continuous mode, registers are written directly for now
+                    tim.$timxcr.write(|w| unsafe {
+                        w.cont().set_bit().ck_pscx().bits(ckpsc)
+                    });
+                    tim.$perxr.write(|w| unsafe { w.perx().bits(period) });
+                    tim.$cmp1xr.write(|w| unsafe { w.cmp1x().bits(MIN_PERIOD as u16) });
+
+                    // Set at the start of the period, reset on compare 1
+                    tim.$setx1r.write(|w| w.per().set_bit());
+                    tim.$rstx1r.write(|w| w.cmp1().set_bit());
+
+                    // From now on, transfer the preload registers at the
+                    // start of every period
+                    tim.$timxcr.modify(|_, w| w.preen().set_bit().tx_repu().set_bit());
+
+                    // Start the counter
+                    self.master.mcr.modify(|_, w| w.$txcen().set_bit());
+
+                    HrPwm { tim }
+                }
+            }
+
+            impl HrPwm<$TIMX> {
+                /// Returns the period of the timing unit, in periods of the
+                /// prescaled HRTIM clock
+                pub fn period(&self) -> u16 {
+                    self.tim.$perxr.read().perx().bits()
+                }
+
+                /// Releases the timing unit
+                pub fn free(self) -> $TIMX {
+                    self.tim
+                }
+            }
+
+            impl hal::PwmPin for HrPwm<$TIMX> {
+                type Duty = u16;
+
+                fn disable(&mut self) {
+                    // unsafe: write-1-to-disable register
+                    unsafe {
+                        (*HRTIM_COMMON::ptr()).disr.write(|w| w.$tx1odis().set_bit());
+                    }
+                }
+
+                fn enable(&mut self) {
+                    // unsafe: write-1-to-enable register
+                    unsafe {
+                        (*HRTIM_COMMON::ptr()).oenr.write(|w| w.$tx1oen().set_bit());
+                    }
+                }
+
+                fn get_duty(&self) -> u16 {
+                    self.tim.$cmp1xr.read().cmp1x().bits()
+                }
+
+                fn get_max_duty(&self) -> u16 {
+                    self.period()
+                }
+
+                /// Sets the duty cycle. The compare register has a minimum
+                /// value of 3, so duty cycles below this are rounded up
+                fn set_duty(&mut self, duty: u16) {
+                    let duty = duty.clamp(MIN_PERIOD as u16, self.period());
+
+                    self.tim.$cmp1xr.write(|w| unsafe { w.cmp1x().bits(duty) });
+                }
+            }
+        )+
+    }
+}
+
+hrtim_timer! {
+    HRTIM_TIMA: (timer_a, timacr, perar, cmp1ar, seta1r, rsta1r,
+                 tacen, ta1oen, ta1odis),
+    HRTIM_TIMB: (timer_b, timbcr, perbr, cmp1br, setb1r, rstb1r,
+                 tbcen, tb1oen, tb1odis),
+    HRTIM_TIMC: (timer_c, timccr, percr, cmp1cr, setc1r, rstc1r,
+                 tccen, tc1oen, tc1odis),
+    HRTIM_TIMD: (timer_d, timdcr, perdr, cmp1dr, setd1r, rstd1r,
+                 tdcen, td1oen, td1odis),
+    HRTIM_TIME: (timer_e, timecr, perer, cmp1er, sete1r, rste1r,
+                 tecen, te1oen, te1odis),
+}
+
+// Output `CHx1` of each timing unit
+impl Pins<HRTIM_TIMA> for gpio::PC6<Alternate<1>> {}
+impl Pins<HRTIM_TIMB> for gpio::PC8<Alternate<1>> {}
+impl Pins<HRTIM_TIMC> for gpio::PA9<Alternate<2>> {}
+impl Pins<HRTIM_TIMD> for gpio::PA11<Alternate<2>> {}
+impl Pins<HRTIM_TIME> for gpio::PG6<Alternate<2>> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prescaler_period_smallest_prescaler() {
+        // 480MHz / 100kHz fits without prescaling
+        assert_eq!(prescaler_period(480_000_000, 100_000), (0, 4800));
+        // 480MHz / 0xFFFD = 7.3kHz, so 5kHz needs a prescaler of 2
+        assert_eq!(prescaler_period(480_000_000, 5_000), (1, 48000));
+        // Lowest prescaler for 100Hz is 128
+        assert_eq!(prescaler_period(480_000_000, 100), (7, 37500));
+    }
+
+    #[test]
+    #[should_panic]
+    fn prescaler_period_too_low() {
+        prescaler_period(480_000_000, 50);
+    }
+
+    #[test]
+    #[should_panic]
+    fn prescaler_period_too_high() {
+        prescaler_period(480_000_000, 200_000_000);
+    }
+}
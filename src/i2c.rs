@@ -4,12 +4,125 @@
 //!
 //! - [I2C simple example](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/i2c.rs)
 //! - [I2C example using I2C4 and BDMA](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/i2c4_bdma.rs)
+//!
+//! # Bus timing
+//!
+//! The TIMINGR register is calculated from the I2C kernel clock and the bus
+//! frequency by [`Timing::new`], so that the timing meets the I2C
+//! specification for Standard-mode, Fast-mode or Fast-mode Plus. The
+//! constructors panic if this is not possible, so `Timing::new` can be called
+//! first to check if a bus frequency is achievable.
+//!
+//! The analog noise filter on SCL and SDA is enabled by the constructors.
+//! It delays both signals by up to 80ns, which the bus timing allows for.
+//! Disabling it reduces the delay, but spikes on the bus are then no longer
+//! suppressed, and the timing margins are reduced accordingly. The timing
+//! must be recalculated by [`Timing::new_no_analog_filter`], which extends
+//! the data hold time and SCL low period to restore the margin for spikes.
+//! [`TIMING_FOR_400KHZ_NO_ANALOG_FILTER`] is precalculated for a 400kHz bus
+//! and a 64MHz kernel clock.
+//!
+//! ```
+//! i2c.disable_analog_filter();
+//! i2c.set_timing(i2c::TIMING_FOR_400KHZ_NO_ANALOG_FILTER);
+//! ```
+//!
+//! # embedded-hal 1.0
+//!
+//! `I2c` also implements the embedded-hal 1.0
+//! [`I2c`](embedded_hal_1::i2c::I2c) trait for 7-bit addresses. All the
+//! operations passed to
+//! [`transaction`](embedded_hal_1::i2c::I2c::transaction) are performed as a
+//! single I2C transaction: adjacent operations in the same direction are
+//! transferred back to back, a change of direction generates a repeated
+//! START, and a STOP is only generated at the end.
+//!
+//! # Slave
+//!
+//! The I2C can also respond as a slave when one of its own addresses is
+//! addressed by another master, for example to emulate a sensor or to
+//! communicate with another microcontroller. Bytes are received and
+//! transmitted one at a time, and SCL is stretched until each
+//! [`SlaveEvent`] is serviced by
+//! [`slave_event`](I2c#method.slave_event).
+//!
+//! ```
+//! i2c.set_own_address(0x42);
+//!
+//! loop {
+//!     match nb::block!(i2c.slave_event())? {
+//!         SlaveEvent::AddressMatch { .. } => index = 0,
+//!         SlaveEvent::Receive(byte) => register = byte,
+//!         SlaveEvent::TransmitRequest => {
+//!             i2c.slave_transmit(registers[register as usize + index]);
+//!             index += 1;
+//!         }
+//!         SlaveEvent::Stop => {}
+//!     }
+//! }
+//! ```
+//!
+//! To service the bus from an interrupt handler instead, listen for the
+//! [`AddressMatch`](Event::AddressMatch), [`Receive`](Event::Receive),
+//! [`Transmit`](Event::Transmit), [`Stop`](Event::Stop) and
+//! [`Errors`](Event::Errors) events, and call `slave_event` from the
+//! `I2Cx_EV` and `I2Cx_ER` handlers.
+//!
+//! # EEPROM
+//!
+//! [`I2cEeprom`] writes to an I2C EEPROM with 16-bit memory addresses,
+//! splitting each write at the page boundaries of the EEPROM.
+//!
+//! ```
+//! let mut eeprom = I2cEeprom::new(i2c, delay);
+//!
+//! // 24C256: 64 byte pages
+//! eeprom.write_page(0x50, 0x0100, &data, 64)?;
+//! eeprom.read_bytes(0x50, 0x0100, &mut buffer)?;
+//! ```
+//!
+//! # Async
+//!
+//! With the `async` feature, [`I2cAsync`] implements the
+//! [embedded-hal-async](https://docs.rs/embedded-hal-async) `I2c` trait for
+//! I2C1, I2C2 and I2C3. The data for each operation is transferred by a DMA1
+//! or DMA2 stream, and the task is woken by the interrupt handlers for the
+//! streams and the I2C.
+//!
+//! ```
+//! use stm32h7xx_hal::dma::dma::{AsyncStream, StreamsTuple};
+//! use stm32h7xx_hal::i2c::{I2c, I2cAsync};
+//!
+//! let streams = StreamsTuple::new(dp.DMA1, ccdr.peripheral.DMA1);
+//! let mut i2c = I2cAsync::new(i2c, streams.0, streams.1);
+//!
+//! i2c.write_read(0x76, &[0xD0], &mut id).await?;
+//!
+//! #[interrupt]
+//! fn I2C1_EV() {
+//!     I2c::<pac::I2C1>::on_interrupt();
+//! }
+//!
+//! #[interrupt]
+//! fn I2C1_ER() {
+//!     I2c::<pac::I2C1>::on_interrupt();
+//! }
+//!
+//! // And DMA1_STR0 and DMA1_STR1 as for SPI
+//! ```
 
 use core::cmp;
 use core::marker::PhantomData;
 
 use crate::gpio::{self, Alternate, OpenDrain};
 use crate::hal::blocking::i2c::{Read, Write, WriteRead};
+use crate::rcc::rec::I2c4ClkSel;
+#[cfg(not(feature = "rm0468"))]
+use crate::rcc::rec::{I2c123ClkSel, I2c123ClkSelGetter};
+use embedded_hal_1::i2c::Operation;
+// On RM0468 parts the I2C1/2/3 kernel clock is shared with I2C5
+#[cfg(feature = "rm0468")]
+use crate::rcc::rec::{I2c1235ClkSel as I2c123ClkSel, I2c1235ClkSelGetter};
 use crate::rcc::{rec, CoreClocks, ResetEnable};
 use crate::stm32::{I2C1, I2C2, I2C3, I2C4};
 use crate::time::Hertz;
@@ -32,6 +145,39 @@ pub enum Event {
     Errors,
     /// Not Acknowledge received (NACKIE)
     NotAcknowledge,
+    /// Own address matched in slave mode (ADDRIE)
+    AddressMatch,
+}
+
+/// Direction of a transfer to the I2C as a slave
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SlaveDirection {
+    /// The master writes bytes, which are received by the slave
+    Write,
+    /// The master reads bytes, which are transmitted by the slave
+    Read,
+}
+
+/// Bus events when the I2C is addressed as a slave
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SlaveEvent {
+    /// The master addressed one of the own addresses of the I2C with a START
+    /// or repeated START
+    AddressMatch {
+        /// The 7-bit address that matched
+        address: u8,
+        /// The direction of the transfer
+        direction: SlaveDirection,
+    },
+    /// A byte was received from the master
+    Receive(u8),
+    /// The master is reading the next byte, which must be written with
+    /// [`slave_transmit`](I2c#method.slave_transmit)
+    TransmitRequest,
+    /// The master ended the transfer with a STOP
+    Stop,
 }
 
 /// I2C Stop Configuration
@@ -57,12 +203,29 @@ pub enum Error {
     Arbitration,
     /// No ack received
     NotAcknowledge,
+    /// A DMA transfer error occurred
+    Dma,
     // Overrun, // slave mode only
     // Pec, // SMBUS mode only
     // Timeout, // SMBUS mode only
     // Alert, // SMBUS mode only
 }
 
+impl embedded_hal_1::i2c::Error for Error {
+    fn kind(&self) -> embedded_hal_1::i2c::ErrorKind {
+        use embedded_hal_1::i2c::{ErrorKind, NoAcknowledgeSource};
+
+        match self {
+            Error::Bus => ErrorKind::Bus,
+            Error::Arbitration => ErrorKind::ArbitrationLoss,
+            Error::NotAcknowledge => {
+                ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown)
+            }
+            Error::Dma => ErrorKind::Other,
+        }
+    }
+}
+
 /// A trait to represent the SCL Pin of an I2C Port
 pub trait PinScl<I2C> {}
 
@@ -146,123 +309,311 @@ macro_rules! busy_wait {
     };
 }
 
-// Calculate I2C timing for Analog Filter ON, Digital Filter OFF
-macro_rules! i2c_timing {
-    ($i2cclk:ident, $freq:ident) => {{
-        // Refer to RM0433 Rev 7 Figure 539 for setup and hold timing:
-        //
-        // t_I2CCLK = 1 / PCLK1
-        // t_PRESC  = (PRESC + 1) * t_I2CCLK
-        // t_SCLL   = (SCLL + 1) * t_PRESC
-        // t_SCLH   = (SCLH + 1) * t_PRESC
-        //
-        // t_SYNC1 + t_SYNC2 > 4 * t_I2CCLK
-        // t_SCL ~= t_SYNC1 + t_SYNC2 + t_SCLL + t_SCLH
-        let ratio = $i2cclk / $freq;
-
-        // For the standard-mode configuration method, we must have a ratio of 4
-        // or higher
-        assert!(
-            ratio >= 4,
-            "The I2C PCLK must be at least 4 times the bus frequency!"
-        );
+mod eeprom;
+pub use eeprom::I2cEeprom;
 
-        let (presc_reg, scll, sclh, sdadel, scldel) = if $freq > 100_000 {
-            // Fast-mode (Fm) or Fast-mode Plus (Fm+)
-            // here we pick SCLL + 1 = 2 * (SCLH + 1)
+// Declared after the macros above, which it also uses
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub use asynch::{I2cAsync, Instance};
+
+/// Timing for the I2C bus, as the fields of the TIMINGR register
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timing {
+    /// Timing prescaler (PRESC)
+    pub presc: u8,
+    /// SCL low period (SCLL)
+    pub scll: u8,
+    /// SCL high period (SCLH)
+    pub sclh: u8,
+    /// Data hold time (SDADEL)
+    pub sdadel: u8,
+    /// Data setup time (SCLDEL)
+    pub scldel: u8,
+}
 
-            // Prescaler, 96 ticks for sclh/scll. Round up then subtract 1
-            let presc_reg = ((ratio - 1) / 96) as u8;
-            // ratio < 1200 by pclk 120MHz max., therefore presc < 16
+/// Error calculating the I2C bus timing
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TimingError {
+    /// The bus frequency is faster than Fast-mode Plus (1MHz)
+    FrequencyTooHigh,
+    /// The I2C kernel clock is too slow for this bus frequency
+    ClockTooSlow,
+    /// The I2C kernel clock is too fast for this bus frequency
+    ClockTooFast,
+}
 
-            // Actual precale value selected
-            let presc = (presc_reg + 1) as u32;
+/// Bus timing limits from the I2C specification, in nanoseconds
+///
+/// UM10204 rev 6.: https://www.nxp.com/docs/en/user-guide/UM10204.pdf
+struct Spec {
+    /// Minimum SCL low period, tLOW
+    t_low_min: u64,
+    /// Maximum fall time, tf
+    t_fall_max: u64,
+    /// Maximum rise time, tr
+    t_rise_max: u64,
+    /// Minimum data setup time, tSU;DAT
+    t_sudat_min: u64,
+    /// Maximum data hold time, tHD;DAT
+    t_hddat_max: u64,
+}
 
-            let sclh = ((ratio / presc) - 3) / 3;
-            let scll = (2 * (sclh + 1)) - 1;
+impl Spec {
+    fn new(freq: u32) -> Self {
+        match freq {
+            // Standard mode (Sm)
+            0..=100_000 => Spec {
+                t_low_min: 4700,
+                t_fall_max: 300,
+                t_rise_max: 1000,
+                t_sudat_min: 250,
+                t_hddat_max: 3450,
+            },
+            // Fast mode (Fm)
+            100_001..=400_000 => Spec {
+                t_low_min: 1300,
+                t_fall_max: 300,
+                t_rise_max: 300,
+                t_sudat_min: 100,
+                t_hddat_max: 900,
+            },
+            // Fast mode Plus (Fm+)
+            _ => Spec {
+                t_low_min: 500,
+                t_fall_max: 120,
+                t_rise_max: 120,
+                t_sudat_min: 50,
+                t_hddat_max: 450,
+            },
+        }
+    }
+}
 
-            let (sdadel, scldel) = if $freq > 400_000 {
-                // Fast-mode Plus (Fm+)
-                assert!($i2cclk >= 17_000_000); // See table in datsheet
+/// Minimum delay of the analog filter in nanoseconds. From H7 Datasheet
+const T_AF_MIN: u64 = 50;
+/// Maximum delay of the analog filter in nanoseconds. From H7 Datasheet
+const T_AF_MAX: u64 = 80;
+
+impl Timing {
+    /// Calculates the I2C bus timing for a kernel clock of `i2c_clk` and a
+    /// bus frequency of `frequency`, with the analog filter on and the
+    /// digital filter off
+    ///
+    /// The SCL frequency generated is at most `frequency`, and the SCL low
+    /// period and data setup and hold times meet the I2C specification for
+    /// the Standard-mode, Fast-mode or Fast-mode Plus bus that `frequency`
+    /// falls within. An error is returned if this cannot be achieved with
+    /// this kernel clock.
+    pub fn new(i2c_clk: Hertz, frequency: Hertz) -> Result<Self, TimingError> {
+        i2c_timing(i2c_clk.raw(), frequency.raw(), true)
+    }
 
-                let sdadel = $i2cclk / 8_000_000 / presc;
-                let scldel = $i2cclk / 4_000_000 / presc - 1;
+    /// Calculates the I2C bus timing for a kernel clock of `i2c_clk` and a
+    /// bus frequency of `frequency`, with both the analog and digital
+    /// filters off
+    ///
+    /// Without the analog filter, SCL and SDA are no longer delayed by it,
+    /// and spikes on the bus are not suppressed. The data hold time and the
+    /// SCL low period are extended by the minimum filter delay to keep the
+    /// same margins as [`Timing::new`].
+    pub fn new_no_analog_filter(
+        i2c_clk: Hertz,
+        frequency: Hertz,
+    ) -> Result<Self, TimingError> {
+        i2c_timing(i2c_clk.raw(), frequency.raw(), false)
+    }
+}
 
-                (sdadel, scldel)
-            } else {
-                // Fast-mode (Fm)
-                assert!($i2cclk >= 8_000_000); // See table in datsheet
+/// Timing for a 400kHz Fast-mode bus with the analog filter off, for a 64MHz
+/// kernel clock such as the HSI
+///
+/// This is the value returned by [`Timing::new_no_analog_filter`] for these
+/// clocks, which already includes the additional margin for spikes that are
+/// no longer suppressed by the analog filter.
+pub const TIMING_FOR_400KHZ_NO_ANALOG_FILTER: Timing = Timing {
+    presc: 1,
+    scll: 55,
+    sclh: 25,
+    sdadel: 12,
+    scldel: 15,
+};
+
+/// Calculate I2C timing for Digital Filter OFF, and the Analog Filter ON if
+/// `analog_filter` is true
+fn i2c_timing(
+    i2c_clk: u32,
+    freq: u32,
+    analog_filter: bool,
+) -> Result<Timing, TimingError> {
+    // Maximum f_SCL for Fast-mode Plus (Fm+)
+    if freq > 1_000_000 {
+        return Err(TimingError::FrequencyTooHigh);
+    }
+    if freq == 0 {
+        return Err(TimingError::ClockTooFast);
+    }
 
-                let sdadel = $i2cclk / 3_000_000 / presc;
-                let scldel = $i2cclk / 1_000_000 / presc - 1;
+    // Refer to RM0433 Rev 7 Figure 539 for setup and hold timing:
+    //
+    // t_I2CCLK = 1 / PCLK1
+    // t_PRESC  = (PRESC + 1) * t_I2CCLK
+    // t_SCLL   = (SCLL + 1) * t_PRESC
+    // t_SCLH   = (SCLH + 1) * t_PRESC
+    //
+    // t_SYNC1 + t_SYNC2 > 4 * t_I2CCLK
+    // t_SCL ~= t_SYNC1 + t_SYNC2 + t_SCLL + t_SCLH
+    let ratio = i2c_clk / freq;
+
+    // For the standard-mode configuration method, we must have a ratio of 4
+    // or higher
+    if ratio < 4 {
+        return Err(TimingError::ClockTooSlow);
+    }
 
-                (sdadel, scldel)
-            };
+    let (presc_reg, scll, sclh, sdadel, scldel) = if freq > 100_000 {
+        // Fast-mode (Fm) or Fast-mode Plus (Fm+)
+        // here we pick SCLL + 1 = 2 * (SCLH + 1)
 
-            (
-                presc_reg,
-                scll as u8,
-                sclh as u8,
-                sdadel as u8,
-                scldel as u8,
-            )
+        // See table in datasheet
+        let i2c_clk_min = if freq > 400_000 {
+            17_000_000
         } else {
-            // Standard-mode (Sm)
-            // here we pick SCLL = SCLH
-            assert!($i2cclk >= 2_000_000); // See table in datsheet
-
-            // Prescaler, 128 or 256 ticks for sclh/scll. Round up then
-            // subtract 1
-            let presc_reg = (ratio - 1)
-                / if $freq < 8000 {
-                    256
-                } else if $freq < 80_000 {
-                    128
-                } else {
-                    64
-                };
-            let presc_reg = cmp::min(presc_reg, 15) as u8;
-
-            // Actual prescale value selected
-            let presc = (presc_reg + 1) as u32;
-
-            let sclh = ((ratio / presc) - 2) / 2;
-            let scll = sclh;
-
-            // Speed check
-            assert!(
-                sclh < 256,
-                "The I2C PCLK is too fast for this bus frequency!"
-            );
+            8_000_000
+        };
+        if i2c_clk < i2c_clk_min {
+            return Err(TimingError::ClockTooSlow);
+        }
 
-            let sdadel = $i2cclk / 2_000_000 / presc;
-            let scldel = $i2cclk / 500_000 / presc - 1;
+        // Prescaler, 96 ticks for sclh/scll. Round up then subtract 1
+        let presc_reg = (ratio - 1) / 96;
+        if presc_reg > 15 {
+            return Err(TimingError::ClockTooFast);
+        }
+
+        // Actual precale value selected
+        let presc = presc_reg + 1;
+
+        let sclh = ((ratio / presc) - 3) / 3;
+        let scll = (2 * (sclh + 1)) - 1;
+
+        let (sdadel, scldel) = if freq > 400_000 {
+            // Fast-mode Plus (Fm+)
+            let sdadel = i2c_clk / 8_000_000 / presc;
+            let scldel = (i2c_clk / 4_000_000 / presc).saturating_sub(1);
 
-            (
-                presc_reg,
-                scll as u8,
-                sclh as u8,
-                sdadel as u8,
-                scldel as u8,
-            )
+            (sdadel, scldel)
+        } else {
+            // Fast-mode (Fm)
+            let sdadel = i2c_clk / 3_000_000 / presc;
+            let scldel = (i2c_clk / 1_000_000 / presc).saturating_sub(1);
+
+            (sdadel, scldel)
         };
 
-        // Sanity check
-        assert!(presc_reg < 16);
+        (presc_reg, scll, sclh, sdadel, scldel)
+    } else {
+        // Standard-mode (Sm)
+        // here we pick SCLL = SCLH
 
-        // Keep values within reasonable limits for fast per_ck
-        let sdadel = cmp::max(sdadel, 1);
-        let scldel = cmp::max(scldel, 4);
+        // See table in datasheet
+        if i2c_clk < 2_000_000 {
+            return Err(TimingError::ClockTooSlow);
+        }
+
+        // Prescaler, 128 or 256 ticks for sclh/scll. Round up then
+        // subtract 1
+        let presc_reg = (ratio - 1)
+            / if freq < 8000 {
+                256
+            } else if freq < 80_000 {
+                128
+            } else {
+                64
+            };
+        let presc_reg = cmp::min(presc_reg, 15);
 
-        let sdadel = cmp::min(sdadel, 15);
-        let scldel = cmp::min(scldel, 15);
+        // Actual prescale value selected
+        let presc = presc_reg + 1;
+
+        let sclh = ((ratio / presc) - 2) / 2;
+        let scll = sclh;
+
+        // Speed check
+        if sclh > 255 {
+            return Err(TimingError::ClockTooFast);
+        }
+
+        let sdadel = i2c_clk / 2_000_000 / presc;
+        let scldel = (i2c_clk / 500_000 / presc).saturating_sub(1);
 
         (presc_reg, scll, sclh, sdadel, scldel)
-    }};
+    };
+
+    // Without the analog filter, SDA and SCL are no longer delayed by
+    // tAF. Extend the data hold time and the SCL low period by tAF(min) so
+    // that the margins for spikes on the bus are not reduced
+    let (scll, sdadel, t_af_min, t_af_max) = if analog_filter {
+        (scll, sdadel, T_AF_MIN, T_AF_MAX)
+    } else {
+        let t_presc = 1_000_000_000 * (presc_reg as u64 + 1);
+        let extra = |t_ns: u64| {
+            ((t_ns * i2c_clk as u64 + t_presc - 1) / t_presc) as u32
+        };
+        (scll + extra(2 * T_AF_MIN), sdadel + extra(T_AF_MIN), 0, 0)
+    };
+    if scll > 255 {
+        return Err(TimingError::ClockTooFast);
+    }
+
+    // Keep values within reasonable limits for fast per_ck
+    let sdadel = cmp::max(sdadel, 1);
+    let scldel = cmp::max(scldel, 4);
+
+    let sdadel = cmp::min(sdadel, 15);
+    let scldel = cmp::min(scldel, 15);
+
+    // Check the timing against the specification. Each period is compared
+    // in units of 1 / (i2c_clk * 1e9) to avoid division
+    let spec = Spec::new(freq);
+    let presc = (presc_reg + 1) as u64;
+    let ns = |t_ns: u64| t_ns * i2c_clk as u64;
+    let ticks = |ticks: u64| ticks * 1_000_000_000;
+
+    // See RM0433 Rev 7 Section 47.4.5
+    //
+    // tSCLL >= tLOW(min)
+    if ticks((scll as u64 + 1) * presc) < ns(spec.t_low_min) {
+        return Err(TimingError::ClockTooSlow);
+    }
+    // tSDADEL >= {tf + tHD;DAT(min) - tAF(min) - [(DNF + 3) x tI2CCLK]}
+    if ticks(sdadel as u64 * presc + 3) < ns(spec.t_fall_max - t_af_min) {
+        return Err(TimingError::ClockTooFast);
+    }
+    // tSDADEL <= {tHD;DAT(max) - tAF(max) - [(DNF + 4) x tI2CCLK]}
+    if ticks(sdadel as u64 * presc + 4) > ns(spec.t_hddat_max - t_af_max) {
+        return Err(TimingError::ClockTooSlow);
+    }
+    // tSCLDEL >= tr + tSU;DAT(min)
+    if ticks(scldel as u64 * presc) < ns(spec.t_rise_max + spec.t_sudat_min) {
+        return Err(TimingError::ClockTooFast);
+    }
+
+    Ok(Timing {
+        presc: presc_reg as u8,
+        scll: scll as u8,
+        sclh: sclh as u8,
+        sdadel: sdadel as u8,
+        scldel: scldel as u8,
+    })
 }
 
 macro_rules! i2c {
-    ($($I2CX:ident: ($i2cX:ident, $Rec:ident, $pclkX:ident),)+) => {
+    ($($I2CX:ident: ($i2cX:ident, $Rec:ident, $ClkSel:ident, $pclkX:ident),)+) => {
         $(
             impl I2c<$I2CX> {
                 /// Create and initialise a new I2C peripheral.
@@ -271,24 +622,35 @@ macro_rules! i2c {
                 ///
                 /// # Panics
                 ///
-                /// Panics if the ratio between `frequency` and the i2c_ker_ck
-                /// is out of bounds. The acceptable range is [4, 8192].
+                /// Panics if a bus timing meeting the I2C specification for
+                /// `frequency` cannot be achieved with the i2c_ker_ck. See
+                /// [`Timing::new`]
                 ///
-                /// Panics if the `frequency` is too fast. The maximum is 1MHz.
+                /// Panics if the kernel clock selected by the kernel clock
+                /// mux is not running.
                 pub fn $i2cX (
                     i2c: $I2CX,
                     frequency: Hertz,
                     prec: rec::$Rec,
                     clocks: &CoreClocks
                 ) -> Self {
-                    let _ = prec.enable().reset(); // drop, can be recreated by free method
-
-                    let freq: u32 = frequency.raw();
+                    let prec = prec.enable().reset();
 
-                    // Maximum f_SCL for Fast-mode Plus (Fm+)
-                    assert!(freq <= 1_000_000);
+                    let i2c_clk = match prec.get_kernel_clk_mux() {
+                        $ClkSel::Pll3R => {
+                            clocks.pll3_r_ck().expect("I2C: PLL3_R must be enabled")
+                        }
+                        $ClkSel::HsiKer => {
+                            clocks.hsi_ck().expect("I2C: HSI clock must be enabled")
+                        }
+                        $ClkSel::CsiKer => {
+                            clocks.csi_ck().expect("I2C: CSI clock must be enabled")
+                        }
+                        _ => clocks.$pclkX(),
+                    };
 
-                    let i2c_clk: u32 = clocks.$pclkX().raw();
+                    let timing = Timing::new(i2c_clk, frequency)
+                        .expect("I2C: The bus frequency is not achievable with this kernel clock");
 
                     // Clear PE bit in I2C_CR1
                     i2c.cr1.modify(|_, w| w.pe().clear_bit());
@@ -299,18 +661,17 @@ macro_rules! i2c {
                     i2c.cr1.modify(|_, w| w.anfoff().clear_bit());
 
                     // Configure timing
-                    let (presc_reg, scll, sclh, sdadel, scldel) = i2c_timing!(i2c_clk, freq);
                     i2c.timingr.write(|w|
                         w.presc()
-                            .bits(presc_reg)
+                            .bits(timing.presc)
                             .scll()
-                            .bits(scll)
+                            .bits(timing.scll)
                             .sclh()
-                            .bits(sclh)
+                            .bits(timing.sclh)
                             .sdadel()
-                            .bits(sdadel)
+                            .bits(timing.sdadel)
                             .scldel()
-                            .bits(scldel)
+                            .bits(timing.scldel)
                     );
 
                     // Enable the peripheral
@@ -349,6 +710,7 @@ macro_rules! i2c {
                             Event::Stop => w.stopie().set_bit(),
                             Event::Errors => w.errie().set_bit(),
                             Event::NotAcknowledge => w.nackie().set_bit(),
+                            Event::AddressMatch => w.addrie().set_bit(),
                         }
                     });
                 }
@@ -363,6 +725,7 @@ macro_rules! i2c {
                             Event::Stop => w.stopie().clear_bit(),
                             Event::Errors => w.errie().clear_bit(),
                             Event::NotAcknowledge => w.nackie().clear_bit(),
+                            Event::AddressMatch => w.addrie().clear_bit(),
                         }
                     });
                     let _ = self.i2c.cr1.read();
@@ -379,6 +742,7 @@ macro_rules! i2c {
                                 .arlocf().set_bit()
                                 .ovrcf().set_bit(),
                             Event::NotAcknowledge => w.nackcf().set_bit(),
+                            Event::AddressMatch => w.addrcf().set_bit(),
                             _ => w
                         }
                     });
@@ -386,12 +750,196 @@ macro_rules! i2c {
                     let _ = self.i2c.isr.read(); // Delay 2 peripheral clocks
                 }
 
+                /// Runs `f` with the peripheral disabled, for registers that
+                /// can only be written whilst PE is cleared. The peripheral
+                /// is enabled again afterwards if it was enabled before
+                fn disable_temporarily<F>(&mut self, f: F)
+                where
+                    F: FnOnce(&$I2CX),
+                {
+                    let enabled = self.i2c.cr1.read().pe().bit_is_set();
+                    self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+                    // PE must be kept low for at least 3 APB clock cycles
+                    let _ = self.i2c.cr1.read();
+                    let _ = self.i2c.cr1.read();
+                    let _ = self.i2c.cr1.read();
+
+                    f(&self.i2c);
+
+                    self.i2c.cr1.modify(|_, w| w.pe().bit(enabled));
+                }
+
+                /// Disables the analog noise filter on SCL and SDA
+                ///
+                /// The bus timing must also be replaced with one calculated
+                /// for the analog filter off, see
+                /// [`Timing::new_no_analog_filter`]. Disabling the
+                /// peripheral aborts any transfer in progress.
+                pub fn disable_analog_filter(&mut self) {
+                    self.disable_temporarily(|i2c| {
+                        i2c.cr1.modify(|_, w| w.anfoff().set_bit())
+                    });
+                }
+
+                /// Enables the analog noise filter on SCL and SDA
+                ///
+                /// The bus timing must also be replaced with one calculated
+                /// for the analog filter on, see [`Timing::new`].
+                /// Disabling the peripheral aborts any transfer in progress.
+                pub fn enable_analog_filter(&mut self) {
+                    self.disable_temporarily(|i2c| {
+                        i2c.cr1.modify(|_, w| w.anfoff().clear_bit())
+                    });
+                }
+
+                /// Replaces the bus timing. Disabling the peripheral aborts
+                /// any transfer in progress
+                pub fn set_timing(&mut self, timing: Timing) {
+                    self.disable_temporarily(|i2c| {
+                        i2c.timingr.write(|w|
+                            w.presc()
+                                .bits(timing.presc)
+                                .scll()
+                                .bits(timing.scll)
+                                .sclh()
+                                .bits(timing.sclh)
+                                .sdadel()
+                                .bits(timing.sdadel)
+                                .scldel()
+                                .bits(timing.scldel)
+                        )
+                    });
+                }
+
                 /// Releases the I2C peripheral
                 pub fn free(self) -> ($I2CX, rec::$Rec) {
                     (self.i2c, rec::$Rec { _marker: PhantomData })
                 }
             }
 
+            /// Slave methods
+            ///
+            /// The I2C responds as a slave when another master addresses
+            /// one of its own addresses. SCL is stretched after the address
+            /// and after each byte until the event is serviced, so the bytes
+            /// can be handled one at a time.
+            impl I2c<$I2CX> {
+                /// Sets the primary own address (OAR1), so that the I2C
+                /// responds as a slave to the 7-bit `address`
+                pub fn set_own_address(&mut self, address: u8) {
+                    assert!(address < 0x80, "I2C: own address must be 7-bit");
+
+                    // OA1 can only be written whilst OA1EN is cleared
+                    self.i2c.oar1.write(|w| w.oa1en().disabled());
+                    self.i2c.oar1.write(|w| {
+                        w.oa1()
+                            .bits(u16::from(address) << 1)
+                            .oa1mode()
+                            .bit7()
+                            .oa1en()
+                            .enabled()
+                    });
+                }
+
+                /// Sets the secondary own address (OAR2), so that the I2C
+                /// also responds as a slave to the 7-bit `address`
+                ///
+                /// The lowest `mask_bits` bits of the address are not
+                /// compared, so that the I2C responds to a range of
+                /// 2<sup>`mask_bits`</sup> addresses. Addresses reserved by
+                /// the I2C specification are never acknowledged.
+                pub fn set_own_address2(&mut self, address: u8, mask_bits: u8) {
+                    assert!(address < 0x80, "I2C: own address must be 7-bit");
+                    assert!(mask_bits < 8, "I2C: at most 7 bits can be masked");
+
+                    // OA2 can only be written whilst OA2EN is cleared
+                    self.i2c.oar2.write(|w| w.oa2en().disabled());
+                    self.i2c.oar2.write(|w| {
+                        w.oa2()
+                            .bits(address)
+                            .oa2msk()
+                            .bits(mask_bits)
+                            .oa2en()
+                            .enabled()
+                    });
+                }
+
+                /// Stops responding to both own addresses
+                pub fn disable_own_addresses(&mut self) {
+                    self.i2c.oar1.write(|w| w.oa1en().disabled());
+                    self.i2c.oar2.write(|w| w.oa2en().disabled());
+                }
+
+                /// Returns the next bus event as a slave
+                ///
+                /// An address match releases SCL, and a received byte is
+                /// read from the data register. A
+                /// [`TransmitRequest`](SlaveEvent::TransmitRequest) is
+                /// returned until the byte is written with
+                /// [`slave_transmit`](I2c#method.slave_transmit).
+                pub fn slave_event(&mut self) -> nb::Result<SlaveEvent, Error> {
+                    let isr = self.i2c.isr.read();
+
+                    if isr.berr().is_error() {
+                        self.i2c.icr.write(|w| w.berrcf().set_bit());
+                        return Err(nb::Error::Other(Error::Bus));
+                    } else if isr.arlo().is_lost() {
+                        self.i2c.icr.write(|w| w.arlocf().set_bit());
+                        return Err(nb::Error::Other(Error::Arbitration));
+                    }
+
+                    // A byte received before a repeated START or STOP is
+                    // returned first
+                    if isr.rxne().is_not_empty() {
+                        return Ok(SlaveEvent::Receive(
+                            self.i2c.rxdr.read().rxdata().bits()
+                        ));
+                    }
+
+                    if isr.addr().is_match() {
+                        let direction = if isr.dir().is_read() {
+                            // Flush the byte left over in TXDR from a
+                            // previous transfer, if any
+                            self.i2c.isr.write(|w| w.txe().set_bit());
+                            SlaveDirection::Read
+                        } else {
+                            SlaveDirection::Write
+                        };
+                        let address = isr.addcode().bits();
+
+                        // Release SCL
+                        self.i2c.icr.write(|w| w.addrcf().set_bit());
+                        return Ok(SlaveEvent::AddressMatch { address, direction });
+                    }
+
+                    if isr.txis().is_empty() {
+                        return Ok(SlaveEvent::TransmitRequest);
+                    }
+
+                    // The master does not acknowledge the last byte that it
+                    // reads, which is not an error for the slave
+                    if isr.nackf().is_nack() {
+                        self.i2c.icr.write(|w| w.nackcf().set_bit());
+                    }
+
+                    if isr.stopf().is_stop() {
+                        self.i2c.icr.write(|w| w.stopcf().set_bit());
+                        // The master may have stopped reading before the
+                        // last byte written was transmitted
+                        self.i2c.isr.write(|w| w.txe().set_bit());
+                        return Ok(SlaveEvent::Stop);
+                    }
+
+                    Err(nb::Error::WouldBlock)
+                }
+
+                /// Writes the next `byte` to transmit to the master, after a
+                /// [`TransmitRequest`](SlaveEvent::TransmitRequest)
+                pub fn slave_transmit(&mut self, byte: u8) {
+                    self.i2c.txdr.write(|w| w.txdata().bits(byte));
+                }
+            }
+
             /// Master controller methods
             ///
             /// These infallible methods are used to begin or end parts of
@@ -526,10 +1074,9 @@ macro_rules! i2c {
                 ///
                 /// # Panics
                 ///
-                /// Panics if the ratio between `frequency` and the i2c_ker_ck
-                /// is out of bounds. The acceptable range is [4, 8192].
-                ///
-                /// Panics if the `frequency` is too fast. The maximum is 1MHz.
+                /// Panics if a bus timing meeting the I2C specification for
+                /// `frequency` cannot be achieved with the i2c_ker_ck. See
+                /// [`Timing::new`]
                 fn i2c<PINS>(self, _pins: PINS, frequency: Hertz,
                                 prec: rec::$Rec,
                                 clocks: &CoreClocks) -> I2c<$I2CX>
@@ -546,10 +1093,9 @@ macro_rules! i2c {
                 ///
                 /// # Panics
                 ///
-                /// Panics if the ratio between `frequency` and the i2c_ker_ck
-                /// is out of bounds. The acceptable range is [4, 8192].
-                ///
-                /// Panics if the `frequency` is too fast. The maximum is 1MHz.
+                /// Panics if a bus timing meeting the I2C specification for
+                /// `frequency` cannot be achieved with the i2c_ker_ck. See
+                /// [`Timing::new`]
                 fn i2c_unchecked(self, frequency: Hertz,
                                     prec: rec::$Rec,
                                     clocks: &CoreClocks) -> I2c<$I2CX> {
@@ -667,6 +1213,153 @@ macro_rules! i2c {
                     Ok(())
                 }
             }
+
+            impl I2c<$I2CX> {
+                /// Internal implementation for embedded-hal 1.0
+                /// I2c::transaction
+                ///
+                /// Transfers a group of adjacent operations in the same
+                /// direction, following a START or repeated START. Groups of
+                /// more than 255 bytes use NBYTES reload mode. Ends with the
+                /// bus stalled by software end mode, ready for a repeated
+                /// START or a STOP.
+                fn transaction_group(
+                    &mut self,
+                    addr: u8,
+                    read: bool,
+                    first: bool,
+                    group: &mut [Operation<'_>],
+                ) -> Result<(), Error> {
+                    let mut remaining: usize = group
+                        .iter()
+                        .map(|op| match op {
+                            Operation::Read(buffer) => buffer.len(),
+                            Operation::Write(bytes) => bytes.len(),
+                        })
+                        .sum();
+                    let mut nbytes = cmp::min(remaining, 255);
+
+                    // Wait for any previous address sequence to end
+                    // automatically. A repeated START is generated
+                    // immediately since the previous group is stalled with TC
+                    // set
+                    if first {
+                        while self.i2c.cr2.read().start().bit_is_set() {};
+                    }
+
+                    // ST SAD+R/W or SR SAD+R/W
+                    self.i2c.cr2.write(|w| {
+                        w.sadd()
+                            .bits(u16(addr << 1))
+                            .add10().clear_bit()
+                            .rd_wrn()
+                            .bit(read)
+                            .nbytes()
+                            .bits(nbytes as u8)
+                            .reload()
+                            .bit(remaining > 255)
+                            .start()
+                            .set_bit()
+                            .autoend()
+                            .software()
+                    });
+
+                    for op in group {
+                        match op {
+                            Operation::Write(bytes) => {
+                                for byte in bytes.iter() {
+                                    self.reload(&mut nbytes, remaining)?;
+
+                                    // Wait until we are allowed to send data
+                                    busy_wait!(self.i2c, txis, is_empty);
+
+                                    // Put byte on the wire
+                                    self.i2c.txdr.write(|w| w.txdata().bits(*byte));
+                                    nbytes -= 1;
+                                    remaining -= 1;
+                                }
+                            }
+                            Operation::Read(buffer) => {
+                                for byte in buffer.iter_mut() {
+                                    self.reload(&mut nbytes, remaining)?;
+
+                                    // Wait until we have received something
+                                    busy_wait!(self.i2c, rxne, is_not_empty);
+
+                                    *byte = self.i2c.rxdr.read().rxdata().bits();
+                                    nbytes -= 1;
+                                    remaining -= 1;
+                                }
+                            }
+                        }
+                    }
+
+                    // Wait until the group finishes
+                    busy_wait!(self.i2c, tc, is_complete);
+
+                    Ok(())
+                }
+
+                /// In NBYTES reload mode, waits for the current `nbytes` to
+                /// be transferred and then reloads NBYTES for the next (up
+                /// to) 255 bytes of `remaining`
+                fn reload(&mut self, nbytes: &mut usize, remaining: usize) -> Result<(), Error> {
+                    if *nbytes == 0 {
+                        busy_wait!(self.i2c, tcr, is_complete);
+
+                        *nbytes = cmp::min(remaining, 255);
+                        self.i2c.cr2.modify(|_, w| {
+                            w.nbytes()
+                                .bits(*nbytes as u8)
+                                .reload()
+                                .bit(remaining > 255)
+                        });
+                    }
+
+                    Ok(())
+                }
+            }
+
+            impl embedded_hal_1::i2c::ErrorType for I2c<$I2CX> {
+                type Error = Error;
+            }
+
+            impl embedded_hal_1::i2c::I2c for I2c<$I2CX> {
+                fn transaction(
+                    &mut self,
+                    address: u8,
+                    operations: &mut [Operation<'_>],
+                ) -> Result<(), Error> {
+                    if operations.is_empty() {
+                        return Ok(());
+                    }
+
+                    let mut first = true;
+                    let mut rest = operations;
+                    while !rest.is_empty() {
+                        // Adjacent operations in the same direction are
+                        // coalesced
+                        let read = matches!(rest[0], Operation::Read(_));
+                        let len = rest
+                            .iter()
+                            .position(|op| matches!(op, Operation::Read(_)) != read)
+                            .unwrap_or(rest.len());
+                        let (group, tail) = core::mem::take(&mut rest).split_at_mut(len);
+
+                        self.transaction_group(address, read, first, group)?;
+                        first = false;
+                        rest = tail;
+                    }
+
+                    // Stop
+                    self.master_stop();
+
+                    // Wait for stop
+                    busy_wait!(self.i2c, busy, is_not_busy);
+
+                    Ok(())
+                }
+            }
         )+
     };
 }
@@ -739,15 +1432,22 @@ pins! {
 }
 
 i2c!(
-    I2C1: (i2c1, I2c1, pclk1),
-    I2C2: (i2c2, I2c2, pclk1),
-    I2C3: (i2c3, I2c3, pclk1),
-    I2C4: (i2c4, I2c4, pclk4),
+    I2C1: (i2c1, I2c1, I2c123ClkSel, pclk1),
+    I2C2: (i2c2, I2c2, I2c123ClkSel, pclk1),
+    I2C3: (i2c3, I2c3, I2c123ClkSel, pclk1),
+    I2C4: (i2c4, I2c4, I2c4ClkSel, pclk4),
 );
 
 #[cfg(test)]
 mod tests {
-    use core::cmp;
+    use super::{Timing, TimingError};
+    use crate::time::Hertz;
+
+    /// Timing register values for the given clocks
+    fn i2c_timing(i2c_clk: u32, freq: u32) -> (u8, u8, u8, u8, u8) {
+        let t = super::i2c_timing(i2c_clk, freq, true).unwrap();
+        (t.presc, t.scll, t.sclh, t.sdadel, t.scldel)
+    }
 
     /// Runs a timing testcase over PCLK and I2C clock ranges
     fn i2c_timing_testcase<F>(f: F)
@@ -772,11 +1472,59 @@ mod tests {
         }
     }
 
+    #[test]
+    /// Test the timing with the analog filter off
+    fn i2c_timing_no_analog_filter() {
+        assert_eq!(
+            Timing::new_no_analog_filter(Hertz::MHz(64), Hertz::kHz(400)),
+            Ok(super::TIMING_FOR_400KHZ_NO_ANALOG_FILTER)
+        );
+
+        i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
+            let t = super::i2c_timing(i2c_clk, freq, false).unwrap();
+
+            let presc = (t.presc + 1) as f32;
+            let t_i2c_clk = 1. / (i2c_clk as f32);
+
+            // No analog filter delay, 2 i2c_clk cycles for each sync
+            let t_high_low = t.sclh as f32 + 1. + t.scll as f32 + 1.;
+            let t_scl = 4. * t_i2c_clk + (t_high_low * presc * t_i2c_clk);
+            assert!(1. / t_scl <= 1.02 * freq as f32);
+
+            // Data hold time covers the fall time without the analog filter
+            let t_fall_max = if freq > 400_000 { 120e-9 } else { 300e-9 };
+            let t_sdadel = (t.sdadel as f32 * presc + 3.) * t_i2c_clk;
+            assert!(t_sdadel >= t_fall_max);
+        });
+    }
+
+    #[test]
+    /// Test the errors for targets that cannot be achieved
+    fn i2c_timing_errors() {
+        let timing = |i2c_clk: u32, freq: u32| {
+            Timing::new(Hertz::from_raw(i2c_clk), Hertz::from_raw(freq))
+        };
+
+        assert_eq!(
+            timing(100_000_000, 1_000_001),
+            Err(TimingError::FrequencyTooHigh)
+        );
+        assert_eq!(timing(100_000_000, 0), Err(TimingError::ClockTooFast));
+        assert_eq!(timing(1_000_000, 100_000), Err(TimingError::ClockTooSlow));
+        assert_eq!(timing(1_000_000, 10_000), Err(TimingError::ClockTooSlow));
+        assert_eq!(
+            timing(10_000_000, 1_000_000),
+            Err(TimingError::ClockTooSlow)
+        );
+        assert_eq!(timing(120_000_000, 4_000), Err(TimingError::ClockTooFast));
+        assert!(timing(16_000_000, 400_000).is_ok());
+    }
+
     #[test]
     /// Test the SCL frequency is within the expected range
     fn i2c_frequency() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, scll, sclh, _, _) = i2c_timing!(i2c_clk, freq);
+            let (presc_reg, scll, sclh, _, _) = i2c_timing(i2c_clk, freq);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -814,7 +1562,7 @@ mod tests {
     /// Test that the low period of SCL is greater than the minimum specification
     fn i2c_scl_low() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, scll, _, _, _) = i2c_timing!(i2c_clk, freq);
+            let (presc_reg, scll, _, _, _) = i2c_timing(i2c_clk, freq);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -844,7 +1592,7 @@ mod tests {
     /// Test the SDADEL value is greater than the minimum specification
     fn i2c_sdadel_minimum() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, _, _, sdadel, _) = i2c_timing!(i2c_clk, freq);
+            let (presc_reg, _, _, sdadel, _) = i2c_timing(i2c_clk, freq);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -884,7 +1632,7 @@ mod tests {
     /// Test the SDADEL value is less than the maximum specification
     fn i2c_sdadel_maximum() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, _, _, sdadel, _) = i2c_timing!(i2c_clk, freq);
+            let (presc_reg, _, _, sdadel, _) = i2c_timing(i2c_clk, freq);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -918,7 +1666,7 @@ mod tests {
     /// Test the SCLDEL value is greater than the minimum specification
     fn i2c_scldel_minimum() {
         i2c_timing_testcase(|i2c_clk: u32, freq: u32| {
-            let (presc_reg, _, _, _, scldel) = i2c_timing!(i2c_clk, freq);
+            let (presc_reg, _, _, _, scldel) = i2c_timing(i2c_clk, freq);
 
             // Timing parameters
             let presc = (presc_reg + 1) as f32;
@@ -0,0 +1,402 @@
+//! Async I2C using DMA1 or DMA2
+
+use core::cmp;
+use core::future::poll_fn;
+use core::task::Poll;
+
+use atomic_waker::AtomicWaker;
+use embedded_hal_1::i2c::Operation;
+
+use super::{Error, I2c};
+use crate::dma::dma::{start_async_transfer, AsyncStream};
+use crate::dma::traits::TargetAddress;
+use crate::dma::{MemoryToPeripheral, PeripheralToMemory};
+use crate::stm32::{i2c1, I2C1, I2C2, I2C3};
+use crate::Sealed;
+
+type P2M = PeripheralToMemory;
+type M2P = MemoryToPeripheral;
+
+/// Maximum number of bytes in a single DMA transfer
+const MAX_TRANSFER: usize = u16::MAX as usize;
+
+/// Wakers for the tasks waiting on I2C1, I2C2 and I2C3
+static WAKERS: [AtomicWaker; 3] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const WAKER: AtomicWaker = AtomicWaker::new();
+    [WAKER; 3]
+};
+
+/// An I2C peripheral that can be used with DMA1 or DMA2
+pub trait Instance: Sealed {
+    #[doc(hidden)]
+    fn ptr() -> *const i2c1::RegisterBlock;
+    #[doc(hidden)]
+    fn waker() -> &'static AtomicWaker;
+}
+
+macro_rules! instance {
+    ($($I2CX:ident: $index:expr,)+) => {
+        $(
+            impl Sealed for $I2CX {}
+            impl Instance for $I2CX {
+                fn ptr() -> *const i2c1::RegisterBlock {
+                    // On RM0455 parts I2C3 has its own register block type,
+                    // with the same layout as I2C1
+                    $I2CX::ptr() as *const i2c1::RegisterBlock
+                }
+                fn waker() -> &'static AtomicWaker {
+                    &WAKERS[$index]
+                }
+            }
+        )+
+    };
+}
+
+instance! {
+    I2C1: 0,
+    I2C2: 1,
+    I2C3: 2,
+}
+
+impl<I2C: Instance> I2c<I2C> {
+    /// Handles the event and error interrupts for [`I2cAsync`], by disabling
+    /// them and waking the task waiting on this I2C. Call this from both the
+    /// `I2Cx_EV` and `I2Cx_ER` interrupt handlers
+    pub fn on_interrupt() {
+        let i2c = regs::<I2C>();
+        i2c.cr1.modify(|_, w| {
+            w.tcie()
+                .clear_bit()
+                .nackie()
+                .clear_bit()
+                .errie()
+                .clear_bit()
+        });
+
+        I2C::waker().wake();
+    }
+}
+
+/// I2C bus implementing the [embedded-hal-async] `I2c` trait, with the data
+/// transferred by DMA
+///
+/// Each operation of a transaction is transferred by DMA, on the Tx stream
+/// for writes and the Rx stream for reads. As for the
+/// blocking implementation, adjacent operations in the same direction are
+/// transferred back to back, a change of direction generates a repeated
+/// START, and a STOP is only generated at the end.
+///
+/// The interrupt handlers for both streams must call
+/// [`on_interrupt`](AsyncStream::on_interrupt), and the event and error
+/// interrupt handlers for the I2C must call
+/// [`I2c::on_interrupt`](I2c::on_interrupt).
+///
+/// The buffers passed to each operation must be in memory that is accessible
+/// by DMA1 and DMA2, and must not be cached by the data cache.
+///
+/// [embedded-hal-async]: https://docs.rs/embedded-hal-async
+pub struct I2cAsync<I2C, TX, RX> {
+    i2c: I2c<I2C>,
+    tx: TX,
+    rx: RX,
+}
+
+impl<I2C, TX, RX> I2cAsync<I2C, TX, RX>
+where
+    I2C: Instance,
+    I2c<I2C>:
+        TargetAddress<M2P, MemSize = u8> + TargetAddress<P2M, MemSize = u8>,
+    TX: AsyncStream,
+    RX: AsyncStream,
+{
+    /// Creates an async I2C bus from an I2C and two DMA streams
+    pub fn new(i2c: I2c<I2C>, tx: TX, rx: RX) -> Self {
+        I2cAsync { i2c, tx, rx }
+    }
+
+    /// Releases the I2C and DMA streams
+    pub fn free(self) -> (I2c<I2C>, TX, RX) {
+        (self.i2c, self.tx, self.rx)
+    }
+
+    /// Transfers a group of adjacent operations in the same direction,
+    /// following a START or repeated START. Ends with the bus stalled by
+    /// software end mode, ready for a repeated START or a STOP.
+    async fn transaction_group(
+        &mut self,
+        addr: u8,
+        read: bool,
+        first: bool,
+        group: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        let total: usize = group
+            .iter()
+            .map(|op| match op {
+                Operation::Read(buffer) => buffer.len(),
+                Operation::Write(bytes) => bytes.len(),
+            })
+            .sum();
+        let nbytes = cmp::min(total, 255);
+        // Bytes not yet loaded into NBYTES
+        let mut pending = total - nbytes;
+
+        let i2c = regs::<I2C>();
+
+        // Wait for any previous address sequence to end automatically. A
+        // repeated START is generated immediately since the previous group
+        // is stalled with TC set
+        if first {
+            while i2c.cr2.read().start().bit_is_set() {}
+        }
+
+        // ST SAD+R/W or SR SAD+R/W
+        i2c.cr2.write(|w| {
+            w.sadd()
+                .bits(u16::from(addr) << 1)
+                .add10()
+                .clear_bit()
+                .rd_wrn()
+                .bit(read)
+                .nbytes()
+                .bits(nbytes as u8)
+                .reload()
+                .bit(pending > 0)
+                .start()
+                .set_bit()
+                .autoend()
+                .software()
+        });
+
+        for op in group {
+            match op {
+                Operation::Write(bytes) => {
+                    // Each DMA transfer is limited to 65535 bytes
+                    for chunk in bytes.chunks(MAX_TRANSFER) {
+                        // unsafe: the stream is disabled before `bytes` is
+                        // released, either below or by the transaction guard
+                        unsafe {
+                            start_async_transfer::<_, M2P, _>(
+                                &mut self.tx,
+                                &self.i2c,
+                                chunk.as_ptr() as usize,
+                                true,
+                                chunk.len() as u16,
+                            );
+                        }
+                        wait_stream::<I2C, _>(&mut self.tx, &mut pending)
+                            .await?;
+                        self.tx.disable();
+                    }
+                }
+                Operation::Read(buffer) => {
+                    for chunk in buffer.chunks_mut(MAX_TRANSFER) {
+                        // unsafe: the stream is disabled before `buffer` is
+                        // released, either below or by the transaction guard
+                        unsafe {
+                            start_async_transfer::<_, P2M, _>(
+                                &mut self.rx,
+                                &self.i2c,
+                                chunk.as_mut_ptr() as usize,
+                                true,
+                                chunk.len() as u16,
+                            );
+                        }
+                        wait_stream::<I2C, _>(&mut self.rx, &mut pending)
+                            .await?;
+                        self.rx.disable();
+                    }
+                }
+            }
+        }
+
+        // Wait until the group finishes
+        poll_fn(|cx| {
+            I2C::waker().register(cx.waker());
+            match service(i2c, &mut pending) {
+                Err(e) => Poll::Ready(Err(e)),
+                Ok(true) => Poll::Ready(Ok(())),
+                Ok(false) => {
+                    listen(i2c);
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    /// Aborts any ongoing transfer, and disables the DMA requests and
+    /// interrupts
+    fn stop(&mut self) {
+        self.tx.disable();
+        self.rx.disable();
+
+        let i2c = regs::<I2C>();
+        i2c.cr1.modify(|_, w| {
+            w.txdmaen()
+                .clear_bit()
+                .rxdmaen()
+                .clear_bit()
+                .tcie()
+                .clear_bit()
+                .nackie()
+                .clear_bit()
+                .errie()
+                .clear_bit()
+        });
+
+        // Release the bus if the transaction did not finish
+        if i2c.isr.read().busy().is_busy() {
+            i2c.cr2.modify(|_, w| w.stop().set_bit());
+            while i2c.isr.read().busy().is_busy() {}
+        }
+    }
+}
+
+/// Returns the registers of `I2C`
+fn regs<I2C: Instance>() -> &'static i2c1::RegisterBlock {
+    // unsafe: the registers are only accessed by the owner of the I2C, except
+    // for the interrupt enables which are also cleared by `on_interrupt`
+    unsafe { &*I2C::ptr() }
+}
+
+/// Enables the interrupts that wake the task
+fn listen(i2c: &i2c1::RegisterBlock) {
+    i2c.cr1
+        .modify(|_, w| w.tcie().set_bit().nackie().set_bit().errie().set_bit());
+}
+
+/// Checks for errors, and reloads NBYTES from `pending` if the transfer is
+/// waiting for a reload. Returns `true` if the transfer of NBYTES is complete
+/// with no reload necessary
+fn service(
+    i2c: &i2c1::RegisterBlock,
+    pending: &mut usize,
+) -> Result<bool, Error> {
+    let isr = i2c.isr.read();
+
+    if isr.berr().is_error() {
+        i2c.icr.write(|w| w.berrcf().set_bit());
+        return Err(Error::Bus);
+    } else if isr.arlo().is_lost() {
+        i2c.icr.write(|w| w.arlocf().set_bit());
+        return Err(Error::Arbitration);
+    } else if isr.nackf().bit_is_set() {
+        i2c.icr.write(|w| w.stopcf().set_bit().nackcf().set_bit());
+        flush_txdr!(i2c);
+        return Err(Error::NotAcknowledge);
+    }
+
+    if isr.tcr().is_complete() {
+        let nbytes = cmp::min(*pending, 255);
+        *pending -= nbytes;
+        i2c.cr2.modify(|_, w| {
+            w.nbytes().bits(nbytes as u8).reload().bit(*pending > 0)
+        });
+    }
+
+    Ok(isr.tc().is_complete())
+}
+
+/// Waits for the DMA transfer on `stream` to complete, reloading NBYTES
+/// whenever necessary
+async fn wait_stream<I2C: Instance, S: AsyncStream>(
+    stream: &mut S,
+    pending: &mut usize,
+) -> Result<(), Error> {
+    let i2c = regs::<I2C>();
+    poll_fn(|cx| {
+        I2C::waker().register(cx.waker());
+        if let Err(e) = service(i2c, pending) {
+            return Poll::Ready(Err(e));
+        }
+
+        match stream.poll_transfer(cx) {
+            Poll::Ready(true) => Poll::Ready(Ok(())),
+            Poll::Ready(false) => Poll::Ready(Err(Error::Dma)),
+            Poll::Pending => {
+                listen(i2c);
+                Poll::Pending
+            }
+        }
+    })
+    .await
+}
+
+/// Stops the ongoing transaction when dropped
+struct Transaction<'a, I2C, TX, RX>(&'a mut I2cAsync<I2C, TX, RX>)
+where
+    I2C: Instance,
+    I2c<I2C>:
+        TargetAddress<M2P, MemSize = u8> + TargetAddress<P2M, MemSize = u8>,
+    TX: AsyncStream,
+    RX: AsyncStream;
+
+impl<'a, I2C, TX, RX> Drop for Transaction<'a, I2C, TX, RX>
+where
+    I2C: Instance,
+    I2c<I2C>:
+        TargetAddress<M2P, MemSize = u8> + TargetAddress<P2M, MemSize = u8>,
+    TX: AsyncStream,
+    RX: AsyncStream,
+{
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+impl<I2C, TX, RX> embedded_hal_1::i2c::ErrorType for I2cAsync<I2C, TX, RX> {
+    type Error = Error;
+}
+
+impl<I2C, TX, RX> embedded_hal_async::i2c::I2c for I2cAsync<I2C, TX, RX>
+where
+    I2C: Instance,
+    I2c<I2C>:
+        TargetAddress<M2P, MemSize = u8> + TargetAddress<P2M, MemSize = u8>,
+    TX: AsyncStream,
+    RX: AsyncStream,
+{
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if operations.is_empty() {
+            return Ok(());
+        }
+
+        let i2c = regs::<I2C>();
+        i2c.cr1
+            .modify(|_, w| w.txdmaen().set_bit().rxdmaen().set_bit());
+
+        // The transaction is stopped when the guard is dropped, including
+        // when this future is dropped before it completes
+        let transaction = Transaction(self);
+
+        let mut first = true;
+        let mut rest = operations;
+        while !rest.is_empty() {
+            // Adjacent operations in the same direction are coalesced
+            let read = matches!(rest[0], Operation::Read(_));
+            let len = rest
+                .iter()
+                .position(|op| matches!(op, Operation::Read(_)) != read)
+                .unwrap_or(rest.len());
+            let (group, tail) = core::mem::take(&mut rest).split_at_mut(len);
+
+            transaction
+                .0
+                .transaction_group(address, read, first, group)
+                .await?;
+            first = false;
+            rest = tail;
+        }
+
+        // Stop
+        i2c.cr2.write(|w| w.stop().set_bit());
+        while i2c.isr.read().busy().is_busy() {}
+
+        Ok(())
+    }
+}
@@ -0,0 +1,225 @@
+//! I2C EEPROM with 16-bit memory addresses
+
+use core::cmp;
+
+use embedded_hal_1::i2c::{I2c, Operation};
+
+use crate::hal::blocking::delay::DelayMs;
+
+/// Write cycle time of the EEPROM, in milliseconds
+const WRITE_CYCLE_MS: u8 = 5;
+
+/// Returns the number of bytes that can be written at `mem_addr` without
+/// crossing a page boundary, up to `len`
+fn page_chunk_len(mem_addr: u16, len: usize, page_size: u16) -> usize {
+    let to_boundary = page_size - mem_addr % page_size;
+    cmp::min(len, usize::from(to_boundary))
+}
+
+/// An I2C EEPROM with 16-bit memory addresses, such as the 24C32 to 24C512
+/// and the M24 series
+///
+/// The EEPROM is accessed through any embedded-hal 1.0 I2C bus, such as
+/// [`I2c`](crate::i2c::I2c) or a device on a [shared bus](crate::bus). The
+/// `delay` waits for the write cycle of the EEPROM after each page is
+/// written.
+pub struct I2cEeprom<I2C, DELAY> {
+    i2c: I2C,
+    delay: DELAY,
+}
+
+impl<I2C, DELAY> I2cEeprom<I2C, DELAY>
+where
+    I2C: I2c,
+    DELAY: DelayMs<u8>,
+{
+    /// Creates an EEPROM on the I2C bus `i2c`
+    pub fn new(i2c: I2C, delay: DELAY) -> Self {
+        I2cEeprom { i2c, delay }
+    }
+
+    /// Releases the I2C bus and the delay
+    pub fn free(self) -> (I2C, DELAY) {
+        (self.i2c, self.delay)
+    }
+
+    /// Writes `data` starting at `mem_addr`
+    ///
+    /// The data is split at each page boundary, where `page_size` is the
+    /// size of the page buffer of the EEPROM in bytes. Each page is written
+    /// in its own I2C transaction, followed by a delay of 5ms for the write
+    /// cycle.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `page_size` is zero, or if `data` extends beyond the end of
+    /// the 16-bit address space.
+    pub fn write_page(
+        &mut self,
+        device_addr: u8,
+        mem_addr: u16,
+        data: &[u8],
+        page_size: u16,
+    ) -> Result<(), I2C::Error> {
+        assert!(page_size > 0, "EEPROM page size must not be zero");
+        assert!(
+            usize::from(mem_addr) + data.len() <= 1 << 16,
+            "EEPROM write extends beyond the 16-bit address space"
+        );
+
+        let mut addr = mem_addr;
+        let mut data = data;
+        while !data.is_empty() {
+            let len = page_chunk_len(addr, data.len(), page_size);
+            let (page, rest) = data.split_at(len);
+
+            // The memory address and the data are transferred back to back
+            self.i2c.transaction(
+                device_addr,
+                &mut [
+                    Operation::Write(&addr.to_be_bytes()),
+                    Operation::Write(page),
+                ],
+            )?;
+            self.delay.delay_ms(WRITE_CYCLE_MS);
+
+            addr = addr.wrapping_add(len as u16);
+            data = rest;
+        }
+        Ok(())
+    }
+
+    /// Writes a single byte at `mem_addr`, followed by a delay of 5ms for
+    /// the write cycle
+    pub fn write_byte(
+        &mut self,
+        device_addr: u8,
+        mem_addr: u16,
+        byte: u8,
+    ) -> Result<(), I2C::Error> {
+        let [hi, lo] = mem_addr.to_be_bytes();
+        self.i2c.write(device_addr, &[hi, lo, byte])?;
+        self.delay.delay_ms(WRITE_CYCLE_MS);
+        Ok(())
+    }
+
+    /// Reads `buffer.len()` bytes starting at `mem_addr`. The EEPROM
+    /// increments the address after each byte, across page boundaries
+    pub fn read_bytes(
+        &mut self,
+        device_addr: u8,
+        mem_addr: u16,
+        buffer: &mut [u8],
+    ) -> Result<(), I2C::Error> {
+        self.i2c
+            .write_read(device_addr, &mem_addr.to_be_bytes(), buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::convert::Infallible;
+    use embedded_hal_1::i2c::ErrorType;
+
+    #[derive(Default)]
+    struct MockI2c {
+        /// Bytes written in each transaction
+        writes: Vec<(u8, Vec<u8>)>,
+    }
+
+    impl ErrorType for MockI2c {
+        type Error = Infallible;
+    }
+
+    impl I2c for MockI2c {
+        fn transaction(
+            &mut self,
+            address: u8,
+            operations: &mut [Operation<'_>],
+        ) -> Result<(), Infallible> {
+            let mut bytes = Vec::new();
+            for op in operations {
+                match op {
+                    Operation::Write(data) => bytes.extend_from_slice(data),
+                    Operation::Read(buffer) => buffer.fill(0xA5),
+                }
+            }
+            self.writes.push((address, bytes));
+            Ok(())
+        }
+    }
+
+    #[derive(Default)]
+    struct MockDelay(u32);
+
+    impl DelayMs<u8> for MockDelay {
+        fn delay_ms(&mut self, ms: u8) {
+            self.0 += u32::from(ms);
+        }
+    }
+
+    #[test]
+    fn chunk_len() {
+        assert_eq!(page_chunk_len(0, 100, 32), 32);
+        assert_eq!(page_chunk_len(30, 100, 32), 2);
+        assert_eq!(page_chunk_len(30, 1, 32), 1);
+        assert_eq!(page_chunk_len(0xFFF8, 100, 8), 8);
+    }
+
+    #[test]
+    fn write_splits_at_page_boundaries() {
+        let mut eeprom =
+            I2cEeprom::new(MockI2c::default(), MockDelay::default());
+        let data: Vec<u8> = (0..20).collect();
+        eeprom.write_page(0x50, 0x0106, &data, 8).unwrap();
+
+        let (i2c, delay) = eeprom.free();
+        assert_eq!(
+            i2c.writes,
+            vec![
+                (0x50, vec![0x01, 0x06, 0, 1]),
+                (0x50, vec![0x01, 0x08, 2, 3, 4, 5, 6, 7, 8, 9]),
+                (0x50, vec![0x01, 0x10, 10, 11, 12, 13, 14, 15, 16, 17]),
+                (0x50, vec![0x01, 0x18, 18, 19]),
+            ]
+        );
+        assert_eq!(delay.0, 20);
+    }
+
+    #[test]
+    fn write_to_end_of_memory() {
+        let mut eeprom =
+            I2cEeprom::new(MockI2c::default(), MockDelay::default());
+        eeprom.write_page(0x50, 0xFFFE, &[1, 2], 64).unwrap();
+        assert_eq!(
+            eeprom.free().0.writes,
+            vec![(0x50, vec![0xFF, 0xFE, 1, 2])]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn write_beyond_end_of_memory() {
+        let mut eeprom =
+            I2cEeprom::new(MockI2c::default(), MockDelay::default());
+        let _ = eeprom.write_page(0x50, 0xFFFE, &[1, 2, 3], 64);
+    }
+
+    #[test]
+    fn byte_and_read() {
+        let mut eeprom =
+            I2cEeprom::new(MockI2c::default(), MockDelay::default());
+        eeprom.write_byte(0x51, 0x1234, 0x42).unwrap();
+        let mut buffer = [0; 3];
+        eeprom.read_bytes(0x51, 0x0010, &mut buffer).unwrap();
+        assert_eq!(buffer, [0xA5; 3]);
+
+        let (i2c, delay) = eeprom.free();
+        assert_eq!(
+            i2c.writes,
+            vec![(0x51, vec![0x12, 0x34, 0x42]), (0x51, vec![0x00, 0x10])]
+        );
+        assert_eq!(delay.0, 5);
+    }
+}
@@ -6,6 +6,7 @@
 //!
 //! * [Power Configuration](crate::pwr)
 //! * [Reset and Clock Control](crate::rcc)
+//! * [Clock Recovery System](crate::crs)
 //!
 //! Digital IO
 //!
@@ -16,6 +17,8 @@
 //!
 //! * [Analog to Digital Converter (ADC)](crate::adc)
 //! * [Digital to Analog Converter (DAC)](crate::dac)
+//! * [Comparators (COMP)](crate::comp)
+//! * [Operational Amplifiers (OPAMP)](crate::opamp)
 //!
 //! Digital Busses
 //!
@@ -23,12 +26,15 @@
 //! * [Serial Peripheral Interface (SPI)](crate::spi)
 //! * [Serial Data (USART/UART)](crate::serial)
 //! * [Serial Audio Interface](crate::sai)
+//! * [S/PDIF Receiver](crate::spdifrx)
 //! * [Quad or Octo SPI](crate::xspi) Feature gate `xspi`
 //! * [Ethernet](crate::ethernet) Feature gate `ethernet`
 //! * [USB HS](crate::usb_hs) Feature gate `usb_hs`
 //! * [LCD-TFT Display Controller](crate::ltdc) Feature gate `ltdc`
 //! * MIPI DSI (STM32H747/757 only) Feature gate `dsi`
 //! * [CAN and CAN-FD](crate::can) Feature gate `can`
+//! * [Digital Camera Interface (DCMI)](crate::dcmi)
+//! * [1-Wire](crate::onewire)
 //!
 //! External Memory
 //!
@@ -38,7 +44,9 @@
 //! Timing functions
 //!
 //! * [Pulse Width Modulation (PWM)](crate::pwm)
+//! * [High Resolution Timer (HRTIM)](crate::hrtim) Only on RM0433 and RM0399 parts
 //! * [Quadrature Encoder Interface](crate::qei)
+//! * [PWM Input](crate::pwm_input)
 //! * [Real-Time Clock](crate::rtc) Feature gate `rtc`
 //! * [Timers](crate::timer)
 //! * [Delays](crate::delay)
@@ -54,7 +62,10 @@
 //!
 //! Cargo Features
 //!
-//! * [`defmt`](https://defmt.ferrous-systems.com/) formatting for some types can be enabled with the feature `defmt`.
+//! * [`defmt`](https://defmt.ferrous-systems.com/) formatting for the error types, configuration types and clock frequencies can be enabled with the feature `defmt`.
+//! * [`embedded-io`](https://docs.rs/embedded-io) `Read` and `Write` traits for [Serial](crate::serial) can be enabled with the feature `embedded-io`.
+//! * Sharing an SPI or I2C bus between several embedded-hal 1.0 device drivers, with [bus](crate::bus), can be enabled with the feature `bus`.
+//! * [`embedded-hal-async`](https://docs.rs/embedded-hal-async) traits using DMA, for [SPI](crate::spi::SpiAsync) and [I2C](crate::i2c::I2cAsync), and the [`embedded-io-async`](https://docs.rs/embedded-io-async) `Read` trait for a [serial receiver](crate::serial::RxAsync), and [continuous ADC conversions](crate::adc::AdcAsync), can be enabled with the feature `async`. This requires Rust 1.75 or later.
 
 #![cfg_attr(not(test), no_std)]
 #![cfg_attr(docsrs, feature(doc_cfg))]
@@ -145,15 +156,24 @@ pub use crate::stm32::interrupt;
 
 #[cfg(feature = "device-selected")]
 pub mod adc;
+#[cfg(all(feature = "device-selected", feature = "bus"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "bus")))]
+pub mod bus;
 #[cfg(all(feature = "device-selected", feature = "can"))]
 #[cfg_attr(docsrs, doc(cfg(feature = "can")))]
 pub mod can;
+#[cfg(feature = "device-selected")]
+pub mod comp;
 #[cfg(all(feature = "device-selected", feature = "crc"))]
 #[cfg_attr(docsrs, doc(cfg(feature = "crc")))]
 pub mod crc;
 #[cfg(feature = "device-selected")]
+pub mod crs;
+#[cfg(feature = "device-selected")]
 pub mod dac;
 #[cfg(feature = "device-selected")]
+pub mod dcmi;
+#[cfg(feature = "device-selected")]
 pub mod delay;
 #[cfg(feature = "device-selected")]
 pub mod dma;
@@ -176,6 +196,10 @@ pub mod fmc;
 #[cfg(feature = "device-selected")]
 pub mod gpio;
 #[cfg(feature = "device-selected")]
+pub mod hall_sensor;
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
+pub mod hrtim;
+#[cfg(feature = "device-selected")]
 pub mod i2c;
 #[cfg(feature = "device-selected")]
 pub mod independent_watchdog;
@@ -183,10 +207,16 @@ pub mod independent_watchdog;
 #[cfg_attr(docsrs, doc(cfg(feature = "ltdc")))]
 pub mod ltdc;
 #[cfg(feature = "device-selected")]
+pub mod onewire;
+#[cfg(feature = "device-selected")]
+pub mod opamp;
+#[cfg(feature = "device-selected")]
 pub mod prelude;
 #[cfg(feature = "device-selected")]
 pub mod pwm;
 #[cfg(feature = "device-selected")]
+pub mod pwm_input;
+#[cfg(feature = "device-selected")]
 pub mod pwr;
 #[cfg(feature = "device-selected")]
 pub mod qei;
@@ -207,6 +237,8 @@ pub mod serial;
 #[cfg(feature = "device-selected")]
 pub mod signature;
 #[cfg(feature = "device-selected")]
+pub mod spdifrx;
+#[cfg(feature = "device-selected")]
 pub mod spi;
 #[cfg(feature = "device-selected")]
 pub mod system_watchdog;
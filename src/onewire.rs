@@ -0,0 +1,552 @@
+//! 1-Wire bus master
+//!
+//! A 1-Wire bus master using an open drain GPIO pin, with the time slots
+//! measured by the counter of a [`Timer`](crate::timer::Timer). The bus
+//! needs a pull-up resistor, typically 4.7kΩ. The internal pull-up of the pin
+//! is usually too weak, except for very short buses.
+//!
+//! The driver uses the standard speed timings recommended by Maxim
+//! application note 126. Each time slot is performed in a critical section,
+//! so interrupts are delayed by up to 70µs, or 480µs during a reset.
+//!
+//! # Usage
+//!
+//! ```
+//! let pin = gpioa.pa0.into_open_drain_output_in_state(PinState::High);
+//! let timer = dp.TIM6.tick_timer(1.MHz(), ccdr.peripheral.TIM6, &ccdr.clocks);
+//!
+//! let mut onewire = OneWire::new(pin, timer);
+//!
+//! // Find all the devices on the bus
+//! let mut search = Search::new();
+//! while let Some(rom) = onewire.search(&mut search)? {
+//!     info!("Found device with family code {:02x}", rom.family_code());
+//! }
+//!
+//! // Start a temperature conversion on all DS18B20s
+//! onewire.reset()?;
+//! onewire.skip_rom();
+//! onewire.write_byte(0x44);
+//! ```
+//!
+//! The timer's kernel clock must be a multiple of 1MHz.
+//!
+//! Device specific commands are left to device driver crates, which can
+//! build on the [`reset`](OneWire::reset), [`select`](OneWire::select),
+//! [`read_byte`](OneWire::read_byte) and [`write_byte`](OneWire::write_byte)
+//! primitives.
+
+use core::convert::Infallible;
+
+use crate::hal::digital::v2::{InputPin, OutputPin};
+use crate::time::Hertz;
+use crate::timer::TickTimer;
+
+/// Search ROM command
+pub const SEARCH_ROM: u8 = 0xF0;
+/// Read ROM command, only valid when there is a single device on the bus
+pub const READ_ROM: u8 = 0x33;
+/// Match ROM command
+pub const MATCH_ROM: u8 = 0x55;
+/// Skip ROM command
+pub const SKIP_ROM: u8 = 0xCC;
+/// Alarm search command
+pub const ALARM_SEARCH: u8 = 0xEC;
+
+// Standard speed timings in µs, from Maxim application note 126
+const A: u32 = 6;
+const B: u32 = 64;
+const C: u32 = 60;
+const D: u32 = 10;
+const E: u32 = 9;
+const F: u32 = 55;
+const H: u32 = 480;
+const I: u32 = 70;
+const J: u32 = 410;
+
+/// 1-Wire errors
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Error {
+    /// No device answered the reset pulse with a presence pulse
+    NoPresence,
+    /// The bus is held low, either by a short circuit or by a missing
+    /// pull-up resistor
+    BusLow,
+    /// The CRC of a ROM code read from the bus is invalid
+    InvalidCrc,
+}
+
+/// The 64-bit ROM code of a 1-Wire device
+///
+/// The code is stored in the order it is sent on the bus: the family code,
+/// the serial number and then the CRC.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Rom(pub [u8; 8]);
+
+impl Rom {
+    /// Returns the family code, which identifies the type of device
+    pub fn family_code(&self) -> u8 {
+        self.0[0]
+    }
+
+    /// Returns true if the CRC in the last byte of the ROM code is valid
+    pub fn is_crc_valid(&self) -> bool {
+        crc8(&self.0) == 0
+    }
+}
+
+/// Calculates the Dallas/Maxim CRC-8 of `data`, as used by ROM codes and by
+/// the scratchpads of many devices
+///
+/// The CRC of data followed by its CRC is zero.
+pub fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0;
+    for byte in data {
+        let mut byte = *byte;
+        for _ in 0..8 {
+            let mix = (crc ^ byte) & 1;
+            crc >>= 1;
+            if mix != 0 {
+                crc ^= 0x8C;
+            }
+            byte >>= 1;
+        }
+    }
+    crc
+}
+
+/// State of a search for the devices on a bus
+///
+/// Each call to [`OneWire::search`] with the same `Search` finds the next
+/// device.
+#[derive(Clone, Debug)]
+pub struct Search {
+    rom: [u8; 8],
+    last_discrepancy: u8,
+    done: bool,
+    command: u8,
+}
+
+impl Search {
+    /// Searches for all the devices on the bus
+    pub fn new() -> Self {
+        Search {
+            rom: [0; 8],
+            last_discrepancy: 0,
+            done: false,
+            command: SEARCH_ROM,
+        }
+    }
+
+    /// Searches for the devices that have an alarm condition
+    pub fn alarms() -> Self {
+        Search {
+            command: ALARM_SEARCH,
+            ..Search::new()
+        }
+    }
+
+    /// Searches for the devices of a single family. The search may also
+    /// return devices of other families, which should be ignored
+    pub fn family(family_code: u8) -> Self {
+        let mut search = Search::new();
+        search.rom[0] = family_code;
+        // Take the branch of the family code at every bit of the first byte
+        search.last_discrepancy = 64;
+        search
+    }
+}
+
+impl Default for Search {
+    fn default() -> Self {
+        Search::new()
+    }
+}
+
+/// Reading and writing single bits on the bus
+trait BitIo {
+    fn read_bit(&mut self) -> bool;
+    fn write_bit(&mut self, bit: bool);
+}
+
+/// Performs one pass of the search ROM algorithm from Maxim application
+/// note 187, after the reset and search command. Returns the ROM code of the
+/// device that was found, or `None` if no device answered
+fn search_pass<B: BitIo>(bus: &mut B, search: &mut Search) -> Option<Rom> {
+    let mut last_zero = 0;
+
+    for id_bit_number in 1..=64u8 {
+        let byte = usize::from((id_bit_number - 1) / 8);
+        let mask = 1 << ((id_bit_number - 1) % 8);
+
+        let id_bit = bus.read_bit();
+        let cmp_id_bit = bus.read_bit();
+
+        let direction = match (id_bit, cmp_id_bit) {
+            // No devices are still taking part in the search
+            (true, true) => return None,
+            // All the devices have the same bit
+            (false, true) => false,
+            (true, false) => true,
+            // Discrepancy
+            (false, false) => {
+                let direction = if id_bit_number < search.last_discrepancy {
+                    search.rom[byte] & mask != 0
+                } else {
+                    id_bit_number == search.last_discrepancy
+                };
+                if !direction {
+                    last_zero = id_bit_number;
+                }
+                direction
+            }
+        };
+
+        if direction {
+            search.rom[byte] |= mask;
+        } else {
+            search.rom[byte] &= !mask;
+        }
+        bus.write_bit(direction);
+    }
+
+    search.last_discrepancy = last_zero;
+    search.done = last_zero == 0;
+
+    Some(Rom(search.rom))
+}
+
+/// 1-Wire bus master
+pub struct OneWire<PIN, TIM> {
+    pin: PIN,
+    timer: TIM,
+}
+
+impl<PIN, TIM> OneWire<PIN, TIM>
+where
+    PIN: OutputPin<Error = Infallible> + InputPin<Error = Infallible>,
+    TIM: TickTimer,
+{
+    /// Creates a 1-Wire bus master from an open drain pin and a timer
+    ///
+    /// The timer is configured to count at 1MHz.
+    pub fn new(mut pin: PIN, mut timer: TIM) -> Self {
+        let _ = pin.set_high();
+
+        timer.pause();
+        timer.set_tick_freq(Hertz::MHz(1));
+        timer.apply_freq();
+        timer.resume();
+
+        OneWire { pin, timer }
+    }
+
+    /// Releases the pin and the timer
+    pub fn free(self) -> (PIN, TIM) {
+        (self.pin, self.timer)
+    }
+
+    fn release(&mut self) {
+        let _ = self.pin.set_high();
+    }
+
+    fn drive_low(&mut self) {
+        let _ = self.pin.set_low();
+    }
+
+    fn is_high(&self) -> bool {
+        match self.pin.is_high() {
+            Ok(high) => high,
+            Err(e) => match e {},
+        }
+    }
+
+    /// Waits until `us` microseconds after the timer was last reset
+    fn wait_until(&self, us: u32) {
+        while self.timer.counter() < us {}
+    }
+
+    /// Sends a reset pulse, returning `Ok` if at least one device answered
+    /// with a presence pulse
+    pub fn reset(&mut self) -> Result<(), Error> {
+        if !self.is_high() {
+            return Err(Error::BusLow);
+        }
+
+        let presence = cortex_m::interrupt::free(|_| {
+            self.timer.reset_counter();
+            self.drive_low();
+            self.wait_until(H);
+            self.release();
+
+            self.timer.reset_counter();
+            self.wait_until(I);
+            let presence = !self.is_high();
+            self.wait_until(I + J);
+            presence
+        });
+
+        if !self.is_high() {
+            Err(Error::BusLow)
+        } else if presence {
+            Ok(())
+        } else {
+            Err(Error::NoPresence)
+        }
+    }
+
+    /// Writes a single bit
+    pub fn write_bit(&mut self, bit: bool) {
+        let (low, high) = if bit { (A, B) } else { (C, D) };
+
+        cortex_m::interrupt::free(|_| {
+            self.timer.reset_counter();
+            self.drive_low();
+            self.wait_until(low);
+            self.release();
+            self.wait_until(low + high);
+        });
+    }
+
+    /// Reads a single bit
+    pub fn read_bit(&mut self) -> bool {
+        cortex_m::interrupt::free(|_| {
+            self.timer.reset_counter();
+            self.drive_low();
+            self.wait_until(A);
+            self.release();
+            self.wait_until(A + E);
+            let bit = self.is_high();
+            self.wait_until(A + E + F);
+            bit
+        })
+    }
+
+    /// Writes a byte, least significant bit first
+    pub fn write_byte(&mut self, byte: u8) {
+        for i in 0..8 {
+            self.write_bit(byte & (1 << i) != 0);
+        }
+    }
+
+    /// Reads a byte, least significant bit first
+    pub fn read_byte(&mut self) -> u8 {
+        (0..8).fold(0, |byte, i| byte | (u8::from(self.read_bit()) << i))
+    }
+
+    /// Writes all the bytes in `bytes`
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_byte(*byte);
+        }
+    }
+
+    /// Fills `bytes` with bytes read from the bus
+    pub fn read_bytes(&mut self, bytes: &mut [u8]) {
+        for byte in bytes {
+            *byte = self.read_byte();
+        }
+    }
+
+    /// Sends the skip ROM command, to address all the devices on the bus.
+    /// Must follow a reset
+    pub fn skip_rom(&mut self) {
+        self.write_byte(SKIP_ROM);
+    }
+
+    /// Resets the bus and addresses a single device with the match ROM
+    /// command
+    pub fn select(&mut self, rom: &Rom) -> Result<(), Error> {
+        self.reset()?;
+        self.write_byte(MATCH_ROM);
+        self.write_bytes(&rom.0);
+        Ok(())
+    }
+
+    /// Resets the bus and reads the ROM code of the only device on the bus
+    pub fn read_rom(&mut self) -> Result<Rom, Error> {
+        self.reset()?;
+        self.write_byte(READ_ROM);
+
+        let mut rom = Rom([0; 8]);
+        self.read_bytes(&mut rom.0);
+
+        if rom.is_crc_valid() {
+            Ok(rom)
+        } else {
+            Err(Error::InvalidCrc)
+        }
+    }
+
+    /// Finds the next device on the bus, returning `Ok(None)` once all the
+    /// devices have been found
+    pub fn search(
+        &mut self,
+        search: &mut Search,
+    ) -> Result<Option<Rom>, Error> {
+        if search.done {
+            return Ok(None);
+        }
+
+        match self.reset() {
+            Ok(()) => {}
+            Err(Error::NoPresence) => {
+                search.done = true;
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        }
+        self.write_byte(search.command);
+
+        match search_pass(self, search) {
+            Some(rom) if rom.is_crc_valid() => Ok(Some(rom)),
+            Some(_) => Err(Error::InvalidCrc),
+            None => {
+                search.done = true;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<PIN, TIM> BitIo for OneWire<PIN, TIM>
+where
+    PIN: OutputPin<Error = Infallible> + InputPin<Error = Infallible>,
+    TIM: TickTimer,
+{
+    fn read_bit(&mut self) -> bool {
+        OneWire::read_bit(self)
+    }
+    fn write_bit(&mut self, bit: bool) {
+        OneWire::write_bit(self, bit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Devices taking part in a search, with wired-AND reads
+    struct Bus {
+        roms: Vec<[u8; 8]>,
+        active: Vec<bool>,
+        bit: usize,
+        complement: bool,
+    }
+
+    impl Bus {
+        fn new(roms: &[[u8; 8]]) -> Self {
+            Bus {
+                roms: roms.to_vec(),
+                active: vec![true; roms.len()],
+                bit: 0,
+                complement: false,
+            }
+        }
+
+        fn restart(&mut self) {
+            self.active = vec![true; self.roms.len()];
+            self.bit = 0;
+            self.complement = false;
+        }
+
+        fn rom_bit(rom: &[u8; 8], bit: usize) -> bool {
+            rom[bit / 8] & (1 << (bit % 8)) != 0
+        }
+    }
+
+    impl BitIo for Bus {
+        fn read_bit(&mut self) -> bool {
+            let (bit, complement) = (self.bit, self.complement);
+            self.complement = !complement;
+            self.roms
+                .iter()
+                .zip(&self.active)
+                .filter(|(_, active)| **active)
+                .all(|(rom, _)| Bus::rom_bit(rom, bit) != complement)
+        }
+
+        fn write_bit(&mut self, direction: bool) {
+            for (rom, active) in self.roms.iter().zip(&mut self.active) {
+                if Bus::rom_bit(rom, self.bit) != direction {
+                    *active = false;
+                }
+            }
+            self.bit += 1;
+        }
+    }
+
+    fn with_crc(mut rom: [u8; 8]) -> [u8; 8] {
+        rom[7] = crc8(&rom[..7]);
+        rom
+    }
+
+    #[test]
+    fn crc8_rom() {
+        // ROM code from the DS18B20 datasheet example
+        let rom = Rom([0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2]);
+        assert_eq!(crc8(&rom.0[..7]), 0xA2);
+        assert!(rom.is_crc_valid());
+
+        let rom = Rom([0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x01, 0xA2]);
+        assert!(!rom.is_crc_valid());
+    }
+
+    #[test]
+    fn search_finds_all_devices() {
+        let roms = [
+            with_crc([0x28, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0]),
+            with_crc([0x28, 0x81, 0x02, 0x03, 0x04, 0x05, 0x06, 0]),
+            with_crc([0x10, 0xAA, 0x55, 0x00, 0xFF, 0x00, 0x01, 0]),
+            with_crc([0x28, 0x01, 0x02, 0x03, 0x04, 0x05, 0x07, 0]),
+        ];
+        let mut bus = Bus::new(&roms);
+        let mut search = Search::new();
+
+        let mut found = Vec::new();
+        while !search.done {
+            bus.restart();
+            found.push(search_pass(&mut bus, &mut search).unwrap().0);
+        }
+
+        found.sort();
+        let mut expected = roms.to_vec();
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[test]
+    fn search_single_device() {
+        let rom = with_crc([0x28, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0]);
+        let mut bus = Bus::new(&[rom]);
+        let mut search = Search::new();
+
+        assert_eq!(search_pass(&mut bus, &mut search), Some(Rom(rom)));
+        assert!(search.done);
+    }
+
+    #[test]
+    fn search_no_devices() {
+        let mut bus = Bus::new(&[]);
+        let mut search = Search::new();
+
+        assert_eq!(search_pass(&mut bus, &mut search), None);
+    }
+
+    #[test]
+    fn search_family() {
+        let roms = [
+            with_crc([0x10, 0xAA, 0x55, 0x00, 0xFF, 0x00, 0x01, 0]),
+            with_crc([0x28, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0]),
+            with_crc([0x3B, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0]),
+        ];
+        let mut bus = Bus::new(&roms);
+        let mut search = Search::family(0x28);
+
+        let rom = search_pass(&mut bus, &mut search).unwrap();
+        assert_eq!(rom.family_code(), 0x28);
+    }
+}
@@ -0,0 +1,329 @@
+//! Operational Amplifiers (OPAMP)
+//!
+//! There are two operational amplifiers, OPAMP1 and OPAMP2. Both share a
+//! single register block and reset line, so they are split from the `OPAMP`
+//! peripheral together with [`OpampExt::opamp`].
+//!
+//! Each operational amplifier can be configured as:
+//!
+//! * A voltage follower, with [`follower`](Opamp::follower)
+//! * A Programmable Gain Amplifier (PGA) with a non-inverting gain of 2, 4,
+//!   8 or 16, with [`pga`](Opamp::pga)
+//! * A standalone operational amplifier, with external feedback between the
+//!   output and the inverting input, with [`standalone`](Opamp::standalone)
+//!
+//! The non-inverting input is either a GPIO pin or a DAC1 output. The output
+//! of OPAMP1 on PC4 is also an ADC input (ADC12_INP4), so the amplified
+//! signal can be sampled by ADC1 or ADC2 on that pin.
+//!
+//! ```
+//! use stm32h7xx_hal::opamp::{OpampExt, PgaGain};
+//!
+//! let (opamp1, _opamp2) = dp.OPAMP.opamp(ccdr.peripheral.OPAMP);
+//!
+//! let input = gpiob.pb0.into_analog();
+//! let output = gpioc.pc4.into_analog();
+//!
+//! let opamp1 = opamp1.calibrate(&mut delay);
+//! let opamp1 = opamp1.pga(input, output, PgaGain::Gain4);
+//! ```
+//!
+//! # Calibration
+//!
+//! The input offset is trimmed in the factory, but this is only valid for a
+//! supply of 3.3V at 30°C. [`calibrate`](Opamp::calibrate) measures and sets
+//! new trimming values for the normal power mode.
+
+use core::marker::PhantomData;
+
+use crate::comp::{Dac1Out1, Dac1Out2};
+use crate::gpio::{self, Analog};
+use crate::hal::blocking::delay::DelayUs;
+use crate::rcc::{rec, ResetEnable};
+use crate::stm32::OPAMP;
+
+/// Disabled operational amplifier (type state)
+pub struct Disabled;
+/// Operational amplifier in follower mode (type state)
+pub struct Follower;
+/// Operational amplifier in PGA mode (type state)
+pub struct Pga;
+/// Operational amplifier with external feedback (type state)
+pub struct Standalone;
+
+/// OPAMP1 (type state)
+pub struct O1;
+/// OPAMP2 (type state)
+pub struct O2;
+
+/// Non-inverting gain of the PGA
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PgaGain {
+    Gain2 = 0b0000,
+    Gain4 = 0b0001,
+    Gain8 = 0b0010,
+    Gain16 = 0b0011,
+}
+
+/// Non-inverting inputs of an operational amplifier
+pub trait NonInvertingInput<OP> {
+    /// Value of the VP_SEL field
+    const VP_SEL: u8;
+}
+
+/// Inverting inputs of an operational amplifier, for standalone mode
+pub trait InvertingInput<OP> {
+    /// Value of the VM_SEL field
+    const VM_SEL: u8;
+}
+
+/// Output pin of an operational amplifier
+pub trait OutputPin<OP> {}
+
+macro_rules! opamp_pins {
+    ($($OP:ident: [$VINP:ident, $Dac:ident], [$VINM0:ident, $VINM1:ident],
+       $VOUT:ident,)+) => {
+        $(
+            impl NonInvertingInput<$OP> for gpio::$VINP<Analog> {
+                const VP_SEL: u8 = 0b00;
+            }
+            impl NonInvertingInput<$OP> for $Dac {
+                const VP_SEL: u8 = 0b01;
+            }
+            impl InvertingInput<$OP> for gpio::$VINM0<Analog> {
+                const VM_SEL: u8 = 0b00;
+            }
+            impl InvertingInput<$OP> for gpio::$VINM1<Analog> {
+                const VM_SEL: u8 = 0b01;
+            }
+            impl OutputPin<$OP> for gpio::$VOUT<Analog> {}
+        )+
+    };
+}
+
+opamp_pins! {
+    O1: [PB0, Dac1Out1], [PC5, PA7], PC4,
+    O2: [PE9, Dac1Out2], [PE8, PG1], PE7,
+}
+
+/// Inverting input selection for follower mode
+const VM_SEL_FOLLOWER: u8 = 0b11;
+/// Inverting input selection for PGA mode
+const VM_SEL_PGA: u8 = 0b10;
+/// Calibration reference of 10% of VDDA, for the PMOS differential pair
+const CALSEL_10: u8 = 0b01;
+/// Calibration reference of 90% of VDDA, for the NMOS differential pair
+const CALSEL_90: u8 = 0b11;
+
+/// A single operational amplifier
+pub struct Opamp<OP, MODE> {
+    _op: PhantomData<OP>,
+    _mode: PhantomData<MODE>,
+}
+
+impl<OP, MODE> Opamp<OP, MODE> {
+    fn new() -> Self {
+        Opamp {
+            _op: PhantomData,
+            _mode: PhantomData,
+        }
+    }
+}
+
+pub trait OpampExt {
+    /// Enables and resets the operational amplifiers, and splits them into
+    /// OPAMP1 and OPAMP2
+    fn opamp(
+        self,
+        prec: rec::Opamp,
+    ) -> (Opamp<O1, Disabled>, Opamp<O2, Disabled>);
+}
+
+impl OpampExt for OPAMP {
+    fn opamp(
+        self,
+        prec: rec::Opamp,
+    ) -> (Opamp<O1, Disabled>, Opamp<O2, Disabled>) {
+        prec.enable().reset();
+
+        (Opamp::new(), Opamp::new())
+    }
+}
+
+macro_rules! opamp {
+    ($($OP:ident: ($csr:ident, $otr:ident),)+) => {
+        $(
+            impl Opamp<$OP, Disabled> {
+                fn enable<MODE>(vp_sel: u8, vm_sel: u8, gain: PgaGain) -> Opamp<$OP, MODE> {
+                    // unsafe: only this operational amplifier's registers
+                    let csr = unsafe { &(*OPAMP::ptr()).$csr };
+
+                    // VP_SEL is missing from the OPAMP2_CSR definition in
+                    // the PAC, so it is written directly to bits 3:2
+                    csr.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(0b11 << 2)) | ((vp_sel as u32) << 2))
+                    });
+                    csr.modify(|_, w| unsafe {
+                        w.vm_sel().bits(vm_sel).pga_gain().bits(gain as u8)
+                    });
+                    csr.modify(|_, w| w.opaen().set_bit());
+
+                    Opamp::new()
+                }
+
+                /// Enables the operational amplifier as a voltage follower
+                pub fn follower<VINP, VOUT>(
+                    self,
+                    _input: VINP,
+                    _output: VOUT,
+                ) -> Opamp<$OP, Follower>
+                where
+                    VINP: NonInvertingInput<$OP>,
+                    VOUT: OutputPin<$OP>,
+                {
+                    Self::enable(VINP::VP_SEL, VM_SEL_FOLLOWER, PgaGain::Gain2)
+                }
+
+                /// Enables the operational amplifier as a non-inverting
+                /// Programmable Gain Amplifier
+                pub fn pga<VINP, VOUT>(
+                    self,
+                    _input: VINP,
+                    _output: VOUT,
+                    gain: PgaGain,
+                ) -> Opamp<$OP, Pga>
+                where
+                    VINP: NonInvertingInput<$OP>,
+                    VOUT: OutputPin<$OP>,
+                {
+                    Self::enable(VINP::VP_SEL, VM_SEL_PGA, gain)
+                }
+
+                /// Enables the operational amplifier without internal
+                /// feedback. The feedback network is connected externally
+                /// between the output and the inverting input
+                pub fn standalone<VINP, VINM, VOUT>(
+                    self,
+                    _non_inverting: VINP,
+                    _inverting: VINM,
+                    _output: VOUT,
+                ) -> Opamp<$OP, Standalone>
+                where
+                    VINP: NonInvertingInput<$OP>,
+                    VINM: InvertingInput<$OP>,
+                    VOUT: OutputPin<$OP>,
+                {
+                    Self::enable(VINP::VP_SEL, VINM::VM_SEL, PgaGain::Gain2)
+                }
+
+                /// Calibrates the input offset of the operational amplifier
+                /// for the normal power mode, and uses the new trimming
+                /// values from now on. Takes about 10ms.
+                ///
+                /// See RM0433 Rev 7 Section 27.3.5
+                pub fn calibrate<T>(self, delay: &mut T) -> Self
+                where
+                    T: DelayUs<u32>,
+                {
+                    // unsafe: only this operational amplifier's registers
+                    let opamp = unsafe { &*OPAMP::ptr() };
+
+                    opamp.$csr.modify(|_, w| {
+                        w.opahsm()
+                            .clear_bit()
+                            .usertrim()
+                            .set_bit()
+                            .calon()
+                            .set_bit()
+                            .opaen()
+                            .set_bit()
+                    });
+
+                    // NMOS differential pair
+                    opamp.$csr.modify(|_, w| unsafe { w.calsel().bits(CALSEL_90) });
+                    let trimoffsetn = Self::trim(delay, false, |t| {
+                        opamp.$otr.modify(|_, w| unsafe { w.trimoffsetn().bits(t) })
+                    });
+
+                    // PMOS differential pair
+                    opamp.$csr.modify(|_, w| unsafe { w.calsel().bits(CALSEL_10) });
+                    let trimoffsetp = Self::trim(delay, true, |t| {
+                        opamp.$otr.modify(|_, w| unsafe { w.trimoffsetp().bits(t) })
+                    });
+
+                    opamp.$otr.modify(|_, w| unsafe {
+                        w.trimoffsetn()
+                            .bits(trimoffsetn)
+                            .trimoffsetp()
+                            .bits(trimoffsetp)
+                    });
+                    opamp.$csr.modify(|_, w| w.calon().clear_bit().opaen().clear_bit());
+
+                    self
+                }
+
+                /// Binary search for the trimming value where CALOUT
+                /// changes state
+                fn trim<T, F>(delay: &mut T, pmos: bool, set: F) -> u8
+                where
+                    T: DelayUs<u32>,
+                    F: Fn(u8),
+                {
+                    let calout = |delay: &mut T| {
+                        // Wait for the output to settle
+                        delay.delay_us(1_000_u32);
+                        // unsafe: read only
+                        unsafe { (*OPAMP::ptr()).$csr.read().calout().bit_is_set() }
+                    };
+
+                    let mut trim = 16;
+                    let mut step = 8;
+                    while step > 0 {
+                        set(trim);
+                        if calout(delay) != pmos {
+                            trim -= step;
+                        } else {
+                            trim += step;
+                        }
+                        step >>= 1;
+                    }
+
+                    // The search finishes on either side of the transition
+                    set(trim);
+                    if calout(delay) != pmos {
+                        trim -= 1;
+                    }
+
+                    trim
+                }
+            }
+
+            impl Opamp<$OP, Pga> {
+                /// Changes the gain of the PGA
+                pub fn set_gain(&mut self, gain: PgaGain) {
+                    // unsafe: only this operational amplifier's registers
+                    let csr = unsafe { &(*OPAMP::ptr()).$csr };
+
+                    csr.modify(|_, w| unsafe { w.pga_gain().bits(gain as u8) });
+                }
+            }
+
+            impl<MODE> Opamp<$OP, MODE> {
+                /// Disables the operational amplifier
+                pub fn disable(self) -> Opamp<$OP, Disabled> {
+                    // unsafe: only this operational amplifier's registers
+                    let csr = unsafe { &(*OPAMP::ptr()).$csr };
+                    csr.modify(|_, w| w.opaen().clear_bit());
+
+                    Opamp::new()
+                }
+            }
+        )+
+    };
+}
+
+opamp! {
+    O1: (opamp1_csr, opamp1_otr),
+    O2: (opamp2_csr, opamp2_otr),
+}
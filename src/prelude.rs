@@ -15,10 +15,15 @@ pub use crate::flash::FlashExt as _stm32h7xx_hal_flash_FlashExt;
 #[cfg(feature = "fmc")]
 #[cfg_attr(docsrs, doc(cfg(feature = "fmc")))]
 pub use crate::fmc::FmcExt as _stm32h7xx_hal_fmc_FmcExt;
+#[cfg(feature = "fmc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fmc")))]
+pub use crate::fmc::SdramExt as _stm32h7xx_hal_fmc_SdramExt;
 pub use crate::gpio::GpioExt as _stm32h7xx_hal_gpio_GpioExt;
+pub use crate::hall_sensor::HallSensorExt as _stm32_hal_hall_sensor_HallSensorExt;
 pub use crate::i2c::I2cExt as _stm32h7xx_hal_i2c_I2cExt;
 pub use crate::pwm::PwmAdvExt as _stm32_hal_pwm_PwmAdvExt;
 pub use crate::pwm::PwmExt as _stm32_hal_pwm_PwmExt;
+pub use crate::pwm_input::PwmInputExt as _stm32_hal_pwm_input_PwmInputExt;
 pub use crate::pwr::PwrExt as _stm32h7xx_hal_pwr_PwrExt;
 pub use crate::rcc::RccExt as _stm32h7xx_hal_rcc_RccExt;
 pub use crate::rng::RngCore as _stm32h7xx_hal_rng_RngCore;
@@ -139,6 +139,9 @@
 //! This produces a symmetrical PWM waveform, with increasing duty cycle moving both the inactive and active edge equally.
 //! When a component is placed across multiple PWM channels with different duty cycles in center aligned mode, the component will see twice the ripple frequency as the PWM switching frequency.
 //!
+//! In center aligned PWM the [frequency](struct.PwmBuilder.html#method.frequency) is still the PWM cycle frequency, and
+//! duty cycles are still scaled by `get_max_duty`, which is equal to the period rather than the period + 1.
+//!
 //! ## PWM channel polarity
 //!
 //! A PWM channel is active or inactive based on the duty cycle, alignment, etc. However, the actual GPIO signal level that represents active vs inactive is configurable.
@@ -160,6 +163,67 @@
 //!
 //! The deadtime must be 4032 counts of the timer clock or less or the builder will assert/panic. For a 200MHz timer this is 20 microseconds; slower timers can have even longer deadtimes.
 //!
+//! ## Duty cycle updates
+//!
+//! By default, a new duty cycle set with `set_duty` is preloaded, and applies
+//! from the next update event at the start of a PWM cycle. This means that
+//! each cycle has either the old or the new duty cycle. To apply preloaded
+//! duty cycles immediately, call [force_update](struct.Pwm.html#method.force_update),
+//! which also restarts the PWM cycle.
+//!
+//! With preload disabled by [enable_preload](struct.Pwm.html#method.enable_preload),
+//! a new duty cycle applies immediately. If the counter has already passed the
+//! new value, the output is not reset until the following cycle, so the current
+//! cycle has an abnormal pulse.
+//!
+//! ```
+//!   // Apply new duty cycles immediately
+//!   c1.enable_preload(false);
+//!   c1.enable();
+//! ```
+//!
+//! ## Repetition counter and cycle complete interrupt
+//!
+//! The [PwmControl](struct.PwmControl.html) can enable an interrupt at the end of each PWM
+//! cycle with [listen_cycle_complete](struct.PwmControl.html#method.listen_cycle_complete). This is a good place
+//! to update duty cycles, since the new values are loaded at the start of the next cycle.
+//!
+//! The timers with break inputs (`TIM1`, `TIM8`, `TIM15`, `TIM16`, `TIM17`) also have a
+//! repetition counter. After calling [set_repetition_counter](struct.PwmControl.html#method.set_repetition_counter)
+//! with `rep`, the update event and the cycle complete interrupt only occur every `rep + 1` PWM cycles, which
+//! reduces the interrupt overhead of high frequency PWM. Duty cycle updates are then also
+//! only loaded every `rep + 1` cycles.
+//!
+//! ```
+//!   // Interrupt every 4th PWM cycle
+//!   control.set_repetition_counter(3);
+//!   control.listen_cycle_complete();
+//!
+//!   // In the timer update interrupt handler
+//!   control.clear_cycle_complete();
+//!   c1.set_duty(next_duty);
+//! ```
+//!
+//! In center aligned PWM there are two update events per PWM cycle, so the repetition counter counts half cycles.
+//!
+//! ## Clock generation
+//!
+//! A PWM channel can be put in output compare toggle mode with [Pwm::into_toggle](struct.Pwm.html#method.into_toggle),
+//! which returns a [TimerToggle](struct.TimerToggle.html). The output then toggles once per timer period, producing
+//! a square wave with an exact 50% duty cycle that can be used to clock external devices.
+//!
+//! ```
+//!   let c1 = device.TIM3.pwm(pin, 1.MHz(), prec, &clocks);
+//!
+//!   // Generate a 2MHz clock
+//!   let mut clk_out = c1.into_toggle(&clocks);
+//!   clk_out.set_frequency(2.MHz());
+//!   clk_out.enable();
+//! ```
+//!
+//! The [PwmPin](../hal/trait.PwmPin.html) implementation of [TimerToggle](struct.TimerToggle.html) uses the timer
+//! period as the duty cycle, so `set_duty(n)` sets the output to toggle every `n + 1` counts.
+//!
 //! ## Disabled or faulted state
 //!
 //! At initialization, when a PWM channel is disabled, or while a fault is active, the PWM outputs will be in a high impedance state.
@@ -276,6 +340,18 @@ impl<TIM, const CHANNEL: u8, COMP> Pwm<TIM, CHANNEL, COMP> {
     }
 }
 
+/// TimerToggle is a timer channel in output compare toggle mode, created by
+/// calling [into_toggle](struct.Pwm.html#method.into_toggle) on a PWM channel
+///
+/// The output toggles each time the counter reaches zero, producing a square
+/// wave at half the frequency of the timer's update events. Since the period
+/// is shared by all the channels of a timer, changing the frequency also
+/// changes the frequency of the other channels.
+pub struct TimerToggle<TIM, const CHANNEL: u8, COMP> {
+    pwm: Pwm<TIM, CHANNEL, COMP>,
+    clk: Hertz,
+}
+
 /// PwmBuilder is used to configure advanced PWM features
 pub struct PwmBuilder<TIM, PINS, CHANNEL, FAULT, COMP, WIDTH> {
     _markers: PhantomData<(TIM, PINS, CHANNEL, FAULT, COMP)>,
@@ -967,6 +1043,10 @@ pins! {
 
 // Period and prescaler calculator for 32-bit timers
 // Returns (arr, psc)
+//
+// In edge aligned PWM one cycle is ARR+1 counts long. In center aligned PWM the
+// counter counts up to ARR then back down to 0, so one cycle is 2*ARR counts
+// long
 fn calculate_frequency_32bit(
     base_freq: Hertz,
     freq: Hertz,
@@ -979,7 +1059,13 @@ fn calculate_frequency_32bit(
     };
 
     // Round to the nearest period
-    let arr = (base_freq.raw() + (divisor >> 1)) / divisor - 1;
+    let counts = (base_freq.raw() + (divisor >> 1)) / divisor;
+
+    let arr = if let Alignment::Center = alignment {
+        counts
+    } else {
+        counts - 1
+    };
 
     (arr, 0)
 }
@@ -992,16 +1078,28 @@ fn calculate_frequency_16bit(
     freq: Hertz,
     alignment: Alignment,
 ) -> (u32, u16) {
-    let ideal_period =
-        calculate_frequency_32bit(base_freq, freq, alignment).0 + 1;
+    let (ideal_arr, _) = calculate_frequency_32bit(base_freq, freq, alignment);
+
+    // Counts in one cycle (edge aligned) or half a cycle (center aligned) and
+    // the maximum number of counts that fit in a 16-bit ARR
+    let (ideal_period, max_period) = if let Alignment::Center = alignment {
+        (ideal_arr, 0xFFFF)
+    } else {
+        (ideal_arr + 1, 1 << 16)
+    };
 
     // Division factor is (PSC + 1)
-    let prescale = (ideal_period - 1) / (1 << 16);
+    let prescale = (ideal_period - 1) / max_period;
 
     // This will always fit in a 16-bit value because u32::MAX / (1 << 16) fits in a 16 bit
 
     // Round to the nearest period
-    let period = (ideal_period + (prescale >> 1)) / (prescale + 1) - 1;
+    let period = (ideal_period + (prescale >> 1)) / (prescale + 1);
+    let period = if let Alignment::Center = alignment {
+        period
+    } else {
+        period - 1
+    };
 
     // It should be impossible to fail these asserts
     assert!(period <= 0xFFFF);
@@ -1010,6 +1108,31 @@ fn calculate_frequency_16bit(
     (period, prescale as u16)
 }
 
+// Period and prescaler calculator for an output compare channel in toggle
+// mode, which toggles twice in each cycle of `freq`
+// Returns (arr, psc)
+fn calculate_toggle_frequency(
+    base_freq: Hertz,
+    freq: Hertz,
+    bits: u32,
+) -> (u32, u16) {
+    let toggle_freq = freq
+        .raw()
+        .checked_mul(2)
+        .filter(|f| *f > 0 && *f <= base_freq.raw() / 2) // The counter stops if ARR = 0
+        .expect(
+            "Toggle frequency must be at most a quarter of the timer clock",
+        );
+    let toggle_freq = Hertz::from_raw(toggle_freq);
+
+    match bits {
+        16 => {
+            calculate_frequency_16bit(base_freq, toggle_freq, Alignment::Left)
+        }
+        _ => calculate_frequency_32bit(base_freq, toggle_freq, Alignment::Left),
+    }
+}
+
 // Deadtime calculator helper function
 // Returns (BDTR.DTG, CR1.CKD)
 fn calculate_deadtime(base_freq: Hertz, deadtime: NanoSeconds) -> (u8, u8) {
@@ -1310,7 +1433,8 @@ macro_rules! tim_hal {
                     self
                 }
 
-                /// Set the period; PWM count runs from 0 to period, repeating every (period+1) counts
+                /// Set the period; PWM count runs from 0 to period, repeating every (period+1) counts.
+                /// For center aligned PWM the count runs from 0 to period and back, repeating every 2*period counts
                 #[must_use]
                 pub fn period(mut self, period: $typ) -> Self {
                     let prescaler = match self.count {
@@ -1368,6 +1492,42 @@ macro_rules! tim_hal {
                 )?
             }
 
+            impl<FAULT> PwmControl<$TIMX, FAULT> {
+                /// Start listening for the end of each PWM cycle, or each
+                /// `rep + 1` PWM cycles if a repetition counter is set
+                pub fn listen_cycle_complete(&mut self) {
+                    let tim = unsafe { &*<$TIMX>::ptr() };
+
+                    tim.dier.modify(|_, w| w.uie().set_bit());
+                }
+
+                /// Stop listening for the end of each PWM cycle
+                pub fn unlisten_cycle_complete(&mut self) {
+                    let tim = unsafe { &*<$TIMX>::ptr() };
+
+                    tim.dier.modify(|_, w| w.uie().clear_bit());
+                    let _ = tim.dier.read();
+                    let _ = tim.dier.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Returns true if a PWM cycle has completed since the flag
+                /// was last cleared
+                pub fn is_cycle_complete(&self) -> bool {
+                    let tim = unsafe { &*<$TIMX>::ptr() };
+
+                    tim.sr.read().uif().bit_is_set()
+                }
+
+                /// Clears the cycle complete flag
+                pub fn clear_cycle_complete(&mut self) {
+                    let tim = unsafe { &*<$TIMX>::ptr() };
+
+                    tim.sr.modify(|_, w| w.uif().clear_bit());
+                    let _ = tim.sr.read();
+                    let _ = tim.sr.read(); // Delay 2 peripheral clocks
+                }
+            }
+
             // Timers with break/fault, dead time, and complimentary capabilities
             $(
                 impl<PINS, CHANNEL, COMP> PwmBuilder<$TIMX, PINS, CHANNEL, FaultDisabled, COMP, $typ> {
@@ -1387,6 +1547,21 @@ macro_rules! tim_hal {
                     }
                 }
 
+                impl<FAULT> PwmControl<$TIMX, FAULT> {
+                    /// Set the repetition counter, so that the update event and
+                    /// the cycle complete interrupt only occur every `rep + 1`
+                    /// PWM cycles (or half cycles for center aligned PWM)
+                    ///
+                    /// Takes effect from the next update event.
+                    pub fn set_repetition_counter(&mut self, rep: u8) {
+                        let tim = unsafe { &*<$TIMX>::ptr() };
+
+                        // unsafe: the REP field accepts any 8-bit value
+                        #[allow(unused_unsafe)]
+                        unsafe { tim.rcr.write(|w| w.rep().bits(rep)); }
+                    }
+                }
+
                 impl FaultMonitor for PwmControl<$TIMX, FaultEnabled> {
                     fn is_fault_active(&self) -> bool {
                         let tim = unsafe { &*<$TIMX>::ptr() };
@@ -1435,6 +1610,13 @@ tim_hal! {
     pac::TIM17: (tim17, Tim17, u16, 16, BDTR: bdtr, set_bit, tim17_af1, set_bit),
 }
 
+/// Returns true if the CMS field of a timer's CR1 register selects a center
+/// aligned mode. On timers without center aligned modes these bits are
+/// reserved and read as zero
+fn is_center_aligned(cr1: u32) -> bool {
+    cr1 & (0b11 << 5) != 0
+}
+
 pub trait PwmPinEnable {
     fn ccer_enable(&mut self);
     fn ccer_disable(&mut self);
@@ -1461,12 +1643,15 @@ macro_rules! tim_pin_hal {
                 fn enable(&mut self) {
                     let tim = unsafe { &*<$TIMX>::ptr() };
 
-                    tim.$ccmrx_output().modify(|_, w|
+                    // Preload is enabled by default, unless it was disabled
+                    // with `enable_preload` after the channel was created
+                    tim.$ccmrx_output().modify(|r, w| {
+                        let preload = r.$ocxm().is_frozen() || r.$ocxpe().is_enabled();
                         w.$ocxpe()
-                            .enabled() // Enable preload
+                            .bit(preload)
                             .$ocxm()
                             .pwm_mode1() // PWM Mode
-                    );
+                    });
 
                     self.ccer_enable();
                 }
@@ -1474,7 +1659,14 @@ macro_rules! tim_pin_hal {
                 fn get_duty(&self) -> Self::Duty {
                     let tim = unsafe { &*<$TIMX>::ptr() };
 
-                    tim.ccr[$CH as usize].read().ccr().bits()
+                    let duty = tim.ccr[$CH as usize].read().ccr().bits();
+
+                    // See set_duty
+                    if is_center_aligned(tim.cr1.read().bits()) {
+                        core::cmp::min(duty, tim.arr.read().arr().bits())
+                    } else {
+                        duty
+                    }
                 }
 
                 fn get_max_duty(&self) -> Self::Duty {
@@ -1482,6 +1674,13 @@ macro_rules! tim_pin_hal {
 
                     let arr = tim.arr.read().arr().bits();
 
+                    // In center aligned PWM, one PWM cycle is 2*ARR counts long
+                    // and the output is active for 2*CCR counts, so valid PWM
+                    // duty cycles are 0 to ARR
+                    if is_center_aligned(tim.cr1.read().bits()) {
+                        return arr;
+                    }
+
                     // One PWM cycle is ARR+1 counts long
                     // Valid PWM duty cycles are 0 to ARR+1
                     // However, if ARR is 65535 on a 16-bit timer, we can't add 1
@@ -1497,12 +1696,159 @@ macro_rules! tim_pin_hal {
                 fn set_duty(&mut self, duty: Self::Duty) {
                     let tim = unsafe { &*<$TIMX>::ptr() };
 
+                    // In center aligned PWM the output is inactive for one
+                    // count at the center of the cycle when CCR = ARR, so the
+                    // maximum duty cycle is written as ARR+1 to hold the output
+                    // active
+                    let arr = tim.arr.read().arr().bits();
+                    let duty = if is_center_aligned(tim.cr1.read().bits())
+                        && duty >= arr
+                        && arr != Self::Duty::MAX
+                    {
+                        arr + 1
+                    } else {
+                        duty
+                    };
+
                     tim.ccr[$CH as usize].write(|w| w.ccr().bits(duty));
                 }
             }
 
+            impl<COMP> Pwm<$TIMX, $CH, COMP> {
+                /// Enable or disable the preload of the duty cycle. Preload
+                /// is enabled by default
+                ///
+                /// With preload enabled, a new duty cycle is loaded at the
+                /// next update event. Otherwise it applies immediately, which
+                /// can produce a short or long pulse if the counter has
+                /// already passed the new value. See the
+                /// [module documentation](index.html#duty-cycle-updates).
+                pub fn enable_preload(&mut self, enable: bool) {
+                    let tim = unsafe { &*<$TIMX>::ptr() };
+
+                    tim.$ccmrx_output().modify(|_, w|
+                        w.$ocxpe()
+                            .bit(enable)
+                            .$ocxm()
+                            .pwm_mode1() // PWM Mode
+                    );
+                }
+
+                /// Put the channel in output compare toggle mode, for
+                /// generating a clock
+                ///
+                /// The output frequency is initially half the PWM frequency.
+                pub fn into_toggle(self, clocks: &CoreClocks) -> TimerToggle<$TIMX, $CH, COMP> {
+                    let tim = unsafe { &*<$TIMX>::ptr() };
+
+                    let clk = <$TIMX>::get_clk(clocks)
+                        .expect(concat!(stringify!($TIMX), ": Input clock not running!"));
+
+                    tim.$ccmrx_output().modify(|_, w|
+                        w.$ocxpe()
+                            .enabled() // Enable preload
+                            .$ocxm()
+                            .toggle() // Toggle on match
+                    );
+                    tim.ccr[$CH as usize].write(|w| w.ccr().bits(0));
+
+                    TimerToggle { pwm: self, clk }
+                }
+            }
+
+            impl<COMP> TimerToggle<$TIMX, $CH, COMP> {
+                /// Put the channel back in PWM mode
+                pub fn into_pwm(self) -> Pwm<$TIMX, $CH, COMP> {
+                    let tim = unsafe { &*<$TIMX>::ptr() };
+
+                    tim.$ccmrx_output().modify(|_, w|
+                        w.$ocxpe()
+                            .enabled() // Enable preload
+                            .$ocxm()
+                            .pwm_mode1() // PWM Mode
+                    );
+
+                    self.pwm
+                }
+            }
+
         )+
 
+        impl<const C: u8, COMP> Pwm<$TIMX, C, COMP> {
+            /// Generate an update event, which loads the preloaded duty
+            /// cycles of all the channels of this timer immediately
+            ///
+            /// The update event also restarts the counter, so the current
+            /// PWM cycle is cut short.
+            pub fn force_update(&mut self) {
+                let tim = unsafe { &*<$TIMX>::ptr() };
+
+                tim.egr.write(|w| w.ug().set_bit());
+            }
+        }
+
+        impl<const C: u8, COMP> TimerToggle<$TIMX, C, COMP> {
+            /// Set the frequency of the output. The requested frequency will
+            /// be rounded to the nearest achievable frequency, and must be
+            /// at most a quarter of the timer clock.
+            ///
+            /// The new frequency applies immediately, restarting the counter
+            /// of the timer.
+            pub fn set_frequency(&mut self, freq: Hertz) {
+                let tim = unsafe { &*<$TIMX>::ptr() };
+
+                let (period, prescaler) =
+                    calculate_toggle_frequency(self.clk, freq, <$typ>::BITS);
+
+                tim.psc.write(|w| w.psc().bits(prescaler));
+                tim.arr.write(|w| w.arr().bits(period as $typ));
+                tim.egr.write(|w| w.ug().set_bit());
+            }
+
+            /// Returns the frequency of the output
+            pub fn frequency(&self) -> Hertz {
+                let tim = unsafe { &*<$TIMX>::ptr() };
+
+                let psc = tim.psc.read().psc().bits() as u64 + 1;
+                let arr = tim.arr.read().arr().bits() as u64 + 1;
+
+                Hertz::from_raw((self.clk.raw() as u64 / (2 * psc * arr)) as u32)
+            }
+        }
+
+        impl<const C: u8, COMP> hal::PwmPin for TimerToggle<$TIMX, C, COMP>
+            where Pwm<$TIMX, C, COMP>: PwmPinEnable {
+            type Duty = $typ;
+
+            fn disable(&mut self) {
+                self.pwm.ccer_disable();
+            }
+
+            fn enable(&mut self) {
+                self.pwm.ccer_enable();
+            }
+
+            /// Returns the timer period. The output toggles every `duty + 1`
+            /// counts
+            fn get_duty(&self) -> Self::Duty {
+                let tim = unsafe { &*<$TIMX>::ptr() };
+
+                tim.arr.read().arr().bits()
+            }
+
+            fn get_max_duty(&self) -> Self::Duty {
+                Self::Duty::MAX
+            }
+
+            /// Sets the timer period, so that the output toggles every
+            /// `duty + 1` counts. The counter stops if `duty` is zero
+            fn set_duty(&mut self, duty: Self::Duty) {
+                let tim = unsafe { &*<$TIMX>::ptr() };
+
+                tim.arr.write(|w| w.arr().bits(duty));
+            }
+        }
+
         // Enable implementation for ComplementaryImpossible
         impl<const C: u8> PwmPinEnable for Pwm<$TIMX, C, ComplementaryImpossible> {
             fn ccer_enable(&mut self) {
@@ -1519,6 +1865,7 @@ macro_rules! tim_pin_hal {
 
 
         impl<const C: u8, COMP> Pwm<$TIMX, C, COMP> {
+            /// Set the polarity of the channel's output (CCER.CCxP)
             pub fn set_polarity(&mut self, pol: Polarity) {
                 let tim = unsafe { &*<$TIMX>::ptr() };
 
@@ -1815,3 +2162,91 @@ lptim_hal! {
     pac::LPTIM4: (lptim4, Lptim4),
     pac::LPTIM5: (lptim5, Lptim5),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frequency_edge_aligned() {
+        let clk = Hertz::from_raw(200_000_000);
+
+        let (arr, psc) = calculate_frequency_32bit(
+            clk,
+            Hertz::from_raw(10_000),
+            Alignment::Left,
+        );
+        assert_eq!((arr, psc), (19_999, 0));
+
+        let (arr, psc) = calculate_frequency_16bit(
+            clk,
+            Hertz::from_raw(1_000),
+            Alignment::Left,
+        );
+        assert_eq!((arr + 1) * (psc as u32 + 1), 200_000);
+        assert!(arr <= 0xFFFF);
+    }
+
+    #[test]
+    fn frequency_toggle() {
+        let clk = Hertz::from_raw(200_000_000);
+
+        // The output toggles once per period
+        let (arr, psc) =
+            calculate_toggle_frequency(clk, Hertz::from_raw(1_000_000), 32);
+        assert_eq!((arr, psc), (99, 0));
+
+        let (arr, psc) =
+            calculate_toggle_frequency(clk, Hertz::from_raw(100), 16);
+        assert_eq!((arr + 1) * (psc as u32 + 1), 1_000_000);
+        assert!(arr <= 0xFFFF);
+
+        let (arr, psc) =
+            calculate_toggle_frequency(clk, Hertz::from_raw(50_000_000), 16);
+        assert_eq!((arr, psc), (1, 0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn frequency_toggle_too_fast() {
+        let clk = Hertz::from_raw(200_000_000);
+
+        calculate_toggle_frequency(clk, Hertz::from_raw(50_000_001), 16);
+    }
+
+    #[test]
+    fn frequency_center_aligned() {
+        let clk = Hertz::from_raw(200_000_000);
+
+        // One cycle is 2 * ARR counts
+        let (arr, psc) = calculate_frequency_32bit(
+            clk,
+            Hertz::from_raw(10_000),
+            Alignment::Center,
+        );
+        assert_eq!((arr, psc), (10_000, 0));
+
+        let (arr, psc) = calculate_frequency_16bit(
+            clk,
+            Hertz::from_raw(1_000),
+            Alignment::Center,
+        );
+        assert_eq!(2 * arr * (psc as u32 + 1), 200_000);
+        assert!(arr <= 0xFFFF);
+
+        // The longest half cycle that fits in ARR needs no prescaler
+        let (arr, psc) = calculate_frequency_16bit(
+            Hertz::from_raw(131_070_000),
+            Hertz::from_raw(1_000),
+            Alignment::Center,
+        );
+        assert_eq!((arr, psc), (0xFFFF, 0));
+    }
+
+    #[test]
+    fn center_aligned_cms() {
+        assert!(!is_center_aligned(0b0000_0001));
+        assert!(is_center_aligned(0b0010_0001));
+        assert!(is_center_aligned(0b0110_0001));
+    }
+}
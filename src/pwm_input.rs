@@ -0,0 +1,337 @@
+//! # PWM Input
+//!
+//! Measures the frequency and duty cycle of a signal on a single timer
+//! input, using two capture channels of the timer.
+//!
+//! The input pin can be either the channel 1 or the channel 2 pin of the
+//! timer. The channel of the pin captures the counter on each rising edge,
+//! which is the period of the signal, and the other channel of the pair
+//! captures the counter on each falling edge, which is the pulse width. The
+//! timer is in slave reset mode, so the counter is reset to zero on each
+//! rising edge.
+//!
+//! ```
+//! let pin = gpioa.pa0.into_alternate();
+//!
+//! let mut input = dp.TIM2.pwm_input(
+//!     pin,
+//!     10.Hz(),
+//!     ccdr.peripheral.TIM2,
+//!     &ccdr.clocks,
+//! );
+//!
+//! if let Some((frequency, duty)) = input.read() {
+//!     // ...
+//! }
+//! ```
+//!
+//! The minimum frequency sets the timer prescaler, so that one period of a
+//! signal at the minimum frequency fits in the counter. Lower frequencies
+//! overflow the counter and are not measured.
+use crate::gpio::{self, Alternate};
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::{TIM1, TIM2, TIM3, TIM4, TIM5, TIM8};
+use crate::time::Hertz;
+use crate::timer::{smcr_slave_bits, GetClk, SlaveMode};
+
+/// Pins that can be the input of a [`PwmInput`] on the timer `TIM`
+pub trait Pin<TIM> {
+    /// Timer channel of the pin, either 1 or 2
+    const CHANNEL: u8;
+}
+
+macro_rules! pins {
+    ($($TIMX:ty:
+       CH1: [$($( #[ $pmeta1:meta ] )* $CH1:ty),*]
+       CH2: [$($( #[ $pmeta2:meta ] )* $CH2:ty),*])+) => {
+        $(
+            $(
+                $( #[ $pmeta1 ] )*
+                impl Pin<$TIMX> for $CH1 {
+                    const CHANNEL: u8 = 1;
+                }
+            )*
+            $(
+                $( #[ $pmeta2 ] )*
+                impl Pin<$TIMX> for $CH2 {
+                    const CHANNEL: u8 = 2;
+                }
+            )*
+        )+
+    }
+}
+
+pins! {
+    TIM1:
+        CH1: [
+            gpio::PA8<Alternate<1>>,
+            gpio::PE9<Alternate<1>>,
+            #[cfg(not(feature = "stm32h7b0"))]
+            gpio::PK1<Alternate<1>>
+        ]
+        CH2: [
+            gpio::PA9<Alternate<1>>,
+            gpio::PE11<Alternate<1>>,
+            #[cfg(not(feature = "stm32h7b0"))]
+            gpio::PJ11<Alternate<1>>
+        ]
+
+    TIM2:
+        CH1: [
+            gpio::PA0<Alternate<1>>,
+            gpio::PA5<Alternate<1>>,
+            gpio::PA15<Alternate<1>>
+        ]
+        CH2: [
+            gpio::PA1<Alternate<1>>,
+            gpio::PB3<Alternate<1>>
+        ]
+
+    TIM3:
+        CH1: [
+            gpio::PA6<Alternate<2>>,
+            gpio::PB4<Alternate<2>>,
+            gpio::PC6<Alternate<2>>
+        ]
+        CH2: [
+            gpio::PA7<Alternate<2>>,
+            gpio::PB5<Alternate<2>>,
+            gpio::PC7<Alternate<2>>
+        ]
+
+    TIM4:
+        CH1: [
+            gpio::PB6<Alternate<2>>,
+            gpio::PD12<Alternate<2>>
+        ]
+        CH2: [
+            gpio::PB7<Alternate<2>>,
+            gpio::PD13<Alternate<2>>
+        ]
+
+    TIM5:
+        CH1: [
+            gpio::PA0<Alternate<2>>,
+            gpio::PH10<Alternate<2>>
+        ]
+        CH2: [
+            gpio::PA1<Alternate<2>>,
+            gpio::PH11<Alternate<2>>
+        ]
+
+    TIM8:
+        CH1: [
+            gpio::PC6<Alternate<3>>,
+            #[cfg(not(feature = "rm0468"))]
+            gpio::PI5<Alternate<3>>,
+            #[cfg(not(feature = "stm32h7b0"))]
+            gpio::PJ8<Alternate<3>>
+        ]
+        CH2: [
+            gpio::PC7<Alternate<3>>,
+            #[cfg(not(feature = "rm0468"))]
+            gpio::PI6<Alternate<3>>,
+            #[cfg(not(any(feature = "stm32h7b0", feature = "rm0468")))]
+            gpio::PJ6<Alternate<3>>,
+            #[cfg(not(feature = "stm32h7b0"))]
+            gpio::PJ10<Alternate<3>>
+        ]
+}
+
+/// Returns the prescaler value (PSC) so that one period at `min_freq`
+/// fits in a counter with maximum value `max_count`
+pub(crate) fn calculate_prescaler(
+    clk: u32,
+    min_freq: u32,
+    max_count: u32,
+) -> u16 {
+    assert!(min_freq > 0, "The minimum frequency must not be zero");
+
+    // Number of counter ticks in one period at the minimum frequency
+    let ticks = u64::from(clk) / u64::from(min_freq);
+    // The timer clock is at most a few hundred MHz, so this always fits
+    // in the 16-bit prescaler
+    let divider = ticks / u64::from(max_count) + 1;
+
+    (divider - 1) as u16
+}
+
+/// Returns the frequency and duty cycle from the `period` and `pulse` width
+/// in counter ticks at `tick_freq`
+fn frequency_and_duty(
+    tick_freq: u32,
+    period: u32,
+    pulse: u32,
+) -> Option<(Hertz, f32)> {
+    if period == 0 {
+        return None;
+    }
+
+    let frequency = Hertz::from_raw(tick_freq / period);
+    let duty = core::cmp::min(pulse, period) as f32 / period as f32;
+    Some((frequency, duty))
+}
+
+/// Timer in PWM input mode, measuring the frequency and duty cycle of the
+/// signal on one input pin
+pub struct PwmInput<TIM> {
+    tim: TIM,
+    tick_freq: Hertz,
+    channel: u8,
+}
+
+pub trait PwmInputExt: Sized {
+    type Rec: ResetEnable;
+
+    /// Configures the timer to measure the signal on `pin`. Signals with a
+    /// frequency lower than `min_frequency` are not measured
+    ///
+    /// Panics if `min_frequency` is zero.
+    fn pwm_input<PIN>(
+        self,
+        pin: PIN,
+        min_frequency: Hertz,
+        prec: Self::Rec,
+        clocks: &CoreClocks,
+    ) -> PwmInput<Self>
+    where
+        PIN: Pin<Self>;
+}
+
+macro_rules! tim_hal {
+    ($($TIM:ident: ($Rec:ident, $max:expr),)+) => {
+        $(
+            impl PwmInputExt for $TIM {
+                type Rec = rec::$Rec;
+
+                fn pwm_input<PIN>(
+                    self,
+                    _pin: PIN,
+                    min_frequency: Hertz,
+                    prec: rec::$Rec,
+                    clocks: &CoreClocks,
+                ) -> PwmInput<$TIM>
+                where
+                    PIN: Pin<$TIM>,
+                {
+                    // enable and reset peripheral to a clean slate
+                    let _ = prec.enable().reset(); // drop
+
+                    let clk = $TIM::get_clk(clocks)
+                        .expect(concat!(stringify!($TIM), ": Input clock not running!"))
+                        .raw();
+                    let psc = calculate_prescaler(clk, min_frequency.raw(), $max);
+
+                    // The channel of the pin captures the period on the
+                    // rising edge of its own input, and the other channel
+                    // captures the pulse width on the falling edge of the
+                    // same input. The rising edge also resets the counter
+                    let (ccmr1, ccer, trigger) = match PIN::CHANNEL {
+                        // CC1S = TI1, CC2S = TI1, CC2P = falling, TS = TI1FP1
+                        1 => (0b10 << 8 | 0b01, 1 << 5, 0b00101),
+                        // CC1S = TI2, CC2S = TI2, CC1P = falling, TS = TI2FP2
+                        _ => (0b01 << 8 | 0b10, 1 << 1, 0b00110),
+                    };
+
+                    // unsafe: valid capture selections
+                    self.ccmr1_input().write(|w| unsafe { w.bits(ccmr1) });
+                    // unsafe: CC1E, CC2E and the polarity of the pulse
+                    // channel
+                    self.ccer.write(|w| unsafe { w.bits(ccer | 1 << 4 | 1) });
+                    // unsafe: only the SMS and TS fields are modified
+                    self.smcr.modify(|r, w| unsafe {
+                        w.bits(smcr_slave_bits(r.bits(), trigger, SlaveMode::Reset))
+                    });
+
+                    self.psc.write(|w| w.psc().bits(psc));
+                    #[allow(unused_unsafe)] // method is safe for some timers
+                    self.arr.write(|w| unsafe { w.bits($max) });
+
+                    // Only counter overflow sets the update flag, not the
+                    // reset from the trigger input
+                    self.cr1.write(|w| w.urs().set_bit());
+                    self.egr.write(|w| w.ug().set_bit());
+                    self.cr1.modify(|_, w| w.cen().set_bit());
+
+                    PwmInput {
+                        tim: self,
+                        tick_freq: Hertz::from_raw(clk / (u32::from(psc) + 1)),
+                        channel: PIN::CHANNEL,
+                    }
+                }
+            }
+
+            impl PwmInput<$TIM> {
+                /// Returns the frequency and the duty cycle, between 0.0
+                /// and 1.0, of the last complete period of the signal
+                ///
+                /// Returns `None` if no period has been measured yet, or if
+                /// the counter overflowed since the last call. The counter
+                /// overflows when the signal stops, or when its frequency is
+                /// lower than the minimum frequency.
+                pub fn read(&mut self) -> Option<(Hertz, f32)> {
+                    let (period, pulse) = match self.channel {
+                        1 => (0, 1),
+                        _ => (1, 0),
+                    };
+                    let overflow = self.tim.sr.read().uif().bit_is_set();
+
+                    // Reading the capture registers clears the capture flags
+                    let period = self.tim.ccr[period].read().bits();
+                    let pulse = self.tim.ccr[pulse].read().bits();
+
+                    if overflow {
+                        self.tim.sr.modify(|_, w| w.uif().clear_bit());
+                        return None;
+                    }
+
+                    frequency_and_duty(self.tick_freq.raw(), period, pulse)
+                }
+
+                /// Returns the frequency of the counter
+                pub fn tick_frequency(&self) -> Hertz {
+                    self.tick_freq
+                }
+
+                /// Stops the timer and releases the TIM peripheral
+                pub fn release(self) -> ($TIM, rec::$Rec) {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    (self.tim, rec::$Rec { _marker: core::marker::PhantomData })
+                }
+            }
+        )+
+    }
+}
+
+tim_hal! {
+    TIM1: (Tim1, 0xFFFF),
+    TIM8: (Tim8, 0xFFFF),
+    TIM2: (Tim2, u32::MAX),
+    TIM3: (Tim3, 0xFFFF),
+    TIM4: (Tim4, 0xFFFF),
+    TIM5: (Tim5, u32::MAX),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prescaler() {
+        // 200MHz at 1kHz is 200_000 ticks
+        assert_eq!(calculate_prescaler(200_000_000, 1_000, 0xFFFF), 3);
+        assert_eq!(calculate_prescaler(200_000_000, 1, u32::MAX), 0);
+        assert_eq!(calculate_prescaler(200_000_000, 4_000, 0xFFFF), 0);
+    }
+
+    #[test]
+    fn duty() {
+        let (freq, duty) =
+            frequency_and_duty(1_000_000, 20_000, 1_500).unwrap();
+        assert_eq!(freq.raw(), 50);
+        assert_eq!(duty, 0.075);
+
+        assert!(frequency_and_duty(1_000_000, 0, 0).is_none());
+        assert_eq!(frequency_and_duty(1_000_000, 100, 200).unwrap().1, 1.0);
+    }
+}
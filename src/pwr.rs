@@ -69,6 +69,25 @@
 //! [^rm0468ecc]: These parts allow up to 550MHz by setting an additional bit in
 //! User Register 18, but this is not supported through the HAL.
 //!
+//! # VBAT
+//!
+//! A rechargeable battery or supercapacitor on VBAT, which maintains the RTC
+//! and the backup domain when VDD is removed, can be charged from VDD
+//! through an internal resistor. The level of VBAT can be checked with the
+//! VBAT monitor. Both are controlled through the [`Vbat`] handle, which is
+//! taken from the frozen [`PowerConfiguration`].
+//!
+//! ```rust
+//!     let mut pwrcfg = dp.PWR.constrain().freeze();
+//!     let mut vbat = pwrcfg.vbat().unwrap();
+//!     vbat.enable_charging(VbatChargingResistor::R5k);
+//!     vbat.enable_monitoring();
+//!
+//!     if vbat.level() == VbatStatus::Low {
+//!         // ...
+//!     }
+//! ```
+//!
 
 use crate::rcc::backup::BackupREC;
 use crate::stm32::PWR;
@@ -133,6 +152,28 @@ pub enum VoltageScale {
     Scale3,
 }
 
+/// VBAT charging resistor
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VbatChargingResistor {
+    /// Charge VBAT through a 5kΩ resistor
+    R5k,
+    /// Charge VBAT through a 1.5kΩ resistor
+    R1k5,
+}
+
+/// VBAT level, compared with the thresholds of the VBAT monitor
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum VbatStatus {
+    /// VBAT is below the low threshold
+    Low,
+    /// VBAT is between the low and high thresholds
+    Normal,
+    /// VBAT is above the high threshold
+    High,
+}
+
 /// Power Configuration
 ///
 /// Generated when the PWR peripheral is frozen. The existence of this
@@ -141,6 +182,7 @@ pub enum VoltageScale {
 pub struct PowerConfiguration {
     pub(crate) vos: VoltageScale,
     pub(crate) backup: Option<BackupREC>,
+    pub(crate) vbat: Option<Vbat>,
 }
 
 impl PowerConfiguration {
@@ -152,6 +194,21 @@ impl PowerConfiguration {
     pub fn backup(&mut self) -> Option<BackupREC> {
         self.backup.take()
     }
+
+    /// Takes the [`Vbat`] handle, which controls VBAT charging and
+    /// monitoring. Returns `None` if it has already been taken
+    pub fn vbat(&mut self) -> Option<Vbat> {
+        self.vbat.take()
+    }
+}
+
+/// VBAT charging and monitoring
+///
+/// Taken from the [`PowerConfiguration`] with
+/// [`vbat`](PowerConfiguration::vbat), so that it remains available after
+/// the PWR peripheral is frozen.
+pub struct Vbat {
+    _private: (),
 }
 
 /// SMPS Supply Configuration - Dual Core parts
@@ -365,6 +422,62 @@ impl Pwr {
     }
 }
 
+impl Vbat {
+    fn rb(&self) -> &crate::stm32::pwr::RegisterBlock {
+        // unsafe: only the VBAT bits of CR2 and CR3 are accessed through
+        // this handle, and there is only one handle
+        unsafe { &*PWR::ptr() }
+    }
+
+    /// Charges the battery on VBAT from VDD through the internal
+    /// `resistor`
+    ///
+    /// Charging only takes place when VDD is present. Only enable charging
+    /// for a rechargeable battery or supercapacitor.
+    pub fn enable_charging(&mut self, resistor: VbatChargingResistor) {
+        self.rb().cr3.modify(|_, w| {
+            w.vbrs()
+                .bit(resistor == VbatChargingResistor::R1k5)
+                .vbe()
+                .set_bit()
+        });
+    }
+
+    /// Stops charging the battery on VBAT
+    pub fn disable_charging(&mut self) {
+        self.rb().cr3.modify(|_, w| w.vbe().clear_bit());
+    }
+
+    /// Enables the VBAT and temperature monitoring, which is required by
+    /// [`level`](Self::level)
+    pub fn enable_monitoring(&mut self) {
+        self.rb().cr2.modify(|_, w| w.monen().set_bit());
+    }
+
+    /// Disables the VBAT and temperature monitoring
+    pub fn disable_monitoring(&mut self) {
+        self.rb().cr2.modify(|_, w| w.monen().clear_bit());
+    }
+
+    /// Returns the level of VBAT compared with the thresholds of the VBAT
+    /// monitor
+    ///
+    /// The monitoring must be enabled with
+    /// [`enable_monitoring`](Self::enable_monitoring).
+    pub fn level(&self) -> VbatStatus {
+        // VBATL and VBATH are at bits 20 and 21 on all parts, but are
+        // missing from some of the PACs
+        let cr2 = self.rb().cr2.read().bits();
+        if cr2 & (1 << 20) != 0 {
+            VbatStatus::Low
+        } else if cr2 & (1 << 21) != 0 {
+            VbatStatus::High
+        } else {
+            VbatStatus::Normal
+        }
+    }
+}
+
 /// Builder methods
 impl Pwr {
     #[cfg(feature = "smps")]
@@ -553,6 +666,7 @@ impl Pwr {
         PowerConfiguration {
             vos,
             backup: Some(backup),
+            vbat: Some(Vbat { _private: () }),
         }
     }
 }
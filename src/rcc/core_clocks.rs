@@ -1,5 +1,6 @@
 //! Structure to represent frozen core clock frequencies
 
+use super::PeripheralClocks;
 use crate::time::Hertz;
 
 /// Frozen core clock frequencies
@@ -7,6 +8,7 @@ use crate::time::Hertz;
 /// The existence of this value indicates that the core clock
 /// configuration can no longer be changed
 #[derive(Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct CoreClocks {
     pub(super) hclk: Hertz,
     pub(super) pclk1: Hertz,
@@ -38,6 +40,7 @@ pub struct CoreClocks {
     pub(super) timy_ker_ck: Hertz,
     pub(super) sys_ck: Hertz,
     pub(super) c_ck: Hertz,
+    pub(super) peripheral_clocks: PeripheralClocks,
 }
 
 /// Getters for pclk and ppre
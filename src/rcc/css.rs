@@ -0,0 +1,56 @@
+//! Clock Security System (CSS) on HSE
+//!
+//! When the CSS is enabled and the HSE oscillator fails, the hardware
+//! switches the system clock to HSI, sets the `HSECSSF` flag and raises a
+//! Non-Maskable Interrupt (NMI).
+//!
+//! The CSS is enabled with [`Rcc::enable_css`](super::Rcc::enable_css). The
+//! NMI vector is part of the static vector table, so the application must
+//! define the NMI exception handler and call [`css_nmi`] from it.
+//!
+//! The handler executes at NMI priority. It cannot be pre-empted, and must
+//! not call any functions that require a clock derived from the HSE.
+
+use core::mem;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+use crate::stm32::RCC;
+
+/// Handler called by [`css_nmi`]. Null if the CSS is not enabled
+static CSS_HANDLER: AtomicPtr<()> = AtomicPtr::new(core::ptr::null_mut());
+
+pub(super) fn set_css_handler(handler: fn()) {
+    CSS_HANDLER.store(handler as *mut (), Ordering::Release);
+}
+
+/// Returns `true` if the CSS has detected a failure of the HSE
+pub fn css_triggered() -> bool {
+    // unsafe: read only
+    unsafe { (*RCC::ptr()).cifr.read().hsecssf().bit_is_set() }
+}
+
+/// Handles a CSS failure. To be called from the NMI exception handler
+///
+/// If the CSS has detected a failure of the HSE, clears the `HSECSSF` flag
+/// and calls the handler passed to
+/// [`Rcc::enable_css`](super::Rcc::enable_css). Otherwise does nothing.
+pub fn css_nmi() {
+    if !css_triggered() {
+        return;
+    }
+
+    // unsafe: write-1-to-clear register
+    unsafe { (*RCC::ptr()).cicr.write(|w| w.hsecssc().set_bit()) };
+
+    let handler = CSS_HANDLER.load(Ordering::Acquire);
+    if !handler.is_null() {
+        // unsafe: only ever set from a `fn()` in `set_css_handler`
+        let handler: fn() = unsafe { mem::transmute(handler) };
+        handler();
+    }
+}
+
+/// Default CSS handler. Triggers a system reset
+pub fn css_reset() {
+    cortex_m::peripheral::SCB::sys_reset()
+}
@@ -1,6 +1,18 @@
 //! Micro-Controller Out (MCO) pins
+//!
+//! The source and frequency of each MCO signal is set using the builder
+//! methods on [`Rcc`], such as `mco1_from_hsi48`. Once the clocks are frozen,
+//! the output can be routed to its pin with [`Mco1Pin`] or [`Mco2Pin`].
+//!
+//! ```rust
+//! let ccdr = rcc.mco1_from_hsi48(24.MHz()).freeze(pwrcfg, &dp.SYSCFG);
+//!
+//! let mco1 = Mco1Pin::new(gpioa.pa8, &ccdr.clocks);
+//! assert_eq!(mco1.frequency().raw(), 24_000_000);
+//! ```
 
-use super::Rcc;
+use super::{CoreClocks, Rcc};
+use crate::gpio::{Alternate, PinMode, Speed, PA8, PC9};
 use crate::time::Hertz;
 
 pub use crate::stm32::rcc::cfgr::MCO1_A as MCO1;
@@ -199,3 +211,52 @@ mco2_setters! {
     mco2_from_csi: Csi "CSI",
     mco2_from_lsi: Lsi "the LSI"
 }
+
+macro_rules! mco_pins {
+    ($($Pin:ident: $PXi:ident, $mco_ck:ident, $name:expr;)+) => {
+        $(
+            #[doc = concat!($name, " output pin")]
+            #[derive(Debug)]
+            pub struct $Pin {
+                pin: $PXi<Alternate<0>>,
+                frequency: Hertz,
+            }
+
+            impl $Pin {
+                #[doc = concat!("Configures `pin` as the ", $name, " output")]
+                ///
+                /// The pin is set to its alternate function and to very high
+                /// speed.
+                ///
+                /// # Panics
+                ///
+                #[doc = concat!("Panics if ", $name, " was not enabled when the clocks were frozen")]
+                pub fn new<MODE: PinMode>(
+                    pin: $PXi<MODE>,
+                    clocks: &CoreClocks,
+                ) -> Self {
+                    let frequency = clocks.$mco_ck().unwrap_or_else(|| {
+                        panic!("{} is not enabled in the RCC", $name)
+                    });
+                    let pin = pin.into_alternate().speed(Speed::VeryHigh);
+
+                    Self { pin, frequency }
+                }
+
+                #[doc = concat!("Returns the frequency of the ", $name, " output")]
+                pub fn frequency(&self) -> Hertz {
+                    self.frequency
+                }
+
+                /// Releases the pin
+                pub fn free(self) -> $PXi<Alternate<0>> {
+                    self.pin
+                }
+            }
+        )+
+    };
+}
+mco_pins! {
+    Mco1Pin: PA8, mco1_ck, "MCO1";
+    Mco2Pin: PC9, mco2_ck, "MCO2";
+}
@@ -164,22 +164,29 @@ use crate::stm32::rcc::d1ccipr::CKPERSEL_A as CKPERSEL;
 
 pub mod backup;
 mod core_clocks;
+mod css;
+mod peripheral_clocks;
 mod pll;
 pub mod rec;
 mod reset_reason;
 
 pub use core_clocks::CoreClocks;
+pub use css::{css_nmi, css_reset, css_triggered};
+pub(crate) use peripheral_clocks::lse_ck;
+pub use peripheral_clocks::PeripheralClocks;
 pub use pll::{PllConfig, PllConfigStrategy};
 pub use rec::{LowPowerMode, PeripheralREC, ResetEnable};
 pub use reset_reason::ResetReason;
 
 mod mco;
 use mco::{MCO1Config, MCO2Config, MCO1, MCO2};
+pub use mco::{Mco1Pin, Mco2Pin};
 
 /// Configuration of the core clocks
 pub struct Config {
     hse: Option<u32>,
     bypass_hse: bool,
+    css_handler: Option<fn()>,
     sys_ck: Option<u32>,
     per_ck: Option<u32>,
     rcc_hclk: Option<u32>,
@@ -207,6 +214,7 @@ impl RccExt for RCC {
             config: Config {
                 hse: None,
                 bypass_hse: false,
+                css_handler: None,
                 sys_ck: None,
                 per_ck: None,
                 rcc_hclk: None,
@@ -321,6 +329,33 @@ macro_rules! pll_strategy_setter {
     }
 }
 
+/// Setter definition for pll 1 - 3 fractional-N divider
+macro_rules! pll_fracn_setter {
+    ($($pll:ident: $name:ident,)+) => {
+        $(
+            /// Set the fractional part of the PLL feedback divider
+            /// (FRACN), overriding the value chosen by the PLL divider
+            /// strategy
+            ///
+            /// The VCO frequency is `ref_ck * (DIVN + FRACN / 8192)`, so
+            /// all outputs of the PLL are increased by up to one reference
+            /// clock period. The resulting frequencies are returned in
+            /// `ccdr.clocks` as usual.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `fracn` is greater than 8191
+            #[must_use]
+            pub fn $name(mut self, fracn: u16) -> Self
+            {
+                assert!(fracn <= 8191, "FRACN must be less than 8192");
+                self.config.$pll.fracn = Some(fracn);
+                self
+            }
+        )+
+    }
+}
+
 impl Rcc {
     /// Uses HSE (external oscillator) instead of HSI (internal RC
     /// oscillator) as the clock source. Will result in a hang if an
@@ -339,6 +374,34 @@ impl Rcc {
         self
     }
 
+    /// Enable the Clock Security System (CSS) on HSE. Requires `use_hse`.
+    ///
+    /// The CSS is enabled by `freeze`. If the HSE then fails, the hardware
+    /// switches the system clock to HSI and raises a Non-Maskable Interrupt
+    /// (NMI). The NMI exception handler must call [`css_nmi`], which in turn
+    /// calls `handler`. Use [`css_reset`] to trigger a system reset.
+    ///
+    /// The handler executes at NMI priority, and must not call any
+    /// functions that require a clock derived from the HSE. Once enabled,
+    /// the CSS can only be disabled by a reset.
+    ///
+    /// ```rust
+    /// let ccdr = rcc
+    ///     .use_hse(25.MHz())
+    ///     .enable_css(rcc::css_reset)
+    ///     .freeze(pwrcfg, &dp.SYSCFG);
+    ///
+    /// #[exception]
+    /// fn NonMaskableInt() {
+    ///     rcc::css_nmi();
+    /// }
+    /// ```
+    #[must_use]
+    pub fn enable_css(mut self, handler: fn()) -> Self {
+        self.config.css_handler = Some(handler);
+        self
+    }
+
     /// Set input frequency to the SCGU
     #[must_use]
     pub fn sys_ck(mut self, freq: Hertz) -> Self {
@@ -400,6 +463,12 @@ impl Rcc {
         pll2: pll2_strategy,
         pll3: pll3_strategy,
     }
+
+    pll_fracn_setter! {
+        pll1: pll1_fracn,
+        pll2: pll2_fracn,
+        pll3: pll3_fracn,
+    }
 }
 
 /// Divider calculator for pclk 1 - 4
@@ -853,9 +922,21 @@ impl Rcc {
                 });
                 while rcc.cr.read().hserdy().is_not_ready() {}
 
+                // Clock Security System
+                if let Some(handler) = self.config.css_handler {
+                    css::set_css_handler(handler);
+                    rcc.cr.modify(|_, w| w.hsecsson().on());
+                }
+
                 Some(Hertz::from_raw(hse))
             }
-            None => None,
+            None => {
+                assert!(
+                    self.config.css_handler.is_none(),
+                    "The Clock Security System requires HSE"
+                );
+                None
+            }
         };
 
         // PLL
@@ -1116,40 +1197,44 @@ impl Rcc {
             );
         }
 
+        let mut clocks = CoreClocks {
+            hclk: Hertz::from_raw(rcc_hclk),
+            pclk1: Hertz::from_raw(rcc_pclk1),
+            pclk2: Hertz::from_raw(rcc_pclk2),
+            pclk3: Hertz::from_raw(rcc_pclk3),
+            pclk4: Hertz::from_raw(rcc_pclk4),
+            ppre1,
+            ppre2,
+            ppre3,
+            ppre4,
+            csi_ck: Some(Hertz::from_raw(csi)),
+            hsi_ck: Some(Hertz::from_raw(hsi)),
+            hsi48_ck: Some(Hertz::from_raw(hsi48)),
+            lsi_ck: Some(Hertz::from_raw(lsi)),
+            per_ck: Some(Hertz::from_raw(per_ck)),
+            hse_ck,
+            mco1_ck,
+            mco2_ck,
+            pll1_p_ck,
+            pll1_q_ck,
+            pll1_r_ck,
+            pll2_p_ck,
+            pll2_q_ck,
+            pll2_r_ck,
+            pll3_p_ck,
+            pll3_q_ck,
+            pll3_r_ck,
+            timx_ker_ck: Hertz::from_raw(rcc_timx_ker_ck),
+            timy_ker_ck: Hertz::from_raw(rcc_timy_ker_ck),
+            sys_ck,
+            c_ck: Hertz::from_raw(sys_d1cpre_ck),
+            peripheral_clocks: PeripheralClocks::default(),
+        };
+        clocks.peripheral_clocks = clocks.read_peripheral_clocks();
+
         // Return frozen clock configuration
         Ccdr {
-            clocks: CoreClocks {
-                hclk: Hertz::from_raw(rcc_hclk),
-                pclk1: Hertz::from_raw(rcc_pclk1),
-                pclk2: Hertz::from_raw(rcc_pclk2),
-                pclk3: Hertz::from_raw(rcc_pclk3),
-                pclk4: Hertz::from_raw(rcc_pclk4),
-                ppre1,
-                ppre2,
-                ppre3,
-                ppre4,
-                csi_ck: Some(Hertz::from_raw(csi)),
-                hsi_ck: Some(Hertz::from_raw(hsi)),
-                hsi48_ck: Some(Hertz::from_raw(hsi48)),
-                lsi_ck: Some(Hertz::from_raw(lsi)),
-                per_ck: Some(Hertz::from_raw(per_ck)),
-                hse_ck,
-                mco1_ck,
-                mco2_ck,
-                pll1_p_ck,
-                pll1_q_ck,
-                pll1_r_ck,
-                pll2_p_ck,
-                pll2_q_ck,
-                pll2_r_ck,
-                pll3_p_ck,
-                pll3_q_ck,
-                pll3_r_ck,
-                timx_ker_ck: Hertz::from_raw(rcc_timx_ker_ck),
-                timy_ker_ck: Hertz::from_raw(rcc_timy_ker_ck),
-                sys_ck,
-                c_ck: Hertz::from_raw(sys_d1cpre_ck),
-            },
+            clocks,
             peripheral: unsafe {
                 // unsafe: we consume self which was a singleton, hence
                 // we can safely create a singleton here
@@ -0,0 +1,376 @@
+//! Kernel clock frequencies of the peripherals
+
+#[cfg(feature = "rm0468")]
+use super::rec::I2c1235ClkSel as I2c123ClkSel;
+#[cfg(not(feature = "rm0468"))]
+use super::rec::I2c123ClkSel;
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
+use super::rec::OctospiClkSel;
+#[cfg(not(feature = "rm0455"))]
+use super::rec::SpdifClkSel as SpdifrxClkSel;
+#[cfg(feature = "rm0455")]
+use super::rec::SpdifrxClkSel;
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
+use super::rec::Usart16910ClkSel as Usart16ClkSel;
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
+use super::rec::Usart16ClkSel;
+use super::rec::{
+    AdcClkSel, CecClkSel, Dfsdm1ClkSel, FdcanClkSel, FmcClkSel, I2c4ClkSel,
+    Lptim1ClkSel, Lptim2ClkSel, RngClkSel, Sai1ClkSel, SdmmcClkSel,
+    Spi123ClkSel, Spi45ClkSel, Spi6ClkSel, Usart234578ClkSel, UsbClkSel,
+};
+#[cfg(not(feature = "rm0455"))]
+use super::rec::{Lptim345ClkSel, Sai4AClkSel, Sai4BClkSel};
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
+use super::rec::{QspiClkSel, Sai23ClkSel};
+#[cfg(feature = "rm0455")]
+use super::rec::{Sai2AClkSel, Sai2BClkSel};
+use super::CoreClocks;
+#[cfg(not(feature = "rm0455"))]
+use crate::stm32::rcc::d3ccipr::LPUART1SEL_A as Lpuart1ClkSel;
+#[cfg(feature = "rm0455")]
+use crate::stm32::rcc::srdccipr::LPUART1SEL_A as Lpuart1ClkSel;
+use crate::stm32::RCC;
+use crate::time::Hertz;
+
+/// LSE frequency, if the LSE is running
+pub(crate) fn lse_ck() -> Option<Hertz> {
+    // unsafe: read only
+    let bdcr = &unsafe { &*RCC::ptr() }.bdcr;
+
+    if bdcr.read().lserdy().is_ready() {
+        Some(Hertz::from_raw(32_768))
+    } else {
+        None
+    }
+}
+
+macro_rules! peripheral_clocks {
+    ($($(#[$attr:meta])* $clk:ident: $doc:expr,)+) => {
+        /// Kernel clock frequencies of the peripherals
+        ///
+        /// Returned by [`CoreClocks::peripheral_clocks`]. Each field is
+        /// `None` if the selected kernel clock is not running, or cannot be
+        /// known (for example an external clock pin)
+        #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+        pub struct PeripheralClocks {
+            $(
+                $(#[$attr])*
+                #[doc=$doc]
+                /// kernel clock
+                pub $clk: Option<Hertz>,
+            )+
+        }
+
+        #[cfg(feature = "defmt")]
+        impl defmt::Format for PeripheralClocks {
+            fn format(&self, f: defmt::Formatter) {
+                defmt::write!(f, "PeripheralClocks {{");
+                $(
+                    $(#[$attr])*
+                    defmt::write!(
+                        f,
+                        " {=str}: {}",
+                        stringify!($clk),
+                        self.$clk.map(|ck| ck.raw())
+                    );
+                )+
+                defmt::write!(f, " }}");
+            }
+        }
+    };
+}
+
+peripheral_clocks! {
+    spi123_ck: "SPI1/2/3",
+    spi45_ck: "SPI4/5",
+    spi6_ck: "SPI6",
+    usart16_ck: "USART1/6 (and USART9/10 where present)",
+    usart234578_ck: "USART2/3/4/5/7/8",
+    i2c123_ck: "I2C1/2/3 (and I2C5 where present)",
+    i2c4_ck: "I2C4",
+    fdcan_ck: "FDCAN",
+    sdmmc_ck: "SDMMC",
+    adc_ck: "ADC",
+    rng_ck: "RNG",
+    usb_ck: "USB",
+    fmc_ck: "FMC",
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    qspi_ck: "QUADSPI",
+    #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+    octospi_ck: "OCTOSPI1/2",
+    sai1_ck: "SAI1",
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    sai23_ck: "SAI2/3",
+    #[cfg(feature = "rm0455")]
+    sai2a_ck: "SAI2 sub-block A",
+    #[cfg(feature = "rm0455")]
+    sai2b_ck: "SAI2 sub-block B",
+    #[cfg(not(feature = "rm0455"))]
+    sai4a_ck: "SAI4 sub-block A",
+    #[cfg(not(feature = "rm0455"))]
+    sai4b_ck: "SAI4 sub-block B",
+    spdifrx_ck: "SPDIFRX",
+    dfsdm1_ck: "DFSDM1",
+    cec_ck: "CEC",
+    lpuart1_ck: "LPUART1",
+    lptim1_ck: "LPTIM1",
+    lptim2_ck: "LPTIM2",
+    #[cfg(not(feature = "rm0455"))]
+    lptim345_ck: "LPTIM3/4/5",
+    #[cfg(feature = "rm0455")]
+    lptim3_ck: "LPTIM3",
+}
+
+/// Kernel clock of a SAI, from the value of its kernel clock mux
+macro_rules! sai_ck {
+    ($clocks:expr, $sel:expr, $ClkSel:ident) => {
+        match $sel {
+            Some($ClkSel::Pll1Q) => $clocks.pll1_q_ck(),
+            Some($ClkSel::Pll2P) => $clocks.pll2_p_ck(),
+            Some($ClkSel::Pll3P) => $clocks.pll3_p_ck(),
+            Some($ClkSel::Per) => $clocks.per_ck(),
+            // I2S_CKIN is an external pin
+            _ => None,
+        }
+    };
+}
+
+/// Kernel clock of a LPTIM, from the value of its kernel clock mux
+macro_rules! lptim_ck {
+    ($clocks:expr, $sel:expr, $ClkSel:ident, $Pclk:ident: $pclk:ident) => {
+        match $sel {
+            Some($ClkSel::$Pclk) => Some($clocks.$pclk()),
+            Some($ClkSel::Pll2P) => $clocks.pll2_p_ck(),
+            Some($ClkSel::Pll3R) => $clocks.pll3_r_ck(),
+            Some($ClkSel::Lse) => lse_ck(),
+            Some($ClkSel::Lsi) => $clocks.lsi_ck(),
+            Some($ClkSel::Per) => $clocks.per_ck(),
+            None => None,
+        }
+    };
+}
+
+impl CoreClocks {
+    /// Returns the kernel clock frequencies of the peripherals
+    ///
+    /// The kernel clock multiplexers are read once, at the end of
+    /// `freeze`. Kernel clocks that are selected afterwards through the
+    /// [`PeripheralREC`](super::PeripheralREC) are not reflected here; use
+    /// the `get_kernel_clk_mux` method of the peripheral's `rec` to check
+    /// those. This is useful for debugging and validating a clock
+    /// configuration.
+    pub fn peripheral_clocks(&self) -> PeripheralClocks {
+        self.peripheral_clocks
+    }
+
+    /// Reads the kernel clock multiplexers and calculates the kernel clock
+    /// frequencies of the peripherals
+    pub(super) fn read_peripheral_clocks(&self) -> PeripheralClocks {
+        // unsafe: read only
+        let rcc = unsafe { &*RCC::ptr() };
+
+        #[cfg(not(feature = "rm0455"))]
+        let (ccipr, ccip1r, ccip2r, srdccipr) = (
+            rcc.d1ccipr.read(),
+            rcc.d2ccip1r.read(),
+            rcc.d2ccip2r.read(),
+            rcc.d3ccipr.read(),
+        );
+        #[cfg(feature = "rm0455")]
+        let (ccipr, ccip1r, ccip2r, srdccipr) = (
+            rcc.cdccipr.read(),
+            rcc.cdccip1r.read(),
+            rcc.cdccip2r.read(),
+            rcc.srdccipr.read(),
+        );
+
+        #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+        let usart16sel = ccip2r.usart16sel().variant();
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        let usart16sel = ccip2r.usart16910sel().variant();
+        #[cfg(not(feature = "rm0468"))]
+        let i2c123sel = ccip2r.i2c123sel().variant();
+        #[cfg(feature = "rm0468")]
+        let i2c123sel = ccip2r.i2c1235sel().variant();
+        #[cfg(not(feature = "rm0455"))]
+        let spdifrxsel = ccip1r.spdifsel().variant();
+        #[cfg(feature = "rm0455")]
+        let spdifrxsel = ccip1r.spdifrxsel().variant();
+
+        PeripheralClocks {
+            spi123_ck: match ccip1r.spi123sel().variant() {
+                Some(Spi123ClkSel::Pll1Q) => self.pll1_q_ck(),
+                Some(Spi123ClkSel::Pll2P) => self.pll2_p_ck(),
+                Some(Spi123ClkSel::Pll3P) => self.pll3_p_ck(),
+                Some(Spi123ClkSel::Per) => self.per_ck(),
+                // I2S_CKIN is an external pin
+                _ => None,
+            },
+            spi45_ck: match ccip1r.spi45sel().variant() {
+                Some(Spi45ClkSel::Apb) => Some(self.pclk2()),
+                Some(Spi45ClkSel::Pll2Q) => self.pll2_q_ck(),
+                Some(Spi45ClkSel::Pll3Q) => self.pll3_q_ck(),
+                Some(Spi45ClkSel::HsiKer) => self.hsi_ck(),
+                Some(Spi45ClkSel::CsiKer) => self.csi_ck(),
+                Some(Spi45ClkSel::Hse) => self.hse_ck(),
+                None => None,
+            },
+            spi6_ck: match srdccipr.spi6sel().variant() {
+                Some(Spi6ClkSel::RccPclk4) => Some(self.pclk4()),
+                Some(Spi6ClkSel::Pll2Q) => self.pll2_q_ck(),
+                Some(Spi6ClkSel::Pll3Q) => self.pll3_q_ck(),
+                Some(Spi6ClkSel::HsiKer) => self.hsi_ck(),
+                Some(Spi6ClkSel::CsiKer) => self.csi_ck(),
+                Some(Spi6ClkSel::Hse) => self.hse_ck(),
+                None => None,
+            },
+            usart16_ck: match usart16sel {
+                Some(Usart16ClkSel::RccPclk2) => Some(self.pclk2()),
+                Some(Usart16ClkSel::Pll2Q) => self.pll2_q_ck(),
+                Some(Usart16ClkSel::Pll3Q) => self.pll3_q_ck(),
+                Some(Usart16ClkSel::HsiKer) => self.hsi_ck(),
+                Some(Usart16ClkSel::CsiKer) => self.csi_ck(),
+                Some(Usart16ClkSel::Lse) => lse_ck(),
+                None => None,
+            },
+            usart234578_ck: match ccip2r.usart234578sel().variant() {
+                Some(Usart234578ClkSel::RccPclk1) => Some(self.pclk1()),
+                Some(Usart234578ClkSel::Pll2Q) => self.pll2_q_ck(),
+                Some(Usart234578ClkSel::Pll3Q) => self.pll3_q_ck(),
+                Some(Usart234578ClkSel::HsiKer) => self.hsi_ck(),
+                Some(Usart234578ClkSel::CsiKer) => self.csi_ck(),
+                Some(Usart234578ClkSel::Lse) => lse_ck(),
+                None => None,
+            },
+            i2c123_ck: match i2c123sel {
+                I2c123ClkSel::RccPclk1 => Some(self.pclk1()),
+                I2c123ClkSel::Pll3R => self.pll3_r_ck(),
+                I2c123ClkSel::HsiKer => self.hsi_ck(),
+                I2c123ClkSel::CsiKer => self.csi_ck(),
+            },
+            i2c4_ck: match srdccipr.i2c4sel().variant() {
+                I2c4ClkSel::RccPclk4 => Some(self.pclk4()),
+                I2c4ClkSel::Pll3R => self.pll3_r_ck(),
+                I2c4ClkSel::HsiKer => self.hsi_ck(),
+                I2c4ClkSel::CsiKer => self.csi_ck(),
+            },
+            fdcan_ck: match ccip1r.fdcansel().variant() {
+                Some(FdcanClkSel::Hse) => self.hse_ck(),
+                Some(FdcanClkSel::Pll1Q) => self.pll1_q_ck(),
+                Some(FdcanClkSel::Pll2Q) => self.pll2_q_ck(),
+                None => None,
+            },
+            sdmmc_ck: match ccipr.sdmmcsel().variant() {
+                SdmmcClkSel::Pll1Q => self.pll1_q_ck(),
+                SdmmcClkSel::Pll2R => self.pll2_r_ck(),
+            },
+            adc_ck: match srdccipr.adcsel().variant() {
+                Some(AdcClkSel::Pll2P) => self.pll2_p_ck(),
+                Some(AdcClkSel::Pll3R) => self.pll3_r_ck(),
+                Some(AdcClkSel::Per) => self.per_ck(),
+                None => None,
+            },
+            rng_ck: match ccip2r.rngsel().variant() {
+                RngClkSel::Hsi48 => self.hsi48_ck(),
+                RngClkSel::Pll1Q => self.pll1_q_ck(),
+                RngClkSel::Lse => lse_ck(),
+                RngClkSel::Lsi => self.lsi_ck(),
+            },
+            usb_ck: match ccip2r.usbsel().variant() {
+                UsbClkSel::Disable => None,
+                UsbClkSel::Pll1Q => self.pll1_q_ck(),
+                UsbClkSel::Pll3Q => self.pll3_q_ck(),
+                UsbClkSel::Hsi48 => self.hsi48_ck(),
+            },
+            fmc_ck: match ccipr.fmcsel().variant() {
+                FmcClkSel::RccHclk3 => Some(self.hclk()),
+                FmcClkSel::Pll1Q => self.pll1_q_ck(),
+                FmcClkSel::Pll2R => self.pll2_r_ck(),
+                FmcClkSel::Per => self.per_ck(),
+            },
+            #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+            qspi_ck: match ccipr.qspisel().variant() {
+                QspiClkSel::RccHclk3 => Some(self.hclk()),
+                QspiClkSel::Pll1Q => self.pll1_q_ck(),
+                QspiClkSel::Pll2R => self.pll2_r_ck(),
+                QspiClkSel::Per => self.per_ck(),
+            },
+            #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+            octospi_ck: match ccipr.octospisel().variant() {
+                OctospiClkSel::RccHclk3 => Some(self.hclk()),
+                OctospiClkSel::Pll1Q => self.pll1_q_ck(),
+                OctospiClkSel::Pll2R => self.pll2_r_ck(),
+                OctospiClkSel::Per => self.per_ck(),
+            },
+            sai1_ck: sai_ck!(self, ccip1r.sai1sel().variant(), Sai1ClkSel),
+            #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+            sai23_ck: sai_ck!(self, ccip1r.sai23sel().variant(), Sai23ClkSel),
+            #[cfg(feature = "rm0455")]
+            sai2a_ck: sai_ck!(self, ccip1r.sai2asel().variant(), Sai2AClkSel),
+            #[cfg(feature = "rm0455")]
+            sai2b_ck: sai_ck!(self, ccip1r.sai2bsel().variant(), Sai2BClkSel),
+            #[cfg(not(feature = "rm0455"))]
+            sai4a_ck: sai_ck!(self, srdccipr.sai4asel().variant(), Sai4AClkSel),
+            #[cfg(not(feature = "rm0455"))]
+            sai4b_ck: sai_ck!(self, srdccipr.sai4bsel().variant(), Sai4BClkSel),
+            spdifrx_ck: match spdifrxsel {
+                SpdifrxClkSel::Pll1Q => self.pll1_q_ck(),
+                SpdifrxClkSel::Pll2R => self.pll2_r_ck(),
+                SpdifrxClkSel::Pll3R => self.pll3_r_ck(),
+                SpdifrxClkSel::HsiKer => self.hsi_ck(),
+            },
+            dfsdm1_ck: match ccip1r.dfsdm1sel().variant() {
+                Dfsdm1ClkSel::RccPclk2 => Some(self.pclk2()),
+                Dfsdm1ClkSel::Sys => Some(self.sys_ck()),
+            },
+            cec_ck: match ccip2r.cecsel().variant() {
+                Some(CecClkSel::Lse) => lse_ck(),
+                Some(CecClkSel::Lsi) => self.lsi_ck(),
+                // csi_ker_ck is divided by 122 for the CEC
+                Some(CecClkSel::CsiKer) => {
+                    self.csi_ck().map(|ck| Hertz::from_raw(ck.raw() / 122))
+                }
+                None => None,
+            },
+            lpuart1_ck: match srdccipr.lpuart1sel().variant() {
+                Some(Lpuart1ClkSel::RccPclkD3) => Some(self.pclk4()),
+                Some(Lpuart1ClkSel::Pll2Q) => self.pll2_q_ck(),
+                Some(Lpuart1ClkSel::Pll3Q) => self.pll3_q_ck(),
+                Some(Lpuart1ClkSel::HsiKer) => self.hsi_ck(),
+                Some(Lpuart1ClkSel::CsiKer) => self.csi_ck(),
+                Some(Lpuart1ClkSel::Lse) => lse_ck(),
+                None => None,
+            },
+            lptim1_ck: lptim_ck!(
+                self,
+                ccip2r.lptim1sel().variant(),
+                Lptim1ClkSel,
+                RccPclk1: pclk1
+            ),
+            lptim2_ck: lptim_ck!(
+                self,
+                srdccipr.lptim2sel().variant(),
+                Lptim2ClkSel,
+                RccPclk4: pclk4
+            ),
+            #[cfg(not(feature = "rm0455"))]
+            lptim345_ck: lptim_ck!(
+                self,
+                srdccipr.lptim345sel().variant(),
+                Lptim345ClkSel,
+                RccPclk4: pclk4
+            ),
+            #[cfg(feature = "rm0455")]
+            lptim3_ck: match srdccipr.lptim3sel().bits() {
+                0 => Some(self.pclk4()),
+                1 => self.pll2_p_ck(),
+                2 => self.pll3_r_ck(),
+                3 => lse_ck(),
+                4 => self.lsi_ck(),
+                5 => self.per_ck(),
+                _ => None,
+            },
+        }
+    }
+}
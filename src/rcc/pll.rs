@@ -28,6 +28,7 @@ pub struct PllConfig {
     pub(super) p_ck: Option<u32>,
     pub(super) q_ck: Option<u32>,
     pub(super) r_ck: Option<u32>,
+    pub(super) fracn: Option<u16>,
 }
 impl Default for PllConfig {
     fn default() -> PllConfig {
@@ -36,6 +37,7 @@ impl Default for PllConfig {
             p_ck: None,
             q_ck: None,
             r_ck: None,
+            fracn: None,
         }
     }
 }
@@ -235,27 +237,25 @@ macro_rules! pll_setup {
                 rcc.$pllXdivr
                     .modify(|_, w| unsafe { w.$divnX().bits((pll_x_n - 1) as u16) });
 
-                // Configure N divider. Returns the resulting VCO frequency
-                let vco_ck = match pll.strategy {
+                // Calculate FRACN
+                let pll_x_fracn = match pll.strategy {
                     PllConfigStrategy::Fractional => {
-                        // Calculate FRACN
-                        let pll_x_fracn = calc_fracn(ref_x_ck as f32, pll_x_n as f32, pll_x as f32, output as f32);
-                        //RCC_PLL1FRACR
-                        rcc.$pllXfracr.modify(|_, w| {
-                            w.$fracnx().bits(pll_x_fracn)
-                        });
-                        // Enable FRACN
-                        rcc.pllcfgr.modify(|_, w| {
-                            w.$pllXfracen().set()
-                        });
-
-                        calc_vco_ck(ref_x_ck, pll_x_n, pll_x_fracn)
+                        Some(calc_fracn(ref_x_ck as f32, pll_x_n as f32, pll_x as f32, output as f32))
                     },
                     PllConfigStrategy::FractionalNotLess => {
-                        // Calculate FRACN
-                        let mut pll_x_fracn = calc_fracn(ref_x_ck as f32, pll_x_n as f32, pll_x as f32, output as f32);
                         // Round up instead of down for FractionalNotLess
-                        pll_x_fracn += 1;
+                        Some(calc_fracn(ref_x_ck as f32, pll_x_n as f32, pll_x as f32, output as f32) + 1)
+                    },
+                    // Normal
+                    // Iterative
+                    _ => None,
+                };
+                // An explicit FRACN overrides the strategy
+                let pll_x_fracn = pll.fracn.or(pll_x_fracn);
+
+                // Configure N divider. Returns the resulting VCO frequency
+                let vco_ck = match pll_x_fracn {
+                    Some(pll_x_fracn) => {
                         //RCC_PLL1FRACR
                         rcc.$pllXfracr.modify(|_, w| {
                             w.$fracnx().bits(pll_x_fracn)
@@ -267,9 +267,7 @@ macro_rules! pll_setup {
 
                         calc_vco_ck(ref_x_ck, pll_x_n, pll_x_fracn)
                     },
-                    // Normal
-                    // Iterative
-                    _ => {
+                    None => {
                         // No FRACN
                         rcc.pllcfgr.modify(|_, w| {
                             w.$pllXfracen().reset()
@@ -17,6 +17,10 @@
 //! [`PeripheralRec`](struct.PeripheralREC.html) itself. These methods are named
 //! `kernel_xxxx_clk_mux()`.
 //!
+//! Peripheral constructors read the kernel clock multiplexer to find the
+//! frequency of their kernel clock. Therefore the kernel clock should be
+//! selected before the peripheral is constructed.
+//!
 //! # Reset/Enable Example
 //!
 //! ```
@@ -68,6 +72,24 @@
 //!
 //! If REC object is dropped by user code, then the Reset or Enable state of
 //! this peripheral cannot be modified for the lifetime of the program.
+//!
+//! # Resetting a peripheral
+//!
+//! [reset](ResetEnable::reset) only pulses the reset of the peripheral, and
+//! does not modify its enable. This can be used to recover a peripheral
+//! that is stuck, for example an I2C peripheral after a glitch on the bus,
+//! without disabling its clocks. A reset returns all the registers of the
+//! peripheral to their reset values, so it must be configured again
+//! afterwards.
+//!
+//! The drivers in this crate reset their peripheral when they are
+//! constructed, and return the REC object from their `free` method. So a
+//! driver can be recovered by constructing it again:
+//!
+//! ```
+//! let (i2c, rec) = i2c.free();
+//! let i2c = i2c.i2c(pins, 100.kHz(), rec, &ccdr.clocks);
+//! ```
 #![deny(missing_docs)]
 
 use core::marker::PhantomData;
@@ -84,7 +106,12 @@ pub trait ResetEnable {
     /// Disable this peripheral
     #[allow(clippy::return_self_not_must_use)]
     fn disable(self) -> Self;
-    /// Reset this peripheral
+    /// Reset this peripheral, without modifying its enable
+    ///
+    /// The reset is asserted and then released. The reset is released by a
+    /// read-modify-write of the reset register, and the read completes
+    /// after the write that asserted the reset, so the reset is asserted
+    /// for at least one access to the RCC.
     #[allow(clippy::return_self_not_must_use)]
     fn reset(self) -> Self;
 }
@@ -137,6 +164,7 @@ macro_rules! peripheral_reset_and_enable_control {
                 $([ kernel $clk:ident: $pk:ident $(($Variant:ident))* $ccip:ident $clk_doc:expr ])*
                 $([ group clk: $pk_g:ident $( $(($Variant_g:ident))* $ccip_g:ident $clk_doc_g:expr )* ])*
                 $([ fixed clk: $clk_doc_f:expr ])*
+                $([ bits: $en_pfx:ident $rst_pfx:ident ])?
         ),*
     ];)+) => {
         paste::item! {
@@ -190,6 +218,9 @@ macro_rules! peripheral_reset_and_enable_control {
                         $(
                             [fixed clk: $clk_doc_f]
                         )*
+                        $(
+                            [bits: $en_pfx $rst_pfx]
+                        )?
                     );
                 )*
             )+
@@ -214,6 +245,7 @@ macro_rules! peripheral_reset_and_enable_control_generator {
         $([ kernel $clk:ident: $pk:ident $(($Variant:ident))* $ccip:ident $clk_doc:expr ])*
         $([ group clk: $pk_g:ident $pk_g_lower:ident $( $(($Variant_g:ident))* $ccip_g:ident $clk_doc_g:expr )* ])*
         $([ fixed clk: $clk_doc_f:expr ])*
+        $([ bits: $en_pfx:ident $rst_pfx:ident ])?
     ) => {
         paste::item! {
             #[doc = " Reset, Enable and Clock functionality for " $p]
@@ -287,7 +319,7 @@ macro_rules! peripheral_reset_and_enable_control_generator {
                         let lpenr = unsafe {
                             &(*RCC::ptr()).[< $AXBn:lower lpenr >]
                         };
-                        lpenr.modify(|_, w| w.[< $p:lower lpen >]()
+                        lpenr.modify(|_, w| rec_bits!(w, [< $p:lower lpen >] $(, [< $en_pfx lpen >])?)
                                      .bit(lpm != LowPowerMode::Off));
                         // AMEN
                         $(
@@ -310,8 +342,8 @@ macro_rules! peripheral_reset_and_enable_control_generator {
                         let enr = unsafe {
                             &(*RCC::ptr()).[< $AXBn:lower enr >]
                         };
-                        enr.modify(|_, w| w.
-                                   [< $p:lower en >]().set_bit());
+                        enr.modify(|_, w| rec_bits!(w, [< $p:lower en >] $(, [< $en_pfx en >])?)
+                                   .set_bit());
                     });
                     self
                 }
@@ -322,8 +354,8 @@ macro_rules! peripheral_reset_and_enable_control_generator {
                         let enr = unsafe {
                             &(*RCC::ptr()).[< $AXBn:lower enr >]
                         };
-                        enr.modify(|_, w| w.
-                                   [< $p:lower en >]().clear_bit());
+                        enr.modify(|_, w| rec_bits!(w, [< $p:lower en >] $(, [< $en_pfx en >])?)
+                                   .clear_bit());
                     });
                     self
                 }
@@ -334,10 +366,10 @@ macro_rules! peripheral_reset_and_enable_control_generator {
                         let rstr = unsafe {
                             &(*RCC::ptr()).[< $AXBn:lower rstr >]
                         };
-                        rstr.modify(|_, w| w.
-                                    [< $p:lower rst >]().set_bit());
-                        rstr.modify(|_, w| w.
-                                    [< $p:lower rst >]().clear_bit());
+                        rstr.modify(|_, w| rec_bits!(w, [< $p:lower rst >] $(, [< $rst_pfx rst >])?)
+                                    .set_bit());
+                        rstr.modify(|_, w| rec_bits!(w, [< $p:lower rst >] $(, [< $rst_pfx rst >])?)
+                                    .clear_bit());
                     });
                     self
                 }
@@ -446,6 +478,18 @@ macro_rules! peripheral_reset_and_enable_control_generator {
     }
 }
 
+// Selects the enable or reset field of a peripheral. If the PAC names this
+// field differently to the peripheral, the name given with "bits" is used
+// instead
+macro_rules! rec_bits {
+    ($w:ident, $field:ident) => {
+        $w.$field()
+    };
+    ($w:ident, $field:ident, $bits:ident) => {
+        $w.$bits()
+    };
+}
+
 // If the PAC does not fully specify a CCIP field (perhaps because one or
 // more values are reserved), then we use a different return type
 macro_rules! variant_return_type {
@@ -485,6 +529,10 @@ macro_rules! autonomous {
 // Peripherals with an individual kernel clock must be marked "kernel clk". If a
 // kernel clock multiplexer is shared between multiple peripherals, all those
 // peripherals must instead be marked with a common "group clk".
+//
+// If the enable and reset fields in the PAC are not named after the
+// peripheral, the prefixes of the enable (and low-power enable) field and of
+// the reset field are given with "bits".
 peripheral_reset_and_enable_control! {
     #[cfg(all())]
     AHB1, "AMBA High-performance Bus (AHB1) peripherals" => [
@@ -526,6 +574,14 @@ peripheral_reset_and_enable_control! {
     AHB2, "" => [
         Cordic, Fmac
     ];
+    #[cfg(not(feature = "rm0455"))]
+    AHB2, "" => [
+        Dcmi [bits: dcmi camitf]
+    ];
+    #[cfg(feature = "rm0455")]
+    AHB2, "" => [
+        Dcmi [bits: dcmi_pssi dcmi_pssi]
+    ];
 
 
     #[cfg(all())]
@@ -592,6 +648,7 @@ peripheral_reset_and_enable_control! {
 
         Cec [kernel clk: Cec(Variant) d2ccip2 "CEC"],
         Lptim1 [kernel clk: Lptim1(Variant) d2ccip2 "LPTIM1"],
+        Spdifrx [kernel clk: Spdif d2ccip1 "SPDIFRX"],
         Usart2 [group clk: Usart234578(Variant) d2ccip2 "USART2/3/4/5/7/8"]
     ];
     #[cfg(any(feature = "rm0433", feature = "rm0399"))]
@@ -609,6 +666,7 @@ peripheral_reset_and_enable_control! {
         I2c3 [group clk: I2c123],
         Cec [kernel clk: Cec(Variant) cdccip2 "CEC"],
         Lptim1 [kernel clk: Lptim1(Variant) cdccip2 "LPTIM1"],
+        Spdifrx [kernel clk: Spdifrx cdccip1 "SPDIFRX"],
         Usart2 [group clk: Usart234578(Variant) cdccip2 "USART2/3/4/5/7/8"]
     ];
     #[cfg(feature = "rm0468")]
@@ -2,6 +2,24 @@
 //!
 //! Inter-IC Sound.
 //!
+//! # Master Clock
+//!
+//! Most external audio codecs need a master clock (MCLK) at a fixed multiple
+//! of the frame sync frequency fs. When the master clock generator is
+//! enabled, the master channel divides its kernel clock down to 256 × fs, or
+//! to 512 × fs with [`I2SMasterClockRatio::Fs512`]. The divider is computed
+//! from the `audio_freq` passed to the constructor, which panics if the
+//! kernel clock cannot be divided down to the requested MCLK.
+//!
+//! The kernel clock should therefore be an integer multiple of 256 × fs (or
+//! 512 × fs), for example 49.152MHz for fs = 48kHz. Otherwise the nearest
+//! divider is used and the achieved fs differs from the requested one.
+//!
+//! On RM0455 and RM0468 parts the MCLK pin is enabled with
+//! `I2SChanConfig::set_master_clock_output` (it is enabled by default). On
+//! other parts the MCLK pin is driven whenever the master clock generator is
+//! enabled.
+//!
 
 use crate::rcc::{rec, CoreClocks, ResetEnable};
 use crate::sai::{GetClkSAI, Sai, SaiChannel, CLEAR_ALL_FLAGS_BITS, INTERFACE};
@@ -80,6 +98,16 @@ pub enum I2SError {
     NoChannelAvailable,
 }
 
+/// Ratio between the master clock (MCLK) and the frame sync frequency
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum I2SMasterClockRatio {
+    /// MCLK = 256 × fs
+    Fs256,
+    /// MCLK = 512 × fs
+    Fs512,
+}
+
 pub enum I2SClockStrobe {
     Rising,
     Falling,
@@ -154,6 +182,8 @@ pub struct I2SChanConfig {
     frame_sync_active_high: bool,
     oversampling: bool,
     master_clock_disabled: bool,
+    #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+    master_clock_output: bool,
     companding: I2SCompanding,
     complement: I2SComplement,
     protocol: I2SProtocol,
@@ -180,6 +210,8 @@ impl I2SChanConfig {
             frame_sync_active_high: false,
             oversampling: false,
             master_clock_disabled: false,
+            #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+            master_clock_output: true,
             companding: I2SCompanding::Disabled,
             complement: I2SComplement::Ones,
             protocol: I2SProtocol::MSB,
@@ -248,6 +280,16 @@ impl I2SChanConfig {
         self
     }
 
+    /// Set the ratio between the master clock and the frame sync
+    /// frequency, defaults to [`I2SMasterClockRatio::Fs256`]
+    ///
+    /// This is equivalent to
+    /// [`set_oversampling`](I2SChanConfig::set_oversampling)
+    #[must_use]
+    pub fn set_master_clock_ratio(self, ratio: I2SMasterClockRatio) -> Self {
+        self.set_oversampling(ratio == I2SMasterClockRatio::Fs512)
+    }
+
     /// Disable master clock generator
     #[must_use]
     pub fn disable_master_clock(mut self) -> Self {
@@ -255,6 +297,18 @@ impl I2SChanConfig {
         self
     }
 
+    /// Enable or disable the master clock output on the MCLK pin, defaults
+    /// to enabled
+    ///
+    /// Only has an effect on the master channel when the master clock
+    /// generator is enabled.
+    #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+    #[must_use]
+    pub fn set_master_clock_output(mut self, enable: bool) -> Self {
+        self.master_clock_output = enable;
+        self
+    }
+
     /// Sets the protocol to MSB or LSB
     #[must_use]
     pub fn set_protocol(mut self, protocol: I2SProtocol) -> Self {
@@ -440,18 +494,15 @@ macro_rules! i2s {
 
                     // Clock config
                     let ker_ck_a = $SAIX::sai_a_ker_ck(&prec, clocks);
-                    let clock_ratio = if users.master.oversampling {
-                        512
-                    } else {
-                        256
-                    };
-                    let mclk_div =
-                        (ker_ck_a) / (audio_freq * clock_ratio);
-                    let mclk_div: u8 = mclk_div
-                        .try_into()
-                        .expect(concat!(stringify!($SAIX),
-                                        " A: Kernel clock is out of range for required MCLK"
-                        ));
+                    let mclk_div = mclk_divider(
+                        ker_ck_a,
+                        audio_freq,
+                        &users.master,
+                    )
+                    .expect(concat!(
+                        stringify!($SAIX),
+                        " A: Kernel clock is out of range for required MCLK"
+                    ));
 
                     // Configure SAI peripheral
                     let mut per_sai = Sai {
@@ -506,19 +557,16 @@ macro_rules! i2s {
                     }
 
                     // Clock config
-                    let ker_ck_a = $SAIX::sai_b_ker_ck(&prec, clocks);
-                    let clock_ratio = if users.master.oversampling {
-                        512
-                    } else {
-                        256
-                    };
-                    let mclk_div =
-                        (ker_ck_a) / (audio_freq * clock_ratio);
-                    let mclk_div: u8 = mclk_div
-                        .try_into()
-                        .expect(concat!(stringify!($SAIX),
-                                        " B: Kernel clock is out of range for required MCLK"
-                        ));
+                    let ker_ck_b = $SAIX::sai_b_ker_ck(&prec, clocks);
+                    let mclk_div = mclk_divider(
+                        ker_ck_b,
+                        audio_freq,
+                        &users.master,
+                    )
+                    .expect(concat!(
+                        stringify!($SAIX),
+                        " B: Kernel clock is out of range for required MCLK"
+                    ));
 
 
                     // Configure SAI peripheral
@@ -626,6 +674,29 @@ i2s! {
     SAI4, Sai4: [i4s_sai4_ch_a, i4s_sai4_ch_b]
 }
 
+/// Returns the master clock divider that divides the kernel clock `ker_ck`
+/// down to the MCLK required by `config` for a frame sync frequency of
+/// `audio_freq`, or `None` if this divider is out of range
+fn mclk_divider(
+    ker_ck: Hertz,
+    audio_freq: Hertz,
+    config: &I2SChanConfig,
+) -> Option<u8> {
+    let clock_ratio = if config.oversampling { 512 } else { 256 };
+    let mclk = audio_freq.raw().checked_mul(clock_ratio)?;
+    if mclk == 0 || ker_ck.raw() < mclk {
+        return None;
+    }
+
+    // Round to the nearest divider. MCKDIV = 0 also divides by 1, but there
+    // is no need to use it
+    let div = (u64::from(ker_ck.raw()) + u64::from(mclk / 2)) / u64::from(mclk);
+    match div {
+        1..=0b11_1111 => Some(div as u8),
+        _ => None,
+    }
+}
+
 fn i2s_config_channel(
     audio_ch: &CH,
     mode: I2SMode,
@@ -687,6 +758,14 @@ fn i2s_config_channel(
                 .osr()
                 .bit(config.oversampling)
         });
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        audio_ch.cr1.modify(|_, w| {
+            w.mcken().bit(
+                mode == I2SMode::Master
+                    && !config.master_clock_disabled
+                    && config.master_clock_output,
+            )
+        });
         audio_ch.cr2.modify(|_, w| {
             w.fth()
                 .quarter1()
@@ -943,3 +1022,44 @@ pins! {
             gpio::PF6<Alternate<8>>
         ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mclk_divider_range() {
+        let config = I2SChanConfig::new(I2SDir::Tx);
+        let fs = Hertz::from_raw(48_000);
+
+        // 49.152MHz = 4 × 256 × 48kHz
+        let ker_ck = Hertz::from_raw(49_152_000);
+        assert_eq!(mclk_divider(ker_ck, fs, &config), Some(4));
+        let config = config.set_master_clock_ratio(I2SMasterClockRatio::Fs512);
+        assert_eq!(mclk_divider(ker_ck, fs, &config), Some(2));
+
+        // Kernel clock too slow for MCLK = 512 × fs
+        let ker_ck = Hertz::from_raw(12_288_000);
+        assert_eq!(mclk_divider(ker_ck, fs, &config), None);
+
+        // Kernel clock too fast, the divider is at most 63
+        let config = I2SChanConfig::new(I2SDir::Tx);
+        let ker_ck = Hertz::from_raw(64 * 12_288_000);
+        assert_eq!(mclk_divider(ker_ck, fs, &config), None);
+        let ker_ck = Hertz::from_raw(63 * 12_288_000);
+        assert_eq!(mclk_divider(ker_ck, fs, &config), Some(63));
+    }
+
+    #[test]
+    fn mclk_divider_rounds_to_nearest() {
+        let config = I2SChanConfig::new(I2SDir::Tx);
+        let fs = Hertz::from_raw(48_000);
+
+        // 257 × 48kHz is within 1% of 256 × 48kHz
+        let ker_ck = Hertz::from_raw(257 * 48_000);
+        assert_eq!(mclk_divider(ker_ck, fs, &config), Some(1));
+        // 2.6 × 256 × 48kHz
+        let ker_ck = Hertz::from_raw(31_948_800);
+        assert_eq!(mclk_divider(ker_ck, fs, &config), Some(3));
+    }
+}
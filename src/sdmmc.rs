@@ -61,6 +61,19 @@
 //! }
 //! ```
 //!
+//! # FAT filesystem
+//!
+//! With the `sdmmc-fatfs` feature, an initialised SD card can be converted
+//! into an [`SdmmcBlockDevice`], which implements the
+//! [embedded-sdmmc](https://docs.rs/embedded-sdmmc) `BlockDevice`
+//! trait. Reads and writes are in whole 512-byte blocks, and writes of
+//! several blocks use a single multiple block write command.
+//!
+//! ```
+//! let mut controller = Controller::new(sdmmc.sdmmc_block_device(), time_source);
+//! let volume = controller.get_volume(VolumeIdx(0))?;
+//! ```
+//!
 //! # High Speed Signaling - SD Card
 //!
 //! Up to 25MHz supported
@@ -1613,13 +1626,18 @@ pub trait SdmmcPeripheral {
     fn get_capacity(&self) -> CardCapacity;
 }
 
+/// An SD card implementing the embedded-sdmmc `BlockDevice` trait
+///
+/// Created by `sdmmc_block_device`
 #[cfg(feature = "sdmmc-fatfs")]
+#[cfg_attr(docsrs, doc(cfg(feature = "sdmmc-fatfs")))]
 pub struct SdmmcBlockDevice<SDMMC> {
     sdmmc: core::cell::RefCell<SDMMC>,
 }
 
 #[cfg(feature = "sdmmc-fatfs")]
 impl<SDMMC> SdmmcBlockDevice<SDMMC> {
+    /// Releases the SDMMC peripheral
     pub fn free(self) -> SDMMC {
         self.sdmmc.into_inner()
     }
@@ -6,6 +6,137 @@
 //! - [Serial Transfer using DMA](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/serial-dma.rs)
 //! - [Advanced USART Functions](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/serial-advanced.rs)
 //! - [Inverted Signal Levels](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/serial-inverted-loopback.rs)
+//!
+//! # embedded-io
+//!
+//! With the `embedded-io` feature, [`Serial`], [`Rx`] and [`Tx`] implement
+//! the [`embedded_io::Read`] and [`embedded_io::Write`] traits used by
+//! embedded-hal 1.0 drivers. `read` waits for at least one byte, and then
+//! returns all the bytes that have already been received. `write` waits
+//! until at least one byte can be sent, and then writes as many bytes as fit
+//! in the transmit FIFO.
+//!
+//! # Async
+//!
+//! With the `async` feature, [`RxAsync`] implements the
+//! [embedded-io-async](https://docs.rs/embedded-io-async) `Read` trait for a
+//! serial receiver. Each read is transferred by a DMA1 or DMA2 stream, and
+//! completes when the buffer is full or when the receive line becomes idle,
+//! whichever happens first. The task is woken by the interrupt handlers for
+//! the stream and the USART.
+//!
+//! ```
+//! use embedded_io_async::Read;
+//! use stm32h7xx_hal::dma::dma::{AsyncStream, StreamsTuple};
+//! use stm32h7xx_hal::serial::{Rx, RxAsync};
+//!
+//! let (tx, rx) = serial.split();
+//! let streams = StreamsTuple::new(dp.DMA1, ccdr.peripheral.DMA1);
+//! let mut rx = RxAsync::new(rx, streams.0);
+//!
+//! let mut buf = [0; 64];
+//! let n = rx.read(&mut buf).await?;
+//!
+//! #[interrupt]
+//! fn USART1() {
+//!     Rx::<pac::USART1>::on_interrupt();
+//! }
+//!
+//! #[interrupt]
+//! fn DMA1_STR0() {
+//!     Stream0::<DMA1>::on_interrupt();
+//! }
+//! ```
+//!
+//! Both interrupts must be unmasked in the NVIC, for example with
+//! `NVIC::unmask(pac::Interrupt::USART1)`. The interrupt handlers only
+//! disable the interrupt that fired and wake the task, so their priority can
+//! be set freely with `NVIC::set_priority` relative to the executor. If there
+//! are other USART interrupt sources, the USART handler can also handle them
+//! before calling `on_interrupt`.
+//!
+//! # Receiver timeout
+//!
+//! The end of a variable-length packet received by DMA can be detected with
+//! the receiver timeout, which is set when the receive line has been idle
+//! for a number of bit times after the last character. Unlike the
+//! [`Idle`](Event::Idle) event, the gap is programmable.
+//!
+//! ```
+//! // Interrupt 35 bit times after the last character
+//! serial.set_receiver_timeout(35);
+//! serial.listen(serial::Event::ReceiverTimeout);
+//!
+//! // In the interrupt handler
+//! if serial.is_receiver_timeout() {
+//!     serial.clear_receiver_timeout();
+//! }
+//! ```
+//!
+//! # Mute mode
+//!
+//! On a multiprocessor or multidrop bus, such as RS-485, the receiver of
+//! each node can be muted until a message addressed to it begins, so that it
+//! does not receive the messages for other nodes. The receiver is woken
+//! either when the line is idle, or when an address mark character with the
+//! node's address is received. The character that wakes the receiver is
+//! received as normal.
+//!
+//! ```
+//! let config = serial::config::Config::new(115_200.bps())
+//!     .mute_mode(serial::config::WakeupMethod::Address7Bit(0x12));
+//! let mut serial = dp.USART1.serial((tx, rx), config, ccdr.peripheral.USART1, &ccdr.clocks)?;
+//!
+//! // Ignore all characters until the next address mark for this node
+//! serial.enter_mute_mode();
+//! assert!(serial.is_muted());
+//! ```
+//!
+//! # Break
+//!
+//! A break, a character time or more with the line low, delimits frames in
+//! LIN and some other protocols. A received break is reported by `read` as
+//! an [`Error::Framing`], and the next `read` returns the break character of
+//! all zeros. With break detection enabled, the end of a break also sets a
+//! flag, which can raise the [`BreakDetected`](Event::BreakDetected) event.
+//!
+//! ```
+//! let config = serial::config::Config::new(19_200.bps())
+//!     .break_detection(serial::config::BreakLength::Bits11);
+//! let mut serial = dp.USART1.serial((tx, rx), config, ccdr.peripheral.USART1, &ccdr.clocks)?;
+//!
+//! serial.send_break();
+//! serial.listen(serial::Event::BreakDetected);
+//! ```
+//!
+//! # Stop mode wakeup
+//!
+//! A USART can wake the device from Stop mode when a start bit, a character
+//! or a character with a particular address is received. The wakeup event
+//! sets a flag and raises the USART interrupt, which must be unmasked in the
+//! NVIC. The character that caused the wakeup is received as normal.
+//!
+//! The USART kernel clock must keep running in Stop mode, so it must be the
+//! HSI, the CSI or the LSE, selected with `kernel_clk_mux` on the `rec` type
+//! before the USART is constructed. The baud rate is generated from this
+//! clock. LPUART1 is not supported by this module.
+//!
+//! ```
+//! serial.enable_stop_mode_wakeup(serial::WakeupConfig::StartBit);
+//!
+//! // Enter Stop mode, then after wakeup
+//! if serial.is_wakeup() {
+//!     serial.clear_wakeup();
+//! }
+//! ```
+//!
+//! With the `async` feature, `wait_for_wakeup` waits for the wakeup flag,
+//! and is woken by [`Rx::on_interrupt`].
+//!
+//! # Smartcard
+//!
+//! The synchronous USARTs can also communicate with an ISO 7816 smartcard,
+//! see the [`smartcard`] module.
 
 use core::cell::UnsafeCell;
 use core::fmt;
@@ -32,7 +163,9 @@ use crate::stm32::rcc::d2ccip2r::{USART16910SEL_A, USART234578SEL_A};
 #[cfg(any(feature = "rm0433", feature = "rm0399"))]
 use crate::stm32::rcc::d2ccip2r::{USART16SEL_A, USART234578SEL_A};
 
-use crate::stm32::usart1::cr1::{M0_A as M0, PCE_A as PCE, PS_A as PS};
+use crate::stm32::usart1::cr1::{
+    M0_A as M0, PCE_A as PCE, PS_A as PS, WAKE_A as WAKE,
+};
 use crate::stm32::{UART4, UART5, UART7, UART8};
 #[cfg(any(feature = "rm0455", feature = "rm0468"))]
 use crate::stm32::{UART9, USART10};
@@ -44,7 +177,9 @@ use crate::time::Hertz;
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
 pub enum Error {
-    /// Framing error
+    /// Framing error. The character received with the error is not
+    /// discarded, and is returned by the next read. A received break is
+    /// reported as a framing error, followed by a character of all zeros
     Framing,
     /// Noise error
     Noise,
@@ -52,6 +187,10 @@ pub enum Error {
     Overrun,
     /// Parity check error
     Parity,
+    /// DMA transfer error
+    Dma,
+    /// No character was received within the waiting time
+    Timeout,
 }
 
 /// Interrupt event
@@ -70,6 +209,26 @@ pub enum Event {
 
     ///Rx threshlold interrupt enable
     Rxftie,
+
+    /// Receiver timeout, set with `set_receiver_timeout`, has elapsed
+    ReceiverTimeout,
+
+    /// Break detected, when break detection is enabled with
+    /// [`Config::break_detection`](config::Config::break_detection)
+    BreakDetected,
+}
+
+/// Event that wakes the device from Stop mode, selected with
+/// `enable_stop_mode_wakeup`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WakeupConfig {
+    /// A character with this 7-bit address in its LSBs is received
+    AddressMatch(u8),
+    /// A start bit is detected
+    StartBit,
+    /// A character is received
+    RxneSet,
 }
 
 pub mod config {
@@ -90,13 +249,28 @@ pub mod config {
     /// When enabled parity bits will be automatically added by hardware on transmit, and automatically checked by
     /// hardware on receive. For example, `read()` would return [`Error::Parity`](super::Error::Parity).
     ///
-    /// Note that parity bits are included in the serial word length, so if parity is used word length will be set to 9.
+    /// Note that parity bits are included in the serial word length, so if parity is used word length will be set to 9,
+    /// unless it is specified with [`Config::word_length`].
     #[derive(Copy, Clone, PartialEq, Eq)]
     pub enum Parity {
         ParityNone,
         ParityEven,
         ParityOdd,
     }
+    /// The length of each serial word, including the parity bit if parity
+    /// is enabled
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub enum WordLength {
+        /// 7-bit words, which must include a parity bit. There are 6 data
+        /// bits in each word
+        SevenBit,
+        /// 8-bit words
+        EightBit,
+        /// 9-bit words
+        NineBit,
+    }
+
     #[derive(Copy, Clone, PartialEq, Eq)]
     pub enum StopBits {
         #[doc = "1 stop bit"]
@@ -124,6 +298,30 @@ pub mod config {
         IdleLow,
     }
 
+    /// Method used to wake the receiver from mute mode
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub enum WakeupMethod {
+        /// Wake when the receive line is idle
+        IdleLine,
+        /// Wake when an address mark, a character with its MSB set, is
+        /// received with this 4-bit address in its LSBs
+        Address4Bit(u8),
+        /// Wake when an address mark, a character with its MSB set, is
+        /// received with this 7-bit address in its LSBs
+        Address7Bit(u8),
+    }
+
+    /// Minimum length of a break detected by the break detection flag
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub enum BreakLength {
+        /// 10 bit times of zeros
+        Bits10,
+        /// 11 bit times of zeros
+        Bits11,
+    }
+
     /// A structure for specifying the USART or UART configuration. Fields
     /// relating to synchronous mode are ignored for UART peripherals.
     ///
@@ -147,6 +345,9 @@ pub mod config {
         pub rxfifothreshold: FifoThreshold,
         pub txfifothreshold: FifoThreshold,
         pub halfduplex: bool,
+        pub mute_mode: Option<WakeupMethod>,
+        pub word_length: Option<WordLength>,
+        pub break_detection: Option<BreakLength>,
     }
 
     impl Config {
@@ -169,6 +370,9 @@ pub mod config {
                 rxfifothreshold: FifoThreshold::Eighth,
                 txfifothreshold: FifoThreshold::Eighth,
                 halfduplex: false,
+                mute_mode: None,
+                word_length: None,
+                break_detection: None,
             }
         }
 
@@ -266,6 +470,71 @@ pub mod config {
             self.halfduplex = halfduplex;
             self
         }
+
+        /// Specify the word length, including the parity bit
+        ///
+        /// By default each word has 8 data bits, and the word length is 9
+        /// bits if parity is enabled. A [`WordLength::SevenBit`] word must
+        /// include a parity bit, otherwise the configuration is invalid
+        pub fn word_length(mut self, word_length: WordLength) -> Self {
+            self.word_length = Some(word_length);
+            self
+        }
+
+        /// Returns an error if this configuration is not supported by the
+        /// hardware
+        pub(crate) fn validate(&self) -> Result<(), InvalidConfig> {
+            // RM0433 Rev 7 Section 48.5.15: 7-bit words are only supported
+            // with parity
+            if self.word_length == Some(WordLength::SevenBit)
+                && self.parity == Parity::ParityNone
+            {
+                return Err(InvalidConfig);
+            }
+            // RM0433 Rev 7 Section 48.5.16: break detection is part of LIN
+            // mode, which requires 1 stop bit and excludes half duplex mode
+            if self.break_detection.is_some()
+                && (self.stopbits != StopBits::Stop1 || self.halfduplex)
+            {
+                return Err(InvalidConfig);
+            }
+
+            Ok(())
+        }
+
+        /// Enables the break detection flag, which is set when the receive
+        /// line has been low for at least `length` bit times. The
+        /// [`BreakDetected`](super::Event::BreakDetected) event is raised
+        /// with the flag.
+        ///
+        /// Break detection enables LIN mode, so the configuration must
+        /// have 1 stop bit, and cannot be used in half duplex or
+        /// synchronous mode.
+        pub fn break_detection(mut self, length: BreakLength) -> Self {
+            self.break_detection = Some(length);
+            self
+        }
+
+        /// Enables mute mode, for a node on a multiprocessor or multidrop
+        /// bus. Once it enters mute mode, the receiver ignores all
+        /// characters until it is woken by `wakeup`
+        ///
+        /// # Panics
+        ///
+        /// Panics if the address does not fit in 4 or 7 bits
+        pub fn mute_mode(mut self, wakeup: WakeupMethod) -> Self {
+            match wakeup {
+                WakeupMethod::Address4Bit(address) => {
+                    assert!(address < (1 << 4), "Address must fit in 4 bits")
+                }
+                WakeupMethod::Address7Bit(address) => {
+                    assert!(address < (1 << 7), "Address must fit in 7 bits")
+                }
+                WakeupMethod::IdleLine => {}
+            }
+            self.mute_mode = Some(wakeup);
+            self
+        }
     }
 
     #[derive(Debug)]
@@ -285,6 +554,17 @@ pub mod config {
     }
 }
 
+/// Returns the mask of the data bits in a received byte, for the word
+/// length M[1:0] and parity enable PCE. With parity enabled, the MSB of a 7
+/// or 8-bit word is the parity bit
+fn data_mask(m1: bool, m0: bool, pce: bool) -> u8 {
+    match (m1, m0, pce) {
+        (true, _, true) => 0x3F,
+        (false, false, true) => 0x7F,
+        _ => 0xFF,
+    }
+}
+
 pub trait Pins<USART> {
     const SYNCHRONOUS: bool = false;
 }
@@ -607,12 +887,18 @@ macro_rules! usart {
                     let config = config.into();
                     serial.usart.cr1.reset();
 
+                    config.validate()?;
+
                     // If synchronous mode is supported, check that it is not
                     // enabled alongside half duplex mode
                     $(
                         if config.halfduplex & $synchronous {
                             return Err(config::InvalidConfig);
                         }
+                        // Nor alongside break detection (LIN mode)
+                        if config.break_detection.is_some() & $synchronous {
+                            return Err(config::InvalidConfig);
+                        }
                     )?
                     serial.configure(&config $(, $synchronous )?);
 
@@ -693,6 +979,21 @@ macro_rules! usart {
 
                         w.swap().bit(config.swaptxrx);
 
+                        // LIN mode for break detection
+                        w.linen().bit(config.break_detection.is_some());
+                        w.lbdl().bit(config.break_detection == Some(BreakLength::Bits11));
+
+                        // Node address for address mark wakeup
+                        match config.mute_mode {
+                            Some(WakeupMethod::Address4Bit(address)) => {
+                                w.addm7().bit4().add().bits(address);
+                            }
+                            Some(WakeupMethod::Address7Bit(address)) => {
+                                w.addm7().bit7().add().bits(address);
+                            }
+                            _ => {}
+                        }
+
                         w.rxinv().variant(if config.invertrx {
                             RXINV_A::Inverted
                         } else {
@@ -734,6 +1035,16 @@ macro_rules! usart {
                         w
                     });
 
+                    // Word length, M[1:0]
+                    let (m1, m0) = match (config.word_length, config.parity) {
+                        (Some(WordLength::SevenBit), _) => (true, M0::Bit8),
+                        (Some(WordLength::EightBit), _) => (false, M0::Bit8),
+                        (Some(WordLength::NineBit), _) => (false, M0::Bit9),
+                        // 8 data bits, and the parity bit if enabled
+                        (None, Parity::ParityNone) => (false, M0::Bit8),
+                        (None, _) => (false, M0::Bit9),
+                    };
+
                     // Enable transmission and receiving and configure frame
                     // Retain enabled events
                     self.usart.cr1.modify(|_, w| {
@@ -748,12 +1059,17 @@ macro_rules! usart {
                             .re()
                             .enabled()
                             .m1()
-                            .clear_bit()
+                            .bit(m1)
+                            .mme()
+                            .bit(config.mute_mode.is_some())
+                            .wake()
+                            .variant(match config.mute_mode {
+                                Some(WakeupMethod::IdleLine) | None => WAKE::Idle,
+                                _ => WAKE::Address,
+                            })
                             .m0()
-                            .variant(match config.parity {
-                                Parity::ParityNone => M0::Bit8,
-                                _ => M0::Bit9,
-                            }).pce()
+                            .variant(m0)
+                            .pce()
                             .variant(match config.parity {
                                 Parity::ParityNone => PCE::Disabled,
                                 _ => PCE::Enabled,
@@ -771,7 +1087,8 @@ macro_rules! usart {
                 ///
                 /// # Panics
                 ///
-                /// Panics if DMA Rx or Tx are enabled.
+                /// Panics if DMA Rx or Tx are enabled, or if the configuration
+                /// is invalid.
                 pub fn reconfigure(&mut self, config: impl Into<config::Config> $(, $synchronous: bool)?) {
                     if self.dma_rx_enabled() || self.dma_tx_enabled() {
                         panic!("Cannot reconfigure serial while DMA enabled");
@@ -780,6 +1097,7 @@ macro_rules! usart {
                     self.usart.cr1.modify(|_, w| w.ue().disabled());
 
                     let config = config.into();
+                    config.validate().expect("Invalid serial configuration");
                     self.configure(&config $(, $synchronous )?);
                 }
 
@@ -831,6 +1149,12 @@ macro_rules! usart {
                         Event::Rxftie => {
                             self.usart.cr3.modify(|_, w| w.rxftie().set_bit())
                         },
+                        Event::ReceiverTimeout => {
+                            self.usart.cr1.modify(|_, w| w.rtoie().enabled())
+                        },
+                        Event::BreakDetected => {
+                            self.usart.cr2.modify(|_, w| w.lbdie().set_bit())
+                        },
                     }
                 }
 
@@ -852,6 +1176,12 @@ macro_rules! usart {
                         Event::Rxftie => {
                             self.usart.cr3.modify(|_, w| w.rxftie().clear_bit())
                         },
+                        Event::ReceiverTimeout => {
+                            self.usart.cr1.modify(|_, w| w.rtoie().disabled())
+                        },
+                        Event::BreakDetected => {
+                            self.usart.cr2.modify(|_, w| w.lbdie().clear_bit())
+                        },
                     }
                     let _ = self.usart.cr1.read();
                     let _ = self.usart.cr1.read(); // Delay 2 peripheral clocks
@@ -872,6 +1202,137 @@ macro_rules! usart {
                     let _ = self.usart.isr.read(); // Delay 2 peripheral clocks
                 }
 
+                /// Enables the receiver timeout
+                ///
+                /// The receiver timeout status is set when the receive line
+                /// has been idle for `bits` bit times after the last
+                /// character was received, counted from the end of its last
+                /// stop bit. The timeout is a 24-bit value.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `bits` does not fit in 24 bits
+                pub fn set_receiver_timeout(&mut self, bits: u32) {
+                    assert!(bits < (1 << 24), "Receiver timeout must fit in 24 bits");
+
+                    self.usart.rtor.modify(|_, w| w.rto().bits(bits));
+                    self.usart.cr2.modify(|_, w| w.rtoen().enabled());
+                }
+
+                /// Disables the receiver timeout
+                pub fn disable_receiver_timeout(&mut self) {
+                    self.usart.cr2.modify(|_, w| w.rtoen().disabled());
+                }
+
+                /// Return true if the receiver timeout status is set
+                ///
+                /// The receiver timeout status bit is set when the timeout
+                /// set by `set_receiver_timeout` has elapsed. The bit is
+                /// cleared by software, by calling
+                /// `clear_receiver_timeout()`.
+                pub fn is_receiver_timeout(&self) -> bool {
+                    unsafe { (*$USARTX::ptr()).isr.read().rtof().bit_is_set() }
+                }
+
+                /// Clear the receiver timeout status bit
+                pub fn clear_receiver_timeout(&mut self) {
+                    unsafe { (*$USARTX::ptr()).icr.write(|w| w.rtocf().set_bit()) }
+                    let _ = self.usart.isr.read();
+                    let _ = self.usart.isr.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Return true if the break detection flag is set
+                ///
+                /// The flag is only set when break detection is enabled
+                /// with [`Config::break_detection`](config::Config::break_detection),
+                /// and is cleared by calling `clear_break_detected()`.
+                pub fn is_break_detected(&self) -> bool {
+                    self.usart.isr.read().lbdf().bit_is_set()
+                }
+
+                /// Clear the break detection flag
+                pub fn clear_break_detected(&mut self) {
+                    self.usart.icr.write(|w| w.lbdcf().set_bit());
+                }
+
+                /// Sends a break character, after any data already written
+                /// has been transmitted
+                ///
+                /// The break is 13 bit times long when break detection is
+                /// enabled (LIN mode), and one frame long otherwise.
+                pub fn send_break(&mut self) {
+                    self.usart.rqr.write(|w| w.sbkrq().set_bit());
+                }
+
+                /// Puts the receiver in mute mode, until it is woken by the
+                /// wakeup method set with [`Config::mute_mode`](config::Config::mute_mode)
+                ///
+                /// Has no effect if mute mode is not enabled in the
+                /// configuration.
+                pub fn enter_mute_mode(&mut self) {
+                    self.usart.rqr.write(|w| w.mmrq().set_bit());
+                }
+
+                /// Return true if the receiver is in mute mode
+                pub fn is_muted(&self) -> bool {
+                    self.usart.isr.read().rwu().bit_is_set()
+                }
+
+                /// Enables the USART to wake the device from Stop mode on
+                /// `wakeup`, and enables the wakeup interrupt
+                ///
+                /// The USART is briefly disabled to change the wakeup
+                /// event. An address match uses the same address as mute
+                /// mode, which is replaced. The kernel clock must keep
+                /// running in Stop mode, see the [module level
+                /// documentation](crate::serial#stop-mode-wakeup).
+                ///
+                /// # Panics
+                ///
+                /// Panics if the address to match is not 7-bit
+                pub fn enable_stop_mode_wakeup(&mut self, wakeup: WakeupConfig) {
+                    self.usart.cr1.modify(|_, w| w.ue().disabled());
+
+                    // WUS and ADD can only be written whilst UE is cleared
+                    match wakeup {
+                        WakeupConfig::AddressMatch(address) => {
+                            assert!(address < 0x80, "Serial: wakeup address must be 7-bit");
+                            self.usart.cr2.modify(|_, w| w.addm7().bit7().add().bits(address));
+                            self.usart.cr3.modify(|_, w| w.wus().address());
+                        }
+                        WakeupConfig::StartBit => {
+                            self.usart.cr3.modify(|_, w| w.wus().start());
+                        }
+                        WakeupConfig::RxneSet => {
+                            self.usart.cr3.modify(|_, w| w.wus().rxne());
+                        }
+                    }
+                    self.usart.icr.write(|w| w.wucf().set_bit());
+                    self.usart.cr3.modify(|_, w| w.wufie().enabled());
+
+                    self.usart.cr1.modify(|_, w| w.uesm().enabled().ue().enabled());
+                }
+
+                /// Disables wakeup from Stop mode and the wakeup interrupt
+                pub fn disable_stop_mode_wakeup(&mut self) {
+                    self.usart.cr1.modify(|_, w| w.uesm().disabled());
+                    self.usart.cr3.modify(|_, w| w.wufie().disabled());
+                }
+
+                /// Return true if the wakeup flag is set
+                ///
+                /// The flag is set by the event selected with
+                /// `enable_stop_mode_wakeup`, and is cleared by calling
+                /// `clear_wakeup()`.
+                pub fn is_wakeup(&self) -> bool {
+                    self.usart.isr.read().wuf().bit_is_set()
+                }
+
+                /// Clear the wakeup flag
+                pub fn clear_wakeup(&mut self) {
+                    self.usart.icr.write(|w| w.wucf().set_bit());
+                }
+
                 /// Return true if the line busy status is set
                 ///
                 /// The busy status bit is set when there is communication active on the receive line,
@@ -996,10 +1457,17 @@ macro_rules! usart {
                         unsafe { (*$USARTX::ptr()).icr.write(|w| w.orecf().clear() );};
                         nb::Error::Other(Error::Overrun)
                     } else if isr.rxne().bit_is_set() {
+                        // NOTE(unsafe) atomic read with no side effects
+                        let cr1 = unsafe { (*$USARTX::ptr()).cr1.read() };
                         // NOTE(read_volatile) see `write_volatile` below
-                        return Ok(unsafe {
+                        let byte: u8 = unsafe {
                             ptr::read_volatile(&(*$USARTX::ptr()).rdr as *const _ as *const _)
-                        });
+                        };
+                        return Ok(byte & data_mask(
+                            cr1.m1().bit_is_set(),
+                            cr1.m0().bit_is_set(),
+                            cr1.pce().bit_is_set(),
+                        ));
                     } else {
                         nb::Error::WouldBlock
                     })
@@ -1050,6 +1518,38 @@ macro_rules! usart {
                     let _ = usart.isr.read(); // Delay 2 peripheral clocks
                 }
 
+                /// Return true if the receiver timeout status is set
+                ///
+                /// The receiver timeout is configured before splitting, with
+                /// [`Serial::set_receiver_timeout`]. The bit is cleared by
+                /// software, by calling `clear_receiver_timeout()`.
+                pub fn is_receiver_timeout(&self) -> bool {
+                    unsafe { (*$USARTX::ptr()).isr.read().rtof().bit_is_set() }
+                }
+
+                /// Clear the receiver timeout status bit
+                pub fn clear_receiver_timeout(&mut self) {
+                    let usart = unsafe { &*$USARTX::ptr() };
+                    usart.icr.write(|w| w.rtocf().set_bit());
+                    let _ = usart.isr.read();
+                    let _ = usart.isr.read(); // Delay 2 peripheral clocks
+                }
+
+                /// Puts the receiver in mute mode, until it is woken by the
+                /// wakeup method set with [`Config::mute_mode`](config::Config::mute_mode)
+                ///
+                /// Has no effect if mute mode is not enabled in the
+                /// configuration.
+                pub fn enter_mute_mode(&mut self) {
+                    // unsafe: mute mode request accessed by Rx part only
+                    unsafe { &*$USARTX::ptr() }.rqr.write(|w| w.mmrq().set_bit());
+                }
+
+                /// Return true if the receiver is in mute mode
+                pub fn is_muted(&self) -> bool {
+                    unsafe { (*$USARTX::ptr()).isr.read().rwu().bit_is_set() }
+                }
+
                 /// Return true if the line busy status is set
                 ///
                 /// The busy status bit is set when there is communication active on the receive line,
@@ -1142,6 +1642,13 @@ macro_rules! usart {
                     let _ = cr1.read(); // Delay 2 peripheral clocks
                 }
 
+                /// Sends a break character, after any data already written
+                /// has been transmitted
+                pub fn send_break(&mut self) {
+                    // unsafe: sbkrq bit requested by Tx part only
+                    unsafe { &*$USARTX::ptr() }.rqr.write(|w| w.sbkrq().set_bit());
+                }
+
                 /// Enables the Tx DMA stream.
                 pub fn enable_dma_tx(&mut self) {
                     // unsafe: dmat bit accessed by Tx part only
@@ -1242,6 +1749,16 @@ usart! {
     USART10: (usart10, Usart10, pclk2, synchronous),
 }
 
+// Declared after the macros above, so that the inherent impls of `Rx` exist
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub use asynch::{Instance, RxAsync};
+
+pub mod smartcard;
+pub use smartcard::SmartCard;
+
 #[cfg(any(feature = "rm0433", feature = "rm0399"))]
 usart_sel! {
     d2ccip2r, USART16SEL_A, usart16sel, RccPclk2, pclk2;
@@ -1314,3 +1831,148 @@ where
         Ok(())
     }
 }
+
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Error for Error {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Error::Overrun | Error::Dma => embedded_io::ErrorKind::Other,
+            Error::Timeout => embedded_io::ErrorKind::TimedOut,
+            _ => embedded_io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+#[cfg(feature = "embedded-io")]
+mod io {
+    use super::{Error, Rx, Serial, Tx};
+    use embedded_hal::serial;
+    use nb::block;
+
+    /// Reads bytes into `buf` until the receiver would block, after waiting
+    /// for the first byte
+    fn read<R>(rx: &mut R, buf: &mut [u8]) -> Result<usize, Error>
+    where
+        R: serial::Read<u8, Error = Error>,
+    {
+        let Some((first, rest)) = buf.split_first_mut() else {
+            return Ok(0);
+        };
+        *first = block!(rx.read())?;
+
+        let mut n = 1;
+        for byte in rest {
+            match rx.read() {
+                Ok(b) => *byte = b,
+                Err(nb::Error::WouldBlock) => break,
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+            n += 1;
+        }
+        Ok(n)
+    }
+
+    /// Writes bytes from `buf` until the transmitter would block, after
+    /// waiting to write the first byte
+    fn write<W>(tx: &mut W, buf: &[u8]) -> usize
+    where
+        W: serial::Write<u8, Error = core::convert::Infallible>,
+    {
+        let Some((first, rest)) = buf.split_first() else {
+            return 0;
+        };
+        block!(tx.write(*first)).ok();
+
+        1 + rest
+            .iter()
+            .take_while(|byte| tx.write(**byte).is_ok())
+            .count()
+    }
+
+    impl<USART> embedded_io::ErrorType for Serial<USART> {
+        type Error = Error;
+    }
+
+    impl<USART> embedded_io::ErrorType for Rx<USART> {
+        type Error = Error;
+    }
+
+    impl<USART> embedded_io::ErrorType for Tx<USART> {
+        type Error = Error;
+    }
+
+    impl<USART> embedded_io::Read for Serial<USART>
+    where
+        Serial<USART>: serial::Read<u8, Error = Error>,
+    {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            read(self, buf)
+        }
+    }
+
+    impl<USART> embedded_io::Read for Rx<USART>
+    where
+        Rx<USART>: serial::Read<u8, Error = Error>,
+    {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+            read(self, buf)
+        }
+    }
+
+    impl<USART> embedded_io::Write for Serial<USART>
+    where
+        Serial<USART>: serial::Write<u8, Error = core::convert::Infallible>,
+    {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            Ok(write(self, buf))
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            block!(serial::Write::flush(self)).ok();
+            Ok(())
+        }
+    }
+
+    impl<USART> embedded_io::Write for Tx<USART>
+    where
+        Tx<USART>: serial::Write<u8, Error = core::convert::Infallible>,
+    {
+        fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+            Ok(write(self, buf))
+        }
+
+        fn flush(&mut self) -> Result<(), Error> {
+            block!(serial::Write::flush(self)).ok();
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::config::{BreakLength, Config, StopBits, WordLength};
+    use super::data_mask;
+
+    #[test]
+    fn seven_bit_words() {
+        let config = Config::default().word_length(WordLength::SevenBit);
+        assert!(config.validate().is_err());
+        assert!(config.parity_even().validate().is_ok());
+    }
+
+    #[test]
+    fn break_detection() {
+        let config = Config::default().break_detection(BreakLength::Bits11);
+        assert!(config.validate().is_ok());
+        assert!(config.stopbits(StopBits::Stop2).validate().is_err());
+        assert!(config.halfduplex(true).validate().is_err());
+    }
+
+    #[test]
+    fn data_bits() {
+        assert_eq!(data_mask(true, false, true), 0x3F);
+        assert_eq!(data_mask(false, false, true), 0x7F);
+        assert_eq!(data_mask(false, true, true), 0xFF);
+        assert_eq!(data_mask(false, false, false), 0xFF);
+    }
+}
@@ -0,0 +1,281 @@
+//! Async serial receiver using DMA1 or DMA2
+
+use core::cmp;
+use core::future::poll_fn;
+use core::task::{Context, Poll};
+
+use atomic_waker::AtomicWaker;
+
+use super::{Error, Rx, Serial};
+use crate::dma::dma::{start_async_transfer, AsyncStream};
+use crate::dma::traits::TargetAddress;
+use crate::dma::PeripheralToMemory;
+use crate::stm32::{usart1, UART4, UART5, UART7, UART8};
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
+use crate::stm32::{UART9, USART10};
+use crate::stm32::{USART1, USART2, USART3, USART6};
+use crate::Sealed;
+
+type P2M = PeripheralToMemory;
+
+/// Maximum number of bytes in a single DMA transfer
+const MAX_TRANSFER: usize = u16::MAX as usize;
+
+/// Wakers for the tasks waiting on each USART or UART
+static WAKERS: [AtomicWaker; 10] = {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const WAKER: AtomicWaker = AtomicWaker::new();
+    [WAKER; 10]
+};
+
+/// A USART or UART that can receive with DMA1 or DMA2
+pub trait Instance: Sealed {
+    #[doc(hidden)]
+    fn ptr() -> *const usart1::RegisterBlock;
+    #[doc(hidden)]
+    fn waker() -> &'static AtomicWaker;
+}
+
+macro_rules! instance {
+    ($($USARTX:ident: $index:expr,)+) => {
+        $(
+            impl Sealed for $USARTX {}
+            impl Instance for $USARTX {
+                fn ptr() -> *const usart1::RegisterBlock {
+                    $USARTX::ptr() as *const usart1::RegisterBlock
+                }
+                fn waker() -> &'static AtomicWaker {
+                    &WAKERS[$index]
+                }
+            }
+        )+
+    };
+}
+
+instance! {
+    USART1: 0,
+    USART2: 1,
+    USART3: 2,
+    USART6: 3,
+    UART4: 4,
+    UART5: 5,
+    UART7: 6,
+    UART8: 7,
+}
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
+instance! {
+    UART9: 8,
+    USART10: 9,
+}
+
+fn regs<USART: Instance>() -> &'static usart1::RegisterBlock {
+    // unsafe: only the Rx part accesses the Rx interrupt enables, the Rx DMA
+    // enable and the Rx status flags
+    unsafe { &*USART::ptr() }
+}
+
+impl<USART: Instance> Rx<USART> {
+    /// Handles the USART interrupt for [`RxAsync`] and
+    /// [`wait_for_wakeup`](Serial::wait_for_wakeup), by disabling the idle
+    /// line, error and wakeup interrupts and waking the task waiting on this
+    /// receiver. Call this from the `USARTx` or `UARTx` interrupt handler
+    pub fn on_interrupt() {
+        let usart = regs::<USART>();
+        usart.cr1.modify(|_, w| w.idleie().disabled());
+        usart
+            .cr3
+            .modify(|_, w| w.eie().disabled().wufie().disabled());
+
+        USART::waker().wake();
+    }
+}
+
+impl<USART: Instance> Serial<USART> {
+    /// Waits until the wakeup flag is set by the event selected with
+    /// [`enable_stop_mode_wakeup`](Serial#method.enable_stop_mode_wakeup),
+    /// and then clears it
+    ///
+    /// The interrupt handler for the USART must call
+    /// [`Rx::on_interrupt`](Rx::on_interrupt).
+    pub async fn wait_for_wakeup(&mut self) {
+        poll_fn(|cx| {
+            USART::waker().register(cx.waker());
+
+            let usart = regs::<USART>();
+            if usart.isr.read().wuf().bit_is_set() {
+                usart.icr.write(|w| w.wucf().set_bit());
+                return Poll::Ready(());
+            }
+
+            // If the flag is set before the interrupt is enabled, the
+            // interrupt is taken immediately
+            usart.cr3.modify(|_, w| w.wufie().enabled());
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+/// Serial receiver implementing the [embedded-io-async] `Read` trait, with
+/// the data transferred by DMA
+///
+/// Each read starts a DMA transfer into the buffer, and completes when the
+/// buffer is full, or when the receive line becomes idle after at least one
+/// byte has been received. The interrupt handler for the stream must call
+/// [`on_interrupt`](AsyncStream::on_interrupt), and the interrupt handler
+/// for the USART must call [`Rx::on_interrupt`](Rx::on_interrupt).
+///
+/// The buffers passed to each read must be in memory that is accessible by
+/// DMA1 and DMA2, and must not be cached by the data cache.
+///
+/// [embedded-io-async]: https://docs.rs/embedded-io-async
+pub struct RxAsync<USART, STREAM> {
+    rx: Rx<USART>,
+    stream: STREAM,
+}
+
+impl<USART, STREAM> RxAsync<USART, STREAM>
+where
+    USART: Instance,
+    Rx<USART>: TargetAddress<P2M, MemSize = u8>,
+    STREAM: AsyncStream,
+{
+    /// Creates an async receiver from a serial receiver and a DMA stream
+    pub fn new(rx: Rx<USART>, stream: STREAM) -> Self {
+        RxAsync { rx, stream }
+    }
+
+    /// Releases the receiver and the DMA stream
+    pub fn free(self) -> (Rx<USART>, STREAM) {
+        (self.rx, self.stream)
+    }
+
+    /// Starts receiving `len` bytes into `memory`
+    fn start(&mut self, memory: usize, len: u16) {
+        let usart = regs::<USART>();
+
+        // The line may have been idle since the end of the last read
+        usart.icr.write(|w| w.idlecf().set_bit());
+
+        // unsafe: the memory address is valid until the stream is disabled by
+        // `stop`
+        unsafe {
+            start_async_transfer::<_, P2M, _>(
+                &mut self.stream,
+                &self.rx,
+                memory,
+                true,
+                len,
+            );
+        }
+
+        usart.cr3.modify(|_, w| w.dmar().set_bit());
+    }
+
+    /// Aborts any ongoing transfer and disables the Rx DMA request and
+    /// interrupts
+    fn stop(&mut self) {
+        self.stream.disable();
+
+        let usart = regs::<USART>();
+        usart.cr1.modify(|_, w| w.idleie().disabled());
+        usart
+            .cr3
+            .modify(|_, w| w.dmar().clear_bit().eie().disabled());
+    }
+
+    /// Polls for the end of a transfer of `len` bytes, which ends early once
+    /// the line is idle
+    fn poll_complete(
+        &mut self,
+        cx: &mut Context<'_>,
+        len: u16,
+    ) -> Poll<Result<(), Error>> {
+        USART::waker().register(cx.waker());
+
+        match self.stream.poll_transfer(cx) {
+            Poll::Ready(true) => return Poll::Ready(Ok(())),
+            Poll::Ready(false) => return Poll::Ready(Err(Error::Dma)),
+            Poll::Pending => {}
+        }
+
+        let usart = regs::<USART>();
+        let isr = usart.isr.read();
+        if isr.pe().bit_is_set() {
+            usart.icr.write(|w| w.pecf().clear());
+            return Poll::Ready(Err(Error::Parity));
+        } else if isr.fe().bit_is_set() {
+            usart.icr.write(|w| w.fecf().clear());
+            return Poll::Ready(Err(Error::Framing));
+        } else if isr.nf().bit_is_set() {
+            usart.icr.write(|w| w.ncf().clear());
+            return Poll::Ready(Err(Error::Noise));
+        } else if isr.ore().bit_is_set() {
+            usart.icr.write(|w| w.orecf().clear());
+            return Poll::Ready(Err(Error::Overrun));
+        }
+
+        if isr.idle().bit_is_set() {
+            usart.icr.write(|w| w.idlecf().set_bit());
+
+            if STREAM::get_number_of_transfers() < len {
+                return Poll::Ready(Ok(()));
+            }
+        }
+
+        // If the line becomes idle before the interrupts are enabled, the
+        // flag is already set and the interrupt is taken immediately
+        usart.cr1.modify(|_, w| w.idleie().enabled());
+        usart.cr3.modify(|_, w| w.eie().enabled());
+        Poll::Pending
+    }
+}
+
+/// Stops the ongoing transfer when dropped
+struct Transaction<'a, USART, STREAM>(&'a mut RxAsync<USART, STREAM>)
+where
+    USART: Instance,
+    Rx<USART>: TargetAddress<P2M, MemSize = u8>,
+    STREAM: AsyncStream;
+
+impl<'a, USART, STREAM> Drop for Transaction<'a, USART, STREAM>
+where
+    USART: Instance,
+    Rx<USART>: TargetAddress<P2M, MemSize = u8>,
+    STREAM: AsyncStream,
+{
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+impl<USART, STREAM> embedded_io_async::ErrorType for RxAsync<USART, STREAM> {
+    type Error = Error;
+}
+
+impl<USART, STREAM> embedded_io_async::Read for RxAsync<USART, STREAM>
+where
+    USART: Instance,
+    Rx<USART>: TargetAddress<P2M, MemSize = u8>,
+    STREAM: AsyncStream,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let len = cmp::min(buf.len(), MAX_TRANSFER) as u16;
+        self.start(buf.as_mut_ptr() as usize, len);
+
+        // The transfer is stopped when the guard is dropped, including when
+        // this future is dropped before it completes
+        let transaction = Transaction(&mut *self);
+        let result = poll_fn(|cx| transaction.0.poll_complete(cx, len)).await;
+        drop(transaction);
+        result?;
+
+        // The number of remaining transfers is kept when the stream is
+        // disabled, and includes any bytes received after the idle line
+        Ok(usize::from(len - STREAM::get_number_of_transfers()))
+    }
+}
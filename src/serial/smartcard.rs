@@ -0,0 +1,382 @@
+//! ISO 7816 smartcard mode
+//!
+//! The synchronous USARTs can communicate with a smartcard. The USART
+//! supplies the card clock on its CK pin, and the card's single I/O line is
+//! connected to the TX pin, configured as open drain. The card is reset by
+//! a general purpose output connected to its RST contact.
+//!
+//! Characters are transmitted at the default ISO 7816-3 rate of one bit per
+//! 372 card clock cycles, as 8 data bits with even parity and 1.5 stop bits.
+//!
+//! ```
+//! let tx = gpioa.pa9.into_alternate::<7>().set_open_drain();
+//! let rx = gpioa.pa10.into_alternate::<7>();
+//! let ck = gpioa.pa8.into_alternate::<7>();
+//! let rst = gpioa.pa11.into_push_pull_output();
+//!
+//! let serial = dp.USART1.serial((tx, rx), 9600.bps(), ccdr.peripheral.USART1, &ccdr.clocks)?;
+//!
+//! let config = smartcard::Config::new(4.MHz()).guard_time(2).retries(3);
+//! let mut card = SmartCard::new(serial, ck, rst, config)?;
+//!
+//! card.send_atr_request(&mut delay);
+//! let mut atr = [0; 33];
+//! let n = card.read_response(&mut atr, &mut delay)?;
+//! ```
+
+use core::ops::Deref;
+
+use embedded_hal::blocking::delay::DelayUs;
+use embedded_hal::digital::v2::OutputPin;
+use embedded_hal::serial;
+
+use super::config::InvalidConfig;
+use super::{Error, PinCk, Serial};
+use crate::stm32::usart1;
+use crate::stm32::usart1::cr1::{M0_A as M0, PCE_A as PCE, PS_A as PS};
+use crate::stm32::usart1::cr2::STOP_A as STOP;
+use crate::time::Hertz;
+
+/// Number of card clock cycles in one elementary time unit (ETU), for the
+/// default clock rate conversion and baud rate adjustment factors
+const CLOCKS_PER_ETU: u32 = 372;
+
+/// Maximum time between the leading edges of two characters of the answer
+/// to reset, in ETU
+const ATR_WAITING_TIME: u32 = 9600;
+
+/// Maximum number of card clock cycles between the release of the reset and
+/// the leading edge of the first character of the answer to reset
+const ATR_START_CLOCKS: u32 = 40_000;
+
+/// Maximum time between the leading edge of the last character sent to the
+/// card and the leading edge of its response, in ETU. This is the default
+/// waiting time, with a waiting integer of 10
+const WAITING_TIME: u32 = 9600;
+
+/// Time from the leading edge of a character until it has been received,
+/// in ETU. The character and its 1.5 stop bits take 11.5 ETU
+const CHARACTER_TIME: u32 = 12;
+
+/// Minimum number of card clock cycles that the reset must be held low
+const RESET_CLOCKS: u32 = 400;
+
+/// Smartcard configuration
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    pub clock: Hertz,
+    pub guard_time: u8,
+    pub nack: bool,
+    pub retries: u8,
+}
+
+impl Config {
+    /// Creates a configuration for a card clock of at most `clock`, with no
+    /// extra guard time, NACK enabled and no retransmissions
+    pub fn new(clock: Hertz) -> Self {
+        Config {
+            clock,
+            guard_time: 0,
+            nack: true,
+            retries: 0,
+        }
+    }
+
+    /// Extra guard time after each transmitted character, in ETU
+    pub fn guard_time(mut self, etu: u8) -> Self {
+        self.guard_time = etu;
+        self
+    }
+
+    /// Enables or disables sending a NACK when a parity error is received
+    pub fn nack(mut self, nack: bool) -> Self {
+        self.nack = nack;
+        self
+    }
+
+    /// Number of times a character is retransmitted after the card signals
+    /// a parity error, and the number of parity errors signalled to the
+    /// card before the received character is accepted. At most 7
+    pub fn retries(mut self, retries: u8) -> Self {
+        self.retries = retries;
+        self
+    }
+}
+
+impl From<Hertz> for Config {
+    fn from(clock: Hertz) -> Config {
+        Config::new(clock)
+    }
+}
+
+/// Returns the value of the GTPR.PSC field that gives the fastest card
+/// clock not faster than `clock`, or `None` if it cannot be divided from
+/// `ker_ck`
+fn clock_prescaler(ker_ck: u32, clock: u32) -> Option<u8> {
+    if clock == 0 {
+        return None;
+    }
+    // The kernel clock is divided by 2 * PSC
+    let psc = (ker_ck + 2 * clock - 1) / (2 * clock);
+    match psc {
+        0 => Some(1),
+        1..=31 => Some(psc as u8),
+        _ => None,
+    }
+}
+
+/// Returns the time to wait for the first character of a response, in
+/// microseconds, for a card clock of `clock`
+///
+/// `atr` is true if the response is the answer to reset.
+fn first_character_timeout_us(clock: u32, atr: bool) -> u32 {
+    let clocks = if atr {
+        ATR_START_CLOCKS
+    } else {
+        WAITING_TIME * CLOCKS_PER_ETU
+    } + CHARACTER_TIME * CLOCKS_PER_ETU;
+    let clock = u64::from(clock);
+    ((u64::from(clocks) * 1_000_000 + clock - 1) / clock) as u32
+}
+
+/// A USART in ISO 7816 smartcard mode
+pub struct SmartCard<USART, CK, RST> {
+    serial: Serial<USART>,
+    ck: CK,
+    rst: RST,
+    clock: Hertz,
+    /// The answer to reset is expected
+    atr: bool,
+}
+
+impl<USART, CK, RST> SmartCard<USART, CK, RST>
+where
+    USART: Deref<Target = usart1::RegisterBlock>,
+    CK: PinCk<USART>,
+    RST: OutputPin,
+    Serial<USART>: serial::Read<u8, Error = Error>,
+{
+    /// Switches `serial` to smartcard mode, supplying the card clock on `ck`
+    /// and holding the card in reset with `rst`
+    ///
+    /// The frame format and baud rate of `serial` are replaced. Returns an
+    /// error if the card clock cannot be divided from the USART kernel
+    /// clock, or if more than 7 retries are configured.
+    pub fn new(
+        serial: Serial<USART>,
+        ck: CK,
+        mut rst: RST,
+        config: impl Into<Config>,
+    ) -> Result<Self, InvalidConfig> {
+        let config = config.into();
+        let psc = clock_prescaler(serial.ker_ck.raw(), config.clock.raw())
+            .ok_or(InvalidConfig)?;
+        if config.retries > 7 {
+            return Err(InvalidConfig);
+        }
+
+        rst.set_low().ok();
+
+        let usart = &serial.usart;
+        usart.cr1.modify(|_, w| w.ue().disabled());
+
+        // One ETU is 372 card clock cycles
+        let brr = 2 * u32::from(psc) * CLOCKS_PER_ETU;
+        usart.brr.write(|w| w.brr().bits(brr as u16));
+
+        usart
+            .gtpr
+            .write(|w| w.psc().bits(psc).gt().bits(config.guard_time));
+
+        // 1.5 stop bits, continuous card clock
+        usart.cr2.modify(|_, w| {
+            w.linen()
+                .clear_bit()
+                .stop()
+                .variant(STOP::Stop1p5)
+                .clken()
+                .set_bit()
+        });
+
+        // Half duplex and IrDA must be disabled in smartcard mode
+        usart.cr3.modify(|_, w| {
+            w.hdsel()
+                .clear_bit()
+                .iren()
+                .clear_bit()
+                .scarcnt()
+                .bits(config.retries)
+                .nack()
+                .bit(config.nack)
+                .scen()
+                .set_bit()
+        });
+
+        // 8 data bits with even parity
+        usart.cr1.modify(|_, w| {
+            w.m1()
+                .clear_bit()
+                .m0()
+                .variant(M0::Bit9)
+                .pce()
+                .variant(PCE::Enabled)
+                .ps()
+                .variant(PS::Even)
+                .ue()
+                .enabled()
+                .te()
+                .enabled()
+                .re()
+                .enabled()
+        });
+
+        let clock = serial.ker_ck / (2 * u32::from(psc));
+
+        Ok(SmartCard {
+            serial,
+            ck,
+            rst,
+            clock,
+            atr: false,
+        })
+    }
+
+    /// Returns the frequency of the card clock
+    pub fn clock(&self) -> Hertz {
+        self.clock
+    }
+
+    /// Performs a cold reset of the card, after which the card sends its
+    /// answer to reset (ATR)
+    ///
+    /// The reset is held low for at least 400 card clock cycles. Any
+    /// characters already received are discarded, so that the answer can
+    /// be read with [`read_response`](Self::read_response).
+    pub fn send_atr_request<D>(&mut self, delay: &mut D)
+    where
+        D: DelayUs<u32>,
+    {
+        self.rst.set_low().ok();
+        let clock = self.clock.raw();
+        let reset_us = (RESET_CLOCKS * 1_000_000 + clock - 1) / clock;
+        delay.delay_us(reset_us);
+
+        let usart = &self.serial.usart;
+        usart.rqr.write(|w| w.rxfrq().set_bit());
+        usart.icr.write(|w| {
+            w.pecf()
+                .clear()
+                .fecf()
+                .clear()
+                .ncf()
+                .clear()
+                .orecf()
+                .clear()
+                .rtocf()
+                .clear()
+        });
+        usart.rtor.modify(|_, w| w.rto().bits(ATR_WAITING_TIME));
+        usart.cr2.modify(|_, w| w.rtoen().enabled());
+
+        self.rst.set_high().ok();
+        self.atr = true;
+    }
+
+    /// Reads a response from the card into `buf`, and returns the number of
+    /// characters read
+    ///
+    /// The response ends when `buf` is full, or when no character has been
+    /// received for 9600 ETU.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Timeout`] if the first character is not received in
+    /// time. After [`send_atr_request`](Self::send_atr_request) the answer to
+    /// reset must start within 40 000 card clock cycles, and otherwise the
+    /// response must start within 9600 ETU. The wait is timed with `delay`.
+    pub fn read_response<D>(
+        &mut self,
+        buf: &mut [u8],
+        delay: &mut D,
+    ) -> Result<usize, Error>
+    where
+        D: DelayUs<u32>,
+    {
+        let mut timeout_us =
+            first_character_timeout_us(self.clock.raw(), self.atr);
+        self.atr = false;
+
+        let mut n = 0;
+        while n < buf.len() {
+            match serial::Read::read(&mut self.serial) {
+                Ok(byte) => {
+                    buf[n] = byte;
+                    n += 1;
+                }
+                Err(nb::Error::WouldBlock) if n == 0 => {
+                    if timeout_us == 0 {
+                        return Err(Error::Timeout);
+                    }
+                    delay.delay_us(1);
+                    timeout_us -= 1;
+                }
+                Err(nb::Error::WouldBlock) => {
+                    let usart = &self.serial.usart;
+                    if usart.isr.read().rtof().bit_is_set() {
+                        usart.icr.write(|w| w.rtocf().clear());
+                        break;
+                    }
+                }
+                Err(nb::Error::Other(e)) => return Err(e),
+            }
+        }
+        Ok(n)
+    }
+
+    /// Disables smartcard mode and returns the serial port, the clock pin
+    /// and the reset pin
+    ///
+    /// The card is held in reset. The serial port must be reconfigured
+    /// before it is used again.
+    pub fn free(mut self) -> (Serial<USART>, CK, RST) {
+        self.rst.set_low().ok();
+
+        let usart = &self.serial.usart;
+        usart.cr1.modify(|_, w| w.ue().disabled());
+        usart
+            .cr3
+            .modify(|_, w| w.scen().clear_bit().nack().clear_bit());
+        usart
+            .cr2
+            .modify(|_, w| w.clken().clear_bit().rtoen().disabled());
+
+        (self.serial, self.ck, self.rst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prescaler() {
+        // 100MHz kernel clock, 5MHz maximum card clock
+        assert_eq!(clock_prescaler(100_000_000, 5_000_000), Some(10));
+        // Rounded up, so that the card clock is not exceeded
+        assert_eq!(clock_prescaler(100_000_000, 4_000_000), Some(13));
+        assert_eq!(clock_prescaler(8_000_000, 10_000_000), Some(1));
+        assert_eq!(clock_prescaler(200_000_000, 1_000_000), None);
+        assert_eq!(clock_prescaler(100_000_000, 0), None);
+    }
+
+    #[test]
+    fn first_character_timeout() {
+        // 40 000 clocks and one character of 12 * 372 clocks at 4MHz
+        assert_eq!(first_character_timeout_us(4_000_000, true), 11_116);
+        // 9612 ETU at 1MHz
+        assert_eq!(first_character_timeout_us(1_000_000, false), 3_575_664);
+        // Rounded up
+        assert_eq!(first_character_timeout_us(3_000_000, true), 14_822);
+    }
+}
@@ -56,6 +56,16 @@ impl VREFIN_CAL {
     }
 }
 
+/// Temperature in °C of the [`TS_CAL_30`] factory calibration
+pub const TS_CAL1_TEMP: i32 = 30;
+
+/// Temperature in °C of the [`TS_CAL_110`] factory calibration
+#[cfg(any(feature = "rm0433", feature = "rm0399"))]
+pub const TS_CAL2_TEMP: i32 = 110;
+/// Temperature in °C of the [`TS_CAL_110`] factory calibration
+#[cfg(any(feature = "rm0455", feature = "rm0468"))]
+pub const TS_CAL2_TEMP: i32 = 130;
+
 /// A temperature reading taken at 30°C stored at the factory
 pub struct TS_CAL_30;
 
@@ -71,7 +81,10 @@ impl TS_CAL_30 {
     }
 }
 
-/// A temperature reading taken at 110°C stored at the factory
+/// A temperature reading taken at [`TS_CAL2_TEMP`] stored at the factory
+///
+/// On RM0433 and RM0399 parts this reading is taken at 110°C, on RM0455 and
+/// RM0468 parts it is taken at 130°C.
 pub struct TS_CAL_110;
 
 #[cfg(not(feature = "rm0455"))]
@@ -0,0 +1,445 @@
+//! S/PDIF Receiver (SPDIFRX)
+//!
+//! The SPDIFRX receives S/PDIF (IEC-60958) or AES3 digital audio from one of
+//! four inputs. The receiver synchronises to the incoming symbol rate, then
+//! provides each audio sample in the data register together with its parity,
+//! validity, user and channel status bits. The channel status and user data
+//! of each frame are also collected separately for channel A.
+//!
+//! ```
+//! use stm32h7xx_hal::spdifrx::Spdifrx;
+//!
+//! let pin = gpiod.pd7.into_alternate();
+//! let mut spdifrx =
+//!     Spdifrx::new(dp.SPDIFRX, ccdr.peripheral.SPDIFRX, &ccdr.clocks, pin)
+//!         .unwrap();
+//!
+//! let frame = spdifrx.read_frame().unwrap();
+//! let sample = frame.sample();
+//! ```
+//!
+//! # Kernel clock
+//!
+//! The kernel clock must be at least 704 times the audio sampling rate, and is
+//! typically set to PLL1_Q or PLL2_R with the `kernel_clk_mux` method of
+//! `ccdr.peripheral.SPDIFRX`.
+//!
+//! # DMA
+//!
+//! Audio samples can be received by DMA1 or DMA2 with
+//! [`read_dma`](Spdifrx::read_dma), which returns a [`SpdifrxStream`].
+//!
+//! ```
+//! let mut stream = spdifrx.read_dma(streams.0, buffer);
+//! stream.wait().unwrap();
+//! let (spdifrx, dma_stream, buffer) = stream.free();
+//! ```
+
+use core::marker::PhantomData;
+
+use crate::dma::{
+    dma::DmaConfig,
+    traits::{DoubleBufferedStream, Stream},
+    DBTransfer, PeripheralToMemory, Transfer,
+};
+use crate::gpio::{self, Alternate};
+use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::stm32::SPDIFRX;
+
+#[cfg(not(feature = "rm0455"))]
+use crate::rcc::rec::SpdifClkSel;
+#[cfg(feature = "rm0455")]
+use crate::rcc::rec::SpdifrxClkSel as SpdifClkSel;
+
+// The data register has a different name on RM0468 parts
+#[cfg(not(feature = "rm0468"))]
+macro_rules! dr_reg {
+    ($rb:expr) => {
+        $rb.dr_00()
+    };
+}
+#[cfg(feature = "rm0468")]
+macro_rules! dr_reg {
+    ($rb:expr) => {
+        $rb.fmt0_dr
+    };
+}
+
+/// SPDIFRX error
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum SpdifrxError {
+    /// A parity error was detected in a received frame
+    Parity,
+    /// A sample was received before the previous one was read
+    Overrun,
+    /// The receiver detected a framing error, and stopped
+    Framing,
+    /// The receiver could not synchronise to the input within the allowed
+    /// number of retries, and stopped
+    Synchronisation,
+    /// The time between two transitions was too long, and the receiver
+    /// stopped
+    Timeout,
+}
+
+/// Input pin for the SPDIFRX. `INPUT` is the number N of the SPDIFRX_INN
+/// signal
+pub trait SpdifrxPin {
+    const INPUT: u8;
+}
+
+macro_rules! pins {
+    ($($INPUT:literal: [$($PIN:ty),*])+) => {
+        $(
+            $(
+                impl SpdifrxPin for $PIN {
+                    const INPUT: u8 = $INPUT;
+                }
+            )*
+        )+
+    }
+}
+
+pins! {
+    1: [gpio::PD7<Alternate<9>>, gpio::PG11<Alternate<8>>]
+    2: [gpio::PD8<Alternate<9>>, gpio::PG12<Alternate<8>>]
+    3: [gpio::PC4<Alternate<9>>, gpio::PG8<Alternate<8>>]
+    4: [gpio::PC5<Alternate<9>>, gpio::PG9<Alternate<8>>]
+}
+
+/// A single sub-frame received by the SPDIFRX
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SpdifrxFrame {
+    /// Contents of the data register. The audio sample is in bits 23:0, the
+    /// parity error, validity, user, channel status and preamble type bits
+    /// are in bits 29:24
+    pub data: u32,
+    /// Contents of the status register when the sample was read
+    pub status: u32,
+    /// Contents of the channel status register, if new channel status and
+    /// user data was available
+    pub channel_status: Option<u32>,
+}
+
+impl SpdifrxFrame {
+    /// The 24-bit audio sample, sign extended
+    pub fn sample(&self) -> i32 {
+        ((self.data << 8) as i32) >> 8
+    }
+
+    /// Returns true if the parity of the sub-frame was incorrect
+    pub fn parity_error(&self) -> bool {
+        self.data & (1 << 24) != 0
+    }
+
+    /// The validity bit. This is clear if the sample is valid
+    pub fn validity(&self) -> bool {
+        self.data & (1 << 25) != 0
+    }
+
+    /// The user data bit
+    pub fn user(&self) -> bool {
+        self.data & (1 << 26) != 0
+    }
+
+    /// The channel status bit
+    pub fn channel_status_bit(&self) -> bool {
+        self.data & (1 << 27) != 0
+    }
+
+    /// Returns true if this sub-frame starts a block (preamble B)
+    pub fn is_block_start(&self) -> bool {
+        (self.data >> 28) & 0b11 == 0b01
+    }
+
+    /// Returns true if this sub-frame is from channel A (preambles B and M),
+    /// false if it is from channel B (preamble W)
+    pub fn is_channel_a(&self) -> bool {
+        (self.data >> 28) & 0b11 != 0b11
+    }
+}
+
+/// S/PDIF Receiver
+pub struct Spdifrx {
+    rb: SPDIFRX,
+}
+
+impl Spdifrx {
+    /// Enables the SPDIFRX on the input `pin`, then synchronises to the
+    /// incoming signal and waits for the start of a block. Returns once the
+    /// SPDIFRX is receiving data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the SPDIFRX cannot synchronise to the input. In
+    /// this case the SPDIFRX is disabled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the kernel clock is not running
+    pub fn new<PIN>(
+        spdifrx: SPDIFRX,
+        prec: rec::Spdifrx,
+        clocks: &CoreClocks,
+        _pin: PIN,
+    ) -> Result<Self, SpdifrxError>
+    where
+        PIN: SpdifrxPin,
+    {
+        let _ = match prec.get_kernel_clk_mux() {
+            SpdifClkSel::Pll1Q => {
+                clocks.pll1_q_ck().expect("SPDIFRX: PLL1_Q must be enabled")
+            }
+            SpdifClkSel::Pll2R => {
+                clocks.pll2_r_ck().expect("SPDIFRX: PLL2_R must be enabled")
+            }
+            SpdifClkSel::Pll3R => {
+                clocks.pll3_r_ck().expect("SPDIFRX: PLL3_R must be enabled")
+            }
+            SpdifClkSel::HsiKer => {
+                clocks.hsi_ck().expect("SPDIFRX: HSI must be enabled")
+            }
+        };
+
+        prec.enable().reset();
+
+        // Stereo, data right aligned with all status bits, channel status
+        // from channel A, 63 synchronisation retries
+        spdifrx.cr.write(|w| unsafe {
+            w.insel()
+                .bits(PIN::INPUT - 1)
+                .rxsteo()
+                .set_bit()
+                .drfmt()
+                .bits(0b00)
+                .chsel()
+                .clear_bit()
+                .nbtr()
+                .bits(0b11)
+        });
+
+        let mut spdifrx = Spdifrx { rb: spdifrx };
+        match spdifrx.synchronise() {
+            Ok(()) => Ok(spdifrx),
+            Err(e) => {
+                spdifrx.set_state(0b00);
+                Err(e)
+            }
+        }
+    }
+
+    fn set_state(&mut self, state: u8) {
+        self.rb
+            .cr
+            .modify(|_, w| unsafe { w.spdifrxen().bits(state) });
+    }
+
+    /// Returns the error flagged in the status register, if any. Parity and
+    /// overrun errors are cleared
+    fn check_errors(&mut self) -> Result<(), SpdifrxError> {
+        let sr = self.rb.sr.read();
+
+        if sr.serr().bit_is_set() {
+            Err(SpdifrxError::Synchronisation)
+        } else if sr.terr().bit_is_set() {
+            Err(SpdifrxError::Timeout)
+        } else if sr.ferr().bit_is_set() {
+            Err(SpdifrxError::Framing)
+        } else if sr.ovr().bit_is_set() {
+            self.rb.ifcr.write(|w| w.ovrcf().set_bit());
+            Err(SpdifrxError::Overrun)
+        } else if sr.perr().bit_is_set() {
+            self.rb.ifcr.write(|w| w.perrcf().set_bit());
+            Err(SpdifrxError::Parity)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Synchronises to the incoming signal and waits for the start of a
+    /// block, then starts receiving data.
+    ///
+    /// The SPDIFRX stops after a framing, synchronisation or timeout
+    /// error. This method can be used to restart it.
+    pub fn synchronise(&mut self) -> Result<(), SpdifrxError> {
+        // Returning to the idle state clears the errors and the data
+        self.set_state(0b00);
+        self.rb.ifcr.write(|w| {
+            w.perrcf()
+                .set_bit()
+                .ovrcf()
+                .set_bit()
+                .sbdcf()
+                .set_bit()
+                .syncdcf()
+                .set_bit()
+        });
+
+        // The receiver synchronises before it starts receiving
+        self.set_state(0b11);
+        while self.rb.sr.read().syncd().bit_is_clear() {
+            self.check_errors()?;
+        }
+
+        // Discard samples until the start of a block
+        while self.rb.sr.read().sbd().bit_is_clear() {
+            if self.rb.sr.read().rxne().bit_is_set() {
+                let _ = dr_reg!(self.rb).read().bits();
+            }
+            match self.check_errors() {
+                Err(SpdifrxError::Overrun) | Err(SpdifrxError::Parity) => {}
+                result => result?,
+            }
+        }
+        self.rb
+            .ifcr
+            .write(|w| w.sbdcf().set_bit().syncdcf().set_bit());
+
+        Ok(())
+    }
+
+    /// Waits for the next sub-frame, and returns it with the current status
+    /// and channel status.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if one is flagged before the sub-frame is received.
+    /// Parity and overrun errors are cleared, and reception continues.
+    pub fn read_frame(&mut self) -> Result<SpdifrxFrame, SpdifrxError> {
+        loop {
+            self.check_errors()?;
+
+            let sr = self.rb.sr.read();
+            if sr.rxne().bit_is_set() {
+                let data = dr_reg!(self.rb).read().bits();
+                let channel_status = if sr.csrne().bit_is_set() {
+                    Some(self.rb.csr.read().bits())
+                } else {
+                    None
+                };
+
+                return Ok(SpdifrxFrame {
+                    data,
+                    status: sr.bits(),
+                    channel_status,
+                });
+            }
+        }
+    }
+
+    /// Starts receiving samples into `buf` using DMA1 or DMA2, and returns
+    /// immediately. Each word in `buf` is the contents of the data register,
+    /// as described for [`SpdifrxFrame::data`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `buf` is greater than 65535 words.
+    pub fn read_dma<STREAM>(
+        self,
+        stream: STREAM,
+        buf: &'static mut [u32],
+    ) -> SpdifrxStream<STREAM>
+    where
+        STREAM: DoubleBufferedStream + Stream<Config = DmaConfig>,
+    {
+        let config = DmaConfig::default().memory_increment(true);
+        let mut transfer = Transfer::init(stream, self, buf, None, config);
+
+        transfer.start(|spdifrx| {
+            spdifrx.rb.cr.modify(|_, w| w.rxdmaen().set_bit());
+        });
+
+        SpdifrxStream { transfer }
+    }
+
+    /// Returns a reference to the inner peripheral
+    pub fn inner(&self) -> &SPDIFRX {
+        &self.rb
+    }
+
+    /// Returns a mutable reference to the inner peripheral
+    pub fn inner_mut(&mut self) -> &mut SPDIFRX {
+        &mut self.rb
+    }
+
+    /// Disables the SPDIFRX and releases the peripheral
+    pub fn free(mut self) -> (SPDIFRX, rec::Spdifrx) {
+        self.set_state(0b00);
+
+        (
+            self.rb,
+            rec::Spdifrx {
+                _marker: PhantomData,
+            },
+        )
+    }
+}
+
+/// Samples received from the SPDIFRX by DMA1 or DMA2.
+///
+/// Created by [`read_dma`](Spdifrx::read_dma).
+pub struct SpdifrxStream<STREAM>
+where
+    STREAM: DoubleBufferedStream + Stream<Config = DmaConfig>,
+{
+    transfer: Transfer<
+        STREAM,
+        Spdifrx,
+        PeripheralToMemory,
+        &'static mut [u32],
+        DBTransfer,
+    >,
+}
+
+impl<STREAM> SpdifrxStream<STREAM>
+where
+    STREAM: DoubleBufferedStream + Stream<Config = DmaConfig>,
+{
+    /// Returns true if the buffer is full
+    pub fn is_complete(&self) -> bool {
+        self.transfer.get_transfer_complete_flag()
+    }
+
+    /// Blocks until the buffer is full.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a framing, synchronisation or timeout error stops
+    /// the SPDIFRX before the buffer is full, or if samples were lost.
+    pub fn wait(&mut self) -> Result<(), SpdifrxError> {
+        // unsafe: read-only access to the status register of the SPDIFRX
+        // owned by the transfer
+        let sr = unsafe { &(*SPDIFRX::ptr()).sr };
+
+        while !self.is_complete() {
+            let sr = sr.read();
+            if sr.serr().bit_is_set() {
+                return Err(SpdifrxError::Synchronisation);
+            } else if sr.terr().bit_is_set() {
+                return Err(SpdifrxError::Timeout);
+            } else if sr.ferr().bit_is_set() {
+                return Err(SpdifrxError::Framing);
+            }
+        }
+
+        if sr.read().ovr().bit_is_set() {
+            Err(SpdifrxError::Overrun)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Stops the DMA transfer, and returns the SPDIFRX, the DMA stream and
+    /// the buffer. The SPDIFRX continues receiving
+    pub fn free(self) -> (Spdifrx, STREAM, &'static mut [u32]) {
+        let (stream, mut spdifrx, buf, _) = self.transfer.free();
+
+        spdifrx.rb.cr.modify(|_, w| w.rxdmaen().clear_bit());
+        let _ = spdifrx.check_errors();
+
+        (spdifrx, stream, buf)
+    }
+}
@@ -43,6 +43,80 @@
 //! let _: spi:Spi<_, _, u8> = dp.SPI1.spi((sck, spi::NoMiso, mosi), spi::MODE_0, 1.MHz(), ccdr.peripheral.SPI1, &ccdr.clocks);
 //! ```
 //!
+//! The FIFO threshold can be changed with
+//! [`set_fifo_threshold`](Spi#method.set_fifo_threshold). It is a whole
+//! number of words, so with 16 bit words it should be at least
+//! `FifoThreshold::TwoBytes`.
+//!
+//! An SPI with 8 bit words can also transfer 16 or 32 bit words without
+//! packing them into bytes, using `transfer16`, `write16`, `transfer32` and
+//! `write32`. The data size is switched for the duration of each call, so
+//! each word is sent most significant bit first.
+//!
+//! ## embedded-hal 1.0
+//!
+//! `Spi` also implements the embedded-hal 1.0
+//! [`SpiBus`](embedded_hal_1::spi::SpiBus) trait for its word size. To use
+//! it with drivers that require the
+//! [`SpiDevice`](embedded_hal_1::spi::SpiDevice) trait, wrap it together
+//! with a chip select pin in a [`SpiDevice`].
+//!
+//! ## TI Mode
+//!
+//! Some DSPs and data converters use the TI synchronous serial protocol
+//! (SSP). [`into_ti_mode`](Spi#method.into_ti_mode) returns a
+//! [`SpiTiMode`], which also implements
+//! [`SpiBus`](embedded_hal_1::spi::SpiBus). In this protocol the NSS pin
+//! is not held active for a transaction. Instead the hardware outputs a one
+//! clock cycle NSS pulse before each word, which is the frame synchronisation
+//! expected by parts like the TI ADS1x4S08 ADCs.
+//!
+//! ```
+//! let spi: spi::Spi<_, _, u16> = dp.SPI1.spi((sck, miso, mosi, nss), spi::MODE_0, 1.MHz(), ccdr.peripheral.SPI1, &ccdr.clocks);
+//! let mut spi = spi.into_ti_mode();
+//!
+//! spi.transfer_in_place(&mut words)?;
+//! ```
+//!
+//! ## Bidirectional Mode
+//!
+//! Some sensors use a single data line for both directions.
+//! [`into_bidi`](Spi#method.into_bidi) returns a [`SpiBidi`], which
+//! transmits and receives on the MOSI pin and also implements
+//! [`SpiBus`](embedded_hal_1::spi::SpiBus). Since only one direction can be
+//! used at once, `transfer` and `transfer_in_place` return
+//! [`Error::InvalidCall`]. A half-duplex exchange is a `write` followed by a
+//! `read`, for example within a single
+//! [`SpiDevice`](embedded_hal_1::spi::SpiDevice) transaction.
+//!
+//! ```
+//! let spi: spi::Spi<_, _, u8> = dp.SPI1.spi((sck, spi::NoMiso, mosi), spi::MODE_0, 1.MHz(), ccdr.peripheral.SPI1, &ccdr.clocks);
+//! let mut spi = spi.into_bidi();
+//!
+//! spi.write(&[0x80 | WHO_AM_I])?;
+//! let mut id = [0];
+//! spi.read(&mut id)?;
+//! ```
+//!
+//! ## Simplex Modes
+//!
+//! Devices that only ever listen, such as write-only displays, or only ever
+//! talk, such as some ADCs, can be driven with a transmit-only or
+//! receive-only [`CommunicationMode`]. Full duplex remains the default.
+//!
+//! ```
+//! let config = spi::Config::new(spi::MODE_0)
+//!     .communication_mode(spi::CommunicationMode::Transmitter);
+//! let mut spi: spi::Spi<_, _, u8> = dp.SPI1.spi((sck, spi::NoMiso, mosi), config, 1.MHz(), ccdr.peripheral.SPI1, &ccdr.clocks);
+//!
+//! spi.write(&frame)?;
+//! ```
+//!
+//! In receive-only mode the master clocks exactly as many words as are read
+//! by [`SpiBus::read`](embedded_hal_1::spi::SpiBus::read), without any
+//! dummy bytes being sent. Calls that need the other direction return
+//! [`Error::InvalidCall`].
+//!
 //! ## Clocks
 //!
 //! The bitrate calculation is based upon the clock currently assigned
@@ -52,6 +126,36 @@
 //! - SPI4, SPI5: __APB__
 //! - SPI6: __PCLK4__
 //!
+//! # Async
+//!
+//! With the `async` feature, [`SpiAsync`] implements the
+//! [embedded-hal-async](https://docs.rs/embedded-hal-async) `SpiBus` trait
+//! for 8-bit words. Each operation is transferred by a pair of DMA1 or DMA2
+//! streams, and completes when the interrupt handlers for the streams wake
+//! the task.
+//!
+//! ```
+//! use stm32h7xx_hal::dma::dma::{AsyncStream, StreamsTuple};
+//! use stm32h7xx_hal::spi::SpiAsync;
+//!
+//! let streams = StreamsTuple::new(dp.DMA1, ccdr.peripheral.DMA1);
+//! let mut spi = SpiAsync::new(spi, streams.0, streams.1);
+//!
+//! spi.transfer_in_place(&mut buffer).await?;
+//!
+//! #[interrupt]
+//! fn DMA1_STR0() {
+//!     Stream0::<DMA1>::on_interrupt();
+//! }
+//!
+//! #[interrupt]
+//! fn DMA1_STR1() {
+//!     Stream1::<DMA1>::on_interrupt();
+//! }
+//! ```
+//!
+//! The `async` feature requires Rust 1.75 or later.
+//!
 //! # Examples
 //!
 //! - [Blocking SPI](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/spi.rs)
@@ -63,6 +167,7 @@
 //! [embedded_hal]: https://docs.rs/embedded-hal/0.2.3/embedded_hal/spi/index.html
 
 use core::cell::UnsafeCell;
+use core::convert::Infallible;
 use core::marker::PhantomData;
 use core::ptr;
 
@@ -80,10 +185,17 @@ use crate::stm32::rcc::{cdccip1r as ccip1r, srdccipr};
 use crate::stm32::rcc::{d2ccip1r as ccip1r, d3ccipr as srdccipr};
 use crate::stm32::spi1::{
     cfg1::MBR_A as MBR, cfg2::COMM_A as COMM, cfg2::SSIOP_A as SSIOP,
+    cr1::HDDIR_A as HDDIR,
 };
 use crate::stm32::{SPI1, SPI2, SPI3, SPI4, SPI5, SPI6};
 use crate::time::Hertz;
 
+#[cfg(feature = "async")]
+mod asynch;
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub use asynch::SpiAsync;
+
 /// SPI error
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -105,6 +217,114 @@ pub enum Error {
     /// corresponding word was received. May be caused by hardware issues where
     /// the SPI master fails to receive its own clock on the CLK pin
     DuplexFailed,
+    /// A DMA transfer error occurred
+    Dma,
+}
+
+impl embedded_hal_1::spi::Error for Error {
+    fn kind(&self) -> embedded_hal_1::spi::ErrorKind {
+        match self {
+            Error::Overrun => embedded_hal_1::spi::ErrorKind::Overrun,
+            Error::ModeFault => embedded_hal_1::spi::ErrorKind::ModeFault,
+            _ => embedded_hal_1::spi::ErrorKind::Other,
+        }
+    }
+}
+
+/// Error changing the SPI clock frequency with
+/// [`set_baud_rate`](Spi#method.set_baud_rate)
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BaudRateError {
+    /// The closest frequency that can be generated from the kernel clock
+    pub closest: Hertz,
+}
+
+/// Returns the master baud rate divider and its value, so that the SPI
+/// clock generated from `spi_ker_ck` is at most `spi_freq` where possible
+fn baud_rate_divider(spi_ker_ck: u32, spi_freq: u32) -> (MBR, u32) {
+    match (spi_ker_ck + spi_freq - 1) / spi_freq {
+        1..=2 => (MBR::Div2, 2),
+        3..=4 => (MBR::Div4, 4),
+        5..=8 => (MBR::Div8, 8),
+        9..=16 => (MBR::Div16, 16),
+        17..=32 => (MBR::Div32, 32),
+        33..=64 => (MBR::Div64, 64),
+        65..=128 => (MBR::Div128, 128),
+        _ => (MBR::Div256, 256),
+    }
+}
+
+/// Returns the master baud rate divider that generates the SPI clock closest
+/// to `spi_freq` from `spi_ker_ck`, or an error if it is not within 5% of
+/// `spi_freq`
+fn checked_baud_rate_divider(
+    spi_ker_ck: u32,
+    spi_freq: u32,
+) -> Result<MBR, BaudRateError> {
+    // The divider rounds the frequency down, but the next faster divider
+    // may be closer
+    let (slower, divider) = baud_rate_divider(spi_ker_ck, spi_freq);
+    let (mbr, closest) = if divider > 2 {
+        // The divider for a division by exactly divider / 2
+        let (faster, _) = baud_rate_divider(divider / 2, 1);
+        let (f_slower, f_faster) =
+            (spi_ker_ck / divider, spi_ker_ck / (divider / 2));
+        if f_faster.abs_diff(spi_freq) < f_slower.abs_diff(spi_freq) {
+            (faster, f_faster)
+        } else {
+            (slower, f_slower)
+        }
+    } else {
+        (slower, spi_ker_ck / divider)
+    };
+
+    // |closest - spi_freq| <= 5% of spi_freq
+    if u64::from(closest.abs_diff(spi_freq)) * 20 > u64::from(spi_freq) {
+        return Err(BaudRateError {
+            closest: Hertz::from_raw(closest),
+        });
+    }
+
+    Ok(mbr)
+}
+
+/// FIFO threshold, the number of bytes in the FIFO at which the TXP and RXP
+/// flags change
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FifoThreshold {
+    OneByte = 1,
+    TwoBytes = 2,
+    ThreeBytes = 3,
+    FourBytes = 4,
+}
+
+/// Returns the CFG1.FTHLV value for `threshold` with words of `word_bytes`
+///
+/// FTHLV counts whole data frames, so the threshold is rounded down to a
+/// multiple of the frame size, and up to at least one frame.
+fn fifo_threshold_level(threshold: FifoThreshold, word_bytes: u8) -> u8 {
+    let frames = threshold as u8 / word_bytes;
+    if frames == 0 {
+        #[cfg(feature = "log")]
+        log::debug!(
+            "SPI: FIFO threshold of {} bytes is less than one {} byte frame",
+            threshold as u8,
+            word_bytes
+        );
+        0
+    } else {
+        frames - 1
+    }
+}
+
+/// Returns `true` if there is at least one data frame in the Rx FIFO
+///
+/// RXP is only set once the FIFO threshold is reached. Below the threshold,
+/// RXWNE and RXPLVL show whether any data frames have been received.
+fn rx_frame_available(rxp: bool, rxwne: bool, rxplvl: u8) -> bool {
+    rxp || rxwne || rxplvl != 0
 }
 
 /// Enabled SPI peripheral (type state)
@@ -146,15 +366,18 @@ where
 }
 
 /// Specifies the communication mode of the SPI interface.
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum CommunicationMode {
     /// Both RX and TX are used.
     FullDuplex,
 
-    /// Only the SPI TX functionality is used.
+    /// Only the SPI TX functionality is used. The MISO pin is not needed
+    /// and nothing is read back.
     Transmitter,
 
-    /// Only the SPI RX functionality is used.
+    /// Only the SPI RX functionality is used. The MOSI pin is not needed and
+    /// the clock runs only for the words being read.
     Receiver,
 }
 
@@ -229,6 +452,8 @@ impl Config {
     }
 
     /// Select the communication mode of the SPI bus.
+    ///
+    /// By default this is [CommunicationMode::FullDuplex].
     #[must_use]
     pub fn communication_mode(mut self, mode: CommunicationMode) -> Self {
         self.communication_mode = mode;
@@ -492,18 +717,21 @@ pins! {
 }
 
 macro_rules! check_status_error {
-    ($spi:expr; $(  {$flag:ident, $variant:ident, $blk:block}  ),*) => {{
-        let sr = $spi.sr.read();
+    ($spi:expr; $(  {$flag:ident, $variant:ident, $blk:block}  ),*) => {
+        check_status_error!($spi, sr; $( {sr.$flag().$variant(), $blk} ),*)
+    };
+    ($spi:expr, $sr:ident; $(  {$cond:expr, $blk:block}  ),*) => {{
+        let $sr = $spi.sr.read();
 
-        return Err(if sr.ovr().is_overrun() {
+        return Err(if $sr.ovr().is_overrun() {
             nb::Error::Other(Error::Overrun)
-        } else if sr.modf().is_fault() {
+        } else if $sr.modf().is_fault() {
             nb::Error::Other(Error::ModeFault)
-        } else if sr.crce().is_error() {
+        } else if $sr.crce().is_error() {
             nb::Error::Other(Error::Crc)
         }
             $(
-                else if sr.$flag().$variant() { $blk }
+                else if $cond { $blk }
             )*
         else {
             nb::Error::WouldBlock
@@ -526,10 +754,33 @@ pub enum Event {
 pub struct Spi<SPI, ED, WORD = u8> {
     spi: SPI,
     hardware_cs_mode: HardwareCSMode,
+    communication_mode: CommunicationMode,
     _word: PhantomData<WORD>,
     _ed: PhantomData<ED>,
 }
 
+/// SPI using the TI synchronous serial protocol (SSP)
+///
+/// Returned by `into_ti_mode`. The clock idles low, data is sampled on the
+/// falling edge and the hardware chip select (NSS) pin outputs a one clock
+/// cycle frame pulse before each data word.
+pub struct SpiTiMode<SPI, WORD = u8> {
+    spi: Spi<SPI, Enabled, WORD>,
+    /// CFG2 in Motorola mode, restored by `into_motorola_mode`
+    cfg2: u32,
+}
+
+/// SPI in bidirectional (half-duplex) mode, using a single data line
+///
+/// Returned by `into_bidi`. The data line is the MOSI pin, which is driven
+/// whilst transmitting and is an input whilst receiving. The MISO pin is not
+/// used.
+pub struct SpiBidi<SPI, WORD = u8> {
+    spi: Spi<SPI, Enabled, WORD>,
+    /// CFG2 in full duplex mode, restored by `into_full_duplex`
+    cfg2: u32,
+}
+
 pub trait SpiExt<SPI, WORD>: Sized {
     type Rec: ResetEnable;
 
@@ -597,6 +848,32 @@ pub trait HalEnabledSpi:
     /// If it's not either a frame or endless transaction,
     /// an error is returned with [Error::InvalidCall].
     fn end_transaction(&mut self) -> Result<(), Error>;
+
+    /// Suspends the ongoing master transfer (CSUSP), and waits for the
+    /// suspension to complete.
+    ///
+    /// The transfer stops at the end of the current data word. The contents
+    /// of the FIFOs are preserved and the hardware chip select remains
+    /// asserted, so the transfer can be continued with
+    /// [resume](HalEnabledSpi::resume). This also pauses an ongoing DMA
+    /// transfer, since no more requests are made whilst suspended.
+    ///
+    /// Does nothing if no transfer has been started.
+    fn suspend(&mut self);
+
+    /// Resumes a master transfer that was suspended with
+    /// [suspend](HalEnabledSpi::suspend) (CSTART).
+    fn resume(&mut self);
+
+    /// Return `true` if the master transfer is suspended
+    fn is_suspended(&self) -> bool;
+
+    /// Sets the idle time inserted between each data word (MIDI), as a
+    /// number of SCK cycles from 0 to 15.
+    ///
+    /// The SPI is briefly disabled to change this setting, so this must not
+    /// be called during a transfer.
+    fn set_inter_word_delay_cycles(&mut self, cycles: u8);
 }
 
 pub trait HalDisabledSpi: HalSpi {
@@ -716,16 +993,7 @@ macro_rules! spi {
 
                         let spi_freq = freq.raw();
 	                    let spi_ker_ck = Self::kernel_clk_unwrap(clocks).raw();
-                        let mbr = match (spi_ker_ck + spi_freq - 1) / spi_freq {
-                            1..=2 => MBR::Div2,
-                            3..=4 => MBR::Div4,
-                            5..=8 => MBR::Div8,
-                            9..=16 => MBR::Div16,
-                            17..=32 => MBR::Div32,
-                            33..=64 => MBR::Div64,
-                            65..=128 => MBR::Div128,
-                            _ => MBR::Div256,
-                        };
+                        let (mbr, _) = baud_rate_divider(spi_ker_ck, spi_freq);
                         spi.cfg1.modify(|_, w| {
                             w.mbr()
                                 .variant(mbr) // master baud rate
@@ -810,7 +1078,13 @@ macro_rules! spi {
                         // spe: enable the SPI bus
                         spi.cr1.write(|w| w.ssi().slave_not_selected().spe().enabled());
 
-                        Spi { spi, hardware_cs_mode: config.hardware_cs.mode, _word: PhantomData, _ed: PhantomData }
+                        Spi {
+                            spi,
+                            hardware_cs_mode: config.hardware_cs.mode,
+                            communication_mode: config.communication_mode,
+                            _word: PhantomData,
+                            _ed: PhantomData,
+                        }
                     }
                 }
 
@@ -829,6 +1103,73 @@ macro_rules! spi {
                     }
                 }
 
+                impl Spi<$SPIX, Enabled, $TY> {
+                    /// Changes the frequency of the SPI clock to `freq`,
+                    /// without reinitialising the SPI. For example, an SD
+                    /// card is initialised at 400kHz before the clock is
+                    /// increased.
+                    ///
+                    /// Waits for the current transmission to complete before
+                    /// the SPI is briefly disabled. This must not be called
+                    /// during a DMA transfer.
+                    ///
+                    /// Returns an error, and leaves the frequency unchanged,
+                    /// if the closest frequency that can be generated from
+                    /// the kernel clock is not within 5% of `freq`.
+                    ///
+                    /// # Panics
+                    ///
+                    /// Panics if the kernel clock is not running
+                    pub fn set_baud_rate(
+                        &mut self,
+                        freq: Hertz,
+                        clocks: &CoreClocks,
+                    ) -> Result<(), BaudRateError> {
+                        let spi_ker_ck = Self::kernel_clk_unwrap(clocks).raw();
+                        let mbr = checked_baud_rate_divider(spi_ker_ck, freq.raw())?;
+
+                        while self.spi.sr.read().txc().is_ongoing() {}
+
+                        // CFG1 can only be written whilst SPE is cleared
+                        self.internal_disable();
+                        self.spi.cfg1.modify(|_, w| w.mbr().variant(mbr));
+                        self.internal_enable();
+
+                        Ok(())
+                    }
+
+                    /// Sets the FIFO threshold, at which the TXP flag is set
+                    /// when there is space for `threshold` bytes in the FIFO
+                    /// and the RXP flag is set when `threshold` bytes have
+                    /// been received
+                    ///
+                    /// The threshold is a whole number of data frames, so it
+                    /// is rounded down to a multiple of the frame size. A
+                    /// threshold smaller than one frame is raised to one
+                    /// frame, and a debug message is logged. For 16-bit
+                    /// frames, a threshold of at least two bytes is
+                    /// required.
+                    ///
+                    /// The blocking methods also read the data frames that
+                    /// are received below the threshold, so the threshold
+                    /// only changes when DMA requests and RXP/TXP interrupts
+                    /// occur.
+                    ///
+                    /// Waits for the current transmission to complete before
+                    /// the SPI is briefly disabled. This must not be called
+                    /// during a DMA transfer.
+                    pub fn set_fifo_threshold(&mut self, threshold: FifoThreshold) {
+                        let fthlv = fifo_threshold_level(threshold, core::mem::size_of::<$TY>() as u8);
+
+                        while self.spi.sr.read().txc().is_ongoing() {}
+
+                        // CFG1 can only be written whilst SPE is cleared
+                        self.internal_disable();
+                        self.spi.cfg1.modify(|_, w| w.fthlv().bits(fthlv));
+                        self.internal_enable();
+                    }
+                }
+
                 impl HalEnabledSpi for Spi<$SPIX, Enabled, $TY> {
                     type Disabled = Spi<Self::Spi, Disabled, Self::Word>;
 
@@ -844,6 +1185,7 @@ macro_rules! spi {
                         Spi {
                             spi: self.spi,
                             hardware_cs_mode: self.hardware_cs_mode,
+                            communication_mode: self.communication_mode,
                             _word: PhantomData,
                             _ed: PhantomData,
                         }
@@ -885,6 +1227,31 @@ macro_rules! spi {
 
                         Ok(())
                     }
+
+                    fn suspend(&mut self) {
+                        if self.spi.cr1.read().cstart().is_started() {
+                            self.spi.cr1.modify(|_, w| w.csusp().requested());
+                            while self.spi.sr.read().susp().is_not_suspended() {}
+                        }
+                    }
+
+                    fn resume(&mut self) {
+                        if self.is_suspended() {
+                            self.spi.ifcr.write(|w| w.suspc().clear());
+                            self.spi.cr1.modify(|_, w| w.cstart().started());
+                        }
+                    }
+
+                    fn is_suspended(&self) -> bool {
+                        self.spi.sr.read().susp().is_suspended()
+                    }
+
+                    fn set_inter_word_delay_cycles(&mut self, cycles: u8) {
+                        // CFG2 can only be written whilst SPE is cleared
+                        self.internal_disable();
+                        self.spi.cfg2.modify(|_, w| w.midi().bits(cycles.min(0xF)));
+                        self.internal_enable();
+                    }
                 }
 
                 impl HalDisabledSpi for Spi<$SPIX, Disabled, $TY> {
@@ -896,6 +1263,7 @@ macro_rules! spi {
                         Spi {
                             spi: self.spi,
                             hardware_cs_mode: self.hardware_cs_mode,
+                            communication_mode: self.communication_mode,
                             _word: PhantomData,
                             _ed: PhantomData,
                         }
@@ -1062,9 +1430,13 @@ macro_rules! spi {
                     type Error = Error;
 
                     fn read(&mut self) -> nb::Result<$TY, Error> {
-                        check_status_error!(self.spi;
-                        {    // } else if sr.rxp().is_not_empty() {
-                            rxp, is_not_empty,
+                        check_status_error!(self.spi, sr;
+                        {    // } else if a data frame has been received {
+                            rx_frame_available(
+                                sr.rxp().is_not_empty(),
+                                sr.rxwne().is_at_least32(),
+                                sr.rxplvl().bits(),
+                            ),
                             {
                                 // NOTE(read_volatile) read only 1 word
                                 return Ok(unsafe {
@@ -1144,9 +1516,13 @@ macro_rules! spi {
                     /// * Assumes at least one word has already been written to the Tx FIFO
                     #[inline(always)]
                     fn read_duplex_internal(&mut self) -> nb::Result<$TY, Error> {
-                        check_status_error!(self.spi;
-                        {    // else if sr.rxp().is_not_empty()
-                            rxp, is_not_empty,
+                        check_status_error!(self.spi, sr;
+                        {    // else if a data frame has been received
+                            rx_frame_available(
+                                sr.rxp().is_not_empty(),
+                                sr.rxwne().is_at_least32(),
+                                sr.rxplvl().bits(),
+                            ),
                             {
                                 // NOTE(read_volatile) read only 1 word
                                 return Ok(unsafe {
@@ -1156,11 +1532,15 @@ macro_rules! spi {
                                 });
                             }
                         }, { // else if sr.txc().is_completed()
-                            txc, is_completed,
+                            sr.txc().is_completed(),
                             {
                                 let sr = self.spi.sr.read(); // Read SR again on a subsequent PCLK cycle
 
-                                if sr.txc().is_completed() && !sr.rxp().is_not_empty() {
+                                if sr.txc().is_completed() && !rx_frame_available(
+                                    sr.rxp().is_not_empty(),
+                                    sr.rxwne().is_at_least32(),
+                                    sr.rxplvl().bits(),
+                                ) {
                                     // The Tx FIFO completed, but no words were
                                     // available in the Rx FIFO. This is a duplex failure
                                     nb::Error::Other(Error::DuplexFailed)
@@ -1180,6 +1560,10 @@ macro_rules! spi {
                             return Ok(());
                         }
 
+                        if self.communication_mode == CommunicationMode::Receiver {
+                            return Err(Error::InvalidCall);
+                        }
+
                         // Are we in frame mode?
                         if matches!(self.hardware_cs_mode, HardwareCSMode::FrameTransaction) {
                             const MAX_WORDS: usize = 0xFFFF;
@@ -1196,6 +1580,33 @@ macro_rules! spi {
                             })?;
                         }
 
+                        if self.communication_mode == CommunicationMode::Transmitter {
+                            // Nothing is received, so just keep the write
+                            // FIFO filled
+                            for word in write_words {
+                                nb::block!(self.send(*word))?;
+                            }
+
+                            // Wait for the last word to leave the shift register
+                            while self.spi.sr.read().txc().is_ongoing() {}
+                        } else {
+                            self.exchange_words_internal(write_words)?;
+                        }
+
+                        // Are we in frame mode?
+                        if matches!(self.hardware_cs_mode, HardwareCSMode::FrameTransaction) {
+                            // Clean up
+                            self.end_transaction()?;
+                        }
+
+                        Ok(())
+                    }
+
+                    /// Sends `write_words` in full duplex mode, discarding the
+                    /// words received
+                    fn exchange_words_internal(&mut self, write_words: &[$TY]) -> Result<(), Error> {
+                        use hal::spi::FullDuplex;
+
                         // Depth of FIFO to use. All current SPI implementations
                         // have a FIFO depth of at least 8 (see RM0433 Rev 7
                         // Table 409.) but pick 4 as a conservative value.
@@ -1220,12 +1631,6 @@ macro_rules! spi {
                             let _ = nb::block!(self.read_duplex_internal())?;
                         }
 
-                        // Are we in frame mode?
-                        if matches!(self.hardware_cs_mode, HardwareCSMode::FrameTransaction) {
-                            // Clean up
-                            self.end_transaction()?;
-                        }
-
                         Ok(())
                     }
 
@@ -1237,6 +1642,10 @@ macro_rules! spi {
                             return Ok(());
                         }
 
+                        if self.communication_mode != CommunicationMode::FullDuplex {
+                            return Err(Error::InvalidCall);
+                        }
+
                         // Are we in frame mode?
                         if matches!(self.hardware_cs_mode, HardwareCSMode::FrameTransaction) {
                             const MAX_WORDS: usize = 0xFFFF;
@@ -1288,6 +1697,315 @@ macro_rules! spi {
                     }
                 }
 
+                impl Spi<$SPIX, Enabled, $TY>
+                {
+                    /// Internal implementation for embedded-hal 1.0 SpiBus::read
+                    /// in receive-only mode
+                    fn receive_internal(&mut self, words: &mut [$TY]) -> Result<(), Error> {
+                        use hal::spi::FullDuplex;
+
+                        for chunk in words.chunks_mut(0xFFFF) {
+                            // In receive-only mode the clock runs for as long
+                            // as the transaction is started, so set its size
+                            // to stop the clock after the last word. TSIZE
+                            // can only be written whilst SPE is cleared
+                            self.spi.cr1.write(|w| w.ssi().slave_not_selected().spe().disabled());
+                            self.spi.cr2.write(|w| w.tsize().bits(chunk.len() as u16));
+                            self.internal_enable();
+                            self.spi.cr1.modify(|_, w| w.cstart().started());
+
+                            for word in chunk.iter_mut() {
+                                *word = nb::block!(self.read())?;
+                            }
+
+                            while self.spi.sr.read().eot().is_not_completed() {}
+                            self.spi.ifcr.write(|w| w.txtfc().clear().eotc().clear());
+                        }
+
+                        Ok(())
+                    }
+
+                    /// Internal implementation for embedded-hal 1.0 SpiBus::transfer
+                    ///
+                    /// Words past the end of `write` are sent as zero, words
+                    /// past the end of `read` are discarded
+                    fn transfer_internal_split(&mut self, read: &mut [$TY], write: &[$TY]) -> Result<(), Error> {
+                        use hal::spi::FullDuplex;
+
+                        let len = core::cmp::max(read.len(), write.len());
+                        if len == 0 {
+                            return Ok(());
+                        }
+
+                        if self.communication_mode != CommunicationMode::FullDuplex {
+                            return Err(Error::InvalidCall);
+                        }
+
+                        // Are we in frame mode?
+                        if matches!(self.hardware_cs_mode, HardwareCSMode::FrameTransaction) {
+                            const MAX_WORDS: usize = 0xFFFF;
+
+                            // Can we send
+                            if len > MAX_WORDS {
+                                return Err(Error::BufferTooBig { max_size: MAX_WORDS });
+                            }
+
+                            // Setup that we're going to send this amount of bits
+                            // SAFETY: We already checked that `len` is not zero
+                            self.setup_transaction(unsafe {
+                                core::num::NonZeroU16::new_unchecked(len as u16)
+                            })?;
+                        }
+
+                        // Depth of FIFO to use, see transfer_internal_rw
+                        const FIFO_WORDS: usize = 4;
+
+                        let word = |i: usize| write.get(i).copied().unwrap_or(0);
+
+                        // Fill the first half of the write FIFO
+                        for i in 0..core::cmp::min(FIFO_WORDS, len) {
+                            nb::block!(self.send(word(i)))?;
+                        }
+
+                        for i in FIFO_WORDS..len+FIFO_WORDS {
+                            let read_value = if i < len {
+                                // Continue filling write FIFO and emptying read FIFO
+                                nb::block!(self.exchange_duplex_internal(word(i)))?
+                            } else {
+                                // Finish emptying the read FIFO
+                                nb::block!(self.read_duplex_internal())?
+                            };
+
+                            if let Some(r) = read.get_mut(i - FIFO_WORDS) {
+                                *r = read_value;
+                            }
+                        }
+
+                        // Are we in frame mode?
+                        if matches!(self.hardware_cs_mode, HardwareCSMode::FrameTransaction) {
+                            // Clean up
+                            self.end_transaction()?;
+                        }
+
+                        Ok(())
+                    }
+                }
+
+                impl Spi<$SPIX, Enabled, $TY> {
+                    /// Switches to the TI synchronous serial protocol
+                    ///
+                    /// The clock polarity and phase are fixed by the protocol
+                    /// (CPOL = 0, CPHA = 0), and the NSS pin is driven by the
+                    /// hardware with a frame pulse before each word, rather
+                    /// than held active for a whole transaction.
+                    ///
+                    /// The NSS pin must be included in the pins passed to
+                    /// the SPI constructor.
+                    pub fn into_ti_mode(mut self) -> SpiTiMode<$SPIX, $TY> {
+                        self.internal_disable();
+
+                        // CFG2 can only be written whilst SPE is cleared
+                        let cfg2 = self.spi.cfg2.read().bits();
+                        self.spi.cfg2.modify(|_, w| {
+                            w.sp().ti()
+                                .cpol().clear_bit()
+                                .cpha().clear_bit()
+                                .ssm().clear_bit()
+                                .ssoe().enabled()
+                        });
+                        self.internal_enable();
+
+                        SpiTiMode { spi: self, cfg2 }
+                    }
+                }
+
+                impl SpiTiMode<$SPIX, $TY> {
+                    /// Switches back to the Motorola protocol, restoring the
+                    /// previous configuration
+                    pub fn into_motorola_mode(self) -> Spi<$SPIX, Enabled, $TY> {
+                        let mut spi = self.spi;
+                        spi.internal_disable();
+
+                        // unsafe: we restore a value read from the register
+                        spi.spi.cfg2.write(|w| unsafe { w.bits(self.cfg2) });
+                        spi.internal_enable();
+
+                        spi
+                    }
+
+                    /// Returns a reference to the inner peripheral
+                    pub fn inner(&self) -> &$SPIX {
+                        &self.spi.spi
+                    }
+                }
+
+                impl embedded_hal_1::spi::ErrorType for SpiTiMode<$SPIX, $TY> {
+                    type Error = Error;
+                }
+
+                impl embedded_hal_1::spi::SpiBus<$TY> for SpiTiMode<$SPIX, $TY> {
+                    /// Reads `words`, sending zeros
+                    fn read(&mut self, words: &mut [$TY]) -> Result<(), Self::Error> {
+                        embedded_hal_1::spi::SpiBus::read(&mut self.spi, words)
+                    }
+
+                    fn write(&mut self, words: &[$TY]) -> Result<(), Self::Error> {
+                        embedded_hal_1::spi::SpiBus::write(&mut self.spi, words)
+                    }
+
+                    fn transfer(&mut self, read: &mut [$TY], write: &[$TY]) -> Result<(), Self::Error> {
+                        embedded_hal_1::spi::SpiBus::transfer(&mut self.spi, read, write)
+                    }
+
+                    fn transfer_in_place(&mut self, words: &mut [$TY]) -> Result<(), Self::Error> {
+                        embedded_hal_1::spi::SpiBus::transfer_in_place(&mut self.spi, words)
+                    }
+
+                    fn flush(&mut self) -> Result<(), Self::Error> {
+                        embedded_hal_1::spi::SpiBus::flush(&mut self.spi)
+                    }
+                }
+
+                impl Spi<$SPIX, Enabled, $TY> {
+                    /// Switches to bidirectional (half-duplex) mode, where a
+                    /// single data line is used in both directions
+                    ///
+                    /// The data line is the MOSI pin. The SPI should be
+                    /// constructed with `NoMiso` in place of the MISO pin.
+                    pub fn into_bidi(mut self) -> SpiBidi<$SPIX, $TY> {
+                        self.internal_disable();
+
+                        // CFG2 can only be written whilst SPE is cleared
+                        let cfg2 = self.spi.cfg2.read().bits();
+                        self.spi.cfg2.modify(|_, w| w.comm().half_duplex());
+                        self.internal_enable();
+
+                        SpiBidi { spi: self, cfg2 }
+                    }
+                }
+
+                impl SpiBidi<$SPIX, $TY> {
+                    /// Switches back to full duplex mode, restoring the
+                    /// previous configuration
+                    pub fn into_full_duplex(self) -> Spi<$SPIX, Enabled, $TY> {
+                        let mut spi = self.spi;
+                        spi.internal_disable();
+
+                        // unsafe: we restore a value read from the register
+                        spi.spi.cfg2.write(|w| unsafe { w.bits(self.cfg2) });
+                        spi.spi.cr2.write(|w| w.tsize().bits(0));
+                        spi.internal_enable();
+
+                        spi
+                    }
+
+                    /// Returns a reference to the inner peripheral
+                    pub fn inner(&self) -> &$SPIX {
+                        &self.spi.spi
+                    }
+
+                    /// Transmits a single word, driving the data line
+                    pub fn transmit_word(&mut self, word: $TY) -> Result<(), Error> {
+                        self.transmit(&[word])
+                    }
+
+                    /// Receives a single word, with the data line as an input
+                    pub fn receive_word(&mut self) -> Result<$TY, Error> {
+                        let mut word = [0];
+                        self.receive(&mut word)?;
+                        Ok(word[0])
+                    }
+
+                    /// Transmits all of `words`, driving the data line
+                    pub fn transmit(&mut self, words: &[$TY]) -> Result<(), Error> {
+                        use hal::spi::FullDuplex;
+
+                        for chunk in words.chunks(0xFFFF) {
+                            self.start_transaction(HDDIR::Transmitter, chunk.len());
+                            for word in chunk {
+                                // The transaction is started by the first
+                                // word written
+                                nb::block!(self.spi.send(*word))?;
+                            }
+                            self.end_transaction();
+                        }
+
+                        Ok(())
+                    }
+
+                    /// Receives `words`, with the data line as an input
+                    pub fn receive(&mut self, words: &mut [$TY]) -> Result<(), Error> {
+                        use hal::spi::FullDuplex;
+
+                        for chunk in words.chunks_mut(0xFFFF) {
+                            self.start_transaction(HDDIR::Receiver, chunk.len());
+                            // The master generates the clock for exactly
+                            // the number of words in the transaction
+                            self.spi.spi.cr1.modify(|_, w| w.cstart().started());
+                            for word in chunk.iter_mut() {
+                                *word = nb::block!(self.spi.read())?;
+                            }
+                            self.end_transaction();
+                        }
+
+                        Ok(())
+                    }
+
+                    /// Sets the direction of the data line and the number of
+                    /// words in the next transaction
+                    fn start_transaction(&mut self, direction: HDDIR, words: usize) {
+                        // CR2 and the direction can only be written whilst
+                        // SPE is cleared
+                        self.spi.spi.cr1.write(|w| w.ssi().slave_not_selected().spe().disabled());
+                        self.spi.spi.cr2.write(|w| w.tsize().bits(words as u16));
+                        self.spi.clear_modf(); // SPE cannot be set when MODF is set
+                        self.spi.spi.cr1.write(|w| {
+                            w.ssi().slave_not_selected().hddir().variant(direction)
+                        });
+                        self.spi.spi.cr1.modify(|_, w| w.spe().enabled());
+                    }
+
+                    /// Waits for the end of the transaction
+                    fn end_transaction(&mut self) {
+                        while self.spi.spi.sr.read().eot().is_not_completed() {}
+                        self.spi.spi.ifcr.write(|w| w.txtfc().clear().eotc().clear());
+                    }
+                }
+
+                impl embedded_hal_1::spi::ErrorType for SpiBidi<$SPIX, $TY> {
+                    type Error = Error;
+                }
+
+                impl embedded_hal_1::spi::SpiBus<$TY> for SpiBidi<$SPIX, $TY> {
+                    fn read(&mut self, words: &mut [$TY]) -> Result<(), Self::Error> {
+                        self.receive(words)
+                    }
+
+                    fn write(&mut self, words: &[$TY]) -> Result<(), Self::Error> {
+                        self.transmit(words)
+                    }
+
+                    /// Not supported, returns [Error::InvalidCall]. The single
+                    /// data line cannot be used in both directions at once, so
+                    /// use `write` followed by `read` instead
+                    fn transfer(&mut self, _read: &mut [$TY], _write: &[$TY]) -> Result<(), Self::Error> {
+                        Err(Error::InvalidCall)
+                    }
+
+                    /// Not supported, returns [Error::InvalidCall]. The single
+                    /// data line cannot be used in both directions at once, so
+                    /// use `write` followed by `read` instead
+                    fn transfer_in_place(&mut self, _words: &mut [$TY]) -> Result<(), Self::Error> {
+                        Err(Error::InvalidCall)
+                    }
+
+                    /// All the other methods block until the end of the
+                    /// transaction, so there is nothing to flush
+                    fn flush(&mut self) -> Result<(), Self::Error> {
+                        Ok(())
+                    }
+                }
+
                 impl hal::blocking::spi::Transfer<$TY> for Spi<$SPIX, Enabled, $TY> {
                     type Error = Error;
 
@@ -1305,7 +2023,119 @@ macro_rules! spi {
                         self.transfer_internal_w(words)
                     }
                 }
+
+                impl embedded_hal_1::spi::ErrorType for Spi<$SPIX, Enabled, $TY> {
+                    type Error = Error;
+                }
+
+                impl embedded_hal_1::spi::SpiBus<$TY> for Spi<$SPIX, Enabled, $TY> {
+                    /// Reads `words`, sending zeros in full duplex mode
+                    fn read(&mut self, words: &mut [$TY]) -> Result<(), Self::Error> {
+                        match self.communication_mode {
+                            CommunicationMode::Receiver => self.receive_internal(words),
+                            CommunicationMode::Transmitter => Err(Error::InvalidCall),
+                            CommunicationMode::FullDuplex => {
+                                words.fill(0);
+                                self.transfer_internal_rw(words)
+                            }
+                        }
+                    }
+
+                    fn write(&mut self, words: &[$TY]) -> Result<(), Self::Error> {
+                        self.transfer_internal_w(words)
+                    }
+
+                    fn transfer(&mut self, read: &mut [$TY], write: &[$TY]) -> Result<(), Self::Error> {
+                        self.transfer_internal_split(read, write)
+                    }
+
+                    fn transfer_in_place(&mut self, words: &mut [$TY]) -> Result<(), Self::Error> {
+                        self.transfer_internal_rw(words)
+                    }
+
+                    /// All the other methods block until the last word has
+                    /// been received, so there is nothing to flush
+                    fn flush(&mut self) -> Result<(), Self::Error> {
+                        Ok(())
+                    }
+                }
             )+
+
+            impl Spi<$SPIX, Enabled, u8> {
+                /// Runs `f` with the data size of the SPI temporarily set to
+                /// the size of `W`, and the FIFO threshold to one word
+                fn with_word_size<W, R>(
+                    &mut self,
+                    f: impl FnOnce(&mut Spi<$SPIX, Enabled, W>) -> R,
+                ) -> R {
+                    let bits = 8 * core::mem::size_of::<W>() as u8;
+
+                    while self.spi.sr.read().txc().is_ongoing() {}
+
+                    // CFG1 can only be written whilst SPE is cleared
+                    let cfg1 = self.spi.cfg1.read();
+                    let (dsize, fthlv) = (cfg1.dsize().bits(), cfg1.fthlv().bits());
+                    self.internal_disable();
+                    self.spi.cfg1.modify(|_, w| w.dsize().bits(bits - 1).fthlv().one_frame());
+                    self.internal_enable();
+
+                    let result = {
+                        let mut wide = Spi {
+                            // unsafe: the SPI is a zero sized handle to its
+                            // registers, which are not accessed through
+                            // `self` whilst `wide` exists
+                            spi: unsafe { ptr::read(&self.spi) },
+                            hardware_cs_mode: self.hardware_cs_mode,
+                            communication_mode: self.communication_mode,
+                            _word: PhantomData,
+                            _ed: PhantomData,
+                        };
+                        f(&mut wide)
+                    };
+
+                    while self.spi.sr.read().txc().is_ongoing() {}
+
+                    self.internal_disable();
+                    self.spi.cfg1.modify(|_, w| w.dsize().bits(dsize).fthlv().bits(fthlv));
+                    self.internal_enable();
+
+                    result
+                }
+
+                /// Transmits the 16-bit `words`, and replaces them with the
+                /// words received. The SPI is switched to 16-bit words for
+                /// the transfer
+                pub fn transfer16(&mut self, words: &mut [u16]) -> Result<(), Error> {
+                    self.with_word_size(|spi: &mut Spi<$SPIX, Enabled, u16>| {
+                        spi.transfer_internal_rw(words)
+                    })
+                }
+
+                /// Transmits the 16-bit `words`. The SPI is switched to
+                /// 16-bit words for the transfer
+                pub fn write16(&mut self, words: &[u16]) -> Result<(), Error> {
+                    self.with_word_size(|spi: &mut Spi<$SPIX, Enabled, u16>| {
+                        spi.transfer_internal_w(words)
+                    })
+                }
+
+                /// Transmits the 32-bit `words`, and replaces them with the
+                /// words received. The SPI is switched to 32-bit words for
+                /// the transfer
+                pub fn transfer32(&mut self, words: &mut [u32]) -> Result<(), Error> {
+                    self.with_word_size(|spi: &mut Spi<$SPIX, Enabled, u32>| {
+                        spi.transfer_internal_rw(words)
+                    })
+                }
+
+                /// Transmits the 32-bit `words`. The SPI is switched to
+                /// 32-bit words for the transfer
+                pub fn write32(&mut self, words: &[u32]) -> Result<(), Error> {
+                    self.with_word_size(|spi: &mut Spi<$SPIX, Enabled, u32>| {
+                        spi.transfer_internal_w(words)
+                    })
+                }
+            }
         )+
 	}
 }
@@ -1497,3 +2327,168 @@ spi45sel! {
 spi6sel! {
     SPI6,
 }
+
+/// An SPI bus together with a chip select pin, implementing the
+/// embedded-hal 1.0
+/// [`SpiDevice`](embedded_hal_1::spi::SpiDevice) trait
+///
+/// The chip select pin is driven low for the duration of each transaction.
+/// The bus is owned exclusively by this device, so this is not suitable for
+/// sharing a bus between several devices. With the `bus` feature, the
+/// devices in [`bus::spi`](crate::bus::spi) share a bus.
+///
+/// ```
+/// use stm32h7xx_hal::spi::SpiDevice;
+///
+/// let spi: spi::Spi<_, _, u8> = dp.SPI1.spi(...);
+/// let cs = gpioa.pa4.into_push_pull_output();
+///
+/// let mut device = SpiDevice::new(spi, cs, &ccdr.clocks);
+/// ```
+pub struct SpiDevice<SPI, CS> {
+    spi: SPI,
+    cs: CS,
+    c_ck: Hertz,
+}
+
+impl<SPI, CS> SpiDevice<SPI, CS>
+where
+    CS: hal::digital::v2::OutputPin<Error = Infallible>,
+{
+    /// Creates a device from an SPI bus and a chip select pin. The chip
+    /// select pin is set high
+    ///
+    /// The core clock is used to implement delay operations within a
+    /// transaction
+    pub fn new(spi: SPI, mut cs: CS, clocks: &CoreClocks) -> Self {
+        cs.set_high().ok();
+
+        SpiDevice {
+            spi,
+            cs,
+            c_ck: clocks.c_ck(),
+        }
+    }
+
+    /// Releases the SPI bus and the chip select pin
+    pub fn free(self) -> (SPI, CS) {
+        (self.spi, self.cs)
+    }
+}
+
+impl<SPI, CS> embedded_hal_1::spi::ErrorType for SpiDevice<SPI, CS>
+where
+    SPI: embedded_hal_1::spi::ErrorType,
+{
+    type Error = SPI::Error;
+}
+
+impl<W, SPI, CS> embedded_hal_1::spi::SpiDevice<W> for SpiDevice<SPI, CS>
+where
+    W: Copy + 'static,
+    SPI: embedded_hal_1::spi::SpiBus<W>,
+    CS: hal::digital::v2::OutputPin<Error = Infallible>,
+{
+    fn transaction(
+        &mut self,
+        operations: &mut [embedded_hal_1::spi::Operation<'_, W>],
+    ) -> Result<(), Self::Error> {
+        transaction(&mut self.spi, &mut self.cs, self.c_ck, operations)
+    }
+}
+
+/// Performs a sequence of operations on `spi`, with the chip select pin `cs`
+/// driven low for their duration. The core clock `c_ck` is used to implement
+/// delay operations
+pub(crate) fn transaction<W, SPI, CS>(
+    spi: &mut SPI,
+    cs: &mut CS,
+    c_ck: Hertz,
+    operations: &mut [embedded_hal_1::spi::Operation<'_, W>],
+) -> Result<(), SPI::Error>
+where
+    W: Copy + 'static,
+    SPI: embedded_hal_1::spi::SpiBus<W>,
+    CS: hal::digital::v2::OutputPin<Error = Infallible>,
+{
+    use embedded_hal_1::spi::Operation;
+
+    cs.set_low().ok();
+
+    let result = operations.iter_mut().try_for_each(|op| match op {
+        Operation::Read(words) => spi.read(words),
+        Operation::Write(words) => spi.write(words),
+        Operation::Transfer(read, write) => spi.transfer(read, write),
+        Operation::TransferInPlace(words) => spi.transfer_in_place(words),
+        Operation::DelayNs(ns) => {
+            let cycles =
+                (*ns as u64 * c_ck.raw() as u64 + 999_999_999) / 1_000_000_000;
+            cortex_m::asm::delay(cycles as u32);
+            Ok(())
+        }
+    });
+    let flush = spi.flush();
+
+    cs.set_high().ok();
+
+    result.and(flush)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn baud_rate() {
+        assert!(matches!(
+            checked_baud_rate_divider(100_000_000, 25_000_000),
+            Ok(MBR::Div4)
+        ));
+        // 50MHz / 128 = 390.6kHz
+        assert!(matches!(
+            checked_baud_rate_divider(50_000_000, 400_000),
+            Ok(MBR::Div128)
+        ));
+        // 100MHz / 4 = 25MHz, 4.2% above 24MHz
+        assert!(matches!(
+            checked_baud_rate_divider(100_000_000, 24_000_000),
+            Ok(MBR::Div4)
+        ));
+        // 100MHz / 4 = 25MHz, 16.7% below 30MHz
+        assert_eq!(
+            checked_baud_rate_divider(100_000_000, 30_000_000),
+            Err(BaudRateError {
+                closest: Hertz::from_raw(25_000_000)
+            })
+        );
+        // Faster than the kernel clock / 2
+        assert!(checked_baud_rate_divider(100_000_000, 60_000_000).is_err());
+        // Slower than the kernel clock / 256
+        assert!(checked_baud_rate_divider(100_000_000, 100_000).is_err());
+    }
+
+    #[test]
+    fn fifo_threshold() {
+        use FifoThreshold::*;
+
+        assert_eq!(fifo_threshold_level(OneByte, 1), 0);
+        assert_eq!(fifo_threshold_level(FourBytes, 1), 3);
+        assert_eq!(fifo_threshold_level(TwoBytes, 2), 0);
+        assert_eq!(fifo_threshold_level(ThreeBytes, 2), 0);
+        assert_eq!(fifo_threshold_level(FourBytes, 2), 1);
+        assert_eq!(fifo_threshold_level(FourBytes, 4), 0);
+        // Smaller than one frame
+        assert_eq!(fifo_threshold_level(OneByte, 2), 0);
+    }
+
+    #[test]
+    fn rx_below_fifo_threshold() {
+        // One 8 bit frame received with a two byte threshold: RXP is not set,
+        // but the frame can be read, so a one word transfer completes
+        assert!(rx_frame_available(false, false, 1));
+        // Two 16 bit frames received with an eight byte threshold
+        assert!(rx_frame_available(false, true, 0));
+        assert!(rx_frame_available(true, false, 0));
+        assert!(!rx_frame_available(false, false, 0));
+    }
+}
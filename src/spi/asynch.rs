@@ -0,0 +1,259 @@
+//! Async SPI using DMA1 or DMA2
+
+use core::future::poll_fn;
+use core::ops::Deref;
+use core::ptr;
+use core::task::{Context, Poll};
+
+use embedded_hal_async::spi::SpiBus;
+
+use super::{Enabled, Error, Spi};
+use crate::dma::dma::{start_async_transfer, AsyncStream};
+use crate::dma::traits::TargetAddress;
+use crate::dma::{MemoryToPeripheral, PeripheralToMemory};
+use crate::stm32::spi1;
+
+type P2M = PeripheralToMemory;
+type M2P = MemoryToPeripheral;
+
+/// Source of the words transmitted by [`read`](SpiBus::read)
+static DUMMY: u8 = 0;
+/// Destination of the words received by [`write`](SpiBus::write)
+static mut DISCARD: u8 = 0;
+
+/// Maximum number of words in a single DMA transfer
+const MAX_TRANSFER: usize = u16::MAX as usize;
+
+/// SPI bus implementing the [embedded-hal-async] `SpiBus` trait, with both
+/// directions transferred by DMA
+///
+/// Each operation uses the Tx stream to write to the SPI, and the Rx stream to
+/// read from it. The interrupt handlers for both streams must call
+/// [`on_interrupt`](AsyncStream::on_interrupt).
+///
+/// The buffers passed to each operation must be in memory that is accessible
+/// by DMA1 and DMA2, and must not be cached by the data cache.
+///
+/// [embedded-hal-async]: https://docs.rs/embedded-hal-async
+pub struct SpiAsync<SPI, TX, RX> {
+    spi: Spi<SPI, Enabled, u8>,
+    tx: TX,
+    rx: RX,
+}
+
+impl<SPI, TX, RX> SpiAsync<SPI, TX, RX>
+where
+    SPI: Deref<Target = spi1::RegisterBlock>
+        + TargetAddress<M2P, MemSize = u8>
+        + TargetAddress<P2M, MemSize = u8>,
+    TX: AsyncStream,
+    RX: AsyncStream,
+{
+    /// Creates an async SPI bus from an enabled SPI and two DMA streams
+    pub fn new(spi: Spi<SPI, Enabled, u8>, tx: TX, rx: RX) -> Self {
+        SpiAsync { spi, tx, rx }
+    }
+
+    /// Releases the SPI and DMA streams
+    pub fn free(self) -> (Spi<SPI, Enabled, u8>, TX, RX) {
+        (self.spi, self.tx, self.rx)
+    }
+
+    /// Starts a full duplex transfer of `len` bytes, following the order
+    /// recommended by the reference manual for communication using DMA
+    fn start(&mut self, rx: (usize, bool), tx: (usize, bool), len: u16) {
+        let spi = &self.spi.spi;
+
+        // CFG1 can only be written whilst SPE is cleared
+        spi.cr1
+            .write(|w| w.ssi().slave_not_selected().spe().disabled());
+        spi.cfg1.modify(|_, w| w.rxdmaen().enabled());
+
+        // unsafe: the memory addresses are valid until the streams are
+        // disabled by `stop`
+        unsafe {
+            start_async_transfer::<_, P2M, _>(
+                &mut self.rx,
+                spi,
+                rx.0,
+                rx.1,
+                len,
+            );
+            start_async_transfer::<_, M2P, _>(
+                &mut self.tx,
+                spi,
+                tx.0,
+                tx.1,
+                len,
+            );
+        }
+
+        spi.cfg1.modify(|_, w| w.txdmaen().enabled());
+
+        spi.ifcr.write(|w| w.modfc().clear()); // SPE cannot be set when MODF is set
+        spi.cr1
+            .write(|w| w.ssi().slave_not_selected().spe().enabled());
+        spi.cr1.modify(|_, w| w.cstart().started());
+    }
+
+    /// Aborts any ongoing transfer, suspends the SPI and disables its DMA
+    /// requests
+    fn stop(&mut self) {
+        self.tx.disable();
+        self.rx.disable();
+
+        let spi = &self.spi.spi;
+        spi.cr1.modify(|_, w| w.csusp().requested());
+        while spi.cr1.read().cstart().is_started() {}
+        spi.ifcr
+            .write(|w| w.txtfc().clear().eotc().clear().suspc().clear());
+
+        spi.cr1
+            .write(|w| w.ssi().slave_not_selected().spe().disabled());
+        spi.cfg1
+            .modify(|_, w| w.rxdmaen().disabled().txdmaen().disabled());
+
+        spi.ifcr.write(|w| w.modfc().clear()); // SPE cannot be set when MODF is set
+        spi.cr1
+            .write(|w| w.ssi().slave_not_selected().spe().enabled());
+    }
+
+    /// Polls for the end of both DMA transfers
+    fn poll_complete(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Error>> {
+        let tx = self.tx.poll_transfer(cx);
+        let rx = self.rx.poll_transfer(cx);
+
+        match (tx, rx) {
+            (Poll::Ready(false), _) | (_, Poll::Ready(false)) => {
+                Poll::Ready(Err(Error::Dma))
+            }
+            (Poll::Ready(true), Poll::Ready(true)) => {
+                let sr = self.spi.spi.sr.read();
+                Poll::Ready(if sr.ovr().bit_is_set() {
+                    Err(Error::Overrun)
+                } else if sr.modf().bit_is_set() {
+                    Err(Error::ModeFault)
+                } else {
+                    Ok(())
+                })
+            }
+            _ => Poll::Pending,
+        }
+    }
+
+    /// Transfers `len` bytes in DMA transfers of up to 65535 bytes. Each
+    /// address is incremented if its flag is set
+    async fn transfer_dma(
+        &mut self,
+        (rx, rx_increment): (usize, bool),
+        (tx, tx_increment): (usize, bool),
+        len: usize,
+    ) -> Result<(), Error> {
+        let mut offset = 0;
+        while offset < len {
+            let n = core::cmp::min(len - offset, MAX_TRANSFER);
+            let rx_address = if rx_increment { rx + offset } else { rx };
+            let tx_address = if tx_increment { tx + offset } else { tx };
+
+            self.start(
+                (rx_address, rx_increment),
+                (tx_address, tx_increment),
+                n as u16,
+            );
+
+            // The transfer is stopped when the guard is dropped, including
+            // when this future is dropped before it completes
+            let transaction = Transaction(&mut *self);
+            poll_fn(|cx| transaction.0.poll_complete(cx)).await?;
+            drop(transaction);
+
+            offset += n;
+        }
+
+        Ok(())
+    }
+}
+
+/// Stops the ongoing transfer when dropped
+struct Transaction<'a, SPI, TX, RX>(&'a mut SpiAsync<SPI, TX, RX>)
+where
+    SPI: Deref<Target = spi1::RegisterBlock>
+        + TargetAddress<M2P, MemSize = u8>
+        + TargetAddress<P2M, MemSize = u8>,
+    TX: AsyncStream,
+    RX: AsyncStream;
+
+impl<'a, SPI, TX, RX> Drop for Transaction<'a, SPI, TX, RX>
+where
+    SPI: Deref<Target = spi1::RegisterBlock>
+        + TargetAddress<M2P, MemSize = u8>
+        + TargetAddress<P2M, MemSize = u8>,
+    TX: AsyncStream,
+    RX: AsyncStream,
+{
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+impl<SPI, TX, RX> embedded_hal_1::spi::ErrorType for SpiAsync<SPI, TX, RX> {
+    type Error = Error;
+}
+
+impl<SPI, TX, RX> SpiBus<u8> for SpiAsync<SPI, TX, RX>
+where
+    SPI: Deref<Target = spi1::RegisterBlock>
+        + TargetAddress<M2P, MemSize = u8>
+        + TargetAddress<P2M, MemSize = u8>,
+    TX: AsyncStream,
+    RX: AsyncStream,
+{
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        let rx = (words.as_mut_ptr() as usize, true);
+        let tx = (ptr::addr_of!(DUMMY) as usize, false);
+        self.transfer_dma(rx, tx, words.len()).await
+    }
+
+    async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        let rx = (ptr::addr_of_mut!(DISCARD) as usize, false);
+        let tx = (words.as_ptr() as usize, true);
+        self.transfer_dma(rx, tx, words.len()).await
+    }
+
+    async fn transfer(
+        &mut self,
+        read: &mut [u8],
+        write: &[u8],
+    ) -> Result<(), Self::Error> {
+        let common = core::cmp::min(read.len(), write.len());
+        let (read, read_rest) = read.split_at_mut(common);
+        let (write, write_rest) = write.split_at(common);
+
+        let rx = (read.as_mut_ptr() as usize, true);
+        let tx = (write.as_ptr() as usize, true);
+        self.transfer_dma(rx, tx, common).await?;
+
+        // At most one of these is not empty
+        self.read(read_rest).await?;
+        self.write(write_rest).await
+    }
+
+    async fn transfer_in_place(
+        &mut self,
+        words: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        // Each word is read by the Tx stream before the Rx stream writes the
+        // word received in its place
+        let address = words.as_mut_ptr() as usize;
+        self.transfer_dma((address, true), (address, true), words.len())
+            .await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        // Every operation is complete when it returns
+        Ok(())
+    }
+}
@@ -1,4 +1,16 @@
 //! Time units
+//!
+//! The types in this module are aliases of the [`fugit`] rate and duration
+//! types, so values from other crates using `fugit`, such as RTIC
+//! monotonics, can be passed directly to APIs like
+//! `HyperbusConfig::refresh_interval`.
+//! Durations with a different tick rate or backing integer can be
+//! converted with `fugit`'s `convert()` method.
+//!
+//! ```rust
+//! let timeout = fugit::MillisDurationU32::millis(2);
+//! let timeout: MicroSeconds = timeout.convert();
+//! ```
 
 pub use fugit::{
     HertzU32 as Hertz, KilohertzU32 as KiloHertz, MegahertzU32 as MegaHertz,
@@ -4,6 +4,70 @@
 //!
 //! - [Blinky using a Timer](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/blinky_timer.rs)
 //! - [64 bit microsecond timer](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/tick_timer.rs)
+//! - [Low power timer](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/tick_lptimer.rs)
+//! - [Cascaded timers](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/timer_cascade.rs)
+//!
+//! # Free Running Counters
+//!
+//! The counter of a timer can be read and written directly, to measure
+//! elapsed time. TIM2 and TIM5 have 32-bit counters. The advanced-control
+//! timers and TIM2 to TIM5 can also count down.
+//!
+//! ```
+//! let mut timer = dp.TIM2.tick_timer(1.MHz(), ccdr.peripheral.TIM2, &ccdr.clocks);
+//! timer.set_counter(0);
+//! // ...
+//! let elapsed_us = timer.counter();
+//!
+//! timer.set_direction(CountDirection::Down);
+//! ```
+//!
+//! # Master and Slave Timers
+//!
+//! The trigger output of a master timer can control the counter of a slave
+//! timer. The master is configured with `set_master_mode`, and the slave with
+//! `sync_to_master`. Only the connections in the internal trigger table of
+//! the reference manual are accepted by the compiler.
+//!
+//! ```
+//! let master = tim1.set_master_mode(MasterMode::Update);
+//! tim2.sync_to_master(master, SlaveMode::ExternalClock); // Counts TIM1 overflows
+//! ```
+//!
+//! # DMA Burst
+//!
+//! On each update event, a DMA burst can write several consecutive timer
+//! registers, for example all the CCRx registers at once. This plays out PWM
+//! patterns such as the bit encoding of WS2812 LED strips, or waveforms on
+//! several channels. The burst is configured with the first register and
+//! the number of registers, and the returned [`DmaBurst`] is the peripheral
+//! of a DMA transfer.
+//!
+//! The channels whose CCRx registers are written by the burst are put in
+//! PWM mode 1 and their outputs are enabled. Their pins must be configured
+//! in the alternate function mode of the timer.
+//!
+//! ```
+//! static mut DUTY: [u16; 64] = [...]; // CCR1..CCR4 for 16 periods
+//!
+//! let _ch1 = gpioa.pa0.into_alternate::<2>(); // TIM5_CH1, and so on
+//! let burst = timer.dma_burst(BurstRegister::Ccr1, 4);
+//! let config = DmaConfig::default().memory_increment(true);
+//! let mut transfer: Transfer<_, _, MemoryToPeripheral, _, _> =
+//!     Transfer::init(streams.0, burst, unsafe { &mut DUTY }, None, config);
+//! transfer.start(|burst| burst.timer().resume());
+//! ```
+//!
+//! # Low Power Timers
+//!
+//! The low power timers `LPTIM[1-5]` can keep running in Stop mode when
+//! their kernel clock is the LSE or LSI. The kernel clock is selected with
+//! `kernel_clk_mux` on the `rec` type before the timer is constructed. The
+//! LSE must already be running, for example after configuring the RTC.
+//!
+//! The `Event::TimeOut` interrupt can be used to wake up from Stop mode once
+//! the matching EXTI line is enabled. PWM output from a low power timer is
+//! supported by the [pwm](crate::pwm) module.
 
 // TODO: on the h7x3 at least, only TIM2, TIM3, TIM4, TIM5 can support 32 bits.
 // TIM1 is 16 bit.
@@ -32,7 +96,7 @@ use crate::stm32::rcc::{cdccip2r as ccip2r, srdccipr};
 #[cfg(not(feature = "rm0455"))]
 use crate::stm32::rcc::{d2ccip2r as ccip2r, d3ccipr as srdccipr};
 
-use crate::rcc::{rec, CoreClocks, ResetEnable};
+use crate::rcc::{lse_ck, rec, CoreClocks, ResetEnable};
 use crate::stm32;
 use crate::time::Hertz;
 
@@ -78,7 +142,7 @@ impl GetClk for LPTIM1 {
             Some(ccip2r::LPTIM1SEL_A::RccPclk1) => Some(clocks.pclk1()),
             Some(ccip2r::LPTIM1SEL_A::Pll2P) => clocks.pll2_p_ck(),
             Some(ccip2r::LPTIM1SEL_A::Pll3R) => clocks.pll3_r_ck(),
-            Some(ccip2r::LPTIM1SEL_A::Lse) => unimplemented!(),
+            Some(ccip2r::LPTIM1SEL_A::Lse) => lse_ck(),
             Some(ccip2r::LPTIM1SEL_A::Lsi) => clocks.lsi_ck(),
             Some(ccip2r::LPTIM1SEL_A::Per) => clocks.per_ck(),
             _ => unreachable!(),
@@ -100,7 +164,7 @@ impl GetClk for LPTIM2 {
             Some(srdccipr::LPTIM2SEL_A::RccPclk4) => Some(clocks.pclk4()),
             Some(srdccipr::LPTIM2SEL_A::Pll2P) => clocks.pll2_p_ck(),
             Some(srdccipr::LPTIM2SEL_A::Pll3R) => clocks.pll3_r_ck(),
-            Some(srdccipr::LPTIM2SEL_A::Lse) => unimplemented!(),
+            Some(srdccipr::LPTIM2SEL_A::Lse) => lse_ck(),
             Some(srdccipr::LPTIM2SEL_A::Lsi) => clocks.lsi_ck(),
             Some(srdccipr::LPTIM2SEL_A::Per) => clocks.per_ck(),
             _ => unreachable!(),
@@ -120,7 +184,7 @@ impl GetClk for LPTIM3 {
             0 => Some(clocks.pclk4()),
             1 => clocks.pll2_p_ck(),
             2 => clocks.pll3_r_ck(),
-            3 => unimplemented!(),
+            3 => lse_ck(),
             4 => clocks.lsi_ck(),
             5 => clocks.per_ck(),
             _ => unreachable!(),
@@ -143,7 +207,7 @@ macro_rules! impl_clk_lptim345 {
                         Some(srdccipr::LPTIM345SEL_A::RccPclk4) => Some(clocks.pclk4()),
                         Some(srdccipr::LPTIM345SEL_A::Pll2P) => clocks.pll2_p_ck(),
                         Some(srdccipr::LPTIM345SEL_A::Pll3R) => clocks.pll3_r_ck(),
-                        Some(srdccipr::LPTIM345SEL_A::Lse) => unimplemented!(),
+                        Some(srdccipr::LPTIM345SEL_A::Lse) => lse_ck(),
                         Some(srdccipr::LPTIM345SEL_A::Lsi) => clocks.lsi_ck(),
                         Some(srdccipr::LPTIM345SEL_A::Per) => clocks.per_ck(),
                         _ => unreachable!(),
@@ -282,9 +346,55 @@ pub enum Event {
     TimeOut,
 }
 
+/// A timer counting up at a fixed frequency, for drivers that measure
+/// short intervals with the counter of a timer
+///
+/// Each method is equivalent to the method of the same name on the
+/// [`Timer`].
+pub trait TickTimer {
+    /// Configures the timer to count up at the given frequency
+    fn set_tick_freq(&mut self, frequency: Hertz);
+
+    /// Applies frequency changes immediately
+    fn apply_freq(&mut self);
+
+    /// Resets the counter
+    fn reset_counter(&mut self);
+
+    /// Reads the counter
+    fn counter(&self) -> u32;
+
+    /// Pauses the timer
+    fn pause(&mut self);
+
+    /// Resumes the timer
+    fn resume(&mut self);
+}
+
 macro_rules! hal {
     ($($TIMX:ident: ($timX:ident, $Rec:ident, $cntType:ty),)+) => {
         $(
+            impl TickTimer for Timer<$TIMX> {
+                fn set_tick_freq(&mut self, frequency: Hertz) {
+                    Timer::<$TIMX>::set_tick_freq(self, frequency)
+                }
+                fn apply_freq(&mut self) {
+                    Timer::<$TIMX>::apply_freq(self)
+                }
+                fn reset_counter(&mut self) {
+                    Timer::<$TIMX>::reset_counter(self)
+                }
+                fn counter(&self) -> u32 {
+                    Timer::<$TIMX>::counter(self)
+                }
+                fn pause(&mut self) {
+                    Timer::<$TIMX>::pause(self)
+                }
+                fn resume(&mut self) {
+                    Timer::<$TIMX>::resume(self)
+                }
+            }
+
             impl Periodic for Timer<$TIMX> {}
 
             impl CountDown for Timer<$TIMX> {
@@ -493,6 +603,14 @@ macro_rules! hal {
                     self.tim.cnt.read().cnt().bits().into()
                 }
 
+                /// Set the counter of the TIM peripheral
+                ///
+                /// The counter of the 16-bit timers only holds the lower 16
+                /// bits of `count`.
+                pub fn set_counter(&mut self, count: u32) {
+                    self.tim.cnt.write(|w| w.cnt().bits(count as $cntType));
+                }
+
                 /// Start listening for `event`
                 pub fn listen(&mut self, event: Event) {
                     match event {
@@ -604,6 +722,477 @@ hal! {
     TIM24: (tim24, Tim24, u32),
 }
 
+/// Counting direction of a timer
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum CountDirection {
+    /// Counts from 0 up to the auto-reload value
+    Up,
+    /// Counts from the auto-reload value down to 0
+    Down,
+}
+
+macro_rules! direction {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl Timer<$TIMX> {
+                /// Sets the counting direction of the timer
+                ///
+                /// When counting down, the counter is reloaded with the
+                /// auto-reload value on each underflow.
+                pub fn set_direction(&mut self, direction: CountDirection) {
+                    self.tim.cr1.modify(|_, w| {
+                        w.dir().bit(direction == CountDirection::Down)
+                    });
+                }
+
+                /// Returns the counting direction of the timer
+                pub fn direction(&self) -> CountDirection {
+                    if self.tim.cr1.read().dir().bit_is_set() {
+                        CountDirection::Down
+                    } else {
+                        CountDirection::Up
+                    }
+                }
+            }
+        )+
+    }
+}
+
+// Only the advanced-control timers and the general-purpose timers TIM2 to
+// TIM5 can count down
+direction! {
+    TIM1, TIM2, TIM3, TIM4, TIM5, TIM8,
+}
+#[cfg(feature = "rm0468")]
+direction! {
+    TIM23, TIM24,
+}
+
+/// Timer trigger outputs (TRGO) that can pace both the DAC and the ADC
+///
+/// Returned by `trigger_on_update` on the [`Timer`]s that support it, and
+/// accepted by the [dac](crate::dac) and [adc](crate::adc) modules to select
+/// their hardware trigger.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Trgo {
+    Tim1,
+    Tim2,
+    Tim4,
+    Tim6,
+    Tim8,
+}
+
+/// A [`Timer`] with a trigger output that can pace the DAC or the ADC
+///
+/// Each method is equivalent to the method of the same name on the
+/// [`Timer`].
+pub trait TriggerTimer {
+    /// Routes the update event of the timer to its trigger output TRGO
+    fn trigger_on_update(&mut self) -> Trgo;
+
+    /// Configures the timer to underflow at `frequency`
+    fn set_freq(&mut self, frequency: Hertz);
+
+    /// Applies frequency changes immediately
+    fn apply_freq(&mut self);
+
+    /// Pauses the timer
+    fn pause(&mut self);
+
+    /// Resumes the timer
+    fn resume(&mut self);
+}
+
+macro_rules! trgo {
+    ($($TIMX:ident: $trgo:ident,)+) => {
+        $(
+            impl Timer<$TIMX> {
+                /// Routes the update event of the timer to its trigger
+                /// output TRGO. Returns the trigger, to be used by the DAC
+                /// or ADC
+                pub fn trigger_on_update(&mut self) -> Trgo {
+                    self.tim.cr2.modify(|_, w| w.mms().update());
+
+                    Trgo::$trgo
+                }
+            }
+
+            impl TriggerTimer for Timer<$TIMX> {
+                fn trigger_on_update(&mut self) -> Trgo {
+                    Timer::<$TIMX>::trigger_on_update(self)
+                }
+                fn set_freq(&mut self, frequency: Hertz) {
+                    Timer::<$TIMX>::set_freq(self, frequency)
+                }
+                fn apply_freq(&mut self) {
+                    Timer::<$TIMX>::apply_freq(self)
+                }
+                fn pause(&mut self) {
+                    Timer::<$TIMX>::pause(self)
+                }
+                fn resume(&mut self) {
+                    Timer::<$TIMX>::resume(self)
+                }
+            }
+        )+
+    }
+}
+
+trgo! {
+    TIM1: Tim1,
+    TIM2: Tim2,
+    TIM4: Tim4,
+    TIM6: Tim6,
+    TIM8: Tim8,
+}
+
+/// Event output on the trigger output (TRGO) of a master timer
+///
+/// The value of each variant is its TIMx_CR2.MMS value. The compare events
+/// are not available on the basic timers TIM6 and TIM7.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MasterMode {
+    /// The UG bit in TIMx_EGR, or a reset from the slave mode controller
+    Reset = 0,
+    /// The counter enable signal
+    Enable = 1,
+    /// The update event
+    Update = 2,
+    /// A pulse on each capture or compare match of channel 1
+    ComparePulse = 3,
+    /// The OC1REF signal
+    Compare1 = 4,
+    /// The OC2REF signal
+    Compare2 = 5,
+    /// The OC3REF signal
+    Compare3 = 6,
+    /// The OC4REF signal
+    Compare4 = 7,
+}
+
+/// Action of a slave timer on a rising edge of its trigger input
+///
+/// The value of each variant is its TIMx_SMCR.SMS value
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SlaveMode {
+    /// The counter is reset and the registers are updated
+    Reset = 0b0100,
+    /// The counter only runs whilst the trigger is high
+    Gated = 0b0101,
+    /// The counter is started, but not reset
+    Trigger = 0b0110,
+    /// The counter is clocked by the trigger. Cascading a master with
+    /// [`MasterMode::Update`] makes the slave count the overflows of the
+    /// master
+    ExternalClock = 0b0111,
+}
+
+/// Trigger output of a master timer
+///
+/// Returned by `set_master_mode`, and used to synchronise a slave timer
+/// with `sync_to_master`
+#[derive(Copy, Clone, Debug)]
+pub struct TimerMaster<TIM> {
+    mode: MasterMode,
+    _tim: PhantomData<TIM>,
+}
+
+impl<TIM> TimerMaster<TIM> {
+    /// The event output on the trigger output
+    pub fn mode(&self) -> MasterMode {
+        self.mode
+    }
+}
+
+/// Timers that can be a slave of the timer `MASTER`, through one of their
+/// internal trigger inputs
+///
+/// This trait is implemented following the internal trigger connections
+/// table of the reference manual.
+pub trait TimerSlave<MASTER>: crate::Sealed {
+    /// Internal trigger input (ITRx) connected to `MASTER`
+    const ITR: u8;
+}
+
+/// Returns the TIMx_SMCR value that selects the internal trigger `itr` in
+/// slave `mode`, keeping the other fields of `smcr`
+pub(crate) fn smcr_slave_bits(smcr: u32, itr: u8, mode: SlaveMode) -> u32 {
+    let sms = mode as u32;
+    let ts = itr as u32;
+
+    // SMS[2:0] in bits 2:0, SMS[3] in bit 16, TS[2:0] in bits 6:4, TS[4:3]
+    // in bits 21:20
+    let mask = 0x7 | (1 << 16) | (0x7 << 4) | (0x3 << 20);
+    let bits = (sms & 0x7)
+        | ((sms >> 3) << 16)
+        | ((ts & 0x7) << 4)
+        | ((ts >> 3) << 20);
+
+    (smcr & !mask) | bits
+}
+
+macro_rules! master {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl Timer<$TIMX> {
+                /// Selects the event output on the trigger output TRGO of
+                /// the timer. Returns the trigger output, to be used to
+                /// synchronise a slave timer
+                pub fn set_master_mode(&mut self, mode: MasterMode) -> TimerMaster<$TIMX> {
+                    #[allow(unused_unsafe)] // method is safe for some timers
+                    self.tim.cr2.modify(|_, w| unsafe { w.mms().bits(mode as u8) });
+
+                    TimerMaster { mode, _tim: PhantomData }
+                }
+            }
+        )+
+    }
+}
+
+master! {
+    TIM1, TIM2, TIM3, TIM4, TIM5, TIM6, TIM7, TIM8, TIM15,
+}
+
+macro_rules! slave {
+    ($($TIMX:ident: [$($MASTER:ident: $itr:literal),+],)+) => {
+        $(
+            impl crate::Sealed for $TIMX {}
+
+            $(
+                impl TimerSlave<$MASTER> for $TIMX {
+                    const ITR: u8 = $itr;
+                }
+            )+
+
+            impl Timer<$TIMX> {
+                /// Synchronises the timer to the trigger output of
+                /// `master`. Each rising edge of the trigger output has the
+                /// effect selected by `mode`
+                ///
+                /// Only the masters that are connected to one of the
+                /// internal trigger inputs of this timer are accepted.
+                pub fn sync_to_master<MASTER>(&mut self, _master: TimerMaster<MASTER>, mode: SlaveMode)
+                where
+                    $TIMX: TimerSlave<MASTER>,
+                {
+                    let itr = <$TIMX as TimerSlave<MASTER>>::ITR;
+
+                    // unsafe: only the SMS and TS fields are modified
+                    self.tim.smcr.modify(|r, w| unsafe {
+                        w.bits(smcr_slave_bits(r.bits(), itr, mode))
+                    });
+                }
+
+                /// Stops synchronising the timer to a master. The counter is
+                /// clocked by the internal clock
+                pub fn disable_slave_mode(&mut self) {
+                    // unsafe: only the SMS field is cleared
+                    self.tim.smcr.modify(|r, w| unsafe {
+                        w.bits(r.bits() & !(0x7 | (1 << 16)))
+                    });
+                }
+            }
+        )+
+    }
+}
+
+// Refer to the TIMx internal trigger connection tables in RM0433 Rev 7
+slave! {
+    TIM1: [TIM15: 0, TIM2: 1, TIM3: 2, TIM4: 3],
+    TIM8: [TIM1: 0, TIM2: 1, TIM4: 2, TIM5: 3],
+    TIM2: [TIM1: 0, TIM8: 1, TIM3: 2, TIM4: 3],
+    TIM3: [TIM1: 0, TIM2: 1, TIM15: 2, TIM4: 3],
+    TIM4: [TIM1: 0, TIM2: 1, TIM3: 2, TIM8: 3],
+    TIM5: [TIM1: 0, TIM8: 1, TIM3: 2, TIM4: 3],
+    TIM15: [TIM1: 0, TIM3: 1],
+}
+
+/// First timer register written by a DMA burst
+///
+/// The value of each variant is its TIMx_DCR.DBA value, the offset of the
+/// register in 32-bit words. The offsets are the same for every timer, so
+/// the burst is not shortened for registers that a timer does not have, for
+/// example TIMx_RCR on TIM2 to TIM5. A word of the buffer is still
+/// transferred to each of these reserved offsets, and is discarded.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BurstRegister {
+    Cr1 = 0,
+    Cr2 = 1,
+    Smcr = 2,
+    Dier = 3,
+    Sr = 4,
+    Egr = 5,
+    Ccmr1 = 6,
+    Ccmr2 = 7,
+    Ccer = 8,
+    Cnt = 9,
+    Psc = 10,
+    Arr = 11,
+    Rcr = 12,
+    Ccr1 = 13,
+    Ccr2 = 14,
+    Ccr3 = 15,
+    Ccr4 = 16,
+    Bdtr = 17,
+}
+
+/// Returns the TIMx_DCR value for a burst of `length` registers starting at
+/// `start`
+///
+/// # Panics
+///
+/// Panics if `length` is zero, or if the burst extends beyond TIMx_BDTR
+fn dcr_bits(start: BurstRegister, length: u8) -> u32 {
+    let dba = start as u32;
+    let length = u32::from(length);
+    assert!(
+        length >= 1 && dba + length <= BurstRegister::Bdtr as u32 + 1,
+        "Timer DMA burst must contain between 1 register and TIMx_BDTR"
+    );
+
+    // DBL in bits 12:8, DBA in bits 4:0
+    ((length - 1) << 8) | dba
+}
+
+/// Returns a mask of the channels, bit 0 for channel 1, whose CCRx registers
+/// are written by a burst of `length` registers starting at `start`
+fn burst_channels(start: BurstRegister, length: u8) -> u8 {
+    let (first, end) = (start as u8, start as u8 + length);
+    (0..4)
+        .filter(|ch| (first..end).contains(&(BurstRegister::Ccr1 as u8 + ch)))
+        .fold(0, |mask, ch| mask | (1 << ch))
+}
+
+/// Returns the value of a TIMx_CCMRx register `ccmr` with the output
+/// compare channels in bits 0 and 1 of `channels` set to PWM mode 1, with
+/// the preload enabled
+fn ccmr_pwm_bits(ccmr: u32, channels: u8) -> u32 {
+    (0..2)
+        .filter(|ch| channels & (1 << ch) != 0)
+        .fold(ccmr, |ccmr, ch| {
+            // CCxS (output), OCxPE and OCxM
+            let clear = (0x7B << (8 * ch)) | (1 << (16 + 8 * ch));
+            let set = 0x68 << (8 * ch);
+            (ccmr & !clear) | set
+        })
+}
+
+/// Returns the TIMx_CCER bits that enable the outputs of `channels`
+fn ccer_enable_bits(channels: u8) -> u32 {
+    (0..4)
+        .filter(|ch| channels & (1 << ch) != 0)
+        .fold(0, |ccer, ch| ccer | (1 << (4 * ch)))
+}
+
+/// A timer whose registers are written by a DMA burst on each update event
+///
+/// On each update event the timer makes a DMA request, and the DMA transfers
+/// a burst of words to TIMx_DMAR. The timer writes these to the consecutive
+/// registers selected by [`dma_burst`](Timer#method.dma_burst). This is the
+/// peripheral of a memory to peripheral [`Transfer`](crate::dma::Transfer)
+/// on DMA1 or DMA2. Each word of the buffer is written to one register, so
+/// the buffer length should be a multiple of the burst length.
+pub struct DmaBurst<TIM> {
+    timer: Timer<TIM>,
+}
+
+macro_rules! dma_burst {
+    ($($TIMX:ident: ($cntType:ty, $mux:expr, $channels:expr $(, ccmr2: $ccmr2:ident)? $(, bdtr: $moe:ident)?),)+) => {
+        $(
+            impl Timer<$TIMX> {
+                /// Configures a DMA burst of `length` registers starting at
+                /// `start` on each update event of the timer
+                ///
+                /// The channels whose CCRx registers are written by the burst
+                /// are put in PWM mode 1, with the preload enabled, and their
+                /// outputs are enabled. The pins of these channels must be
+                /// configured in the alternate function mode of the timer.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `length` is zero, or if the burst extends
+                /// beyond TIMx_BDTR
+                pub fn dma_burst(self, start: BurstRegister, length: u8) -> DmaBurst<$TIMX> {
+                    let dcr = dcr_bits(start, length);
+
+                    let channels = burst_channels(start, length) & $channels;
+                    if channels != 0 {
+                        // unsafe: only the CCxS, OCxM and OCxPE bits of the
+                        // channels, and their CCxE bits, are changed
+                        self.tim.ccmr1_output().modify(|r, w| unsafe {
+                            w.bits(ccmr_pwm_bits(r.bits(), channels))
+                        });
+                        $(
+                            self.tim.$ccmr2().modify(|r, w| unsafe {
+                                w.bits(ccmr_pwm_bits(r.bits(), channels >> 2))
+                            });
+                        )?
+                        self.tim.ccer.modify(|r, w| unsafe {
+                            w.bits(r.bits() | ccer_enable_bits(channels))
+                        });
+                        $(
+                            self.tim.bdtr.modify(|_, w| w.$moe().set_bit());
+                        )?
+                    }
+
+                    // unsafe: DBA and DBL were checked by dcr_bits
+                    self.tim.dcr.write(|w| unsafe { w.bits(dcr) });
+                    self.tim.dier.modify(|_, w| w.ude().set_bit());
+
+                    DmaBurst { timer: self }
+                }
+            }
+
+            impl DmaBurst<$TIMX> {
+                /// Returns a mutable reference to the timer, for example to
+                /// pause and resume it
+                pub fn timer(&mut self) -> &mut Timer<$TIMX> {
+                    &mut self.timer
+                }
+
+                /// Stops making DMA requests and returns the timer
+                pub fn free(self) -> Timer<$TIMX> {
+                    let tim = &self.timer.tim;
+                    tim.dier.modify(|_, w| w.ude().clear_bit());
+                    tim.dcr.reset();
+
+                    self.timer
+                }
+            }
+
+            unsafe impl crate::dma::traits::TargetAddress<crate::dma::MemoryToPeripheral>
+                for DmaBurst<$TIMX>
+            {
+                #[inline(always)]
+                fn address(&self) -> usize {
+                    &self.timer.tim.dmar as *const _ as usize
+                }
+
+                type MemSize = $cntType;
+
+                const REQUEST_LINE: Option<u8> = Some($mux as u8);
+            }
+        )+
+    }
+}
+
+dma_burst! {
+    TIM1: (u16, crate::dma::dma::DMAReq::Tim1Up, 0b1111, ccmr2: ccmr2_output, bdtr: moe),
+    TIM2: (u32, crate::dma::dma::DMAReq::Tim2Up, 0b1111, ccmr2: ccmr2_output),
+    TIM3: (u16, crate::dma::dma::DMAReq::Tim3Up, 0b1111, ccmr2: ccmr2_output),
+    TIM4: (u16, crate::dma::dma::DMAReq::Tim4Up, 0b1111, ccmr2: ccmr2_output),
+    TIM5: (u32, crate::dma::dma::DMAReq::Tim5Up, 0b1111, ccmr2: ccmr2_output),
+    TIM8: (u16, crate::dma::dma::DMAReq::Tim8Up, 0b1111, ccmr2: ccmr2_output, bdtr: moe),
+    TIM15: (u16, crate::dma::dma::DMAReq::Tim15Up, 0b0011, bdtr: moe),
+    TIM16: (u16, crate::dma::dma::DMAReq::Tim16Up, 0b0001, bdtr: moe),
+    TIM17: (u16, crate::dma::dma::DMAReq::Tim17Up, 0b0001, bdtr: moe),
+}
+
 macro_rules! lptim_hal {
     ($($TIMX:ident: ($timx:ident, $Rec:ident, $timXpac:ident),)+) => {
         $(
@@ -927,6 +1516,46 @@ lptim_hal! {
 mod tests {
     use super::*;
 
+    #[test]
+    fn slave_mode_register_values() {
+        // External clock mode 1 from ITR0
+        assert_eq!(smcr_slave_bits(0, 0, SlaveMode::ExternalClock), 0x0007);
+        // Trigger mode from ITR3, the external trigger fields are kept
+        assert_eq!(smcr_slave_bits(0xFF00, 3, SlaveMode::Trigger), 0xFF36);
+        // The previous slave mode and trigger are replaced
+        assert_eq!(
+            smcr_slave_bits(0x0031_0077, 1, SlaveMode::Reset),
+            0x0000_0014
+        );
+    }
+
+    #[test]
+    fn dma_burst_register_values() {
+        assert_eq!(dcr_bits(BurstRegister::Ccr1, 4), 0x030D);
+        assert_eq!(dcr_bits(BurstRegister::Cr1, 18), 0x1100);
+        assert_eq!(dcr_bits(BurstRegister::Arr, 1), 0x000B);
+    }
+
+    #[test]
+    fn dma_burst_channels() {
+        assert_eq!(burst_channels(BurstRegister::Ccr1, 4), 0b1111);
+        assert_eq!(burst_channels(BurstRegister::Arr, 4), 0b0011);
+        assert_eq!(burst_channels(BurstRegister::Ccr3, 3), 0b1100);
+        assert_eq!(burst_channels(BurstRegister::Cr1, 13), 0);
+
+        // Channel 2 in PWM mode 1 with preload, channel 1 is kept
+        assert_eq!(ccmr_pwm_bits(0x0001_0073, 0b10), 0x0001_6873);
+        // Channel 1 from input capture and OC1M[3]
+        assert_eq!(ccmr_pwm_bits(0x0001_0001, 0b01), 0x0000_0068);
+        assert_eq!(ccer_enable_bits(0b1010), 0x1010);
+    }
+
+    #[test]
+    #[should_panic]
+    fn dma_burst_beyond_bdtr() {
+        dcr_bits(BurstRegister::Ccr4, 3);
+    }
+
     #[test]
     fn timeout_ticks_register_values() {
         assert_eq!(calculate_timeout_ticks_register_values(0), (0, 0));
@@ -0,0 +1,128 @@
+//! Cache maintenance and MPU configuration for memory-mapped xSPI devices
+//!
+//! The memory-mapped region of a QUADSPI or OCTOSPI peripheral is cached by
+//! the Cortex-M7 data cache according to the default memory map. When the
+//! contents of the device are changed through the indirect mode, for example
+//! by programming a flash, the cache still holds the old contents and must be
+//! invalidated before the region is read again.
+
+use cortex_m::peripheral::{MPU, SCB};
+
+/// Memory attributes for the MPU region of a memory-mapped device
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MappedMemory {
+    /// Read-only and cached write-through, for flash that is read or
+    /// executed in place
+    Flash,
+    /// Read-write and cached write-back, for RAM such as a HyperRAM
+    Ram,
+    /// Read-write and not cached, for RAM that is also accessed by DMA
+    NonCacheable,
+}
+
+/// Returns the value of the MPU_RASR register for a region of `size` bytes
+/// with the attributes of `memory`
+fn region_attributes(size: usize, memory: MappedMemory) -> u32 {
+    assert!(
+        size.is_power_of_two() && size >= 32,
+        "MPU region size must be a power of two, and at least 32 bytes"
+    );
+
+    // AP, TEX, C, B
+    let (ap, tex, c, b) = match memory {
+        // Read-only, normal memory, write-through no write-allocate
+        MappedMemory::Flash => (0b110, 0b000, 1, 0),
+        // Full access, normal memory, write-back read and write allocate
+        MappedMemory::Ram => (0b011, 0b001, 1, 1),
+        // Full access, normal memory, non-cacheable
+        MappedMemory::NonCacheable => (0b011, 0b001, 0, 0),
+    };
+    let size_field = size.trailing_zeros() - 1;
+
+    ap << 24 | tex << 19 | c << 17 | b << 16 | size_field << 1 | 1
+}
+
+/// Configures MPU `region` to cover the `size` bytes of a memory-mapped
+/// device at `base`, with the attributes of `memory`, and enables the MPU
+///
+/// The default memory map remains in effect for privileged accesses outside
+/// the configured regions.
+///
+/// # Panics
+///
+/// Panics if `size` is not a power of two of at least 32 bytes, or if `base`
+/// is not aligned to `size`
+///
+/// # Safety
+///
+/// Changing the attributes of memory that is already in use, or replacing
+/// an MPU region configured elsewhere, can change the behaviour of the rest
+/// of the program.
+pub unsafe fn configure_mpu_region(
+    mpu: &mut MPU,
+    region: u8,
+    base: usize,
+    size: usize,
+    memory: MappedMemory,
+) {
+    let rasr = region_attributes(size, memory);
+    assert!(
+        base % size == 0,
+        "MPU region base must be aligned to its size"
+    );
+
+    // The MPU is disabled whilst the region is changed
+    cortex_m::asm::dmb();
+    mpu.ctrl.write(0);
+
+    mpu.rnr.write(u32::from(region));
+    mpu.rbar.write(base as u32);
+    mpu.rasr.write(rasr);
+
+    // ENABLE, PRIVDEFENA
+    mpu.ctrl.write(1 | 1 << 2);
+    cortex_m::asm::dsb();
+    cortex_m::asm::isb();
+}
+
+/// Invalidates the data cache over `len` bytes of a memory-mapped device
+/// starting at `addr`, so that the next read fetches the contents of the
+/// device
+///
+/// Call this after the contents of the device have been changed through the
+/// indirect mode, for example after programming or erasing a flash. The
+/// whole cache lines that contain the start and end of the range are
+/// invalidated.
+///
+/// # Safety
+///
+/// Any data written by the CPU to the range that is still in the cache is
+/// discarded. This cannot occur for a region configured as
+/// [`MappedMemory::Flash`] or [`MappedMemory::NonCacheable`].
+pub unsafe fn invalidate_mapped_region(scb: &mut SCB, addr: usize, len: usize) {
+    scb.invalidate_dcache_by_address(addr, len);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attributes() {
+        // 256MB flash: SIZE = 27
+        assert_eq!(
+            region_attributes(0x1000_0000, MappedMemory::Flash),
+            0x0602_0037
+        );
+        // 8MB RAM: SIZE = 22
+        assert_eq!(
+            region_attributes(0x80_0000, MappedMemory::Ram),
+            0x030B_002D
+        );
+        assert_eq!(
+            region_attributes(32, MappedMemory::NonCacheable),
+            0x0308_0009
+        );
+    }
+}
@@ -33,6 +33,15 @@
 //! qspi.write(0x00, &[0xAB, 0xCD]).unwrap();
 //! ```
 //!
+//! Large reads from QUADSPI can be transferred to memory by the MDMA with
+//! `read_indirect_dma`, which returns a
+//! [`QspiDmaTransfer`](struct.QspiDmaTransfer.html).
+//!
+//! ```
+//! let transfer = qspi.read_indirect_dma(0x00, buffer, mdma_streams.0);
+//! let (qspi, stream, buffer) = transfer.wait();
+//! ```
+//!
 //! For OCTOSPI there are two peripherals, which can be initialised separately.
 //!
 //! ```
@@ -55,6 +64,103 @@
 //!     .unwrap();
 //! ```
 //!
+//! For OCTOSPI, any indirect mode transaction can also be described by a
+//! [`Command`](struct.Command.html), and executed with a single call to
+//! `transfer`. Each phase of the command can use a different number of IO
+//! lines.
+//!
+//! ```
+//! use stm32h7xx_hal::xspi::{Command, OctospiMode as M, OctospiWord as XW};
+//!
+//! // Example RDID Read Identification, in 8-bit mode
+//! let cmd = Command::new()
+//!     .instruction(XW::U16(0x9F60), M::EightBit)
+//!     .address(XW::U32(0), M::EightBit)
+//!     .dummy_cycles(4)
+//!     .read(M::EightBit);
+//!
+//! let mut read: [u8; 3] = [0; 3];
+//! octospi.transfer(cmd, Some(&mut read)).unwrap();
+//! ```
+//!
+//! Alternate bytes are sent between the address and dummy phases if they
+//! are given, and there are none by default. Many NOR flash devices use them
+//! as mode bits to stay in continuous read mode, so that the instruction can
+//! be left out of subsequent reads.
+//!
+//! ```
+//! // Fast Read Quad I/O (0xEB), with mode bits 0xA0 for continuous read
+//! let cmd = Command::new()
+//!     .instruction(XW::U8(0xEB), M::OneBit)
+//!     .address(XW::U24(0x1000), M::FourBit)
+//!     .alternate_bytes(XW::U8(0xA0), M::FourBit)
+//!     .dummy_cycles(4)
+//!     .read(M::FourBit);
+//! octospi.transfer(cmd, Some(&mut buffer)).unwrap();
+//!
+//! // The device is now in continuous read mode, so there is no instruction
+//! let cmd = Command::new()
+//!     .address(XW::U24(0x2000), M::FourBit)
+//!     .alternate_bytes(XW::U8(0xA0), M::FourBit)
+//!     .dummy_cycles(4)
+//!     .read(M::FourBit);
+//! octospi.transfer(cmd, Some(&mut buffer)).unwrap();
+//! ```
+//!
+//! A command is sent in single transfer rate (STR) mode unless it sets its
+//! own data rate, whatever the data rate of the configuration. An octal DTR
+//! flash device can therefore be switched from STR mode with
+//! `switch_flash_mode`, and then accessed with DTR commands.
+//!
+//! ```
+//! // Octal DTR Read (0xEE11) of 16 bytes from address 0x1000
+//! let cmd = Command::new()
+//!     .instruction(XW::U16(0xEE11), M::EightBit)
+//!     .address(XW::U32(0x1000), M::EightBit)
+//!     .dummy_cycles(20)
+//!     .read(M::EightBit)
+//!     .data_rate(DataRate::Double);
+//! octospi.transfer(cmd, Some(&mut buffer)).unwrap();
+//! ```
+//!
+//! The chip select (nCS) is released at the end of each transaction. Some
+//! devices need it to stay asserted across a command and a following
+//! command. For OCTOSPI, `write_chained` sends the following commands as
+//! part of the data phase of the first, so they must use the same number of
+//! IO lines as that data phase.
+//!
+//! ```
+//! // Instruction 0x71, followed by instruction 0x66 with nCS held
+//! let cmd = Command::new()
+//!     .instruction(XW::U8(0x71), M::OneBit)
+//!     .write(M::OneBit);
+//! octospi.write_chained(cmd, &[&[0x00, 0x10], &[0x66]]).unwrap();
+//! ```
+//!
+//! For OCTOSPI, the JEDEC Serial Flash Discoverable Parameters (SFDP) of a
+//! NOR flash device can be read with `read_sfdp`. This gives the capacity,
+//! erase types and supported modes of the device, so that a flash driver can
+//! support several devices. The parser in [`SfdpInfo`] can also be used with
+//! other peripherals.
+//!
+//! ```
+//! let mut buf = [0; 64];
+//! let info = octospi.read_sfdp(&mut buf).unwrap();
+//! let size_bytes = info.density_bytes();
+//! ```
+//!
+//! Many flash devices start in single line mode, and must be switched to
+//! quad or octal mode by writing a configuration register. For OCTOSPI, the
+//! sequence of commands is described by a
+//! [`FlashModeSwitch`](struct.FlashModeSwitch.html) and executed
+//! with `switch_flash_mode`, which also waits for the device to complete the
+//! write.
+//!
+//! ```
+//! let quad_enable = FlashModeSwitch::new(write_enable, write_sr2, &[0x02], read_sr);
+//! octospi.switch_flash_mode(&quad_enable).unwrap();
+//! ```
+//!
 //! # Configuration
 //!
 //! A [`Config`](#struct.Config) struct is used to configure the xSPI.
@@ -64,6 +170,40 @@
 //! let config = xspi::Config::new(12.MHz()).fifo_threshold(16);
 //! ```
 //!
+//! The bus clock is divided from the kernel clock by the smallest division
+//! factor that does not exceed the requested frequency. To run the bus
+//! directly from the kernel clock, use
+//! [`bypass_prescaler`](Config#method.bypass_prescaler). The achieved
+//! division factor and frequency can be checked after initialisation.
+//!
+//! ```
+//! let config = xspi::Config::new(100.MHz()).bypass_prescaler();
+//! // ...
+//! assert_eq!(octospi.divisor(), 1);
+//! let frequency = octospi.frequency();
+//! ```
+//!
+//! Octal DTR flash devices can be used with OCTOSPI by selecting the double
+//! transfer rate, and the data strobe if the device drives one.
+//!
+//! ```
+//! let config = xspi::Config::new(100.MHz())
+//!     .mode(xspi::OctospiMode::EightBit)
+//!     .data_rate(xspi::DataRate::Double)
+//!     .data_strobe(true);
+//! ```
+//!
+//! The memory type selects the byte order of octal DTR devices, and the
+//! protocol of octal RAMs.
+//!
+//! ```
+//! let config = xspi::Config::new(100.MHz())
+//!     .mode(xspi::OctospiMode::EightBit)
+//!     .data_rate(xspi::DataRate::Double)
+//!     .data_strobe(true)
+//!     .memory_type(xspi::MemoryType::ApMemory);
+//! ```
+//!
 //! # Hyperbus
 //!
 //! This driver supports a memory-mapped Hyperbus mode for the OCTOSPI
@@ -76,6 +216,9 @@
 //!     .read_write_recovery(4) // 50ns
 //!     .access_initial_latency(6);
 //!
+//! // Optionally check the configuration, including in release builds
+//! config.validate(&ccdr.clocks).unwrap();
+//!
 //! let hyperram = dp.OCTOSPI1.octospi_hyperbus_unchecked(
 //!     config,
 //!     &ccdr.clocks,
@@ -87,6 +230,70 @@
 //! let ram = unsafe { slice::from_raw_parts_mut(ram_ptr, size_u32) };
 //! ```
 //!
+//! Some HyperRAM devices must be configured before they are used, for example
+//! to change their latency. The `OCTOSPI` peripheral cannot be used to create
+//! both an indirect mode interface and a Hyperbus interface, so an existing
+//! indirect mode interface is instead converted with `into_hyperbus`. The
+//! steps are:
+//!
+//! 1. Use the indirect mode interface, if needed
+//! 2. Convert it with `into_hyperbus`. This resets the peripheral
+//! 3. Write the device registers with `write_register`. The register
+//!    accesses use the indirect mode in the Hyperbus register space
+//! 4. Call `init` to enter memory-mapped mode
+//!
+//! ```
+//! let mut hyperram = octospi.into_hyperbus(config, &ccdr.clocks);
+//!
+//! // Configuration Register 0: 5 clock initial latency, fixed latency
+//! hyperram.write_register(0x1000, 0x8f0f);
+//!
+//! let ram_ptr: *mut u32 = hyperram.init();
+//! ```
+//!
+//! # Memory-mapped QUADSPI
+//!
+//! The QUADSPI peripheral can map the flash into the address space, so that
+//! code can be executed in place.
+//!
+//! ```
+//! // Fast Read Quad I/O (0xEB) in QPI mode, with 24-bit addresses and 6
+//! // dummy cycles
+//! let flash_ptr: *mut u32 = qspi.into_memory_mapped(
+//!     0xEB,
+//!     xspi::AddressSize::TwentyFourBit,
+//!     6,
+//!     xspi::QspiMode::FourBit,
+//! );
+//! ```
+//!
+//! # Caches
+//!
+//! Reads from a memory-mapped device are cached by the data cache. After the
+//! device is changed through the indirect mode, for example by programming a
+//! flash, the cache must be invalidated with [`invalidate_mapped_region`]
+//! before the region is read again, otherwise the old contents are read
+//! from the cache.
+//!
+//! The attributes of the region can also be set with an MPU region using
+//! [`configure_mpu_region`]. A flash is mapped read-only and write-through,
+//! and a RAM that is also accessed by DMA can be mapped as non-cacheable.
+//!
+//! ```
+//! let mut cp = cortex_m::Peripherals::take().unwrap();
+//!
+//! // 16MB flash mapped at 0x9000_0000
+//! unsafe {
+//!     xspi::configure_mpu_region(&mut cp.MPU, 0, 0x9000_0000, 16 << 20, xspi::MappedMemory::Flash);
+//! }
+//!
+//! // ... the flash sector at 0x9001_0000 is programmed in indirect mode
+//!
+//! unsafe {
+//!     xspi::invalidate_mapped_region(&mut cp.SCB, 0x9001_0000, 4096);
+//! }
+//! ```
+//!
 //! # Examples
 //!
 //! - [Simple QSPI example](https://github.com/stm32-rs/stm32h7xx-hal/blob/master/examples/qspi.rs)
@@ -99,7 +306,8 @@
 //!
 //! This driver currently only supports indirect operation mode of the xSPI
 //! interface. Automatic polling or memory-mapped modes are not supported,
-//! except for the OCTOSPI Hyperbus mode.
+//! except for the OCTOSPI Hyperbus mode and QUADSPI memory-mapped reads
+//! (see below).
 //!
 //! It is possible to change operational mode between transactions by
 //! calling [`configure_mode`](#method.configure_mode) or
@@ -116,24 +324,38 @@ mod qspi;
 #[cfg(any(feature = "rm0433", feature = "rm0399"))]
 pub use common::{
     Bank, BankError, BankSelect, Xspi as Qspi, XspiError as QspiError,
-    XspiMode as QspiMode, XspiModes as QspiModes, XspiWord as QspiWord,
+    XspiMode as QspiMode, XspiModes as QspiModes, XspiStatus as QspiStatus,
+    XspiWord as QspiWord,
 };
 #[cfg(any(feature = "rm0433", feature = "rm0399"))]
-pub use qspi::QspiExt as XspiExt;
+pub use qspi::{AddressSize, QspiDmaTransfer, QspiExt as XspiExt};
 
 // Octospi
 #[cfg(any(feature = "rm0455", feature = "rm0468"))]
 mod octospi;
 #[cfg(any(feature = "rm0455", feature = "rm0468"))]
 pub use common::{
-    Xspi as Octospi, XspiError as OctospiError, XspiMode as OctospiMode,
-    XspiModes as OctospiModes, XspiWord as OctospiWord,
+    DataRate, MemoryType, Xspi as Octospi, XspiError as OctospiError,
+    XspiMode as OctospiMode, XspiModes as OctospiModes,
+    XspiStatus as OctospiStatus, XspiWord as OctospiWord,
 };
 #[cfg(any(feature = "rm0455", feature = "rm0468"))]
-pub use octospi::{Hyperbus, HyperbusConfig, OctospiExt as XspiExt};
+pub use octospi::{
+    Command, DataDirection, FlashModeSwitch, Hyperbus, HyperbusConfig,
+    HyperbusConfigError, OctospiExt as XspiExt,
+};
 
 // Both
-pub use common::{Config, Event, SamplingEdge};
+pub use common::{Config, Event, Polarity, SamplingEdge};
+
+mod cache;
+pub use cache::{configure_mpu_region, invalidate_mapped_region, MappedMemory};
+
+mod sfdp;
+pub use sfdp::{
+    SfdpAddressBytes, SfdpEraseRegion, SfdpInfo, SfdpModes,
+    SFDP_BASIC_TABLE_MIN_LEN, SFDP_HEADER_LEN,
+};
 
 /// This modulate contains functionality common to both Quad and Octo SPI
 mod common {
@@ -143,7 +365,9 @@ mod common {
         time::Hertz,
     };
     use core::cell::UnsafeCell;
-    use core::{marker::PhantomData, ptr};
+    use core::{fmt, marker::PhantomData, ptr};
+
+    pub use crate::hal::spi::Polarity;
 
     /// Represents operation modes of the XSPI interface.
     #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -203,6 +427,13 @@ mod common {
     pub enum XspiError {
         Busy,
         Underflow,
+        /// The peripheral was still busy at the end of
+        /// [`wait_idle_timeout`](Xspi::wait_idle_timeout), or the device was
+        /// still busy at the end of a flash mode switch
+        Timeout,
+        /// The Serial Flash Discoverable Parameters read from the device are
+        /// not valid
+        InvalidSfdp,
 
         #[cfg(any(feature = "rm0433", feature = "rm0399"))]
         /// The specified XspiWord does not fit in the available register
@@ -221,7 +452,7 @@ mod common {
     }
     impl XspiWord {
         #[inline(always)]
-        fn size(&self) -> u8 {
+        pub(super) fn size(&self) -> u8 {
             match self {
                 XspiWord::U16(_) => 1,
                 XspiWord::U24(_) => 2,
@@ -230,7 +461,7 @@ mod common {
             }
         }
         #[inline(always)]
-        fn bits(self) -> u32 {
+        pub(super) fn bits(self) -> u32 {
             match self {
                 XspiWord::None => 0,
                 XspiWord::U8(x) => x as u32,
@@ -257,6 +488,75 @@ mod common {
         Rising,
     }
 
+    /// Data rate of the OCTOSPI interface
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+    pub enum DataRate {
+        /// Single transfer rate (STR), one transfer per clock cycle
+        Single,
+        /// Double transfer rate (DTR), one transfer on each clock edge
+        Double,
+    }
+
+    /// Memory type of an OCTOSPI device, which selects the order of the two
+    /// bytes of each 16-bit word in octal DTR mode, and the protocol of
+    /// some octal RAMs
+    ///
+    /// HyperBus devices are used through [`Hyperbus`](super::Hyperbus)
+    /// instead.
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+    pub enum MemoryType {
+        /// Standard mode. The default
+        Standard,
+        /// Micron mode, D0/D1 byte order in octal DTR mode
+        Micron,
+        /// Macronix mode, D1/D0 byte order in octal DTR mode
+        Macronix,
+        /// Macronix RAM mode, D1/D0 byte order in octal DTR mode and the
+        /// data strobe used as the write data mask
+        MacronixRam,
+        /// APMemory mode, for APMemory octal PSRAMs
+        ApMemory,
+    }
+
+    #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+    impl MemoryType {
+        /// Value of the DCR1.MTYP field
+        pub(super) fn mtyp(self) -> u8 {
+            match self {
+                MemoryType::Micron => 0b000,
+                MemoryType::Macronix => 0b001,
+                MemoryType::Standard => 0b010,
+                MemoryType::MacronixRam => 0b011,
+                MemoryType::ApMemory => 0b110,
+            }
+        }
+    }
+
+    /// Decoded view of the XSPI status register, returned by
+    /// [`status`](Xspi::status)
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+    pub struct XspiStatus {
+        /// A transaction is ongoing
+        pub busy: bool,
+        /// Number of bytes in the FIFO
+        pub fifo_level: u8,
+        /// The FIFO threshold has been reached
+        pub fifo_threshold: bool,
+        /// The transfer is complete, or was aborted
+        pub transfer_complete: bool,
+        /// The status polling mode found a match
+        pub status_match: bool,
+        /// The timeout counter expired in memory-mapped mode
+        pub timeout: bool,
+        /// An invalid address was accessed
+        pub transfer_error: bool,
+    }
+
     /// Interrupt events
     #[derive(Copy, Clone, PartialEq, Eq)]
     #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -334,6 +634,16 @@ mod common {
         pub(super) dummy_cycles: u8,
         pub(super) sampling_edge: SamplingEdge,
         pub(super) fifo_threshold: u8,
+        pub(super) bypass_prescaler: bool,
+        pub(super) clock_polarity: Polarity,
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        pub(super) free_running_clock: bool,
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        pub(super) data_rate: DataRate,
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        pub(super) data_strobe: bool,
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        pub(super) memory_type: MemoryType,
     }
 
     impl Config {
@@ -342,6 +652,11 @@ mod common {
         /// * Bus in 1-bit Mode
         /// * No dummy cycle
         /// * Sample on falling edge
+        ///
+        /// # Panics
+        ///
+        /// Initialisation panics if the resulting bus frequency exceeds the
+        /// maximum bus frequency of the device.
         pub fn new(frequency: Hertz) -> Self {
             Config {
                 modes: XspiModes::new(XspiMode::OneBit),
@@ -349,6 +664,16 @@ mod common {
                 dummy_cycles: 0,
                 sampling_edge: SamplingEdge::Falling,
                 fifo_threshold: 1,
+                bypass_prescaler: false,
+                clock_polarity: Polarity::IdleLow,
+                #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+                free_running_clock: false,
+                #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+                data_rate: DataRate::Single,
+                #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+                data_strobe: false,
+                #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+                memory_type: MemoryType::Standard,
             }
         }
 
@@ -428,6 +753,149 @@ mod common {
         }
     }
 
+    impl Config {
+        /// Clock the bus directly from the kernel clock, with a prescaler
+        /// division factor of 1. The requested frequency is ignored.
+        ///
+        /// # Panics
+        ///
+        /// Initialisation panics if the kernel clock exceeds the maximum bus
+        /// frequency of the device.
+        pub fn bypass_prescaler(mut self) -> Self {
+            self.bypass_prescaler = true;
+            self
+        }
+
+        /// Specify the level of the clock whilst the chip select is high.
+        /// [`IdleLow`](Polarity::IdleLow) (clock mode 0) is the default,
+        /// [`IdleHigh`](Polarity::IdleHigh) selects clock mode 3.
+        pub fn clock_polarity(mut self, polarity: Polarity) -> Self {
+            self.clock_polarity = polarity;
+            self
+        }
+
+        /// Keep the clock running whilst the chip select is high, for
+        /// devices that require a continuous clock. Disabled by default.
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        pub fn free_running_clock(mut self, free_running: bool) -> Self {
+            self.free_running_clock = free_running;
+            self
+        }
+
+        /// Specify the data rate. [`Single`](DataRate::Single) is the default.
+        ///
+        /// With [`Double`](DataRate::Double), the address, alternate-bytes
+        /// and data phases are transferred on both clock edges. The
+        /// instruction phase is also transferred on both clock edges if the
+        /// instruction mode of this configuration is 8-bit, as for octal DTR
+        /// flash devices. The sampling edge setting is ignored, since the
+        /// receiver cannot sample on the falling edge in this mode, and the
+        /// output data is held for an extra quarter cycle.
+        ///
+        /// For OCTOSPI, a `Command` executed with `transfer` does not use
+        /// this setting, but has its own data rate.
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        pub fn data_rate(mut self, data_rate: DataRate) -> Self {
+            self.data_rate = data_rate;
+            self
+        }
+
+        /// Use the data strobe (DQS) signal from the device to sample the
+        /// data in double transfer rate mode. Disabled by default.
+        ///
+        /// Only enable this if the device drives a data strobe, and it is
+        /// connected to the DQS pin.
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        pub fn data_strobe(mut self, data_strobe: bool) -> Self {
+            self.data_strobe = data_strobe;
+            self
+        }
+
+        /// Specify the memory type. [`Standard`](MemoryType::Standard) is
+        /// the default.
+        ///
+        /// # Panics
+        ///
+        /// Initialisation panics if the memory type is not valid for the
+        /// rest of the configuration. All memory types other than
+        /// [`Standard`](MemoryType::Standard) require the double transfer
+        /// rate with 8-bit data, and the RAM types also require the data
+        /// strobe.
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        pub fn memory_type(mut self, memory_type: MemoryType) -> Self {
+            self.memory_type = memory_type;
+            self
+        }
+
+        /// Returns true if the memory type is valid for the data rate, the
+        /// data strobe and the data mode
+        #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+        pub(super) fn memory_type_is_valid(&self) -> bool {
+            let octal_dtr = self.data_rate == DataRate::Double
+                && self.modes.data == XspiMode::EightBit;
+
+            match self.memory_type {
+                MemoryType::Standard => true,
+                MemoryType::Micron | MemoryType::Macronix => octal_dtr,
+                MemoryType::MacronixRam | MemoryType::ApMemory => {
+                    octal_dtr && self.data_strobe
+                }
+            }
+        }
+    }
+
+    /// Maximum bus frequency, taken from the device datasheets
+    #[cfg(any(feature = "rm0433", feature = "rm0399"))]
+    pub(super) const MAX_FREQUENCY: u32 = 133_000_000;
+    #[cfg(feature = "rm0455")]
+    pub(super) const MAX_FREQUENCY: u32 = 140_000_000;
+    #[cfg(feature = "rm0468")]
+    pub(super) const MAX_FREQUENCY: u32 = 200_000_000;
+
+    /// Returns the prescaler division factor (1-256) for the configured bus
+    /// frequency
+    pub(super) fn prescaler_divisor(kernel_ck: u32, config: &Config) -> u32 {
+        let divisor = if config.bypass_prescaler {
+            1
+        } else {
+            let frequency = config.frequency.raw();
+            match (kernel_ck + frequency - 1) / frequency {
+                divisor @ 1..=256 => divisor,
+                _ => panic!("Invalid xSPI frequency requested"),
+            }
+        };
+
+        assert!(
+            kernel_ck / divisor <= MAX_FREQUENCY,
+            "The xSPI bus frequency exceeds the maximum"
+        );
+        divisor
+    }
+
+    // embedded-hal 0.2 `Polarity` does not implement `Debug`
+    impl fmt::Debug for Config {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            let clock_polarity = match self.clock_polarity {
+                Polarity::IdleLow => "IdleLow",
+                Polarity::IdleHigh => "IdleHigh",
+            };
+            let mut s = f.debug_struct("Config");
+            s.field("modes", &self.modes)
+                .field("frequency", &self.frequency)
+                .field("dummy_cycles", &self.dummy_cycles)
+                .field("sampling_edge", &self.sampling_edge)
+                .field("fifo_threshold", &self.fifo_threshold)
+                .field("bypass_prescaler", &self.bypass_prescaler)
+                .field("clock_polarity", &format_args!("{}", clock_polarity));
+            #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+            s.field("free_running_clock", &self.free_running_clock)
+                .field("data_rate", &self.data_rate)
+                .field("data_strobe", &self.data_strobe)
+                .field("memory_type", &self.memory_type);
+            s.finish()
+        }
+    }
+
     impl From<Hertz> for Config {
         fn from(frequency: Hertz) -> Self {
             Self::new(frequency)
@@ -442,6 +910,12 @@ mod common {
         /// various phases may be removed. Therefore we need to restore them
         /// after each transaction.
         pub(super) modes: XspiModes,
+
+        /// Prescaler division factor
+        pub(super) divisor: u16,
+
+        /// Achieved bus frequency
+        pub(super) frequency: Hertz,
     }
 
     #[cfg(any(feature = "rm0433", feature = "rm0399"))]
@@ -484,6 +958,16 @@ mod common {
                 &mut self.rb
             }
 
+            /// Returns the prescaler division factor, between 1 and 256
+            pub fn divisor(&self) -> u16 {
+                self.divisor
+            }
+
+            /// Returns the achieved bus frequency
+            pub fn frequency(&self) -> Hertz {
+                self.frequency
+            }
+
             /// Check if the XSPI peripheral is currently busy with a
             /// transaction
             pub fn is_busy(&self) -> Result<(), XspiError> {
@@ -494,6 +978,34 @@ mod common {
                 }
             }
 
+            /// Returns the status flags and the FIFO level
+            pub fn status(&self) -> XspiStatus {
+                let sr = self.rb.sr.read();
+                XspiStatus {
+                    busy: sr.busy().bit_is_set(),
+                    fifo_level: sr.flevel().bits(),
+                    fifo_threshold: sr.ftf().bit_is_set(),
+                    transfer_complete: sr.tcf().bit_is_set(),
+                    status_match: sr.smf().bit_is_set(),
+                    timeout: sr.tof().bit_is_set(),
+                    transfer_error: sr.tef().bit_is_set(),
+                }
+            }
+
+            /// Waits until the XSPI peripheral is not busy, polling the
+            /// busy flag at most `cycles` times
+            ///
+            /// # Errors
+            /// Returns XspiError::Timeout if the peripheral is still busy
+            pub fn wait_idle_timeout(&self, cycles: u32) -> Result<(), XspiError> {
+                for _ in 0..cycles {
+                    if self.is_busy().is_ok() {
+                        return Ok(());
+                    }
+                }
+                self.is_busy().map_err(|_| XspiError::Timeout)
+            }
+
             /// Enable interrupts for the given `event`
             pub fn listen(&mut self, event: Event) {
                 self.rb.cr.modify(|_, w| match event {
@@ -637,7 +1149,7 @@ mod common {
 
                 #[cfg(any(feature = "rm0455", feature = "rm0468"))]
                 {
-                    self.rb.tcr.write(|w| unsafe { w.dcyc().bits(dummy_cycles) });
+                    self.rb.tcr.modify(|_, w| unsafe { w.dcyc().bits(dummy_cycles) });
                     self.rb.cr.modify(|_, w| unsafe { w.fmode().bits(fmode) });
                 }
 
@@ -1018,3 +1530,97 @@ mod common {
     #[cfg(any(feature = "rm0455", feature = "rm0468"))]
     xspi_impl! { stm32::OCTOSPI2, rec::Octospi2, octospisel }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::common::{prescaler_divisor, Config, MAX_FREQUENCY};
+    use crate::time::Hertz;
+
+    #[test]
+    fn divisor_rounds_up() {
+        let kernel_ck = 120_000_000;
+        let divisor =
+            |f| prescaler_divisor(kernel_ck, &Config::new(Hertz::MHz(f)));
+
+        assert_eq!(divisor(120), 1);
+        assert_eq!(divisor(60), 2);
+        assert_eq!(divisor(59), 3);
+        assert_eq!(divisor(1), 120);
+    }
+
+    #[test]
+    #[should_panic]
+    fn divisor_out_of_range() {
+        prescaler_divisor(200_000_000, &Config::new(Hertz::kHz(100)));
+    }
+
+    #[test]
+    #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+    fn command_data_rate() {
+        use super::{Command, DataRate, OctospiMode as M, OctospiWord as XW};
+
+        // With a DTR configuration, a 1-bit command is still sent in STR
+        // mode, as required for example to read the SFDP
+        let _config = Config::new(Hertz::MHz(100)).data_rate(DataRate::Double);
+        let cmd = Command::new()
+            .instruction(XW::U8(0x5A), M::OneBit)
+            .address(XW::U24(0), M::OneBit)
+            .dummy_cycles(8)
+            .read(M::OneBit);
+        assert_eq!(cmd.dtr(), (false, false, false, false));
+
+        // A 1-bit instruction is never sent in DTR mode
+        let cmd = cmd.data_rate(DataRate::Double);
+        assert_eq!(cmd.dtr(), (false, true, true, true));
+
+        let cmd = cmd.instruction(XW::U16(0xEE11), M::EightBit);
+        assert_eq!(cmd.dtr(), (true, true, true, true));
+    }
+
+    #[test]
+    #[should_panic]
+    fn divisor_too_fast() {
+        // Divides by 2, giving 1.5 * MAX_FREQUENCY
+        let config = Config::new(Hertz::from_raw(2 * MAX_FREQUENCY));
+        prescaler_divisor(3 * MAX_FREQUENCY, &config);
+    }
+
+    #[test]
+    fn bypass_prescaler() {
+        let config = Config::new(Hertz::MHz(1)).bypass_prescaler();
+        assert_eq!(prescaler_divisor(MAX_FREQUENCY, &config), 1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn bypass_prescaler_too_fast() {
+        let config = Config::new(Hertz::MHz(1)).bypass_prescaler();
+        prescaler_divisor(MAX_FREQUENCY + 1, &config);
+    }
+
+    #[test]
+    #[cfg(any(feature = "rm0455", feature = "rm0468"))]
+    fn memory_type() {
+        use super::{DataRate, MemoryType, OctospiMode};
+
+        let config = Config::new(Hertz::MHz(100));
+        assert!(config.memory_type_is_valid());
+        assert!(!config
+            .memory_type(MemoryType::Macronix)
+            .memory_type_is_valid());
+
+        let octal_dtr = config
+            .mode(OctospiMode::EightBit)
+            .data_rate(DataRate::Double);
+        assert!(octal_dtr
+            .memory_type(MemoryType::Micron)
+            .memory_type_is_valid());
+        assert!(!octal_dtr
+            .memory_type(MemoryType::ApMemory)
+            .memory_type_is_valid());
+        assert!(octal_dtr
+            .data_strobe(true)
+            .memory_type(MemoryType::ApMemory)
+            .memory_type_is_valid());
+    }
+}
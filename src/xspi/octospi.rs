@@ -2,7 +2,8 @@
 //!
 //! See the parent module for documentation
 
-use core::fmt;
+use core::cell::UnsafeCell;
+use core::{cmp, fmt, ptr};
 
 use crate::{
     gpio::{self, Alternate},
@@ -11,7 +12,14 @@ use crate::{
     time::{Hertz, MicroSeconds},
 };
 
-use super::{Config, Octospi, SamplingEdge};
+use super::{
+    common::prescaler_divisor, Config, DataRate, Octospi, OctospiError,
+    OctospiMode, OctospiWord, Polarity, SamplingEdge, SfdpInfo,
+    SFDP_BASIC_TABLE_MIN_LEN, SFDP_HEADER_LEN,
+};
+
+/// Size of the OCTOSPI hardware FIFO in bytes
+const FIFO_SIZE: u8 = 32;
 
 /// A structure for specifying a Hyperbus configuration.
 ///
@@ -34,6 +42,29 @@ pub struct HyperbusConfig {
     chip_select_high: u8,
     read_write_recovery: u8,
     access_initial_latency: u8,
+    clock_polarity: Polarity,
+    free_running_clock: bool,
+    write_zero_latency: bool,
+    fixed_latency: bool,
+    refresh_disabled: bool,
+}
+
+/// A [`HyperbusConfig`] that cannot be used with the OCTOSPI peripheral,
+/// returned by [`validate`](HyperbusConfig::validate)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HyperbusConfigError {
+    /// The bus frequency cannot be divided from the kernel clock
+    Frequency,
+    /// The device size is less than 32 bytes or more than 256 MBytes
+    DeviceSize,
+    /// The chip select high time is outside the range supported by this part
+    ChipSelectHigh,
+    /// The access initial latency is less than the minimum of 3 cycles
+    AccessInitialLatency,
+    /// The refresh interval is zero, but was not disabled with
+    /// [`disable_refresh_interval`](HyperbusConfig::disable_refresh_interval)
+    RefreshInterval,
 }
 
 impl HyperbusConfig {
@@ -50,6 +81,10 @@ impl HyperbusConfig {
     /// * Chip select high between transactions = 4
     /// * Read-write recovery = 4
     /// * Access initial latency = 6
+    /// * Clock low whilst the chip select is high
+    /// * Clock stopped whilst the chip select is high
+    /// * Latency on write accesses
+    /// * Fixed latency mode
     pub fn new(frequency: Hertz) -> Self {
         HyperbusConfig {
             frequency,
@@ -58,7 +93,66 @@ impl HyperbusConfig {
             chip_select_high: 4,       // 40ns @ 100MHz
             read_write_recovery: 4,    // 40ns @ 100MHz
             access_initial_latency: 6, // 60ns @ 100MHz
+            clock_polarity: Polarity::IdleLow,
+            free_running_clock: false,
+            write_zero_latency: false,
+            fixed_latency: true,
+            refresh_disabled: false,
+        }
+    }
+
+    /// Check that this configuration can be used with the OCTOSPI
+    /// peripheral, with the kernel clock given by `clocks`
+    ///
+    /// The builder methods only check their arguments with debug
+    /// assertions, and
+    /// [`octospi_hyperbus_unchecked`](OctospiExt::octospi_hyperbus_unchecked)
+    /// does not check the configuration. This method can be used to check
+    /// the configuration in release builds.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first invalid parameter found.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the OCTOSPI kernel clock is not enabled
+    pub fn validate(
+        &self,
+        clocks: &CoreClocks,
+    ) -> Result<(), HyperbusConfigError> {
+        // OCTOSPI1 and OCTOSPI2 share the same kernel clock
+        let spi_kernel_ck =
+            Octospi::<stm32::OCTOSPI1>::kernel_clk_unwrap(clocks).raw();
+        let spi_frequency = self.frequency.raw();
+        let divisor = (spi_kernel_ck + spi_frequency - 1)
+            .checked_div(spi_frequency)
+            .unwrap_or(0);
+        if !(1..=256).contains(&divisor) {
+            return Err(HyperbusConfigError::Frequency);
+        }
+
+        if !(5..=28).contains(&self.size_order) {
+            return Err(HyperbusConfigError::DeviceSize);
+        }
+
+        #[cfg(feature = "rm0468")]
+        let max_chip_select_high = 64;
+        #[cfg(not(feature = "rm0468"))]
+        let max_chip_select_high = 8;
+        if !(1..=max_chip_select_high).contains(&self.chip_select_high) {
+            return Err(HyperbusConfigError::ChipSelectHigh);
+        }
+
+        if self.access_initial_latency < 3 {
+            return Err(HyperbusConfigError::AccessInitialLatency);
+        }
+
+        if self.refresh_interval.ticks() == 0 && !self.refresh_disabled {
+            return Err(HyperbusConfigError::RefreshInterval);
         }
+
+        Ok(())
     }
 
     /// Number of bytes in the device, expressed as a power of 2.
@@ -91,15 +185,29 @@ impl HyperbusConfig {
     /// number of rows in the array, with some margin. Called t_CSM in the
     /// memory datasheet.
     ///
-    /// Set to zero to disable the upper limit on the length of read and write
-    /// transactions. In this case you become reponsible for issuing the reads
-    /// needed to cover the required refreshes.
+    /// Setting zero also disables the upper limit, but is reported as an
+    /// error by [`validate`](Self::validate). Use
+    /// [`disable_refresh_interval`](Self::disable_refresh_interval) instead.
     ///
     /// ```
     /// let hyperbusconfig = hyperbusconfig.refresh_interval(4.us());
     /// ```
     pub fn refresh_interval(mut self, refresh_interval: MicroSeconds) -> Self {
         self.refresh_interval = refresh_interval;
+        self.refresh_disabled = false;
+        self
+    }
+
+    /// Disable the upper limit on the length of read and write transactions.
+    /// In this case you become reponsible for issuing the reads needed to
+    /// cover the required refreshes.
+    ///
+    /// ```
+    /// let hyperbusconfig = hyperbusconfig.disable_refresh_interval();
+    /// ```
+    pub fn disable_refresh_interval(mut self) -> Self {
+        self.refresh_interval = MicroSeconds::from_ticks(0);
+        self.refresh_disabled = true;
         self
     }
 
@@ -163,6 +271,330 @@ impl HyperbusConfig {
         self.access_initial_latency = access_initial_latency;
         self
     }
+
+    /// Level of the clock whilst the chip select is high
+    ///
+    /// ```
+    /// let hyperbusconfig = hyperbusconfig.clock_polarity(Polarity::IdleLow);
+    /// ```
+    pub fn clock_polarity(mut self, polarity: Polarity) -> Self {
+        self.clock_polarity = polarity;
+        self
+    }
+
+    /// Keep the clock running whilst the chip select is high. Some devices
+    /// need a continuous clock, for example to align their DQS output
+    ///
+    /// ```
+    /// let hyperbusconfig = hyperbusconfig.free_running_clock(true);
+    /// ```
+    pub fn free_running_clock(mut self, free_running: bool) -> Self {
+        self.free_running_clock = free_running;
+        self
+    }
+
+    /// Write accesses to the memory array have no initial latency
+    ///
+    /// Set this for devices that do not need an initial latency on writes,
+    /// such as Hyperflash. Register writes never have an initial latency.
+    ///
+    /// ```
+    /// let hyperbusconfig = hyperbusconfig.write_zero_latency(true);
+    /// ```
+    pub fn write_zero_latency(mut self, write_zero_latency: bool) -> Self {
+        self.write_zero_latency = write_zero_latency;
+        self
+    }
+
+    /// Use fixed latency mode, where the initial latency is always twice the
+    /// [access initial latency](Self::access_initial_latency)
+    ///
+    /// In variable latency mode the memory signals on RWDS whether an
+    /// additional latency is needed for each access, and the initial latency
+    /// is only doubled when it is. This must match the latency mode
+    /// configured in the memory.
+    ///
+    /// ```
+    /// let hyperbusconfig = hyperbusconfig.fixed_latency(false);
+    /// ```
+    pub fn fixed_latency(mut self, fixed_latency: bool) -> Self {
+        self.fixed_latency = fixed_latency;
+        self
+    }
+}
+
+/// Direction of the data phase of a [`Command`]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DataDirection {
+    /// Data is read from the device (indirect read mode)
+    Read,
+    /// Data is written to the device (indirect write mode)
+    Write,
+}
+
+/// A structure describing a single OCTOSPI transaction in indirect mode.
+///
+/// Each of the instruction, address and alternate-bytes phases is included
+/// only if a word other than [`OctospiWord::None`] is given for it, and each
+/// phase uses its own number of IO lines. The size of each phase is taken
+/// from the size of its word.
+///
+/// This structure uses builder semantics to describe the transaction, which is
+/// then executed by [`transfer`](Octospi#method.transfer).
+///
+/// ```
+/// use stm32h7xx_hal::xspi::{Command, OctospiMode as M, OctospiWord as XW};
+///
+/// // Fast Read Quad Output (0x6B) of 16 bytes from address 0x1000
+/// let cmd = Command::new()
+///     .instruction(XW::U8(0x6B), M::OneBit)
+///     .address(XW::U24(0x1000), M::OneBit)
+///     .dummy_cycles(8)
+///     .read(M::FourBit);
+///
+/// let mut buffer = [0; 16];
+/// octospi.transfer(cmd, Some(&mut buffer)).unwrap();
+/// ```
+///
+/// Each command has its own data rate, which is single transfer rate (STR)
+/// unless set with [`data_rate`](Command::data_rate). The data rate of the
+/// [`Config`] is not used by commands.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Command {
+    instruction: (OctospiWord, OctospiMode),
+    address: (OctospiWord, OctospiMode),
+    alternate_bytes: (OctospiWord, OctospiMode),
+    dummy_cycles: u8,
+    data: (DataDirection, OctospiMode),
+    data_rate: DataRate,
+}
+
+impl Command {
+    /// Create a command without any phase.
+    ///
+    /// * No instruction, address or alternate-bytes phase
+    /// * No dummy cycle
+    /// * Data, if any, is written in 1-bit mode
+    /// * Single transfer rate (STR)
+    pub fn new() -> Self {
+        Command {
+            instruction: (OctospiWord::None, OctospiMode::OneBit),
+            address: (OctospiWord::None, OctospiMode::OneBit),
+            alternate_bytes: (OctospiWord::None, OctospiMode::OneBit),
+            dummy_cycles: 0,
+            data: (DataDirection::Write, OctospiMode::OneBit),
+            data_rate: DataRate::Single,
+        }
+    }
+
+    /// Specify the instruction phase, transferred using `mode`.
+    pub fn instruction(mut self, word: OctospiWord, mode: OctospiMode) -> Self {
+        self.instruction = (word, mode);
+        self
+    }
+
+    /// Specify the address phase, transferred using `mode`.
+    pub fn address(mut self, word: OctospiWord, mode: OctospiMode) -> Self {
+        self.address = (word, mode);
+        self
+    }
+
+    /// Specify the alternate-bytes phase, transferred using `mode`.
+    ///
+    /// The alternate bytes are sent after the address. They are often used
+    /// as the mode bits that keep a NOR flash in continuous read mode.
+    pub fn alternate_bytes(
+        mut self,
+        word: OctospiWord,
+        mode: OctospiMode,
+    ) -> Self {
+        self.alternate_bytes = (word, mode);
+        self
+    }
+
+    /// Specify the number of dummy cycles in between the alternate-bytes
+    /// and data phases.
+    ///
+    /// Hardware supports 0-31 dummy cycles.
+    pub fn dummy_cycles(mut self, cycles: u8) -> Self {
+        debug_assert!(cycles < 32, "Hardware only supports 0-31 dummy cycles");
+
+        self.dummy_cycles = cycles;
+        self
+    }
+
+    /// The data phase reads from the device, using `mode`.
+    pub fn read(mut self, mode: OctospiMode) -> Self {
+        self.data = (DataDirection::Read, mode);
+        self
+    }
+
+    /// The data phase writes to the device, using `mode`.
+    pub fn write(mut self, mode: OctospiMode) -> Self {
+        self.data = (DataDirection::Write, mode);
+        self
+    }
+
+    /// Specify the data rate. [`Single`](DataRate::Single) is the default.
+    ///
+    /// With [`Double`](DataRate::Double), the address, alternate-bytes and
+    /// data phases are transferred on both clock edges. The instruction
+    /// phase is also transferred on both clock edges if it is in 8-bit
+    /// mode. The data strobe is used if it is enabled in the configuration,
+    /// and the configuration also has a double data rate.
+    pub fn data_rate(mut self, data_rate: DataRate) -> Self {
+        self.data_rate = data_rate;
+        self
+    }
+
+    /// Returns the DTR settings of the instruction, address,
+    /// alternate-bytes and data phases
+    pub(super) fn dtr(&self) -> (bool, bool, bool, bool) {
+        let dtr = self.data_rate == DataRate::Double;
+        let instruction_dtr =
+            dtr && self.instruction.1 == OctospiMode::EightBit;
+        (instruction_dtr, dtr, dtr, dtr)
+    }
+}
+
+impl Default for Command {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Maximum length of the register value written by a [`FlashModeSwitch`]
+const MODE_SWITCH_MAX_VALUE_LEN: usize = 4;
+
+/// The sequence of commands that switches a flash device from single line
+/// mode to quad or octal mode, executed by
+/// [`switch_flash_mode`](Octospi#method.switch_flash_mode).
+///
+/// The sequence is:
+///
+/// 1. The write enable command
+/// 2. The write register command, writing the register value
+/// 3. The read status command, repeated until the busy bits of the status
+///    register are clear
+///
+/// The opcodes, modes and register values are all given as commands, so
+/// that the sequence can be used with any device. If the read status
+/// command is already in the new mode, the device's acceptance of the new
+/// mode is also checked.
+///
+/// The device is still in single transfer rate (STR) mode when the sequence
+/// starts, so the write enable and write register commands are always sent
+/// in STR mode. The read status command uses its own data rate.
+///
+/// ```
+/// use stm32h7xx_hal::xspi::{
+///     Command, FlashModeSwitch, OctospiMode as M, OctospiWord as XW,
+/// };
+///
+/// // Winbond W25Q: set the Quad Enable bit in Status Register 2 (0x31)
+/// let quad_enable = FlashModeSwitch::new(
+///     Command::new().instruction(XW::U8(0x06), M::OneBit),
+///     Command::new().instruction(XW::U8(0x31), M::OneBit).write(M::OneBit),
+///     &[0x02],
+///     Command::new().instruction(XW::U8(0x05), M::OneBit).read(M::OneBit),
+/// );
+///
+/// // ISSI IS25: set the Quad Enable bit in the Status Register (0x01)
+/// let quad_enable = FlashModeSwitch::new(
+///     Command::new().instruction(XW::U8(0x06), M::OneBit),
+///     Command::new().instruction(XW::U8(0x01), M::OneBit).write(M::OneBit),
+///     &[0x40],
+///     Command::new().instruction(XW::U8(0x05), M::OneBit).read(M::OneBit),
+/// );
+///
+/// // Macronix MX25UM: write 0x01 to Configuration Register 2 (0x72) at
+/// // address 0 for octal STR mode, then read the status in octal mode
+/// let octal_enable = FlashModeSwitch::new(
+///     Command::new().instruction(XW::U8(0x06), M::OneBit),
+///     Command::new()
+///         .instruction(XW::U8(0x72), M::OneBit)
+///         .address(XW::U32(0), M::OneBit)
+///         .write(M::OneBit),
+///     &[0x01],
+///     Command::new()
+///         .instruction(XW::U16(0x05FA), M::EightBit)
+///         .address(XW::U32(0), M::EightBit)
+///         .dummy_cycles(4)
+///         .read(M::EightBit),
+/// );
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct FlashModeSwitch {
+    write_enable: Command,
+    write_register: Command,
+    value: [u8; MODE_SWITCH_MAX_VALUE_LEN],
+    value_len: usize,
+    read_status: Command,
+    busy_mask: u8,
+    max_polls: u32,
+}
+
+impl FlashModeSwitch {
+    /// Create a mode switch sequence, writing `value` to the device
+    /// register with `write_register`.
+    ///
+    /// * The device is busy while bit 0 of the status register is set
+    ///   (Write In Progress)
+    /// * The status register is read at most 100000 times
+    ///
+    /// # Panics
+    ///
+    /// Panics if `value` is empty or longer than 4 bytes.
+    pub fn new(
+        write_enable: Command,
+        write_register: Command,
+        value: &[u8],
+        read_status: Command,
+    ) -> Self {
+        assert!(
+            !value.is_empty() && value.len() <= MODE_SWITCH_MAX_VALUE_LEN,
+            "The register value must be 1 - 4 bytes"
+        );
+
+        let mut buf = [0; MODE_SWITCH_MAX_VALUE_LEN];
+        buf[..value.len()].copy_from_slice(value);
+
+        FlashModeSwitch {
+            write_enable: write_enable.data_rate(DataRate::Single),
+            write_register: write_register.data_rate(DataRate::Single),
+            value: buf,
+            value_len: value.len(),
+            read_status,
+            busy_mask: 0x01,
+            max_polls: 100_000,
+        }
+    }
+
+    /// Specify the bits of the status register that are set while the
+    /// device is busy.
+    pub fn busy_mask(mut self, mask: u8) -> Self {
+        self.busy_mask = mask;
+        self
+    }
+
+    /// Specify the maximum number of times the status register is read
+    /// before giving up.
+    pub fn max_polls(mut self, polls: u32) -> Self {
+        self.max_polls = polls;
+        self
+    }
+}
+
+/// Steps of a [`FlashModeSwitch`] sequence
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum ModeSwitchStep {
+    WriteEnable,
+    WriteRegister,
+    PollStatus(u32),
+    Done,
 }
 
 /// Type for a Hyperbus interface
@@ -183,6 +615,16 @@ impl<OSPI> fmt::Display for Hyperbus<OSPI> {
     }
 }
 
+/// Name of the bus mode for the number of data lines
+fn mode_name(mode: OctospiMode) -> &'static str {
+    match mode {
+        OctospiMode::OneBit => "SPI",
+        OctospiMode::TwoBit => "Dual SPI",
+        OctospiMode::FourBit => "Quad SPI",
+        OctospiMode::EightBit => "Octo SPI",
+    }
+}
+
 pub trait PinClk<OSPI> {}
 pub trait PinNclk<OSPI> {}
 pub trait PinDQS<OSPI> {}
@@ -426,6 +868,10 @@ macro_rules! octospi_impl {
                 while regs.sr.read().busy().bit_is_set() {}
 
                 let config: Config = config.into();
+                assert!(
+                    config.memory_type_is_valid(),
+                    "The memory type is not valid for the data rate, data strobe and data mode"
+                );
 
                 // Clear all pending flags.
                 regs.fcr.write(|w| {
@@ -449,16 +895,35 @@ macro_rules! octospi_impl {
 
                 regs.dcr1.write(|w| unsafe {
                     w.mtyp()
-                        .bits(2) // standard mode
+                        .bits(config.memory_type.mtyp())
                         // Configure the FSIZE to maximum. It appears that even when addressing
                         // is not used, the flash size violation may still trigger.
                         .devsize()
                         .bits(0x1F)
+                        .ckmode()
+                        .bit(config.clock_polarity == Polarity::IdleHigh)
+                        .frck()
+                        .bit(config.free_running_clock)
                 });
 
-                // Communications configuration register
+                // Communications configuration register. The DTR and DQS
+                // settings are kept by later transactions, except for
+                // commands which have their own data rate
+                let dtr = config.data_rate == DataRate::Double;
+                let instruction_dtr =
+                    dtr && config.modes.instruction == OctospiMode::EightBit;
                 regs.ccr.write(|w| unsafe {
-                    w.dmode()
+                    w.idtr()
+                        .bit(instruction_dtr)
+                        .addtr()
+                        .bit(dtr)
+                        .abdtr()
+                        .bit(dtr)
+                        .ddtr()
+                        .bit(dtr)
+                        .dqse()
+                        .bit(dtr && config.data_strobe)
+                        .dmode()
                         .bits(config.modes.data.reg_value())
                         .admode()
                         .bits(config.modes.address.reg_value())
@@ -469,14 +934,10 @@ macro_rules! octospi_impl {
                 });
 
                 // Prescaler
-                let spi_frequency = config.frequency.raw();
-                let divisor =
-                    match (spi_kernel_ck + spi_frequency - 1) / spi_frequency {
-                        divisor @ 1..=256 => divisor - 1,
-                        _ => panic!("Invalid OCTOSPI frequency requested"),
-                    };
-                regs.dcr2
-                    .write(|w| unsafe { w.prescaler().bits(divisor as u8) });
+                let divisor = prescaler_divisor(spi_kernel_ck, &config);
+                regs.dcr2.write(|w| unsafe {
+                    w.prescaler().bits((divisor - 1) as u8)
+                });
 
                 // Note that we default to setting SSHIFT (sampling on the falling
                 // edge). This is because it appears that the QSPI may have signal
@@ -485,10 +946,16 @@ macro_rules! octospi_impl {
                 // edge. Refer to https://github.com/quartiq/stabilizer/issues/101 for
                 // more information
                 //
-                // SSHIFT must not be set in DDR mode.
+                // SSHIFT must not be set in DDR mode. In DDR mode the output
+                // data is held for an extra quarter cycle (DHQC).
                 regs.tcr.write(|w| unsafe {
                     w.sshift()
-                        .bit(config.sampling_edge == SamplingEdge::Falling)
+                        .bit(
+                            !dtr && config.sampling_edge
+                                == SamplingEdge::Falling,
+                        )
+                        .dhqc()
+                        .bit(dtr)
                         .dcyc()
                         .bits(config.dummy_cycles)
                 });
@@ -499,6 +966,8 @@ macro_rules! octospi_impl {
                 Octospi {
                     rb: regs,
                     modes: config.modes,
+                    divisor: divisor as u16,
+                    frequency: Hertz::from_raw(spi_kernel_ck / divisor),
                 }
             }
 
@@ -561,6 +1030,10 @@ macro_rules! octospi_impl {
                         .bits(hyperbus.size_order)
                         .csht()
                         .bits(hyperbus.chip_select_high - 1)
+                        .ckmode()
+                        .bit(hyperbus.clock_polarity == Polarity::IdleHigh)
+                        .frck()
+                        .bit(hyperbus.free_running_clock)
                 });
 
                 // Prescaler
@@ -625,9 +1098,9 @@ macro_rules! octospi_impl {
                         .tacc()
                         .bits(hyperbus.access_initial_latency)
                         .wzl()
-                        .clear_bit() // latency on write accesses
+                        .bit(hyperbus.write_zero_latency)
                         .lm()
-                        .set_bit() // fixed latency mode
+                        .bit(hyperbus.fixed_latency)
                 });
 
                 Hyperbus {
@@ -638,6 +1111,444 @@ macro_rules! octospi_impl {
             }
         }
 
+        impl Octospi<$peripheral> {
+            /// Execute the transaction described by `cmd` in indirect mode.
+            ///
+            /// The data phase transfers all of `data`, in the direction given
+            /// by the command. If `data` is `None` or empty, there is no data
+            /// phase. The transfer is not limited by the size of the hardware
+            /// FIFO.
+            ///
+            /// The modes set by
+            /// [`configure_modes`](Octospi#method.configure_modes) are not
+            /// used by this method, and are unchanged.
+            ///
+            /// # Errors
+            ///
+            /// Returns `OctospiError::Busy` if an operation is ongoing, or
+            /// `OctospiError::Underflow` if a read ends before all of `data`
+            /// is received.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `cmd` reads from the device but `data` is `None` or
+            /// empty. Panics if the number of dummy cycles is not 0 - 31
+            /// inclusive.
+            pub fn transfer(
+                &mut self,
+                cmd: Command,
+                data: Option<&mut [u8]>,
+            ) -> Result<(), OctospiError> {
+                let data = data.unwrap_or(&mut []);
+                let direction = cmd.data.0;
+                let read = direction == DataDirection::Read;
+                assert!(
+                    !(read && data.is_empty()),
+                    "Must have a non-zero number of data cycles for a read"
+                );
+                assert!(
+                    cmd.dummy_cycles < 32,
+                    "Hardware only supports 0-31 dummy cycles"
+                );
+
+                self.is_busy()?;
+                let ccr = self.start_command(&cmd, data.len());
+
+                let dr = &self.rb.dr as *const _ as *const UnsafeCell<u8>;
+                if read {
+                    for location in data.iter_mut() {
+                        // Wait for a byte in the FIFO. Once the transaction is
+                        // complete the FIFO contains all remaining bytes
+                        while self.rb.sr.read().flevel().bits() == 0 {
+                            if self.rb.sr.read().tcf().bit_is_set()
+                                && self.rb.sr.read().flevel().bits() == 0
+                            {
+                                self.end_command(ccr);
+                                return Err(OctospiError::Underflow);
+                            }
+                        }
+                        // unsafe: byte access to the data register
+                        *location = unsafe {
+                            ptr::read_volatile(UnsafeCell::raw_get(dr))
+                        };
+                    }
+                } else {
+                    for byte in data.iter() {
+                        // Wait for space in the FIFO
+                        while self.rb.sr.read().flevel().bits() >= FIFO_SIZE {}
+                        // unsafe: byte access to the data register
+                        unsafe {
+                            ptr::write_volatile(UnsafeCell::raw_get(dr), *byte)
+                        };
+                    }
+                }
+
+                // Wait for the transaction to complete
+                while self.rb.sr.read().tcf().bit_is_clear() {}
+
+                // Wait for the peripheral to indicate it is no longer busy.
+                while self.is_busy().is_err() {}
+
+                self.end_command(ccr);
+                Ok(())
+            }
+
+            /// Write the command described by `cmd`, followed by each of
+            /// `parts` in turn, in a single transaction in indirect mode.
+            ///
+            /// The chip select is released at the end of every transaction,
+            /// but stays asserted for the whole data phase of a write, even
+            /// while the hardware FIFO is empty. A following command that is
+            /// sent using the same number of IO lines as the data phase of
+            /// `cmd` can therefore be included in `parts`, so that the chip
+            /// select is not released in between. [`transfer`](Octospi#method.transfer)
+            /// releases the chip select after each command.
+            ///
+            /// # Errors
+            ///
+            /// Returns `OctospiError::Busy` if an operation is ongoing.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `cmd` reads from the device. Panics if the number of
+            /// dummy cycles is not 0 - 31 inclusive.
+            pub fn write_chained(
+                &mut self,
+                cmd: Command,
+                parts: &[&[u8]],
+            ) -> Result<(), OctospiError> {
+                assert!(
+                    cmd.data.0 == DataDirection::Write,
+                    "Chained commands must write to the device"
+                );
+                assert!(
+                    cmd.dummy_cycles < 32,
+                    "Hardware only supports 0-31 dummy cycles"
+                );
+
+                self.is_busy()?;
+                let len = parts.iter().map(|part| part.len()).sum();
+                let ccr = self.start_command(&cmd, len);
+
+                let dr = &self.rb.dr as *const _ as *const UnsafeCell<u8>;
+                for byte in parts.iter().flat_map(|part| part.iter()) {
+                    // Wait for space in the FIFO
+                    while self.rb.sr.read().flevel().bits() >= FIFO_SIZE {}
+                    // unsafe: byte access to the data register
+                    unsafe {
+                        ptr::write_volatile(UnsafeCell::raw_get(dr), *byte)
+                    };
+                }
+
+                // Wait for the transaction to complete
+                while self.rb.sr.read().tcf().bit_is_clear() {}
+
+                // Wait for the peripheral to indicate it is no longer busy.
+                while self.is_busy().is_err() {}
+
+                self.end_command(ccr);
+                Ok(())
+            }
+
+            /// Start the transaction described by `cmd`, with a data phase
+            /// of `data_len` bytes
+            ///
+            /// Returns the previous value of CCR, whose DTR and DQS
+            /// settings are restored by `end_command` once the transaction
+            /// is complete
+            fn start_command(
+                &mut self,
+                cmd: &Command,
+                data_len: usize,
+            ) -> stm32::octospi1::ccr::R {
+                // Clear the transfer complete flag.
+                self.rb.fcr.write(|w| w.ctcf().set_bit());
+
+                if data_len != 0 {
+                    self.rb
+                        .dlr
+                        .write(|w| unsafe { w.dl().bits(data_len as u32 - 1) });
+                }
+
+                // Indirect read or write mode
+                let (direction, data_mode) = cmd.data;
+                let fmode = if direction == DataDirection::Read {
+                    0b01
+                } else {
+                    0b00
+                };
+                self.rb.cr.modify(|_, w| unsafe { w.fmode().bits(fmode) });
+                self.rb
+                    .tcr
+                    .modify(|_, w| unsafe { w.dcyc().bits(cmd.dummy_cycles) });
+
+                // A mode of zero removes the phase
+                let mode = |(word, mode): (OctospiWord, OctospiMode)| {
+                    if word != OctospiWord::None {
+                        mode.reg_value()
+                    } else {
+                        0
+                    }
+                };
+                let dmode = if data_len == 0 {
+                    0
+                } else {
+                    data_mode.reg_value()
+                };
+                let (instruction, address, alternate_bytes) =
+                    (cmd.instruction.0, cmd.address.0, cmd.alternate_bytes.0);
+
+                // The command has its own data rate. The data strobe is
+                // only used if it is enabled by the configuration
+                let ccr = self.rb.ccr.read();
+                let (idtr, addtr, abdtr, ddtr) = cmd.dtr();
+                let dqse = ddtr && ccr.dqse().bit_is_set();
+                self.rb.ccr.modify(|_, w| unsafe {
+                    w.idtr()
+                        .bit(idtr)
+                        .addtr()
+                        .bit(addtr)
+                        .abdtr()
+                        .bit(abdtr)
+                        .ddtr()
+                        .bit(ddtr)
+                        .dqse()
+                        .bit(dqse)
+                        .imode()
+                        .bits(mode(cmd.instruction))
+                        .isize()
+                        .bits(instruction.size())
+                        .admode()
+                        .bits(mode(cmd.address))
+                        .adsize()
+                        .bits(address.size())
+                        .abmode()
+                        .bits(mode(cmd.alternate_bytes))
+                        .absize()
+                        .bits(alternate_bytes.size())
+                        .dmode()
+                        .bits(dmode)
+                });
+                self.rb.abr.write(|w| unsafe {
+                    w.alternate().bits(alternate_bytes.bits())
+                });
+
+                // The transaction starts on the write to IR if there are no
+                // address and data phases, otherwise on the write to AR if
+                // there is no data phase or the data phase is a read, and
+                // otherwise on the first write to DR.
+                if instruction != OctospiWord::None {
+                    self.rb.ir.write(|w| unsafe {
+                        w.instruction().bits(instruction.bits())
+                    });
+                }
+                if address != OctospiWord::None {
+                    self.rb
+                        .ar
+                        .write(|w| unsafe { w.address().bits(address.bits()) });
+                }
+
+                ccr
+            }
+
+            /// Restore the DTR and DQS settings of the configuration, from
+            /// the value of CCR returned by `start_command`
+            fn end_command(&mut self, previous: stm32::octospi1::ccr::R) {
+                self.rb.ccr.modify(|_, w| {
+                    w.idtr()
+                        .bit(previous.idtr().bit())
+                        .addtr()
+                        .bit(previous.addtr().bit())
+                        .abdtr()
+                        .bit(previous.abdtr().bit())
+                        .ddtr()
+                        .bit(previous.ddtr().bit())
+                        .dqse()
+                        .bit(previous.dqse().bit())
+                });
+            }
+
+            /// Read the JEDEC Serial Flash Discoverable Parameters (SFDP) of
+            /// a NOR flash device, and parse its Basic Flash Parameter Table
+            ///
+            /// The SFDP data is read with instruction 0x5A in 1-bit STR
+            /// mode, as required by JESD216, with a 24-bit address and 8
+            /// dummy cycles. `buf` is used to
+            /// read the SFDP header, and then as much of the Basic Flash
+            /// Parameter Table as fits. On return it holds the start of the
+            /// table, so that any further parameters can be parsed.
+            ///
+            /// # Errors
+            ///
+            /// Returns `OctospiError::InvalidSfdp` if the device does not
+            /// return valid SFDP data, or any error from
+            /// [`transfer`](Octospi#method.transfer).
+            ///
+            /// # Panics
+            ///
+            /// Panics if `buf` is shorter than
+            /// [`SFDP_BASIC_TABLE_MIN_LEN`](super::SFDP_BASIC_TABLE_MIN_LEN)
+            /// bytes.
+            pub fn read_sfdp(
+                &mut self,
+                buf: &mut [u8],
+            ) -> Result<SfdpInfo, OctospiError> {
+                assert!(
+                    buf.len() >= SFDP_BASIC_TABLE_MIN_LEN,
+                    "Buffer is too short for the Basic Flash Parameter Table"
+                );
+
+                let read_sfdp = |address| {
+                    Command::new()
+                        .instruction(OctospiWord::U8(0x5A), OctospiMode::OneBit)
+                        .address(OctospiWord::U24(address), OctospiMode::OneBit)
+                        .dummy_cycles(8)
+                        .read(OctospiMode::OneBit)
+                        .data_rate(DataRate::Single)
+                };
+
+                let header = &mut buf[..SFDP_HEADER_LEN];
+                self.transfer(read_sfdp(0), Some(header))?;
+                let (address, len) =
+                    SfdpInfo::locate_basic_table(&buf[..SFDP_HEADER_LEN])?;
+
+                let len = cmp::min(len, buf.len());
+                self.transfer(read_sfdp(address), Some(&mut buf[..len]))?;
+
+                SfdpInfo::parse_basic_table(buf)
+            }
+
+            /// Reconfigure this peripheral as a Hyperbus interface
+            ///
+            /// This allows a device to be accessed in indirect mode first,
+            /// for example to reset it, and then used in memory-mapped
+            /// Hyperbus mode. The peripheral is reset, so all the settings
+            /// of the indirect mode are lost.
+            ///
+            /// ```
+            /// let hyperbus = octospi.into_hyperbus(config, &ccdr.clocks);
+            /// ```
+            pub fn into_hyperbus<CONFIG>(
+                self,
+                config: CONFIG,
+                clocks: &CoreClocks,
+            ) -> Hyperbus<$peripheral>
+            where
+                CONFIG: Into<HyperbusConfig>,
+            {
+                let (regs, prec) = self.free();
+                Octospi::$name_hyperbus(regs, config, clocks, prec)
+            }
+
+            /// Switch a flash device to quad or octal mode by executing the
+            /// sequence described by `switch`.
+            ///
+            /// The write enable and write register commands are executed,
+            /// and then the status register is read until the device is no
+            /// longer busy. Subsequent commands to the device should use the
+            /// new mode.
+            ///
+            /// The modes set by
+            /// [`configure_modes`](Octospi#method.configure_modes) are not
+            /// used by this method, and are unchanged.
+            ///
+            /// # Errors
+            ///
+            /// Returns `OctospiError::Timeout` if the device is still busy
+            /// after the maximum number of status register reads, or any
+            /// error from [`transfer`](Octospi#method.transfer).
+            pub fn switch_flash_mode(
+                &mut self,
+                switch: &FlashModeSwitch,
+            ) -> Result<(), OctospiError> {
+                let mut step = ModeSwitchStep::WriteEnable;
+
+                loop {
+                    step = match step {
+                        ModeSwitchStep::WriteEnable => {
+                            self.transfer(switch.write_enable, None)?;
+                            ModeSwitchStep::WriteRegister
+                        }
+                        ModeSwitchStep::WriteRegister => {
+                            let mut value = switch.value;
+                            self.transfer(
+                                switch.write_register,
+                                Some(&mut value[..switch.value_len]),
+                            )?;
+                            ModeSwitchStep::PollStatus(0)
+                        }
+                        ModeSwitchStep::PollStatus(polls) => {
+                            if polls == switch.max_polls {
+                                return Err(OctospiError::Timeout);
+                            }
+
+                            let mut status = [0];
+                            self.transfer(
+                                switch.read_status,
+                                Some(&mut status),
+                            )?;
+                            if status[0] & switch.busy_mask == 0 {
+                                ModeSwitchStep::Done
+                            } else {
+                                ModeSwitchStep::PollStatus(polls + 1)
+                            }
+                        }
+                        ModeSwitchStep::Done => return Ok(()),
+                    }
+                }
+            }
+        }
+
+        impl fmt::Debug for Octospi<$peripheral> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let (fifo_threshold, dummy_cycles, sampling_edge, dtr) =
+                    self.current_settings();
+                f.debug_struct(stringify!(Octospi<$peripheral>))
+                    .field("modes", &self.modes)
+                    .field("frequency", &self.frequency)
+                    .field("divisor", &self.divisor)
+                    .field("fifo_threshold", &fifo_threshold)
+                    .field("dummy_cycles", &dummy_cycles)
+                    .field("sampling_edge", &sampling_edge)
+                    .field("dtr", &dtr)
+                    .finish()
+            }
+        }
+
+        impl fmt::Display for Octospi<$peripheral> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let (fifo_threshold, dummy_cycles, sampling_edge, dtr) =
+                    self.current_settings();
+                write!(f, "{}", mode_name(self.modes.data))?;
+                if dtr {
+                    write!(f, " DTR")?;
+                }
+                write!(f, " with Clock {}; ", self.frequency)?;
+                write!(f, "FIFO threshold: {} bytes; ", fifo_threshold)?;
+                write!(f, "Dummy cycles: {}; ", dummy_cycles)?;
+                write!(f, "Sampling edge: {:?}", sampling_edge)
+            }
+        }
+
+        impl Octospi<$peripheral> {
+            /// Returns the FIFO threshold, dummy cycles, sampling edge and
+            /// DTR mode as currently set in the peripheral
+            fn current_settings(&self) -> (u8, u8, SamplingEdge, bool) {
+                let tcr = self.rb.tcr.read();
+                let sampling_edge = if tcr.sshift().bit_is_set() {
+                    SamplingEdge::Falling
+                } else {
+                    SamplingEdge::Rising
+                };
+                (
+                    self.rb.cr.read().fthres().bits() + 1,
+                    tcr.dcyc().bits(),
+                    sampling_edge,
+                    self.rb.ccr.read().ddtr().bit_is_set(),
+                )
+            }
+        }
+
         impl OctospiExt<$peripheral> for $peripheral {
             type Rec = $rec;
 
@@ -666,6 +1577,81 @@ macro_rules! octospi_impl {
         }
 
         impl Hyperbus<$peripheral> {
+            /// Write `value` to the register at `address` in the register
+            /// space of the Hyperbus device, in indirect mode.
+            ///
+            /// This can be used to configure the device before it is
+            /// initialised with [`init`](Hyperbus#method.init). The address
+            /// is a byte address, for example 0x1000 for Configuration
+            /// Register 0 of the Cypress S70KL1281 HyperRAM.
+            pub fn write_register(&mut self, address: u32, value: u16) {
+                self.register_access(address, false, |dr| unsafe {
+                    // unsafe: halfword access to the data register
+                    ptr::write_volatile(UnsafeCell::raw_get(dr), value);
+                    value
+                });
+            }
+
+            /// Read the register at `address` in the register space of the
+            /// Hyperbus device, in indirect mode.
+            ///
+            /// See [`write_register`](Hyperbus#method.write_register).
+            pub fn read_register(&mut self, address: u32) -> u16 {
+                self.register_access(address, true, |dr| unsafe {
+                    // unsafe: halfword access to the data register
+                    ptr::read_volatile(UnsafeCell::raw_get(dr))
+                })
+            }
+
+            /// Perform a two byte indirect access in the register space,
+            /// using `f` to access the data register. Afterwards the
+            /// peripheral is returned to the disabled, memory-mapped state
+            /// expected by `init`.
+            fn register_access<F>(
+                &mut self,
+                address: u32,
+                read: bool,
+                f: F,
+            ) -> u16
+            where
+                F: FnOnce(*const UnsafeCell<u16>) -> u16,
+            {
+                while self.rb.sr.read().busy().bit_is_set() {}
+
+                // Hyperbus register mode
+                self.rb.dcr1.modify(|_, w| unsafe { w.mtyp().bits(5) });
+
+                // Indirect read or write mode
+                let fmode = if read { 0b01 } else { 0b00 };
+                self.rb.cr.modify(|_, w| unsafe {
+                    w.fmode().bits(fmode).en().set_bit()
+                });
+                self.rb.fcr.write(|w| w.ctcf().set_bit());
+                self.rb.dlr.write(|w| unsafe { w.dl().bits(2 - 1) });
+
+                // A read starts on the write to AR, a write on the write to
+                // DR
+                self.rb.ar.write(|w| unsafe { w.address().bits(address) });
+                let dr = &self.rb.dr as *const _ as *const UnsafeCell<u16>;
+                if read {
+                    while self.rb.sr.read().tcf().bit_is_clear() {}
+                }
+                let value = f(dr);
+
+                // Wait for the transaction to complete
+                while self.rb.sr.read().tcf().bit_is_clear() {}
+                while self.rb.sr.read().busy().bit_is_set() {}
+
+                self.rb.cr.modify(|_, w| unsafe {
+                    w.en().clear_bit().fmode().bits(3) // Memory-mapped
+                });
+                self.rb.dcr1.modify(|_, w| unsafe {
+                    w.mtyp().bits(4) // Hyperbus memory mode
+                });
+
+                value
+            }
+
             /// Initialise a memory-mapped Hyperbus peripheral and return a raw
             /// pointer to the memory
             pub fn init(self) -> *mut u32 {
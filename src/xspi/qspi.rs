@@ -3,12 +3,45 @@
 //! See the parent module for documentation
 
 use crate::{
+    dma::{
+        mdma::{MdmaConfig, MdmaIncrement, MdmaTransferRequest, MdmaTrigger},
+        traits::{MasterStream, Stream},
+        MasterTransfer, PeripheralToMemory, Transfer,
+    },
     gpio::{self, Alternate},
     rcc::{rec, CoreClocks, ResetEnable},
     stm32,
+    time::Hertz,
 };
 
-use super::{common::BankSelect, Bank, Config, Qspi, QspiError, SamplingEdge};
+use super::{
+    common::{prescaler_divisor, BankSelect, Polarity},
+    Bank, Config, Qspi, QspiError, QspiMode, SamplingEdge,
+};
+
+/// Base address of the QUADSPI memory-mapped region
+const QSPI_MEMORY_ADDRESS: usize = 0x9000_0000;
+
+/// Size of the address phase used for memory-mapped reads
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AddressSize {
+    EightBit,
+    SixteenBit,
+    TwentyFourBit,
+    ThirtyTwoBit,
+}
+impl AddressSize {
+    #[inline(always)]
+    fn reg_value(&self) -> u8 {
+        match self {
+            AddressSize::EightBit => 0,
+            AddressSize::SixteenBit => 1,
+            AddressSize::TwentyFourBit => 2,
+            AddressSize::ThirtyTwoBit => 3,
+        }
+    }
+}
 
 /// Used to indicate that an IO pin is not used by the QSPI interface.
 pub struct NoIo {}
@@ -228,7 +261,113 @@ pub trait QspiExt {
         CONFIG: Into<Config>;
 }
 
+/// An indirect read from the QUADSPI interface, with the data transferred
+/// to memory by the MDMA.
+///
+/// Created by [`read_indirect_dma`](Qspi#method.read_indirect_dma).
+pub struct QspiDmaTransfer<STREAM>
+where
+    STREAM: MasterStream + Stream<Config = MdmaConfig>,
+{
+    transfer: Transfer<
+        STREAM,
+        Qspi<stm32::QUADSPI>,
+        PeripheralToMemory,
+        &'static mut [u8],
+        MasterTransfer,
+    >,
+}
+
+impl<STREAM> QspiDmaTransfer<STREAM>
+where
+    STREAM: MasterStream + Stream<Config = MdmaConfig>,
+{
+    /// Returns true if the QUADSPI transaction is complete and all of the data
+    /// has been transferred to the buffer
+    pub fn is_complete(&self) -> bool {
+        // unsafe: read-only access to the status register of the QUADSPI
+        // owned by the transfer
+        let qspi = unsafe { &*stm32::QUADSPI::ptr() };
+
+        qspi.sr.read().tcf().bit_is_set()
+            && self.transfer.get_transfer_complete_flag()
+    }
+
+    /// Blocks until the read is complete, and returns the QUADSPI, the MDMA
+    /// stream and the buffer containing the data
+    pub fn wait(self) -> (Qspi<stm32::QUADSPI>, STREAM, &'static mut [u8]) {
+        while !self.is_complete() {}
+
+        let (stream, qspi, buf, _) = self.transfer.free();
+
+        // Clear the transfer complete flag and stop the DMA requests
+        qspi.rb.fcr.write(|w| w.ctcf().set_bit());
+        qspi.rb.cr.modify(|_, w| w.dmaen().clear_bit());
+
+        // Wait for the peripheral to indicate it is no longer busy.
+        while qspi.is_busy().is_err() {}
+
+        (qspi, stream, buf)
+    }
+}
+
 impl Qspi<stm32::QUADSPI> {
+    /// Begin a read of `buf.len()` bytes from `address` over the QUADSPI
+    /// interface, using the current operational mode, with the data
+    /// transferred from the FIFO to `buf` by the MDMA. This method returns
+    /// immediately, call [`wait`](QspiDmaTransfer::wait) on the result to
+    /// obtain the data.
+    ///
+    /// The MDMA transfers the FIFO each time the FIFO threshold is reached, so
+    /// the [FIFO threshold](super::Config#method.fifo_threshold) should be a
+    /// multiple of 4 bytes.
+    ///
+    /// If an operation is ongoing, this method waits for it to complete
+    /// first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the length of `buf` is zero or greater than 65536 bytes.
+    pub fn read_indirect_dma<STREAM>(
+        self,
+        address: u32,
+        buf: &'static mut [u8],
+        stream: STREAM,
+    ) -> QspiDmaTransfer<STREAM>
+    where
+        STREAM: MasterStream + Stream<Config = MdmaConfig>,
+    {
+        assert!(
+            !buf.is_empty(),
+            "Must have a non-zero number of data cycles"
+        );
+
+        while self.is_busy().is_err() {}
+
+        let length = buf.len();
+        let threshold = self.rb.cr.read().fthres().bits() + 1;
+        let config = MdmaConfig::default()
+            .source_increment(MdmaIncrement::Fixed)
+            .destination_increment(MdmaIncrement::Increment)
+            // Triggered by hardware each time the FIFO threshold is reached
+            .hardware_transfer_request(MdmaTransferRequest::QuadspiFtTrg)
+            .trigger_mode(MdmaTrigger::Buffer)
+            .buffer_length(threshold);
+
+        self.rb.cr.modify(|_, w| w.dmaen().set_bit());
+
+        let mut transfer =
+            Transfer::init_master(stream, self, buf, None, config);
+
+        // Start the QUADSPI once the MDMA is ready. This cannot fail as the
+        // peripheral is not busy
+        transfer.start(|qspi| {
+            qspi.begin_read(address, length).unwrap();
+        });
+
+        QspiDmaTransfer { transfer }
+    }
+
     /// Switches between single-bank mode on Bank 1 and single-bank mode on Bank 2.
     /// Note that it has no effect in dual-flash mode.
     pub fn change_bank_unchecked(
@@ -248,6 +387,81 @@ impl Qspi<stm32::QUADSPI> {
         Ok(())
     }
 
+    /// Transition to memory-mapped mode, so that the CPU and other bus
+    /// masters can read the flash (and execute code from it) directly. Each
+    /// access to the mapped region is translated into a read command with the
+    /// given `instruction`, `address_size` and number of `dummy_cycles`. All
+    /// phases of the command use `mode`.
+    ///
+    /// Returns a raw pointer to the start of the memory-mapped region.
+    ///
+    /// The QUADSPI peripheral must be clocked (and remain clocked) before the
+    /// caches are enabled, otherwise a speculative fetch from the mapped
+    /// region may stall the bus. The caches do not observe changes to the
+    /// flash contents, so each time the flash has been written the data cache
+    /// must be invalidated over the mapped region, and the instruction cache
+    /// invalidated, before the new contents are read or executed.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of dummy cycles is not 0 - 31 inclusive.
+    pub fn into_memory_mapped(
+        self,
+        instruction: u8,
+        address_size: AddressSize,
+        dummy_cycles: u8,
+        mode: QspiMode,
+    ) -> *mut u32 {
+        assert!(
+            dummy_cycles < 32,
+            "Hardware only supports 0-31 dummy cycles"
+        );
+
+        // CCR can only be written when the peripheral is not busy
+        while self.rb.sr.read().busy().bit_is_set() {}
+
+        // The timeout counter would release nCS when the bus is idle; keep it
+        // disabled so that sequential accesses are not interrupted.
+        self.rb.cr.modify(|_, w| w.tcen().clear_bit());
+
+        // Clear all pending flags.
+        self.rb.fcr.write(|w| {
+            w.ctof()
+                .set_bit()
+                .csmf()
+                .set_bit()
+                .ctcf()
+                .set_bit()
+                .ctef()
+                .set_bit()
+        });
+
+        let mode = mode.reg_value();
+        self.rb.ccr.write(|w| unsafe {
+            w.fmode()
+                .bits(0b11) // Memory-mapped
+                .imode()
+                .bits(mode)
+                .instruction()
+                .bits(instruction)
+                .admode()
+                .bits(mode)
+                .adsize()
+                .bits(address_size.reg_value())
+                .abmode()
+                .bits(0) // No alternate-bytes phase
+                .dcyc()
+                .bits(dummy_cycles)
+                .dmode()
+                .bits(mode)
+        });
+
+        // Ensure the peripheral is enabled
+        self.rb.cr.modify(|_, w| w.en().set_bit());
+
+        QSPI_MEMORY_ADDRESS as *mut u32
+    }
+
     pub fn qspi_unchecked<CONFIG>(
         regs: stm32::QUADSPI,
         config: CONFIG,
@@ -271,7 +485,12 @@ impl Qspi<stm32::QUADSPI> {
 
         // Configure the FSIZE to maximum. It appears that even when addressing is not used, the
         // flash size violation may still trigger.
-        regs.dcr.write(|w| unsafe { w.fsize().bits(0x1F) });
+        regs.dcr.write(|w| unsafe {
+            w.fsize()
+                .bits(0x1F)
+                .ckmode()
+                .bit(config.clock_polarity == Polarity::IdleHigh)
+        });
 
         // Clear all pending flags.
         regs.fcr.write(|w| {
@@ -301,12 +520,7 @@ impl Qspi<stm32::QUADSPI> {
                 .bits(config.dummy_cycles)
         });
 
-        let spi_frequency = config.frequency.raw();
-        let divisor = match (spi_kernel_ck + spi_frequency - 1) / spi_frequency
-        {
-            divisor @ 1..=256 => divisor - 1,
-            _ => panic!("Invalid QSPI frequency requested"),
-        };
+        let divisor = prescaler_divisor(spi_kernel_ck, &config);
 
         // Write the prescaler and the SSHIFT bit.
         //
@@ -320,7 +534,7 @@ impl Qspi<stm32::QUADSPI> {
         // SSHIFT must not be set in DDR mode.
         regs.cr.write(|w| unsafe {
             w.prescaler()
-                .bits(divisor as u8)
+                .bits((divisor - 1) as u8)
                 .sshift()
                 .bit(config.sampling_edge == SamplingEdge::Falling)
                 .fthres()
@@ -339,6 +553,8 @@ impl Qspi<stm32::QUADSPI> {
         Qspi {
             rb: regs,
             modes: config.modes,
+            divisor: divisor as u16,
+            frequency: Hertz::from_raw(spi_kernel_ck / divisor),
         }
     }
 }
@@ -0,0 +1,303 @@
+//! Parser for the JEDEC Serial Flash Discoverable Parameters (SFDP, JESD216)
+//!
+//! The SFDP data starts with a header, followed by the parameter headers. The
+//! first parameter header always describes the JEDEC Basic Flash Parameter
+//! Table, which gives the capacity, erase types and fast read modes of the
+//! device.
+
+use super::common::XspiError;
+
+/// Size of the SFDP header and the first parameter header in bytes
+pub const SFDP_HEADER_LEN: usize = 16;
+
+/// Minimum size of the Basic Flash Parameter Table in bytes (JESD216, 9
+/// DWORDs)
+pub const SFDP_BASIC_TABLE_MIN_LEN: usize = 36;
+
+/// "SFDP" signature at the start of the header
+const SIGNATURE: u32 = 0x5044_4653;
+
+/// One of the erase types supported by a flash device
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SfdpEraseRegion {
+    /// Size of the region erased in bytes, or zero if this erase type is not
+    /// supported
+    pub size_bytes: u32,
+    /// Instruction for this erase type
+    pub instruction: u8,
+}
+
+impl SfdpEraseRegion {
+    /// Returns true if the device supports this erase type
+    pub fn is_supported(&self) -> bool {
+        self.size_bytes != 0
+    }
+}
+
+/// Number of address bytes supported by a flash device
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SfdpAddressBytes {
+    /// 3-byte addressing only
+    Three,
+    /// 3-byte addressing by default, 4-byte addressing can be entered
+    ThreeOrFour,
+    /// 4-byte addressing only
+    Four,
+}
+
+/// Operating modes supported by a flash device
+///
+/// The fast read modes are given as instruction-address-data lines, for
+/// example 1-1-4 is Fast Read Quad Output.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SfdpModes {
+    /// Addressing modes
+    pub address_bytes: SfdpAddressBytes,
+    /// Fast read 1-1-2
+    pub read_1_1_2: bool,
+    /// Fast read 1-2-2
+    pub read_1_2_2: bool,
+    /// Fast read 1-1-4
+    pub read_1_1_4: bool,
+    /// Fast read 1-4-4
+    pub read_1_4_4: bool,
+    /// Fast read 2-2-2
+    pub read_2_2_2: bool,
+    /// Fast read 4-4-4
+    pub read_4_4_4: bool,
+    /// Double transfer rate (DTR) clocking
+    pub double_transfer_rate: bool,
+}
+
+/// Information about a flash device from its Basic Flash Parameter Table
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SfdpInfo {
+    /// Capacity of the device in bits
+    pub density_bits: u64,
+    /// Erase types 1 to 4
+    pub erase_regions: [SfdpEraseRegion; 4],
+    /// Supported operating modes
+    pub modes: SfdpModes,
+}
+
+fn dword(data: &[u8], index: usize) -> u32 {
+    let b = &data[4 * index..4 * index + 4];
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+impl SfdpInfo {
+    /// Capacity of the device in bytes
+    pub fn density_bytes(&self) -> u64 {
+        self.density_bits / 8
+    }
+
+    /// Finds the Basic Flash Parameter Table from the first
+    /// [`SFDP_HEADER_LEN`] bytes of the SFDP data. Returns the address of the
+    /// table in the SFDP data and its length in bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidSfdp` if the header does not have the SFDP signature,
+    /// or if the first parameter header does not describe a Basic Flash
+    /// Parameter Table of at least [`SFDP_BASIC_TABLE_MIN_LEN`] bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `header` is shorter than [`SFDP_HEADER_LEN`] bytes.
+    pub fn locate_basic_table(
+        header: &[u8],
+    ) -> Result<(u32, usize), XspiError> {
+        if dword(header, 0) != SIGNATURE {
+            return Err(XspiError::InvalidSfdp);
+        }
+
+        // The first parameter header has ID 0xFF00 and a major revision of 1
+        let parameter = &header[8..SFDP_HEADER_LEN];
+        if parameter[0] != 0x00 || parameter[7] != 0xFF || parameter[2] != 1 {
+            return Err(XspiError::InvalidSfdp);
+        }
+
+        let len = 4 * parameter[3] as usize;
+        if len < SFDP_BASIC_TABLE_MIN_LEN {
+            return Err(XspiError::InvalidSfdp);
+        }
+        let address = dword(parameter, 1) & 0xFF_FFFF;
+
+        Ok((address, len))
+    }
+
+    /// Parses the first [`SFDP_BASIC_TABLE_MIN_LEN`] bytes of the Basic Flash
+    /// Parameter Table
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidSfdp` if the density is zero or does not fit in 64
+    /// bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `table` is shorter than [`SFDP_BASIC_TABLE_MIN_LEN`] bytes.
+    pub fn parse_basic_table(table: &[u8]) -> Result<SfdpInfo, XspiError> {
+        assert!(
+            table.len() >= SFDP_BASIC_TABLE_MIN_LEN,
+            "Basic Flash Parameter Table is too short"
+        );
+
+        let flags = dword(table, 0);
+        let bit = |n: u32| flags & (1 << n) != 0;
+        let address_bytes = match (flags >> 17) & 0b11 {
+            0b00 => SfdpAddressBytes::Three,
+            0b01 => SfdpAddressBytes::ThreeOrFour,
+            0b10 => SfdpAddressBytes::Four,
+            _ => return Err(XspiError::InvalidSfdp),
+        };
+
+        // Density in bits, minus one, or as a power of two when bit 31 is set
+        let density = dword(table, 1);
+        let density_bits = if density & (1 << 31) == 0 {
+            u64::from(density) + 1
+        } else {
+            let order = density & !(1 << 31);
+            if order == 0 || order > 63 {
+                return Err(XspiError::InvalidSfdp);
+            }
+            1 << order
+        };
+
+        let fast_read = dword(table, 4);
+
+        // Erase types 1 to 4 are given as a size (as a power of two) and an
+        // instruction, two to a DWORD
+        let mut erase_regions = [SfdpEraseRegion::default(); 4];
+        for (n, region) in erase_regions.iter_mut().enumerate() {
+            let bits = dword(table, 7 + n / 2) >> (16 * (n % 2));
+            let order = bits & 0xFF;
+            if order != 0 && order < 32 {
+                *region = SfdpEraseRegion {
+                    size_bytes: 1 << order,
+                    instruction: (bits >> 8) as u8,
+                };
+            }
+        }
+
+        Ok(SfdpInfo {
+            density_bits,
+            erase_regions,
+            modes: SfdpModes {
+                address_bytes,
+                read_1_1_2: bit(16),
+                read_1_2_2: bit(20),
+                read_1_4_4: bit(21),
+                read_1_1_4: bit(22),
+                read_2_2_2: fast_read & (1 << 0) != 0,
+                read_4_4_4: fast_read & (1 << 4) != 0,
+                double_transfer_rate: bit(19),
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// SFDP header for a single Basic Flash Parameter Table of 16 DWORDs at
+    /// address 0x30
+    const HEADER: [u8; 16] = [
+        0x53, 0x46, 0x44, 0x50, 0x06, 0x01, 0x00, 0xFF, // SFDP header
+        0x00, 0x06, 0x01, 0x10, 0x30, 0x00, 0x00,
+        0xFF, // Parameter header
+    ];
+
+    /// Basic Flash Parameter Table for a 128 Mbit device
+    const TABLE: [u8; 36] = [
+        0xE5, 0x20, 0xF1,
+        0xFF, // 4kB erase 0x20, 1-1-2, 1-2-2, 1-4-4, 1-1-4
+        0xFF, 0xFF, 0xFF, 0x07, // 128 Mbit
+        0x44, 0xEB, 0x08, 0x6B, //
+        0x08, 0x3B, 0x42, 0xBB, //
+        0xEE, 0xFF, 0xFF, 0xFF, // No 2-2-2 or 4-4-4
+        0xFF, 0xFF, 0x00, 0x00, //
+        0xFF, 0xFF, 0x44, 0xEB, //
+        0x0C, 0x20, 0x0F, 0x52, // 4kB 0x20, 32kB 0x52
+        0x10, 0xD8, 0x00, 0x00, // 64kB 0xD8
+    ];
+
+    #[test]
+    fn locate_basic_table() {
+        assert_eq!(SfdpInfo::locate_basic_table(&HEADER), Ok((0x30, 64)));
+    }
+
+    #[test]
+    fn bad_signature() {
+        let mut header = HEADER;
+        header[0] = 0xFF;
+        assert_eq!(
+            SfdpInfo::locate_basic_table(&header),
+            Err(XspiError::InvalidSfdp)
+        );
+    }
+
+    #[test]
+    fn not_basic_table() {
+        let mut header = HEADER;
+        header[15] = 0x01;
+        assert_eq!(
+            SfdpInfo::locate_basic_table(&header),
+            Err(XspiError::InvalidSfdp)
+        );
+    }
+
+    #[test]
+    fn parse_basic_table() {
+        let info = SfdpInfo::parse_basic_table(&TABLE).unwrap();
+
+        assert_eq!(info.density_bits, 128 * 1024 * 1024);
+        assert_eq!(info.density_bytes(), 16 * 1024 * 1024);
+        assert_eq!(
+            info.erase_regions,
+            [
+                SfdpEraseRegion {
+                    size_bytes: 4096,
+                    instruction: 0x20
+                },
+                SfdpEraseRegion {
+                    size_bytes: 32768,
+                    instruction: 0x52
+                },
+                SfdpEraseRegion {
+                    size_bytes: 65536,
+                    instruction: 0xD8
+                },
+                SfdpEraseRegion::default(),
+            ]
+        );
+        assert!(!info.erase_regions[3].is_supported());
+        assert_eq!(
+            info.modes,
+            SfdpModes {
+                address_bytes: SfdpAddressBytes::Three,
+                read_1_1_2: true,
+                read_1_2_2: true,
+                read_1_1_4: true,
+                read_1_4_4: true,
+                read_2_2_2: false,
+                read_4_4_4: false,
+                double_transfer_rate: false,
+            }
+        );
+    }
+
+    #[test]
+    fn density_power_of_two() {
+        let mut table = TABLE;
+        // 2^34 bits
+        table[4..8].copy_from_slice(&[0x22, 0x00, 0x00, 0x80]);
+        let info = SfdpInfo::parse_basic_table(&table).unwrap();
+        assert_eq!(info.density_bits, 1 << 34);
+    }
+}